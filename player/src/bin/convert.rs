@@ -0,0 +1,53 @@
+/*! This is a converter between the RON and binary formats of WebGPU traces.
+!*/
+
+use player::ActionReader;
+use wgc::device::{trace, TraceFormat};
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+};
+
+fn main() {
+    env_logger::init();
+
+    let mut args = std::env::args().skip(1);
+    let dir = match args.next() {
+        Some(arg) if Path::new(&arg).is_dir() => PathBuf::from(arg),
+        _ => panic!("Provide the dir path as the first parameter"),
+    };
+    let format = match args.next().as_deref() {
+        Some("ron") => TraceFormat::Ron,
+        Some("binary") => TraceFormat::Binary,
+        _ => panic!("Provide the target format as the second parameter: 'ron' or 'binary'"),
+    };
+
+    let actions = ActionReader::open(&dir).unwrap();
+    if actions.format() == format {
+        panic!("The trace is already in {:?} format", format);
+    }
+    log::info!(
+        "Converting '{:?}' from {:?} to {:?}",
+        dir,
+        actions.format(),
+        format
+    );
+
+    let file = fs::File::create(dir.join(format.file_name())).unwrap();
+    let mut writer = trace::TraceWriter::new(io::BufWriter::new(file), format).unwrap();
+    let mut count = 0;
+    for action in actions {
+        let action = match action {
+            Ok(action) => action,
+            Err(e) => {
+                println!("Unable to read action {}: {}", count, e);
+                process::exit(1);
+            }
+        };
+        writer.write(&action).unwrap();
+        count += 1;
+    }
+    println!("Converted {} actions", count);
+}
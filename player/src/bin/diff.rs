@@ -0,0 +1,366 @@
+/*! Differential replay of a trace on two adapters.
+
+The same actions are replayed on both adapters in lock step. After every
+submission, the contents of all the `MAP_READ` buffers and all the textures
+are read back on both sides and compared, stopping at the first difference.
+!*/
+
+use player::{
+    compare::first_difference,
+    inspect::{RawId, Resources},
+    parse_backend, set_backend, ActionReader, GlobalPlay as _, IdentityPassThroughFactory,
+};
+use wgc::{device::trace, gfx_select};
+
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+    process,
+};
+
+/// Replay of the trace on one of the adapters.
+struct Side {
+    name: &'static str,
+    global: wgc::hub::Global<IdentityPassThroughFactory>,
+    device: wgc::id::DeviceId,
+    comb_manager: wgc::hub::IdentityManager,
+}
+
+/// Way of picking one of the adapters of a backend.
+enum AdapterChoice {
+    /// Let wgpu-core pick the adapter that fits the power preference.
+    Power(wgt::PowerPreference),
+    /// Position of the adapter in the enumeration order of the backend.
+    Index(usize),
+    /// Part of the adapter name, which usually includes the driver.
+    Name(String),
+}
+
+/// Parse `<backend>[:low-power|:high-performance|:<index>|:<name>]`.
+fn parse_adapter(spec: &str) -> Option<(wgt::Backend, AdapterChoice)> {
+    let mut parts = spec.splitn(2, ':');
+    let backend = parse_backend(parts.next()?)?;
+    let choice = match parts.next() {
+        None | Some("low-power") => AdapterChoice::Power(wgt::PowerPreference::LowPower),
+        Some("high-performance") => AdapterChoice::Power(wgt::PowerPreference::HighPerformance),
+        Some("") => return None,
+        Some(other) => match other.parse() {
+            Ok(index) => AdapterChoice::Index(index),
+            Err(_) => AdapterChoice::Name(other.to_lowercase()),
+        },
+    };
+    Some((backend, choice))
+}
+
+/// Opens a specific adapter of a backend through `wgpu-hal`.
+struct AdapterOpener;
+
+impl AdapterOpener {
+    fn open<A: wgc::hub::HalApi>(
+        &self,
+        name: &str,
+        choice: &AdapterChoice,
+    ) -> Option<(
+        wgc::hub::Global<IdentityPassThroughFactory>,
+        wgc::id::AdapterId,
+    )> {
+        use hal::Instance as _;
+
+        let instance = unsafe {
+            A::Instance::init(&hal::InstanceDescriptor {
+                name,
+                flags: hal::InstanceFlags::empty(),
+            })
+        }
+        .ok()?;
+        let mut exposed = unsafe { instance.enumerate_adapters() };
+        for (index, adapter) in exposed.iter().enumerate() {
+            log::info!("Adapter {}: {:?}", index, adapter.info);
+        }
+        let index = match *choice {
+            AdapterChoice::Power(_) => unreachable!(),
+            AdapterChoice::Index(index) if index < exposed.len() => index,
+            AdapterChoice::Index(_) => return None,
+            AdapterChoice::Name(ref part) => exposed
+                .iter()
+                .position(|adapter| adapter.info.name.to_lowercase().contains(part))?,
+        };
+        let adapter = exposed.swap_remove(index);
+        let global = unsafe {
+            wgc::hub::Global::from_hal_instance::<A>(name, IdentityPassThroughFactory, instance)
+        };
+        let id = unsafe {
+            global.create_adapter_from_hal(adapter, wgc::id::TypedId::zip(0, 0, A::VARIANT))
+        };
+        Some((global, id))
+    }
+}
+
+impl Side {
+    fn new(name: &'static str, spec: &str, desc: &wgc::device::DeviceDescriptor) -> Self {
+        let (backend, choice) =
+            parse_adapter(spec).unwrap_or_else(|| panic!("Unknown adapter '{}'", spec));
+        let (global, adapter) = match choice {
+            AdapterChoice::Power(power_preference) => {
+                let global =
+                    wgc::hub::Global::new(name, IdentityPassThroughFactory, backend.into());
+                let adapter = global
+                    .request_adapter(
+                        &wgc::instance::RequestAdapterOptions {
+                            power_preference,
+                            compatible_surface: None,
+                        },
+                        wgc::instance::AdapterInputs::IdSet(
+                            &[wgc::id::TypedId::zip(0, 0, backend)],
+                            |id| id.backend(),
+                        ),
+                    )
+                    .unwrap_or_else(|_| panic!("Unable to find an adapter for '{}'", spec));
+                (global, adapter)
+            }
+            ref choice => {
+                let probe: wgc::id::AdapterId = wgc::id::TypedId::zip(0, 0, backend);
+                let opener = AdapterOpener;
+                gfx_select!(probe => opener.open(name, choice))
+                    .unwrap_or_else(|| panic!("Unable to find an adapter for '{}'", spec))
+            }
+        };
+
+        let info = gfx_select!(adapter => global.adapter_get_info(adapter)).unwrap();
+        println!("{}: '{}' on {:?}", name, info.name, info.backend);
+        let device = wgc::id::TypedId::zip(1, 0, backend);
+        let (_, error) = gfx_select!(adapter => global.adapter_request_device(
+            adapter,
+            desc,
+            None,
+            device
+        ));
+        if let Some(e) = error {
+            panic!("{}: {:?}", name, e);
+        }
+
+        Self {
+            name,
+            global,
+            device,
+            comb_manager: wgc::hub::IdentityManager::default(),
+        }
+    }
+
+    fn process(&mut self, action: &trace::Action, dir: &Path) {
+        let global = &self.global;
+        let device = self.device;
+        let mut action = action.clone();
+        set_backend(&mut action, device.backend());
+        gfx_select!(device => global.process(device, action, dir, &mut self.comb_manager));
+    }
+
+    fn read_buffer(
+        &mut self,
+        resources: &Resources,
+        raw: RawId,
+        desc: &wgt::BufferDescriptor<Option<String>>,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let global = &self.global;
+        let device = self.device;
+        gfx_select!(device => global.read_buffer(
+            device,
+            raw.to_id(device.backend()),
+            desc.usage,
+            0..desc.size,
+            resources.staging_buffer_id(device.backend()),
+            &mut self.comb_manager
+        ))
+    }
+
+    fn read_texture(
+        &mut self,
+        resources: &Resources,
+        raw: RawId,
+        mip_level: u32,
+        size: wgt::Extent3d,
+        format: wgt::TextureFormat,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        let global = &self.global;
+        let device = self.device;
+        let src = wgc::command::ImageCopyTexture {
+            texture: raw.to_id(device.backend()),
+            mip_level,
+            origin: wgt::Origin3d::ZERO,
+            aspect: wgt::TextureAspect::All,
+        };
+        gfx_select!(device => global.read_texture(
+            device,
+            &src,
+            size,
+            format,
+            resources.staging_buffer_id(device.backend()),
+            &mut self.comb_manager
+        ))
+    }
+}
+
+/// Returns true if the contents of a texture with this descriptor can be copied out.
+fn is_readable<L>(desc: &wgt::TextureDescriptor<L>) -> bool {
+    desc.sample_count == 1
+        && !matches!(
+            desc.format,
+            wgt::TextureFormat::Depth24Plus | wgt::TextureFormat::Depth24PlusStencil8
+        )
+}
+
+/// Read back the contents of every `MAP_READ` buffer and every readable texture
+/// on both sides, and describe the first difference found.
+fn compare(sides: &mut [Side; 2], resources: &Resources, tolerance: u8) -> Option<String> {
+    let [ref mut left, ref mut right] = *sides;
+
+    for (&raw, desc) in resources.buffers.iter() {
+        if !desc.usage.contains(wgt::BufferUsages::MAP_READ) {
+            continue;
+        }
+        match (
+            left.read_buffer(resources, raw, desc),
+            right.read_buffer(resources, raw, desc),
+        ) {
+            (Ok(a), Ok(b)) => {
+                if let Some(diff) = first_difference(&a, &b, tolerance) {
+                    return Some(format!(
+                        "buffer {} {:?} at byte {}: {} vs {}",
+                        raw, desc.label, diff.offset, diff.left, diff.right
+                    ));
+                }
+            }
+            (a, b) => log::warn!(
+                "Unable to read buffer {}: {:?} / {:?}",
+                raw,
+                a.err(),
+                b.err()
+            ),
+        }
+    }
+
+    for (&raw, desc) in resources.textures.iter() {
+        if !is_readable(desc) {
+            log::info!("Skipping texture {} {:?}", raw, desc.label);
+            continue;
+        }
+        let info = desc.format.describe();
+        let (block_width, block_height) = info.block_dimensions;
+        for mip_level in 0..desc.mip_level_count {
+            let size = desc
+                .mip_level_size(mip_level)
+                .unwrap()
+                .physical_size(desc.format);
+            match (
+                left.read_texture(resources, raw, mip_level, size, desc.format),
+                right.read_texture(resources, raw, mip_level, size, desc.format),
+            ) {
+                (Ok(a), Ok(b)) => {
+                    if let Some(diff) = first_difference(&a, &b, tolerance) {
+                        let bytes_per_row =
+                            (size.width / block_width as u32) as usize * info.block_size as usize;
+                        let rows = (size.height / block_height as u32) as usize;
+                        let row = diff.offset / bytes_per_row;
+                        return Some(format!(
+                            "texture {} {:?} mip {} at texel ({}, {}, {}), byte {}: {} vs {}",
+                            raw,
+                            desc.label,
+                            mip_level,
+                            diff.offset % bytes_per_row / info.block_size as usize
+                                * block_width as usize,
+                            row % rows * block_height as usize,
+                            row / rows,
+                            diff.offset,
+                            diff.left,
+                            diff.right
+                        ));
+                    }
+                }
+                (a, b) => log::warn!(
+                    "Unable to read texture {} mip {}: {:?} / {:?}",
+                    raw,
+                    mip_level,
+                    a.err(),
+                    b.err()
+                ),
+            }
+        }
+    }
+
+    None
+}
+
+fn main() {
+    env_logger::init();
+
+    let mut args = std::env::args().skip(1);
+    let dir = match args.next() {
+        Some(arg) if Path::new(&arg).is_dir() => PathBuf::from(arg),
+        _ => panic!("Provide the dir path as the parameter"),
+    };
+    let left_spec = args.next().expect("Provide the first adapter");
+    let right_spec = args.next().expect("Provide the second adapter");
+    let mut tolerance = 0;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tolerance" => {
+                tolerance = args
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .expect("Provide the byte tolerance after '--tolerance'");
+            }
+            other => panic!("Unknown argument '{}'", other),
+        }
+    }
+
+    log::info!("Loading trace '{:?}'", dir);
+    let mut actions = ActionReader::open(&dir).unwrap();
+    let mut sides = match actions.next() {
+        Some(Ok(trace::Action::Init { desc, backend: _ })) => [
+            Side::new("left", &left_spec, &desc),
+            Side::new("right", &right_spec, &desc),
+        ],
+        Some(Err(e)) => panic!("Unable to read the trace: {}", e),
+        _ => panic!("Expected Action::Init"),
+    };
+
+    let mut resources = Resources::default();
+    // Action 0 is the `Init`.
+    let mut index = 1;
+    for action in actions {
+        let mut action = match action {
+            Ok(action) => action,
+            Err(e) => {
+                println!("Unable to read action {}: {}", index, e);
+                process::exit(1);
+            }
+        };
+        // Let every texture be read back for the comparison.
+        if let trace::Action::CreateTexture(_, ref mut desc) = action {
+            if is_readable(desc) {
+                desc.usage |= wgt::TextureUsages::COPY_SRC;
+            }
+        }
+        resources.observe(&action);
+        for side in sides.iter_mut() {
+            side.process(&action, &dir);
+        }
+
+        if let trace::Action::Submit(..) = action {
+            if let Some(difference) = compare(&mut sides, &resources, tolerance) {
+                println!(
+                    "Action {} differs between {} and {}: {}",
+                    index, sides[0].name, sides[1].name, difference
+                );
+                process::exit(1);
+            }
+        }
+        index += 1;
+    }
+
+    if let Some(difference) = compare(&mut sides, &resources, tolerance) {
+        println!("The end of the trace differs: {}", difference);
+        process::exit(1);
+    }
+    println!("No differences found in {} actions", index);
+}
@@ -0,0 +1,59 @@
+/*! Structured fuzzing of the validation in wgpu-core.
+
+Random, but well-formed, sequences of trace actions are replayed on the
+empty backend, which doesn't need a GPU. Validation errors are expected,
+but every panic is a bug, and is reported with the seed that reproduces it.
+!*/
+
+use player::fuzz;
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    process,
+};
+
+fn main() {
+    env_logger::init();
+
+    let mut seed = 0;
+    let mut count = 100;
+    let mut actions = 200;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| -> u64 {
+            args.next()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or_else(|| panic!("Provide a number after '{}'", name))
+        };
+        match arg.as_str() {
+            "--seed" => seed = value("--seed"),
+            "--count" => count = value("--count"),
+            "--actions" => actions = value("--actions") as usize,
+            other => panic!("Unknown argument '{}'", other),
+        }
+    }
+
+    let dir = std::env::temp_dir().join(format!("wgpu-fuzz-{}", process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    fuzz::write_files(&dir).unwrap();
+
+    let mut crashes = Vec::new();
+    for seed in seed..seed + count {
+        log::info!("Seed {}", seed);
+        match panic::catch_unwind(AssertUnwindSafe(|| fuzz::replay(seed, actions, &dir))) {
+            Ok(failures) => log::info!("{} of {} actions failed validation", failures, actions),
+            Err(_) => crashes.push(seed),
+        }
+    }
+    let _ = std::fs::remove_dir_all(&dir);
+
+    if crashes.is_empty() {
+        println!("No crashes in {} sequences of {} actions", count, actions);
+    } else {
+        println!(
+            "Crashed with seeds {:?}, reproduce with `--seed <seed> --count 1 --actions {}`",
+            crashes, actions
+        );
+        process::exit(1);
+    }
+}
@@ -1,14 +1,261 @@
 /*! This is a player for WebGPU traces.
 !*/
 
-use player::{GlobalPlay as _, IdentityPassThroughFactory};
+use player::{
+    inspect::{RawId, Resources},
+    parse_backend, set_backend, ActionReader, GlobalPlay as _, IdentityPassThroughFactory,
+};
 use wgc::{device::trace, gfx_select};
 
 use std::{
     fs,
+    io::{self, BufRead as _, Write as _},
     path::{Path, PathBuf},
 };
 
+/// When to pause the replay and wait for commands on the standard input.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Pause {
+    Never,
+    NextAction,
+    NextSubmit,
+    Frame(usize),
+}
+
+const HELP: &str = "Commands:
+    s, step                        execute the next action
+    n, submit                      execute up to and including the next submission
+    f, frame <N>                   execute until frame N is reached, N being a later frame
+    c, continue                    execute the rest of the trace
+    p, print                       print the next action
+    l, list                        list the live resources
+    b, buffer <index> <epoch> <file>
+                                   dump the contents of a buffer into a file
+    t, texture <index> <epoch> <file> [mip] [layer]
+                                   dump the tightly packed texels of a texture into a file
+    q, quit                        stop the replay
+    h, help                        print this message";
+
+/// Interactive controls for stepping through a trace and inspecting its state.
+struct Debugger {
+    pause: Pause,
+    frame: usize,
+    resources: Resources,
+}
+
+impl Debugger {
+    fn new(pause: Pause) -> Self {
+        Self {
+            pause,
+            frame: 0,
+            resources: Resources::default(),
+        }
+    }
+
+    /// Called before the next action is processed, or with `None` at the end of the trace.
+    /// Returns `false` if the user asked to stop the replay.
+    fn before(
+        &mut self,
+        action: Option<&trace::Action>,
+        global: &wgc::hub::Global<IdentityPassThroughFactory>,
+        device: wgc::id::DeviceId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> bool {
+        let paused = match self.pause {
+            Pause::Never => false,
+            Pause::NextAction => true,
+            Pause::NextSubmit => action.is_none(),
+            Pause::Frame(frame) => action.is_none() || self.frame >= frame,
+        };
+        let keep_going = !paused || self.prompt(action, global, device, comb_manager);
+        if let Some(action) = action {
+            self.resources.observe(action);
+            match *action {
+                trace::Action::Submit(..) if self.pause == Pause::NextSubmit => {
+                    self.pause = Pause::NextAction;
+                }
+                trace::Action::Present(_) => {
+                    self.frame += 1;
+                }
+                _ => {}
+            }
+        }
+        keep_going
+    }
+
+    fn prompt(
+        &mut self,
+        action: Option<&trace::Action>,
+        global: &wgc::hub::Global<IdentityPassThroughFactory>,
+        device: wgc::id::DeviceId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> bool {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            match action {
+                Some(action) => {
+                    let mut summary = format!("{:?}", action);
+                    if summary.len() > 80 {
+                        summary.truncate(77);
+                        summary.push_str("...");
+                    }
+                    print!("[frame {}] next: {}\n> ", self.frame, summary);
+                }
+                None => print!("[frame {}] end of the trace\n> ", self.frame),
+            }
+            let _ = io::stdout().flush();
+
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                _ => {
+                    self.pause = Pause::Never;
+                    return true;
+                }
+            };
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            match words.as_slice() {
+                [] => {}
+                ["s"] | ["step"] => {
+                    self.pause = Pause::NextAction;
+                    return true;
+                }
+                ["n"] | ["submit"] => {
+                    self.pause = Pause::NextSubmit;
+                    return true;
+                }
+                ["f", frame] | ["frame", frame] => match frame.parse() {
+                    Ok(frame) if frame <= self.frame => println!(
+                        "Frame {} is already reached, the replay can't go back",
+                        frame
+                    ),
+                    Ok(frame) => {
+                        self.pause = Pause::Frame(frame);
+                        return true;
+                    }
+                    Err(e) => println!("Invalid frame: {}", e),
+                },
+                ["c"] | ["continue"] => {
+                    self.pause = Pause::Never;
+                    return true;
+                }
+                ["p"] | ["print"] => println!("{:#?}", action),
+                ["l"] | ["list"] => print!("{}", self.resources),
+                ["b", rest @ ..] | ["buffer", rest @ ..] => {
+                    match self.dump_buffer(rest, global, device, comb_manager) {
+                        Ok(()) => println!("Done"),
+                        Err(e) => println!("Unable to dump the buffer: {}", e),
+                    }
+                }
+                ["t", rest @ ..] | ["texture", rest @ ..] => {
+                    match self.dump_texture(rest, global, device, comb_manager) {
+                        Ok(()) => println!("Done"),
+                        Err(e) => println!("Unable to dump the texture: {}", e),
+                    }
+                }
+                ["q"] | ["quit"] => return false,
+                _ => println!("{}", HELP),
+            }
+        }
+    }
+
+    fn dump_buffer(
+        &self,
+        args: &[&str],
+        global: &wgc::hub::Global<IdentityPassThroughFactory>,
+        device: wgc::id::DeviceId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (raw, file) = match *args {
+            [index, epoch, file] => (
+                RawId {
+                    index: index.parse()?,
+                    epoch: epoch.parse()?,
+                },
+                file,
+            ),
+            _ => return Err("expected <index> <epoch> <file>".into()),
+        };
+        let desc = self
+            .resources
+            .buffers
+            .get(&raw)
+            .ok_or("no such buffer is alive")?;
+        let staging = self.resources.staging_buffer_id(device.backend());
+        let data = gfx_select!(device => global.read_buffer(
+            device,
+            raw.to_id(device.backend()),
+            desc.usage,
+            0..desc.size,
+            staging,
+            comb_manager
+        ))?;
+        fs::write(file, data)?;
+        Ok(())
+    }
+
+    fn dump_texture(
+        &self,
+        args: &[&str],
+        global: &wgc::hub::Global<IdentityPassThroughFactory>,
+        device: wgc::id::DeviceId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let (raw, file, mip_level, layer) = match *args {
+            [index, epoch, file, ref rest @ ..] if rest.len() <= 2 => (
+                RawId {
+                    index: index.parse()?,
+                    epoch: epoch.parse()?,
+                },
+                file,
+                rest.first().map_or(Ok(0), |s| s.parse())?,
+                rest.get(1).map_or(Ok(0), |s| s.parse())?,
+            ),
+            _ => return Err("expected <index> <epoch> <file> [mip] [layer]".into()),
+        };
+        let desc = self
+            .resources
+            .textures
+            .get(&raw)
+            .ok_or("no such texture is alive")?;
+        let mut size = desc
+            .mip_level_size(mip_level)
+            .ok_or("mip level is out of range")?
+            .physical_size(desc.format);
+        let origin = match desc.dimension {
+            wgt::TextureDimension::D3 => wgt::Origin3d::ZERO,
+            wgt::TextureDimension::D1 | wgt::TextureDimension::D2 => {
+                if layer >= desc.size.depth_or_array_layers {
+                    return Err("array layer is out of range".into());
+                }
+                size.depth_or_array_layers = 1;
+                wgt::Origin3d {
+                    x: 0,
+                    y: 0,
+                    z: layer,
+                }
+            }
+        };
+        let src = wgc::command::ImageCopyTexture {
+            texture: raw.to_id(device.backend()),
+            mip_level,
+            origin,
+            aspect: wgt::TextureAspect::All,
+        };
+        let staging = self.resources.staging_buffer_id(device.backend());
+        let data = gfx_select!(device => global.read_texture(
+            device,
+            &src,
+            size,
+            desc.format,
+            staging,
+            comb_manager
+        ))?;
+        fs::write(file, data)?;
+        Ok(())
+    }
+}
+
 fn main() {
     #[cfg(feature = "winit")]
     use winit::{event_loop::EventLoop, window::WindowBuilder};
@@ -16,18 +263,38 @@ fn main() {
     env_logger::init();
 
     //TODO: setting for the backend bits
-    //TODO: setting for the target frame, or controls
 
-    let dir = match std::env::args().nth(1) {
+    let mut args = std::env::args().skip(1);
+    let dir = match args.next() {
         Some(arg) if Path::new(&arg).is_dir() => PathBuf::from(arg),
         _ => panic!("Provide the dir path as the parameter"),
     };
+    let mut pause = Pause::Never;
+    let mut backend_override = None;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--step" => pause = Pause::NextAction,
+            "--backend" => {
+                let backend = args.next().and_then(|s| parse_backend(&s)).expect(
+                    "Provide one of 'vulkan', 'metal', 'dx12', 'dx11', or 'gl' after '--backend'",
+                );
+                backend_override = Some(backend);
+            }
+            "--frame" => {
+                let frame = args
+                    .next()
+                    .and_then(|s| s.parse().ok())
+                    .expect("Provide the frame number after '--frame'");
+                pause = Pause::Frame(frame);
+            }
+            other => panic!("Unknown argument '{}'", other),
+        }
+    }
+    let mut debugger = Debugger::new(pause);
 
     log::info!("Loading trace '{:?}'", dir);
-    let file = fs::File::open(dir.join(trace::FILE_NAME)).unwrap();
-    let mut actions: Vec<trace::Action> = ron::de::from_reader(file).unwrap();
-    actions.reverse(); // allows us to pop from the top
-    log::info!("Found {} actions", actions.len());
+    let mut actions = ActionReader::open(&dir).unwrap();
+    log::info!("Reading {:?} actions", actions.format());
 
     #[cfg(feature = "winit")]
     let event_loop = {
@@ -48,8 +315,9 @@ fn main() {
     let surface =
         global.instance_create_surface(&window, wgc::id::TypedId::zip(0, 1, wgt::Backend::Empty));
 
-    let device = match actions.pop() {
-        Some(trace::Action::Init { desc, backend }) => {
+    let device = match actions.next() {
+        Some(Ok(trace::Action::Init { desc, backend })) => {
+            let backend = backend_override.unwrap_or(backend);
             log::info!("Initializing the device for backend: {:?}", backend);
             let adapter = global
                 .request_adapter(
@@ -81,6 +349,7 @@ fn main() {
             }
             id
         }
+        Some(Err(e)) => panic!("Unable to read the trace: {}", e),
         _ => panic!("Expected Action::Init"),
     };
 
@@ -89,8 +358,35 @@ fn main() {
     {
         gfx_select!(device => global.device_start_capture(device));
 
-        while let Some(action) = actions.pop() {
-            gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+        let mut finished = true;
+        for action in &mut actions {
+            let mut action = match action {
+                Ok(action) => action,
+                Err(e) => {
+                    log::error!("Unable to read the next action: {}", e);
+                    finished = false;
+                    break;
+                }
+            };
+            set_backend(&mut action, device.backend());
+            if !debugger.before(
+                Some(&action),
+                &global,
+                device,
+                &mut command_buffer_id_manager,
+            ) {
+                finished = false;
+                break;
+            }
+            gfx_select!(device => global.process(
+                device,
+                action,
+                &dir,
+                &mut command_buffer_id_manager
+            ));
+        }
+        if finished {
+            debugger.before(None, &global, device, &mut command_buffer_id_manager);
         }
 
         gfx_select!(device => global.device_stop_capture(device));
@@ -113,7 +409,29 @@ fn main() {
                     window.request_redraw();
                 }
                 Event::RedrawRequested(_) if resize_config.is_none() => loop {
-                    match actions.pop() {
+                    let mut action = match actions.next().transpose() {
+                        Ok(action) => action,
+                        Err(e) => {
+                            log::error!("Unable to read the next action: {}", e);
+                            *control_flow = ControlFlow::Exit;
+                            break;
+                        }
+                    };
+                    if let Some(ref mut action) = action {
+                        set_backend(action, device.backend());
+                    }
+                    if !done
+                        && !debugger.before(
+                            action.as_ref(),
+                            &global,
+                            device,
+                            &mut command_buffer_id_manager,
+                        )
+                    {
+                        *control_flow = ControlFlow::Exit;
+                        break;
+                    }
+                    match action {
                         Some(trace::Action::ConfigureSurface(_device_id, config)) => {
                             log::info!("Configuring the surface");
                             let current_size: (u32, u32) = window.inner_size().into();
@@ -126,7 +444,11 @@ fn main() {
                                 resize_config = Some(config);
                                 break;
                             } else {
-                                let error = gfx_select!(device => global.surface_configure(surface, device, &config));
+                                let error = gfx_select!(device => global.surface_configure(
+                                    surface,
+                                    device,
+                                    &config
+                                ));
                                 if let Some(e) = error {
                                     panic!("{:?}", e);
                                 }
@@ -139,7 +461,12 @@ fn main() {
                             break;
                         }
                         Some(action) => {
-                            gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+                            gfx_select!(device => global.process(
+                                device,
+                                action,
+                                &dir,
+                                &mut command_buffer_id_manager
+                            ));
                         }
                         None => {
                             if !done {
@@ -153,7 +480,11 @@ fn main() {
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::Resized(_) => {
                         if let Some(config) = resize_config.take() {
-                            let error = gfx_select!(device => global.surface_configure(surface, device, &config));
+                            let error = gfx_select!(device => global.surface_configure(
+                                surface,
+                                device,
+                                &config
+                            ));
                             if let Some(e) = error {
                                 panic!("{:?}", e);
                             }
@@ -0,0 +1,22 @@
+//! Comparison of the contents read back during a replay.
+
+/// First byte that differs by more than the tolerance.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Difference {
+    pub offset: usize,
+    pub left: u8,
+    pub right: u8,
+}
+
+/// Compare two equally sized contents, allowing each byte to differ by up to `tolerance`.
+pub fn first_difference(left: &[u8], right: &[u8], tolerance: u8) -> Option<Difference> {
+    assert_eq!(left.len(), right.len(), "contents have different sizes");
+    left.iter()
+        .zip(right)
+        .position(|(&l, &r)| (l as i16 - r as i16).abs() > tolerance as i16)
+        .map(|offset| Difference {
+            offset,
+            left: left[offset],
+            right: right[offset],
+        })
+}
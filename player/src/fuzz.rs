@@ -0,0 +1,1228 @@
+//! Generation of random, but well-formed, sequences of trace actions.
+//!
+//! The actions only refer to resources that were created earlier in the
+//! sequence and haven't been dropped yet, and the passes are laid out the
+//! same way `wgpu` lays them out. Everything else is random, so replaying
+//! the actions runs into validation errors all the time, but it should
+//! never panic.
+
+use crate::{GlobalPlay as _, IdentityPassThroughFactory};
+use wgc::{
+    binding_model as bm, command,
+    device::trace::{Action, Command},
+    id, pipeline, resource,
+};
+
+use std::{
+    borrow::Cow,
+    fs, io,
+    num::{NonZeroU32, NonZeroU64, NonZeroU8},
+    path::Path,
+};
+
+/// Shader files referred to by the generated actions.
+const SHADERS: &[(&str, &str)] = &[
+    (
+        "fuzz-compute.wgsl",
+        "[[block]]
+struct Data {
+    values: [[stride(4)]] array<u32>;
+};
+
+[[group(0), binding(0)]]
+var<storage, read_write> data: Data;
+
+[[stage(compute), workgroup_size(1)]]
+fn main([[builtin(global_invocation_id)]] id: vec3<u32>) {
+    data.values[id.x] = id.x;
+}
+",
+    ),
+    (
+        "fuzz-render.wgsl",
+        "[[block]]
+struct Globals {
+    color: vec4<f32>;
+};
+
+[[group(0), binding(0)]]
+var<uniform> globals: Globals;
+[[group(0), binding(1)]]
+var tex: texture_2d<f32>;
+[[group(0), binding(2)]]
+var samp: sampler;
+
+[[stage(vertex)]]
+fn vs_main([[location(0)]] position: vec4<f32>) -> [[builtin(position)]] vec4<f32> {
+    return position;
+}
+
+[[stage(fragment)]]
+fn fs_main([[builtin(position)]] coord: vec4<f32>) -> [[location(0)]] vec4<f32> {
+    return globals.color * textureSample(tex, samp, coord.xy);
+}
+",
+    ),
+];
+/// File with the contents of the buffer and texture writes.
+const DATA_FILE: &str = "fuzz-data.bin";
+const DATA_SIZE: u64 = 1024;
+
+const ENTRY_POINTS: &[&str] = &["main", "vs_main", "fs_main", "missing"];
+const BUFFER_SIZES: &[u64] = &[0, 3, 4, 16, 64, 256, 1000, 4096, 1 << 16];
+const OFFSETS: &[u64] = &[0, 1, 4, 16, 256, 4096];
+const EXTENTS: &[u32] = &[0, 1, 4, 16, 64, 100, 256];
+const FORMATS: &[wgt::TextureFormat] = &[
+    wgt::TextureFormat::R8Unorm,
+    wgt::TextureFormat::R32Float,
+    wgt::TextureFormat::Rgba8Unorm,
+    wgt::TextureFormat::Rgba8Uint,
+    wgt::TextureFormat::Bgra8UnormSrgb,
+    wgt::TextureFormat::Rgba16Float,
+    wgt::TextureFormat::Rgba32Float,
+    wgt::TextureFormat::Depth32Float,
+    wgt::TextureFormat::Depth24Plus,
+    wgt::TextureFormat::Depth24PlusStencil8,
+    wgt::TextureFormat::Bc1RgbaUnorm,
+];
+const VIEW_DIMENSIONS: &[wgt::TextureViewDimension] = &[
+    wgt::TextureViewDimension::D1,
+    wgt::TextureViewDimension::D2,
+    wgt::TextureViewDimension::D2Array,
+    wgt::TextureViewDimension::Cube,
+    wgt::TextureViewDimension::CubeArray,
+    wgt::TextureViewDimension::D3,
+];
+const ASPECTS: &[wgt::TextureAspect] = &[
+    wgt::TextureAspect::All,
+    wgt::TextureAspect::All,
+    wgt::TextureAspect::DepthOnly,
+    wgt::TextureAspect::StencilOnly,
+];
+const COMPARE_FUNCTIONS: &[wgt::CompareFunction] = &[
+    wgt::CompareFunction::Never,
+    wgt::CompareFunction::Less,
+    wgt::CompareFunction::Equal,
+    wgt::CompareFunction::Always,
+];
+
+/// Write the shaders and the data files that the generated actions refer to.
+pub fn write_files(dir: &Path) -> io::Result<()> {
+    for &(name, source) in SHADERS {
+        fs::write(dir.join(name), source)?;
+    }
+    let data = (0..DATA_SIZE).map(|i| i as u8).collect::<Vec<_>>();
+    fs::write(dir.join(DATA_FILE), data)
+}
+
+/// Replay `count` actions generated from the `seed` on a device of the empty backend,
+/// with the files written by `write_files` in `dir`.
+///
+/// Returns the number of actions that failed validation.
+pub fn replay(seed: u64, count: usize, dir: &Path) -> usize {
+    type Empty = hal::api::Empty;
+    let backend = wgt::Backend::Empty;
+    let instance = unsafe {
+        <<Empty as hal::Api>::Instance as hal::Instance<Empty>>::init(&hal::InstanceDescriptor {
+            name: "fuzz",
+            flags: hal::InstanceFlags::empty(),
+        })
+    }
+    .unwrap();
+    let exposed = unsafe { hal::Instance::enumerate_adapters(&instance) }
+        .pop()
+        .unwrap();
+    let global = unsafe {
+        wgc::hub::Global::from_hal_instance::<Empty>("fuzz", IdentityPassThroughFactory, instance)
+    };
+    let adapter =
+        unsafe { global.create_adapter_from_hal(exposed, id::TypedId::zip(0, 1, backend)) };
+    let device = id::TypedId::zip(0, 1, backend);
+    let features = global.adapter_features::<Empty>(adapter).unwrap();
+    let (_, error) = global.adapter_request_device::<Empty>(
+        adapter,
+        &wgt::DeviceDescriptor {
+            label: None,
+            features,
+            limits: wgt::Limits::default(),
+        },
+        None,
+        device,
+    );
+    if let Some(e) = error {
+        panic!("{:?}", e);
+    }
+
+    let mut comb_manager = wgc::hub::IdentityManager::default();
+    let mut generator = Generator::new(seed, backend);
+    let mut failures = 0;
+    for _ in 0..count {
+        let action = generator.next_action();
+        log::debug!("{:?}", action);
+        if let Err(e) = global.try_process::<Empty>(device, action, dir, &mut comb_manager) {
+            log::info!("{}", e);
+            failures += 1;
+        }
+    }
+    global.device_poll::<Empty>(device, true).unwrap();
+    failures
+}
+
+/// Xorshift generator, so that every sequence can be reproduced from its seed.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+
+    fn chance(&mut self, percent: u64) -> bool {
+        self.below(100) < percent
+    }
+
+    fn pick<T: Clone>(&mut self, items: &[T]) -> Option<T> {
+        if items.is_empty() {
+            None
+        } else {
+            Some(items[self.below(items.len() as u64) as usize].clone())
+        }
+    }
+
+    fn take<T>(&mut self, items: &mut Vec<T>) -> Option<T> {
+        if items.is_empty() {
+            None
+        } else {
+            Some(items.swap_remove(self.below(items.len() as u64) as usize))
+        }
+    }
+
+    fn count(&mut self, max: usize) -> usize {
+        self.below(max as u64 + 1) as usize
+    }
+
+    fn small(&mut self) -> u32 {
+        // mostly zero, sometimes out of range
+        match self.below(8) {
+            0..=4 => 0,
+            5 | 6 => 1,
+            _ => self.below(16) as u32,
+        }
+    }
+
+    fn non_zero(&mut self, max: u32) -> Option<NonZeroU32> {
+        if self.chance(50) {
+            None
+        } else {
+            NonZeroU32::new(self.below(max as u64 + 1) as u32)
+        }
+    }
+
+    fn label(&mut self) -> Option<Cow<'static, str>> {
+        if self.chance(50) {
+            None
+        } else {
+            Some(Cow::Owned(format!("fuzz{}", self.below(100))))
+        }
+    }
+}
+
+/// Resources created so far, which the following actions may refer to.
+#[derive(Default)]
+struct Live {
+    buffers: Vec<id::BufferId>,
+    textures: Vec<id::TextureId>,
+    texture_views: Vec<id::TextureViewId>,
+    samplers: Vec<id::SamplerId>,
+    bind_group_layouts: Vec<id::BindGroupLayoutId>,
+    pipeline_layouts: Vec<id::PipelineLayoutId>,
+    bind_groups: Vec<id::BindGroupId>,
+    shader_modules: Vec<id::ShaderModuleId>,
+    compute_pipelines: Vec<id::ComputePipelineId>,
+    render_pipelines: Vec<id::RenderPipelineId>,
+    render_bundles: Vec<id::RenderBundleId>,
+    query_sets: Vec<id::QuerySetId>,
+}
+
+/// Generator of an endless sequence of actions for a device on the given backend.
+pub struct Generator {
+    rng: Rng,
+    backend: wgt::Backend,
+    next_index: u32,
+    submission_index: hal::FenceValue,
+    live: Live,
+}
+
+impl Generator {
+    pub fn new(seed: u64, backend: wgt::Backend) -> Self {
+        Self {
+            rng: Rng::new(seed),
+            backend,
+            next_index: 0,
+            submission_index: 0,
+            live: Live::default(),
+        }
+    }
+
+    fn alloc<I: id::TypedId>(&mut self) -> I {
+        self.next_index += 1;
+        I::zip(self.next_index, 1, self.backend)
+    }
+
+    pub fn next_action(&mut self) -> Action<'static> {
+        loop {
+            if let Some(action) = self.try_action() {
+                return action;
+            }
+        }
+    }
+
+    /// Generate an action of a random kind, or `None` if that kind needs
+    /// resources that don't exist yet.
+    fn try_action(&mut self) -> Option<Action<'static>> {
+        let rng = &mut self.rng;
+        let live = &mut self.live;
+        Some(match rng.below(30) {
+            0..=2 => {
+                let id = self.alloc();
+                self.live.buffers.push(id);
+                Action::CreateBuffer(id, self.buffer_descriptor())
+            }
+            3 => Action::FreeBuffer(rng.pick(&live.buffers)?),
+            4 => Action::DestroyBuffer(rng.take(&mut live.buffers)?),
+            5 | 6 => {
+                let id = self.alloc();
+                self.live.textures.push(id);
+                Action::CreateTexture(id, self.texture_descriptor())
+            }
+            7 => Action::FreeTexture(rng.pick(&live.textures)?),
+            8 => Action::DestroyTexture(rng.take(&mut live.textures)?),
+            9 | 10 => {
+                let parent_id = rng.pick(&live.textures)?;
+                let id = self.alloc();
+                self.live.texture_views.push(id);
+                Action::CreateTextureView {
+                    id,
+                    parent_id,
+                    desc: self.texture_view_descriptor(),
+                }
+            }
+            11 => {
+                let id = self.alloc();
+                self.live.samplers.push(id);
+                Action::CreateSampler(id, self.sampler_descriptor())
+            }
+            12 => {
+                let id = self.alloc();
+                self.live.bind_group_layouts.push(id);
+                Action::CreateBindGroupLayout(id, self.bind_group_layout_descriptor())
+            }
+            13 => {
+                let desc = self.pipeline_layout_descriptor();
+                let id = self.alloc();
+                self.live.pipeline_layouts.push(id);
+                Action::CreatePipelineLayout(id, desc)
+            }
+            14 | 15 => {
+                let desc = self.bind_group_descriptor()?;
+                let id = self.alloc();
+                self.live.bind_groups.push(id);
+                Action::CreateBindGroup(id, desc)
+            }
+            16 => {
+                let id = self.alloc();
+                self.live.shader_modules.push(id);
+                Action::CreateShaderModule {
+                    id,
+                    desc: pipeline::ShaderModuleDescriptor {
+                        label: self.rng.label(),
+                    },
+                    data: self.rng.pick(SHADERS)?.0.to_string(),
+                }
+            }
+            17 => {
+                let stage = self.programmable_stage()?;
+                let (layout, implicit_context) = self.pipeline_layout();
+                let id = self.alloc();
+                self.live.compute_pipelines.push(id);
+                Action::CreateComputePipeline {
+                    id,
+                    desc: pipeline::ComputePipelineDescriptor {
+                        label: self.rng.label(),
+                        layout,
+                        stage,
+                    },
+                    implicit_context,
+                }
+            }
+            18 | 19 => {
+                let desc = self.render_pipeline_descriptor()?;
+                let (layout, implicit_context) = self.pipeline_layout();
+                let id = self.alloc();
+                self.live.render_pipelines.push(id);
+                Action::CreateRenderPipeline {
+                    id,
+                    desc: pipeline::RenderPipelineDescriptor { layout, ..desc },
+                    implicit_context,
+                }
+            }
+            20 => {
+                let color_formats = (0..self.rng.count(2))
+                    .filter_map(|_| self.rng.pick(FORMATS))
+                    .collect();
+                let depth_stencil = if self.rng.chance(30) {
+                    Some(wgt::RenderBundleDepthStencil {
+                        format: self.rng.pick(FORMATS)?,
+                        depth_read_only: self.rng.chance(50),
+                        stencil_read_only: self.rng.chance(50),
+                    })
+                } else {
+                    None
+                };
+                let desc = command::RenderBundleEncoderDescriptor {
+                    label: self.rng.label(),
+                    color_formats,
+                    depth_stencil,
+                    sample_count: self.rng.pick(&[1, 1, 4])?,
+                };
+                let base = self.render_pass(true);
+                let id = self.alloc();
+                self.live.render_bundles.push(id);
+                Action::CreateRenderBundle { id, desc, base }
+            }
+            21 => {
+                let ty = match self.rng.below(3) {
+                    0 => wgt::QueryType::Occlusion,
+                    1 => wgt::QueryType::Timestamp,
+                    _ => wgt::QueryType::PipelineStatistics(
+                        wgt::PipelineStatisticsTypes::from_bits_truncate(self.rng.next() as u8),
+                    ),
+                };
+                let desc = resource::QuerySetDescriptor {
+                    label: self.rng.label(),
+                    ty,
+                    count: self.rng.pick(&[0, 1, 4, 8, 10_000])?,
+                };
+                let id = self.alloc();
+                self.live.query_sets.push(id);
+                Action::CreateQuerySet { id, desc }
+            }
+            22 => match rng.below(10) {
+                0 => Action::DestroyTextureView(rng.take(&mut live.texture_views)?),
+                1 => Action::DestroySampler(rng.take(&mut live.samplers)?),
+                2 => Action::DestroyBindGroupLayout(rng.take(&mut live.bind_group_layouts)?),
+                3 => Action::DestroyPipelineLayout(rng.take(&mut live.pipeline_layouts)?),
+                4 => Action::DestroyBindGroup(rng.take(&mut live.bind_groups)?),
+                5 => Action::DestroyShaderModule(rng.take(&mut live.shader_modules)?),
+                6 => Action::DestroyComputePipeline(rng.take(&mut live.compute_pipelines)?),
+                7 => Action::DestroyRenderPipeline(rng.take(&mut live.render_pipelines)?),
+                8 => Action::DestroyRenderBundle(rng.take(&mut live.render_bundles)?),
+                _ => Action::DestroyQuerySet(rng.take(&mut live.query_sets)?),
+            },
+            23 => {
+                let id = rng.pick(&live.buffers)?;
+                let start = rng.pick(OFFSETS)?;
+                let size = rng.below(DATA_SIZE + 1);
+                Action::WriteBuffer {
+                    id,
+                    data: DATA_FILE.to_string(),
+                    range: start..start + size,
+                    queued: rng.chance(70),
+                }
+            }
+            24 => {
+                let to = self.image_copy_texture()?;
+                let layout = self.image_data_layout();
+                Action::WriteTexture {
+                    to,
+                    data: DATA_FILE.to_string(),
+                    layout,
+                    size: self.extent(),
+                }
+            }
+            _ => {
+                self.submission_index += 1;
+                let commands = (0..self.rng.count(4))
+                    .filter_map(|_| self.command())
+                    .collect();
+                Action::Submit(self.submission_index, commands)
+            }
+        })
+    }
+
+    fn buffer_descriptor(&mut self) -> resource::BufferDescriptor<'static> {
+        let rng = &mut self.rng;
+        let usage = if rng.chance(50) {
+            wgt::BufferUsages::from_bits_truncate(rng.next() as u32)
+        } else {
+            rng.pick(&[
+                wgt::BufferUsages::MAP_READ | wgt::BufferUsages::COPY_DST,
+                wgt::BufferUsages::MAP_WRITE | wgt::BufferUsages::COPY_SRC,
+                wgt::BufferUsages::COPY_SRC | wgt::BufferUsages::COPY_DST,
+                wgt::BufferUsages::VERTEX | wgt::BufferUsages::INDEX,
+                wgt::BufferUsages::UNIFORM | wgt::BufferUsages::COPY_DST,
+                wgt::BufferUsages::STORAGE | wgt::BufferUsages::INDIRECT,
+            ])
+            .unwrap()
+        };
+        wgt::BufferDescriptor {
+            label: rng.label(),
+            size: rng.pick(BUFFER_SIZES).unwrap(),
+            usage,
+            mapped_at_creation: rng.chance(20),
+        }
+    }
+
+    fn texture_descriptor(&mut self) -> resource::TextureDescriptor<'static> {
+        let rng = &mut self.rng;
+        let dimension = rng
+            .pick(&[
+                wgt::TextureDimension::D1,
+                wgt::TextureDimension::D2,
+                wgt::TextureDimension::D2,
+                wgt::TextureDimension::D3,
+            ])
+            .unwrap();
+        let size = wgt::Extent3d {
+            width: rng.pick(EXTENTS).unwrap(),
+            height: match dimension {
+                wgt::TextureDimension::D1 if rng.chance(90) => 1,
+                _ => rng.pick(EXTENTS).unwrap(),
+            },
+            depth_or_array_layers: rng.pick(&[1, 1, 2, 6]).unwrap(),
+        };
+        wgt::TextureDescriptor {
+            label: rng.label(),
+            size,
+            mip_level_count: rng.pick(&[1, 1, 2, 4, 10]).unwrap(),
+            sample_count: rng.pick(&[1, 1, 1, 3, 4]).unwrap(),
+            dimension,
+            format: rng.pick(FORMATS).unwrap(),
+            usage: wgt::TextureUsages::from_bits_truncate(rng.next() as u32),
+        }
+    }
+
+    fn texture_view_descriptor(&mut self) -> resource::TextureViewDescriptor<'static> {
+        let rng = &mut self.rng;
+        resource::TextureViewDescriptor {
+            label: rng.label(),
+            format: if rng.chance(80) {
+                None
+            } else {
+                rng.pick(FORMATS)
+            },
+            dimension: if rng.chance(50) {
+                None
+            } else {
+                rng.pick(VIEW_DIMENSIONS)
+            },
+            range: self.subresource_range(),
+        }
+    }
+
+    fn subresource_range(&mut self) -> wgt::ImageSubresourceRange {
+        let rng = &mut self.rng;
+        wgt::ImageSubresourceRange {
+            aspect: rng.pick(ASPECTS).unwrap(),
+            base_mip_level: rng.small(),
+            mip_level_count: rng.non_zero(4),
+            base_array_layer: rng.small(),
+            array_layer_count: rng.non_zero(6),
+        }
+    }
+
+    fn sampler_descriptor(&mut self) -> resource::SamplerDescriptor<'static> {
+        let rng = &mut self.rng;
+        let address_modes = [
+            wgt::AddressMode::ClampToEdge,
+            wgt::AddressMode::Repeat,
+            wgt::AddressMode::MirrorRepeat,
+            wgt::AddressMode::ClampToBorder,
+        ];
+        let filters = [wgt::FilterMode::Nearest, wgt::FilterMode::Linear];
+        resource::SamplerDescriptor {
+            label: rng.label(),
+            address_modes: [
+                rng.pick(&address_modes).unwrap(),
+                rng.pick(&address_modes).unwrap(),
+                rng.pick(&address_modes).unwrap(),
+            ],
+            mag_filter: rng.pick(&filters).unwrap(),
+            min_filter: rng.pick(&filters).unwrap(),
+            mipmap_filter: rng.pick(&filters).unwrap(),
+            lod_min_clamp: rng.below(4) as f32,
+            lod_max_clamp: rng.below(40) as f32,
+            compare: if rng.chance(70) {
+                None
+            } else {
+                rng.pick(COMPARE_FUNCTIONS)
+            },
+            anisotropy_clamp: if rng.chance(70) {
+                None
+            } else {
+                NonZeroU8::new(rng.pick(&[1, 2, 3, 4, 16]).unwrap())
+            },
+            border_color: if rng.chance(70) {
+                None
+            } else {
+                rng.pick(&[
+                    wgt::SamplerBorderColor::TransparentBlack,
+                    wgt::SamplerBorderColor::OpaqueBlack,
+                    wgt::SamplerBorderColor::OpaqueWhite,
+                ])
+            },
+        }
+    }
+
+    fn binding_type(&mut self) -> wgt::BindingType {
+        let rng = &mut self.rng;
+        match rng.below(4) {
+            0 => wgt::BindingType::Buffer {
+                ty: match rng.below(3) {
+                    0 => wgt::BufferBindingType::Uniform,
+                    1 => wgt::BufferBindingType::Storage { read_only: true },
+                    _ => wgt::BufferBindingType::Storage { read_only: false },
+                },
+                has_dynamic_offset: rng.chance(30),
+                min_binding_size: if rng.chance(50) {
+                    None
+                } else {
+                    NonZeroU64::new(rng.pick(&[4, 16, 64]).unwrap())
+                },
+            },
+            1 => wgt::BindingType::Sampler {
+                filtering: rng.chance(50),
+                comparison: rng.chance(30),
+            },
+            2 => wgt::BindingType::Texture {
+                sample_type: rng
+                    .pick(&[
+                        wgt::TextureSampleType::Float { filterable: true },
+                        wgt::TextureSampleType::Float { filterable: false },
+                        wgt::TextureSampleType::Depth,
+                        wgt::TextureSampleType::Sint,
+                        wgt::TextureSampleType::Uint,
+                    ])
+                    .unwrap(),
+                view_dimension: rng.pick(VIEW_DIMENSIONS).unwrap(),
+                multisampled: rng.chance(20),
+            },
+            _ => wgt::BindingType::StorageTexture {
+                access: rng
+                    .pick(&[
+                        wgt::StorageTextureAccess::ReadOnly,
+                        wgt::StorageTextureAccess::WriteOnly,
+                        wgt::StorageTextureAccess::ReadWrite,
+                    ])
+                    .unwrap(),
+                format: rng.pick(FORMATS).unwrap(),
+                view_dimension: rng.pick(VIEW_DIMENSIONS).unwrap(),
+            },
+        }
+    }
+
+    fn bind_group_layout_descriptor(&mut self) -> bm::BindGroupLayoutDescriptor<'static> {
+        let entries = (0..self.rng.count(4))
+            .map(|_| wgt::BindGroupLayoutEntry {
+                binding: self.rng.below(4) as u32,
+                visibility: wgt::ShaderStages::from_bits_truncate(self.rng.next() as u32),
+                ty: self.binding_type(),
+                count: if self.rng.chance(90) {
+                    None
+                } else {
+                    NonZeroU32::new(2)
+                },
+            })
+            .collect::<Vec<_>>();
+        bm::BindGroupLayoutDescriptor {
+            label: self.rng.label(),
+            entries: Cow::Owned(entries),
+        }
+    }
+
+    fn pipeline_layout_descriptor(&mut self) -> bm::PipelineLayoutDescriptor<'static> {
+        let rng = &mut self.rng;
+        let live = &self.live;
+        let bind_group_layouts = (0..rng.count(hal::MAX_BIND_GROUPS + 1))
+            .filter_map(|_| rng.pick(&live.bind_group_layouts))
+            .collect::<Vec<_>>();
+        let push_constant_ranges = if rng.chance(80) {
+            Vec::new()
+        } else {
+            vec![wgt::PushConstantRange {
+                stages: wgt::ShaderStages::from_bits_truncate(rng.next() as u32),
+                range: 0..rng.pick(&[4, 16, 256]).unwrap(),
+            }]
+        };
+        bm::PipelineLayoutDescriptor {
+            label: rng.label(),
+            bind_group_layouts: Cow::Owned(bind_group_layouts),
+            push_constant_ranges: Cow::Owned(push_constant_ranges),
+        }
+    }
+
+    fn buffer_binding(&mut self) -> Option<bm::BufferBinding> {
+        Some(bm::BufferBinding {
+            buffer_id: self.rng.pick(&self.live.buffers)?,
+            offset: self.rng.pick(OFFSETS)?,
+            size: if self.rng.chance(50) {
+                None
+            } else {
+                wgt::BufferSize::new(self.rng.pick(BUFFER_SIZES)?)
+            },
+        })
+    }
+
+    fn bind_group_descriptor(&mut self) -> Option<bm::BindGroupDescriptor<'static>> {
+        let layout = self.rng.pick(&self.live.bind_group_layouts)?;
+        let mut entries = Vec::new();
+        for _ in 0..self.rng.count(4) {
+            let resource = match self.rng.below(5) {
+                0 => bm::BindingResource::Buffer(self.buffer_binding()?),
+                1 => bm::BindingResource::BufferArray(Cow::Owned(
+                    (0..2).filter_map(|_| self.buffer_binding()).collect(),
+                )),
+                2 => bm::BindingResource::Sampler(self.rng.pick(&self.live.samplers)?),
+                3 => bm::BindingResource::TextureView(self.rng.pick(&self.live.texture_views)?),
+                _ => bm::BindingResource::TextureViewArray(Cow::Owned(
+                    (0..2)
+                        .filter_map(|_| self.rng.pick(&self.live.texture_views))
+                        .collect(),
+                )),
+            };
+            entries.push(bm::BindGroupEntry {
+                binding: self.rng.below(4) as u32,
+                resource,
+            });
+        }
+        Some(bm::BindGroupDescriptor {
+            label: self.rng.label(),
+            layout,
+            entries: Cow::Owned(entries),
+        })
+    }
+
+    fn programmable_stage(&mut self) -> Option<pipeline::ProgrammableStageDescriptor<'static>> {
+        Some(pipeline::ProgrammableStageDescriptor {
+            module: self.rng.pick(&self.live.shader_modules)?,
+            entry_point: Cow::Borrowed(self.rng.pick(ENTRY_POINTS)?),
+        })
+    }
+
+    /// Pick an explicit pipeline layout, or the IDs for deriving one.
+    fn pipeline_layout(
+        &mut self,
+    ) -> (
+        Option<id::PipelineLayoutId>,
+        Option<wgc::device::ImplicitPipelineContext>,
+    ) {
+        if self.rng.chance(50) {
+            if let Some(layout) = self.rng.pick(&self.live.pipeline_layouts) {
+                return (Some(layout), None);
+            }
+        }
+        let root_id = self.alloc();
+        self.live.pipeline_layouts.push(root_id);
+        let group_ids = (0..hal::MAX_BIND_GROUPS)
+            .map(|_| {
+                let id = self.alloc();
+                self.live.bind_group_layouts.push(id);
+                id
+            })
+            .collect();
+        (
+            None,
+            Some(wgc::device::ImplicitPipelineContext { root_id, group_ids }),
+        )
+    }
+
+    fn render_pipeline_descriptor(
+        &mut self,
+    ) -> Option<pipeline::RenderPipelineDescriptor<'static>> {
+        let vertex_stage = self.programmable_stage()?;
+        let fragment_stage = self.programmable_stage()?;
+        let rng = &mut self.rng;
+        let buffers = (0..rng.count(2))
+            .map(|_| pipeline::VertexBufferLayout {
+                array_stride: rng.pick(&[0, 4, 16, 20]).unwrap(),
+                step_mode: rng
+                    .pick(&[wgt::VertexStepMode::Vertex, wgt::VertexStepMode::Instance])
+                    .unwrap(),
+                attributes: Cow::Owned(
+                    (0..rng.count(2))
+                        .map(|_| wgt::VertexAttribute {
+                            format: rng
+                                .pick(&[
+                                    wgt::VertexFormat::Float32x4,
+                                    wgt::VertexFormat::Float32x2,
+                                    wgt::VertexFormat::Uint32,
+                                ])
+                                .unwrap(),
+                            offset: rng.pick(&[0, 4, 16]).unwrap(),
+                            shader_location: rng.below(3) as u32,
+                        })
+                        .collect(),
+                ),
+            })
+            .collect::<Vec<_>>();
+        let primitive = wgt::PrimitiveState {
+            topology: rng
+                .pick(&[
+                    wgt::PrimitiveTopology::PointList,
+                    wgt::PrimitiveTopology::LineStrip,
+                    wgt::PrimitiveTopology::TriangleList,
+                    wgt::PrimitiveTopology::TriangleStrip,
+                ])
+                .unwrap(),
+            strip_index_format: if rng.chance(70) {
+                None
+            } else {
+                rng.pick(&[wgt::IndexFormat::Uint16, wgt::IndexFormat::Uint32])
+            },
+            cull_mode: if rng.chance(50) {
+                None
+            } else {
+                rng.pick(&[wgt::Face::Front, wgt::Face::Back])
+            },
+            ..Default::default()
+        };
+        let depth_stencil = if rng.chance(70) {
+            None
+        } else {
+            Some(wgt::DepthStencilState {
+                format: rng.pick(FORMATS).unwrap(),
+                depth_write_enabled: rng.chance(50),
+                depth_compare: rng.pick(COMPARE_FUNCTIONS).unwrap(),
+                stencil: wgt::StencilState::default(),
+                bias: wgt::DepthBiasState::default(),
+            })
+        };
+        let fragment = if rng.chance(20) {
+            None
+        } else {
+            Some(pipeline::FragmentState {
+                stage: fragment_stage,
+                targets: Cow::Owned(
+                    (0..rng.count(2))
+                        .map(|_| wgt::ColorTargetState {
+                            format: rng.pick(FORMATS).unwrap(),
+                            blend: if rng.chance(50) {
+                                None
+                            } else {
+                                Some(wgt::BlendState::ALPHA_BLENDING)
+                            },
+                            write_mask: wgt::ColorWrites::ALL,
+                        })
+                        .collect(),
+                ),
+            })
+        };
+        Some(pipeline::RenderPipelineDescriptor {
+            label: rng.label(),
+            layout: None,
+            vertex: pipeline::VertexState {
+                stage: vertex_stage,
+                buffers: Cow::Owned(buffers),
+            },
+            primitive,
+            depth_stencil,
+            multisample: wgt::MultisampleState {
+                count: rng.pick(&[1, 1, 2, 4]).unwrap(),
+                ..Default::default()
+            },
+            fragment,
+        })
+    }
+
+    fn extent(&mut self) -> wgt::Extent3d {
+        let rng = &mut self.rng;
+        wgt::Extent3d {
+            width: rng.pick(EXTENTS).unwrap(),
+            height: rng.pick(EXTENTS).unwrap(),
+            depth_or_array_layers: rng.pick(&[0, 1, 1, 2]).unwrap(),
+        }
+    }
+
+    fn image_data_layout(&mut self) -> wgt::ImageDataLayout {
+        let rng = &mut self.rng;
+        wgt::ImageDataLayout {
+            offset: rng.pick(OFFSETS).unwrap(),
+            bytes_per_row: if rng.chance(30) {
+                None
+            } else {
+                NonZeroU32::new(rng.pick(&[4, 64, 256, 512, 1024]).unwrap())
+            },
+            rows_per_image: rng.non_zero(256),
+        }
+    }
+
+    fn image_copy_texture(&mut self) -> Option<command::ImageCopyTexture> {
+        let rng = &mut self.rng;
+        Some(command::ImageCopyTexture {
+            texture: rng.pick(&self.live.textures)?,
+            mip_level: rng.small(),
+            origin: wgt::Origin3d {
+                x: rng.pick(&[0, 0, 4, 16]).unwrap(),
+                y: rng.pick(&[0, 0, 4, 16]).unwrap(),
+                z: rng.small(),
+            },
+            aspect: rng.pick(ASPECTS).unwrap(),
+        })
+    }
+
+    fn image_copy_buffer(&mut self) -> Option<command::ImageCopyBuffer> {
+        Some(command::ImageCopyBuffer {
+            buffer: self.rng.pick(&self.live.buffers)?,
+            layout: self.image_data_layout(),
+        })
+    }
+
+    fn command(&mut self) -> Option<Command> {
+        Some(match self.rng.below(10) {
+            0 => Command::CopyBufferToBuffer {
+                src: self.rng.pick(&self.live.buffers)?,
+                src_offset: self.rng.pick(OFFSETS)?,
+                dst: self.rng.pick(&self.live.buffers)?,
+                dst_offset: self.rng.pick(OFFSETS)?,
+                size: self.rng.pick(BUFFER_SIZES)?,
+            },
+            1 => Command::CopyBufferToTexture {
+                src: self.image_copy_buffer()?,
+                dst: self.image_copy_texture()?,
+                size: self.extent(),
+            },
+            2 => Command::CopyTextureToBuffer {
+                src: self.image_copy_texture()?,
+                dst: self.image_copy_buffer()?,
+                size: self.extent(),
+            },
+            3 => Command::CopyTextureToTexture {
+                src: self.image_copy_texture()?,
+                dst: self.image_copy_texture()?,
+                size: self.extent(),
+            },
+            4 => Command::ClearBuffer {
+                dst: self.rng.pick(&self.live.buffers)?,
+                offset: self.rng.pick(OFFSETS)?,
+                size: if self.rng.chance(50) {
+                    None
+                } else {
+                    wgt::BufferSize::new(self.rng.pick(BUFFER_SIZES)?)
+                },
+            },
+            5 => Command::ClearImage {
+                dst: self.rng.pick(&self.live.textures)?,
+                subresource_range: self.subresource_range(),
+            },
+            6 => Command::WriteTimestamp {
+                query_set_id: self.rng.pick(&self.live.query_sets)?,
+                query_index: self.rng.small(),
+            },
+            7 => Command::ResolveQuerySet {
+                query_set_id: self.rng.pick(&self.live.query_sets)?,
+                start_query: self.rng.small(),
+                query_count: self.rng.small(),
+                destination: self.rng.pick(&self.live.buffers)?,
+                destination_offset: self.rng.pick(OFFSETS)?,
+            },
+            8 => Command::RunComputePass {
+                base: self.compute_pass(),
+            },
+            _ => {
+                let target_colors = (0..self.rng.count(2))
+                    .filter_map(|_| {
+                        Some(command::RenderPassColorAttachment {
+                            view: self.rng.pick(&self.live.texture_views)?,
+                            resolve_target: if self.rng.chance(80) {
+                                None
+                            } else {
+                                self.rng.pick(&self.live.texture_views)
+                            },
+                            channel: self.pass_channel(wgt::Color::BLACK),
+                        })
+                    })
+                    .collect();
+                let target_depth_stencil = if self.rng.chance(70) {
+                    None
+                } else {
+                    Some(command::RenderPassDepthStencilAttachment {
+                        view: self.rng.pick(&self.live.texture_views)?,
+                        depth: self.pass_channel(1.0),
+                        stencil: self.pass_channel(0),
+                    })
+                };
+                Command::RunRenderPass {
+                    base: self.render_pass(false),
+                    target_colors,
+                    target_depth_stencil,
+                }
+            }
+        })
+    }
+
+    fn pass_channel<V>(&mut self, clear_value: V) -> command::PassChannel<V> {
+        command::PassChannel {
+            load_op: self
+                .rng
+                .pick(&[command::LoadOp::Clear, command::LoadOp::Load])
+                .unwrap(),
+            store_op: self
+                .rng
+                .pick(&[command::StoreOp::Discard, command::StoreOp::Store])
+                .unwrap(),
+            clear_value,
+            read_only: self.rng.chance(30),
+        }
+    }
+
+    /// Append the dynamic offsets of a `SetBindGroup` to the pass.
+    fn dynamic_offsets<C>(&mut self, base: &mut command::BasePass<C>) -> u8 {
+        let count = self.rng.count(2);
+        for _ in 0..count {
+            let offset = self.rng.pick(&[0, 4, 256]).unwrap();
+            base.dynamic_offsets.push(offset);
+        }
+        count as u8
+    }
+
+    /// Append the label of a debug marker to the pass, returning its length.
+    fn debug_string<C>(&mut self, base: &mut command::BasePass<C>) -> usize {
+        let string = format!("marker{}", self.rng.below(10));
+        base.string_data.extend_from_slice(string.as_bytes());
+        string.len()
+    }
+
+    /// Append push constant values to the pass.
+    fn push_constants<C>(&mut self, base: &mut command::BasePass<C>) -> (u32, u32, u32) {
+        let offset = self.rng.pick(&[0, 4, 64]).unwrap();
+        let size_bytes = self.rng.pick(&[4, 16, 256]).unwrap();
+        let values_offset = base.push_constant_data.len() as u32;
+        base.push_constant_data
+            .extend((0..size_bytes / 4).map(|_| self.rng.next() as u32));
+        (offset, size_bytes, values_offset)
+    }
+
+    fn compute_pass(&mut self) -> command::BasePass<command::ComputeCommand> {
+        use command::ComputeCommand as Cc;
+        let mut base = command::BasePass {
+            label: None,
+            commands: Vec::new(),
+            dynamic_offsets: Vec::new(),
+            string_data: Vec::new(),
+            push_constant_data: Vec::new(),
+        };
+        for _ in 0..self.rng.count(8) {
+            let command = match self.rng.below(11) {
+                0 => match self.rng.pick(&self.live.bind_groups) {
+                    Some(bind_group_id) => Cc::SetBindGroup {
+                        index: self.rng.below(5) as u8,
+                        num_dynamic_offsets: self.dynamic_offsets(&mut base),
+                        bind_group_id,
+                    },
+                    None => continue,
+                },
+                1 | 2 => match self.rng.pick(&self.live.compute_pipelines) {
+                    Some(pipeline) => Cc::SetPipeline(pipeline),
+                    None => continue,
+                },
+                3 => {
+                    let (offset, size_bytes, values_offset) = self.push_constants(&mut base);
+                    Cc::SetPushConstant {
+                        offset,
+                        size_bytes,
+                        values_offset,
+                    }
+                }
+                4 | 5 => Cc::Dispatch([self.rng.small(), self.rng.small(), self.rng.small()]),
+                6 => match self.rng.pick(&self.live.buffers) {
+                    Some(buffer_id) => Cc::DispatchIndirect {
+                        buffer_id,
+                        offset: self.rng.pick(OFFSETS).unwrap(),
+                    },
+                    None => continue,
+                },
+                7 => Cc::PushDebugGroup {
+                    color: 0,
+                    len: self.debug_string(&mut base),
+                },
+                8 => Cc::PopDebugGroup,
+                9 => Cc::InsertDebugMarker {
+                    color: 0,
+                    len: self.debug_string(&mut base),
+                },
+                _ => match self.rng.pick(&self.live.query_sets) {
+                    Some(query_set_id) => match self.rng.below(3) {
+                        0 => Cc::WriteTimestamp {
+                            query_set_id,
+                            query_index: self.rng.small(),
+                        },
+                        1 => Cc::BeginPipelineStatisticsQuery {
+                            query_set_id,
+                            query_index: self.rng.small(),
+                        },
+                        _ => Cc::EndPipelineStatisticsQuery,
+                    },
+                    None => continue,
+                },
+            };
+            base.commands.push(command);
+        }
+        base
+    }
+
+    /// Generate the commands of a render pass, or of a render bundle,
+    /// which only supports a subset of them.
+    fn render_pass(&mut self, bundle: bool) -> command::BasePass<command::RenderCommand> {
+        use command::RenderCommand as Rc;
+        let mut base = command::BasePass {
+            label: None,
+            commands: Vec::new(),
+            dynamic_offsets: Vec::new(),
+            string_data: Vec::new(),
+            push_constant_data: Vec::new(),
+        };
+        let kinds = if bundle { 8 } else { 16 };
+        for _ in 0..self.rng.count(10) {
+            let command = match self.rng.below(kinds) {
+                0 => match self.rng.pick(&self.live.bind_groups) {
+                    Some(bind_group_id) => Rc::SetBindGroup {
+                        index: self.rng.below(5) as u8,
+                        num_dynamic_offsets: self.dynamic_offsets(&mut base),
+                        bind_group_id,
+                    },
+                    None => continue,
+                },
+                1 => match self.rng.pick(&self.live.render_pipelines) {
+                    Some(pipeline) => Rc::SetPipeline(pipeline),
+                    None => continue,
+                },
+                2 => match self.rng.pick(&self.live.buffers) {
+                    Some(buffer_id) => Rc::SetIndexBuffer {
+                        buffer_id,
+                        index_format: self
+                            .rng
+                            .pick(&[wgt::IndexFormat::Uint16, wgt::IndexFormat::Uint32])
+                            .unwrap(),
+                        offset: self.rng.pick(OFFSETS).unwrap(),
+                        size: wgt::BufferSize::new(self.rng.pick(BUFFER_SIZES).unwrap()),
+                    },
+                    None => continue,
+                },
+                3 => match self.rng.pick(&self.live.buffers) {
+                    Some(buffer_id) => Rc::SetVertexBuffer {
+                        slot: self.rng.below(3) as u32,
+                        buffer_id,
+                        offset: self.rng.pick(OFFSETS).unwrap(),
+                        size: wgt::BufferSize::new(self.rng.pick(BUFFER_SIZES).unwrap()),
+                    },
+                    None => continue,
+                },
+                4 => {
+                    let (offset, size_bytes, values_offset) = self.push_constants(&mut base);
+                    Rc::SetPushConstant {
+                        stages: wgt::ShaderStages::from_bits_truncate(self.rng.next() as u32),
+                        offset,
+                        size_bytes,
+                        values_offset: Some(values_offset),
+                    }
+                }
+                5 => Rc::Draw {
+                    vertex_count: self.rng.small(),
+                    instance_count: self.rng.small(),
+                    first_vertex: self.rng.small(),
+                    first_instance: self.rng.small(),
+                },
+                6 => Rc::DrawIndexed {
+                    index_count: self.rng.small(),
+                    instance_count: self.rng.small(),
+                    first_index: self.rng.small(),
+                    base_vertex: self.rng.small() as i32 - 1,
+                    first_instance: self.rng.small(),
+                },
+                7 => match self.rng.pick(&self.live.buffers) {
+                    Some(buffer_id) => Rc::MultiDrawIndirect {
+                        buffer_id,
+                        offset: self.rng.pick(OFFSETS).unwrap(),
+                        count: if bundle { None } else { self.rng.non_zero(3) },
+                        indexed: self.rng.chance(50),
+                    },
+                    None => continue,
+                },
+                // not supported in render bundles
+                8 => match (
+                    self.rng.pick(&self.live.buffers),
+                    self.rng.pick(&self.live.buffers),
+                ) {
+                    (Some(buffer_id), Some(count_buffer_id)) => Rc::MultiDrawIndirectCount {
+                        buffer_id,
+                        offset: self.rng.pick(OFFSETS).unwrap(),
+                        count_buffer_id,
+                        count_buffer_offset: self.rng.pick(OFFSETS).unwrap(),
+                        max_count: self.rng.small(),
+                        indexed: self.rng.chance(50),
+                    },
+                    _ => continue,
+                },
+                9 => Rc::SetBlendConstant(wgt::Color::WHITE),
+                10 => Rc::SetStencilReference(self.rng.small()),
+                11 => Rc::SetViewport {
+                    rect: command::Rect {
+                        x: 0.0,
+                        y: 0.0,
+                        w: self.rng.pick(EXTENTS).unwrap() as f32,
+                        h: self.rng.pick(EXTENTS).unwrap() as f32,
+                    },
+                    depth_min: self.rng.below(2) as f32,
+                    depth_max: self.rng.below(2) as f32,
+                },
+                12 => Rc::SetScissor(command::Rect {
+                    x: self.rng.small(),
+                    y: self.rng.small(),
+                    w: self.rng.pick(EXTENTS).unwrap(),
+                    h: self.rng.pick(EXTENTS).unwrap(),
+                }),
+                13 => match self.rng.below(3) {
+                    0 => Rc::PushDebugGroup {
+                        color: 0,
+                        len: self.debug_string(&mut base),
+                    },
+                    1 => Rc::PopDebugGroup,
+                    _ => Rc::InsertDebugMarker {
+                        color: 0,
+                        len: self.debug_string(&mut base),
+                    },
+                },
+                14 => match self.rng.pick(&self.live.query_sets) {
+                    Some(query_set_id) => match self.rng.below(3) {
+                        0 => Rc::WriteTimestamp {
+                            query_set_id,
+                            query_index: self.rng.small(),
+                        },
+                        1 => Rc::BeginPipelineStatisticsQuery {
+                            query_set_id,
+                            query_index: self.rng.small(),
+                        },
+                        _ => Rc::EndPipelineStatisticsQuery,
+                    },
+                    None => continue,
+                },
+                _ => match self.rng.pick(&self.live.render_bundles) {
+                    Some(bundle) => Rc::ExecuteBundle(bundle),
+                    None => continue,
+                },
+            };
+            base.commands.push(command);
+        }
+        base
+    }
+}
@@ -0,0 +1,159 @@
+//! Book-keeping of the live resources during a replay.
+//!
+//! The player only sees the trace actions, so it has to remember the
+//! descriptors of everything created so far in order to list the resources
+//! or read them back at an arbitrary point of the trace.
+
+use wgc::{device::trace::Action, id};
+
+use std::{collections::BTreeMap, fmt};
+
+type Label = Option<String>;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum ResourceKind {
+    TextureView,
+    Sampler,
+    BindGroupLayout,
+    PipelineLayout,
+    BindGroup,
+    ShaderModule,
+    ComputePipeline,
+    RenderPipeline,
+    RenderBundle,
+    QuerySet,
+}
+
+/// Epoch of the readback buffers, the last one that fits in an ID.
+///
+/// Epochs are only bumped when an index is reused, so traces stay far below it.
+const STAGING_EPOCH: u32 = (1 << 29) - 1;
+
+/// Index and epoch of a resource, without the backend bits.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct RawId {
+    pub index: u32,
+    pub epoch: u32,
+}
+
+impl RawId {
+    fn from_id<I: id::TypedId>(id: I) -> Self {
+        let (index, epoch, _) = id.unzip();
+        Self { index, epoch }
+    }
+
+    pub fn to_id<I: id::TypedId>(self, backend: wgt::Backend) -> I {
+        I::zip(self.index, self.epoch, backend)
+    }
+}
+
+impl fmt::Display for RawId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "({}, {})", self.index, self.epoch)
+    }
+}
+
+/// Live resources of a device being replayed, with their descriptors.
+#[derive(Debug, Default)]
+pub struct Resources {
+    pub buffers: BTreeMap<RawId, wgt::BufferDescriptor<Label>>,
+    pub textures: BTreeMap<RawId, wgt::TextureDescriptor<Label>>,
+    /// Everything that can't be read back, described by its `Debug` output.
+    pub others: BTreeMap<(ResourceKind, RawId), String>,
+    next_buffer_index: u32,
+}
+
+impl Resources {
+    /// Update the set of live resources with an action that is about to be processed.
+    pub fn observe(&mut self, action: &Action) {
+        let to_owned = |label: &wgc::Label| label.as_ref().map(|l| l.to_string());
+        match *action {
+            Action::CreateBuffer(id, ref desc) => {
+                let raw = RawId::from_id(id);
+                self.next_buffer_index = self.next_buffer_index.max(raw.index + 1);
+                self.buffers.insert(raw, desc.map_label(to_owned));
+            }
+            Action::DestroyBuffer(id) => {
+                self.buffers.remove(&RawId::from_id(id));
+            }
+            Action::CreateTexture(id, ref desc) => {
+                self.textures
+                    .insert(RawId::from_id(id), desc.map_label(to_owned));
+            }
+            Action::DestroyTexture(id) => {
+                self.textures.remove(&RawId::from_id(id));
+            }
+            Action::CreateTextureView { id, ref desc, .. } => {
+                self.insert(ResourceKind::TextureView, id, desc)
+            }
+            Action::DestroyTextureView(id) => self.remove(ResourceKind::TextureView, id),
+            Action::CreateSampler(id, ref desc) => self.insert(ResourceKind::Sampler, id, desc),
+            Action::DestroySampler(id) => self.remove(ResourceKind::Sampler, id),
+            Action::CreateBindGroupLayout(id, ref desc) => {
+                self.insert(ResourceKind::BindGroupLayout, id, desc)
+            }
+            Action::DestroyBindGroupLayout(id) => self.remove(ResourceKind::BindGroupLayout, id),
+            Action::CreatePipelineLayout(id, ref desc) => {
+                self.insert(ResourceKind::PipelineLayout, id, desc)
+            }
+            Action::DestroyPipelineLayout(id) => self.remove(ResourceKind::PipelineLayout, id),
+            Action::CreateBindGroup(id, ref desc) => self.insert(ResourceKind::BindGroup, id, desc),
+            Action::DestroyBindGroup(id) => self.remove(ResourceKind::BindGroup, id),
+            Action::CreateShaderModule { id, ref desc, .. } => {
+                self.insert(ResourceKind::ShaderModule, id, desc)
+            }
+            Action::DestroyShaderModule(id) => self.remove(ResourceKind::ShaderModule, id),
+            Action::CreateComputePipeline { id, ref desc, .. } => {
+                self.insert(ResourceKind::ComputePipeline, id, desc)
+            }
+            Action::DestroyComputePipeline(id) => self.remove(ResourceKind::ComputePipeline, id),
+            Action::CreateRenderPipeline { id, ref desc, .. } => {
+                self.insert(ResourceKind::RenderPipeline, id, desc)
+            }
+            Action::DestroyRenderPipeline(id) => self.remove(ResourceKind::RenderPipeline, id),
+            Action::CreateRenderBundle { id, ref desc, .. } => {
+                self.insert(ResourceKind::RenderBundle, id, desc)
+            }
+            Action::DestroyRenderBundle(id) => self.remove(ResourceKind::RenderBundle, id),
+            Action::CreateQuerySet { id, ref desc } => {
+                self.insert(ResourceKind::QuerySet, id, desc)
+            }
+            Action::DestroyQuerySet(id) => self.remove(ResourceKind::QuerySet, id),
+            _ => {}
+        }
+    }
+
+    fn insert<I: id::TypedId>(&mut self, kind: ResourceKind, id: I, desc: &dyn fmt::Debug) {
+        self.others
+            .insert((kind, RawId::from_id(id)), format!("{:?}", desc));
+    }
+
+    fn remove<I: id::TypedId>(&mut self, kind: ResourceKind, id: I) {
+        self.others.remove(&(kind, RawId::from_id(id)));
+    }
+
+    /// Returns a buffer ID that is not used by the trace, suitable for
+    /// temporary readback buffers.
+    ///
+    /// Its index isn't used by any live buffer of the trace, and its epoch
+    /// is one the trace never gets to, so it can't be confused with a buffer
+    /// that the trace creates later on at the same index.
+    pub fn staging_buffer_id(&self, backend: wgt::Backend) -> id::BufferId {
+        id::TypedId::zip(self.next_buffer_index, STAGING_EPOCH, backend)
+    }
+}
+
+impl fmt::Display for Resources {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (id, desc) in self.buffers.iter() {
+            writeln!(f, "Buffer {} {:?}", id, desc)?;
+        }
+        for (id, desc) in self.textures.iter() {
+            writeln!(f, "Texture {} {:?}", id, desc)?;
+        }
+        for (&(kind, id), desc) in self.others.iter() {
+            writeln!(f, "{:?} {} {}", kind, id, desc)?;
+        }
+        Ok(())
+    }
+}
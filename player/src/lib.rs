@@ -8,7 +8,15 @@
 
 use wgc::device::trace;
 
-use std::{borrow::Cow, fmt::Debug, fs, marker::PhantomData, path::Path};
+use std::{
+    borrow::Cow, error::Error, fmt::Debug, fs, io, marker::PhantomData, num::NonZeroU32,
+    ops::Range, path::Path, slice,
+};
+
+pub mod compare;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+pub mod inspect;
 
 #[derive(Debug)]
 pub struct IdentityPassThrough<I>(PhantomData<I>);
@@ -34,19 +42,579 @@ impl<I: Clone + Debug + wgc::id::TypedId> wgc::hub::IdentityHandlerFactory<I>
 }
 impl wgc::hub::GlobalIdentityHandlerFactory for IdentityPassThroughFactory {}
 
+/// Source of the actions of a trace, in either of the trace formats.
+///
+/// RON traces are loaded entirely up front, while binary traces
+/// are decoded one action at a time.
+pub enum ActionReader {
+    Ron(std::vec::IntoIter<trace::Action<'static>>),
+    Binary(trace::BinaryReader<io::BufReader<fs::File>>),
+}
+
+impl ActionReader {
+    /// Open the trace in the given directory, preferring the binary format if both are present.
+    pub fn open(dir: &Path) -> Result<Self, Box<dyn Error>> {
+        let binary_path = dir.join(trace::BINARY_FILE_NAME);
+        if binary_path.exists() {
+            let file = io::BufReader::new(fs::File::open(binary_path)?);
+            Ok(Self::Binary(trace::BinaryReader::new(file)?))
+        } else {
+            let file = fs::File::open(dir.join(trace::FILE_NAME))?;
+            let actions: Vec<trace::Action> = ron::de::from_reader(file)?;
+            Ok(Self::Ron(actions.into_iter()))
+        }
+    }
+
+    pub fn format(&self) -> wgc::device::TraceFormat {
+        match *self {
+            Self::Ron(_) => wgc::device::TraceFormat::Ron,
+            Self::Binary(_) => wgc::device::TraceFormat::Binary,
+        }
+    }
+}
+
+/// Yields an error if a binary trace is truncated or corrupted.
+impl Iterator for ActionReader {
+    type Item = Result<trace::Action<'static>, trace::BinaryTraceError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match *self {
+            Self::Ron(ref mut iter) => iter.next().map(Ok),
+            Self::Binary(ref mut reader) => reader.next(),
+        }
+    }
+}
+
+/// Parse the name of a backend, as given on the command line.
+pub fn parse_backend(name: &str) -> Option<wgt::Backend> {
+    Some(match name {
+        "vulkan" => wgt::Backend::Vulkan,
+        "metal" => wgt::Backend::Metal,
+        "dx12" => wgt::Backend::Dx12,
+        "dx11" => wgt::Backend::Dx11,
+        "gl" => wgt::Backend::Gl,
+        _ => return None,
+    })
+}
+
+/// Replace the backend of all the IDs an action refers to,
+/// so that a trace can be replayed on a different backend.
+pub fn set_backend(action: &mut trace::Action, backend: wgt::Backend) {
+    use trace::Action as A;
+
+    match *action {
+        A::Init { .. } => {}
+        A::ConfigureSurface(ref mut id, _) | A::Present(ref mut id) => rebase(id, backend),
+        A::CreateBuffer(ref mut id, _)
+        | A::FreeBuffer(ref mut id)
+        | A::DestroyBuffer(ref mut id)
+        | A::WriteBuffer { ref mut id, .. } => rebase(id, backend),
+        A::CreateTexture(ref mut id, _)
+        | A::FreeTexture(ref mut id)
+        | A::DestroyTexture(ref mut id) => rebase(id, backend),
+        A::CreateTextureView {
+            ref mut id,
+            ref mut parent_id,
+            ..
+        } => {
+            rebase(id, backend);
+            rebase(parent_id, backend);
+        }
+        A::DestroyTextureView(ref mut id) => rebase(id, backend),
+        A::CreateSampler(ref mut id, _) | A::DestroySampler(ref mut id) => rebase(id, backend),
+        A::GetSurfaceTexture {
+            ref mut id,
+            ref mut parent_id,
+        } => {
+            rebase(id, backend);
+            rebase(parent_id, backend);
+        }
+        A::CreateBindGroupLayout(ref mut id, _) | A::DestroyBindGroupLayout(ref mut id) => {
+            rebase(id, backend)
+        }
+        A::CreatePipelineLayout(ref mut id, ref mut desc) => {
+            rebase(id, backend);
+            for layout in desc.bind_group_layouts.to_mut().iter_mut() {
+                rebase(layout, backend);
+            }
+        }
+        A::DestroyPipelineLayout(ref mut id) => rebase(id, backend),
+        A::CreateBindGroup(ref mut id, ref mut desc) => {
+            rebase(id, backend);
+            rebase(&mut desc.layout, backend);
+            for entry in desc.entries.to_mut().iter_mut() {
+                match entry.resource {
+                    wgc::binding_model::BindingResource::Buffer(ref mut binding) => {
+                        rebase(&mut binding.buffer_id, backend)
+                    }
+                    wgc::binding_model::BindingResource::BufferArray(ref mut bindings) => {
+                        for binding in bindings.to_mut().iter_mut() {
+                            rebase(&mut binding.buffer_id, backend);
+                        }
+                    }
+                    wgc::binding_model::BindingResource::Sampler(ref mut id) => rebase(id, backend),
+                    wgc::binding_model::BindingResource::TextureView(ref mut id) => {
+                        rebase(id, backend)
+                    }
+                    wgc::binding_model::BindingResource::TextureViewArray(ref mut ids) => {
+                        for id in ids.to_mut().iter_mut() {
+                            rebase(id, backend);
+                        }
+                    }
+                }
+            }
+        }
+        A::DestroyBindGroup(ref mut id) => rebase(id, backend),
+        A::CreateShaderModule { ref mut id, .. } | A::DestroyShaderModule(ref mut id) => {
+            rebase(id, backend)
+        }
+        A::CreateComputePipeline {
+            ref mut id,
+            ref mut desc,
+            ref mut implicit_context,
+        } => {
+            rebase(id, backend);
+            if let Some(ref mut layout) = desc.layout {
+                rebase(layout, backend);
+            }
+            rebase(&mut desc.stage.module, backend);
+            rebase_implicit(implicit_context, backend);
+        }
+        A::DestroyComputePipeline(ref mut id) => rebase(id, backend),
+        A::CreateRenderPipeline {
+            ref mut id,
+            ref mut desc,
+            ref mut implicit_context,
+        } => {
+            rebase(id, backend);
+            if let Some(ref mut layout) = desc.layout {
+                rebase(layout, backend);
+            }
+            rebase(&mut desc.vertex.stage.module, backend);
+            if let Some(ref mut fragment) = desc.fragment {
+                rebase(&mut fragment.stage.module, backend);
+            }
+            rebase_implicit(implicit_context, backend);
+        }
+        A::DestroyRenderPipeline(ref mut id) => rebase(id, backend),
+        A::CreateRenderBundle {
+            ref mut id,
+            ref mut base,
+            ..
+        } => {
+            rebase(id, backend);
+            for command in base.commands.iter_mut() {
+                rebase_render_command(command, backend);
+            }
+        }
+        A::DestroyRenderBundle(ref mut id) => rebase(id, backend),
+        A::CreateQuerySet { ref mut id, .. } | A::DestroyQuerySet(ref mut id) => {
+            rebase(id, backend)
+        }
+        A::WriteTexture { ref mut to, .. } => rebase(&mut to.texture, backend),
+        A::Submit(_, ref mut commands) => {
+            for command in commands.iter_mut() {
+                rebase_command(command, backend);
+            }
+        }
+    }
+}
+
+fn rebase<T>(id: &mut wgc::id::Id<T>, backend: wgt::Backend) {
+    use wgc::id::TypedId as _;
+    let (index, epoch, _) = id.unzip();
+    *id = wgc::id::Id::zip(index, epoch, backend);
+}
+
+fn rebase_implicit(
+    context: &mut Option<wgc::device::ImplicitPipelineContext>,
+    backend: wgt::Backend,
+) {
+    if let Some(ref mut context) = *context {
+        rebase(&mut context.root_id, backend);
+        for id in context.group_ids.iter_mut() {
+            rebase(id, backend);
+        }
+    }
+}
+
+fn rebase_command(command: &mut trace::Command, backend: wgt::Backend) {
+    use trace::Command as C;
+
+    match *command {
+        C::CopyBufferToBuffer {
+            ref mut src,
+            ref mut dst,
+            ..
+        } => {
+            rebase(src, backend);
+            rebase(dst, backend);
+        }
+        C::CopyBufferToTexture {
+            ref mut src,
+            ref mut dst,
+            ..
+        } => {
+            rebase(&mut src.buffer, backend);
+            rebase(&mut dst.texture, backend);
+        }
+        C::CopyTextureToBuffer {
+            ref mut src,
+            ref mut dst,
+            ..
+        } => {
+            rebase(&mut src.texture, backend);
+            rebase(&mut dst.buffer, backend);
+        }
+        C::CopyTextureToTexture {
+            ref mut src,
+            ref mut dst,
+            ..
+        } => {
+            rebase(&mut src.texture, backend);
+            rebase(&mut dst.texture, backend);
+        }
+        C::ClearBuffer { ref mut dst, .. } => rebase(dst, backend),
+        C::ClearImage { ref mut dst, .. } => rebase(dst, backend),
+        C::WriteTimestamp {
+            ref mut query_set_id,
+            ..
+        } => rebase(query_set_id, backend),
+        C::ResolveQuerySet {
+            ref mut query_set_id,
+            ref mut destination,
+            ..
+        } => {
+            rebase(query_set_id, backend);
+            rebase(destination, backend);
+        }
+        C::RunComputePass { ref mut base } => {
+            for command in base.commands.iter_mut() {
+                rebase_compute_command(command, backend);
+            }
+        }
+        C::RunRenderPass {
+            ref mut base,
+            ref mut target_colors,
+            ref mut target_depth_stencil,
+        } => {
+            for command in base.commands.iter_mut() {
+                rebase_render_command(command, backend);
+            }
+            for attachment in target_colors.iter_mut() {
+                rebase(&mut attachment.view, backend);
+                if let Some(ref mut resolve_target) = attachment.resolve_target {
+                    rebase(resolve_target, backend);
+                }
+            }
+            if let Some(ref mut attachment) = *target_depth_stencil {
+                rebase(&mut attachment.view, backend);
+            }
+        }
+    }
+}
+
+fn rebase_compute_command(command: &mut wgc::command::ComputeCommand, backend: wgt::Backend) {
+    use wgc::command::ComputeCommand as C;
+
+    match *command {
+        C::SetBindGroup {
+            ref mut bind_group_id,
+            ..
+        } => rebase(bind_group_id, backend),
+        C::SetPipeline(ref mut id) => rebase(id, backend),
+        C::DispatchIndirect {
+            ref mut buffer_id, ..
+        } => rebase(buffer_id, backend),
+        C::WriteTimestamp {
+            ref mut query_set_id,
+            ..
+        }
+        | C::BeginPipelineStatisticsQuery {
+            ref mut query_set_id,
+            ..
+        } => rebase(query_set_id, backend),
+        C::SetPushConstant { .. }
+        | C::Dispatch(_)
+        | C::PushDebugGroup { .. }
+        | C::PopDebugGroup
+        | C::InsertDebugMarker { .. }
+        | C::EndPipelineStatisticsQuery => {}
+    }
+}
+
+fn rebase_render_command(command: &mut wgc::command::RenderCommand, backend: wgt::Backend) {
+    use wgc::command::RenderCommand as C;
+
+    match *command {
+        C::SetBindGroup {
+            ref mut bind_group_id,
+            ..
+        } => rebase(bind_group_id, backend),
+        C::SetPipeline(ref mut id) => rebase(id, backend),
+        C::SetIndexBuffer {
+            ref mut buffer_id, ..
+        }
+        | C::SetVertexBuffer {
+            ref mut buffer_id, ..
+        }
+        | C::MultiDrawIndirect {
+            ref mut buffer_id, ..
+        } => rebase(buffer_id, backend),
+        C::MultiDrawIndirectCount {
+            ref mut buffer_id,
+            ref mut count_buffer_id,
+            ..
+        } => {
+            rebase(buffer_id, backend);
+            rebase(count_buffer_id, backend);
+        }
+        C::WriteTimestamp {
+            ref mut query_set_id,
+            ..
+        }
+        | C::BeginPipelineStatisticsQuery {
+            ref mut query_set_id,
+            ..
+        } => rebase(query_set_id, backend),
+        C::ExecuteBundle(ref mut id) => rebase(id, backend),
+        C::SetBlendConstant(_)
+        | C::SetStencilReference(_)
+        | C::SetViewport { .. }
+        | C::SetScissor(_)
+        | C::SetPushConstant { .. }
+        | C::Draw { .. }
+        | C::DrawIndexed { .. }
+        | C::PushDebugGroup { .. }
+        | C::PopDebugGroup
+        | C::InsertDebugMarker { .. }
+        | C::EndPipelineStatisticsQuery => {}
+    }
+}
+
 pub trait GlobalPlay {
+    /// Record the commands and finish the encoder.
+    ///
+    /// If any of the commands fails, the encoder is dropped.
     fn encode_commands<A: wgc::hub::HalApi>(
         &self,
         encoder: wgc::id::CommandEncoderId,
         commands: Vec<trace::Command>,
-    ) -> wgc::id::CommandBufferId;
+    ) -> Result<wgc::id::CommandBufferId, Box<dyn Error>>;
+    /// Execute an action, returning the first error it runs into.
+    fn try_process<A: wgc::hub::HalApi>(
+        &self,
+        device: wgc::id::DeviceId,
+        action: trace::Action,
+        dir: &Path,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> Result<(), Box<dyn Error>>;
+    /// Execute an action, panicking if it fails.
     fn process<A: wgc::hub::HalApi>(
         &self,
         device: wgc::id::DeviceId,
         action: trace::Action,
         dir: &Path,
         comb_manager: &mut wgc::hub::IdentityManager,
+    ) {
+        if let Err(e) = self.try_process::<A>(device, action, dir, comb_manager) {
+            panic!("{:?}", e);
+        }
+    }
+    /// Read back a range of a buffer at the current point of the replay.
+    ///
+    /// Buffers with `MAP_READ` usage are mapped directly, others are copied
+    /// into a temporary buffer with the `staging` ID, which is released before returning.
+    fn read_buffer<A: wgc::hub::HalApi>(
+        &self,
+        device: wgc::id::DeviceId,
+        buffer: wgc::id::BufferId,
+        usage: wgt::BufferUsages,
+        range: Range<wgt::BufferAddress>,
+        staging: wgc::id::BufferId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> Result<Vec<u8>, Box<dyn Error>>;
+    /// Read back a region of a texture at the current point of the replay.
+    ///
+    /// The `size` has to be a multiple of the format block dimensions.
+    /// The texture is copied into a temporary buffer with the `staging` ID,
+    /// and the returned rows are tightly packed, without the
+    /// `COPY_BYTES_PER_ROW_ALIGNMENT` padding.
+    /// Both aspects of a depth-stencil format are read with
+    /// `TextureAspect::All`, the depth followed by the stencil.
+    fn read_texture<A: wgc::hub::HalApi>(
+        &self,
+        device: wgc::id::DeviceId,
+        src: &wgc::command::ImageCopyTexture,
+        size: wgt::Extent3d,
+        format: wgt::TextureFormat,
+        staging: wgc::id::BufferId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> Result<Vec<u8>, Box<dyn Error>>;
+}
+
+extern "C" fn readback_callback(status: wgc::resource::BufferMapAsyncStatus, user_data: *mut u8) {
+    let success = user_data as *mut bool;
+    unsafe {
+        *success = matches!(status, wgc::resource::BufferMapAsyncStatus::Success);
+    }
+}
+
+/// Map a `MAP_READ` buffer, copy out the given range, and unmap it again.
+fn read_mapped<A: wgc::hub::HalApi>(
+    global: &wgc::hub::Global<IdentityPassThroughFactory>,
+    device: wgc::id::DeviceId,
+    buffer: wgc::id::BufferId,
+    range: Range<wgt::BufferAddress>,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let start = range.start - range.start % wgt::MAP_ALIGNMENT;
+    let end = wgt::math::align_to(range.end, wgt::COPY_BUFFER_ALIGNMENT);
+    let mut success = false;
+    global.buffer_map_async::<A>(
+        buffer,
+        start..end,
+        wgc::resource::BufferMapOperation {
+            host: wgc::device::HostMap::Read,
+            callback: readback_callback,
+            user_data: &mut success as *mut bool as *mut u8,
+        },
+    )?;
+    global.device_poll::<A>(device, true)?;
+    if !success {
+        return Err(format!("Unable to map buffer {:?}", buffer).into());
+    }
+
+    let (ptr, size) = global.buffer_get_mapped_range::<A>(buffer, start, Some(end - start))?;
+    let contents = unsafe { slice::from_raw_parts(ptr, size as usize) };
+    let data = contents[(range.start - start) as usize..(range.end - start) as usize].to_vec();
+    global.buffer_unmap::<A>(buffer)?;
+    Ok(data)
+}
+
+/// Create a readable staging buffer, record a copy into it, and read it back.
+fn read_staged<A: wgc::hub::HalApi>(
+    global: &wgc::hub::Global<IdentityPassThroughFactory>,
+    device: wgc::id::DeviceId,
+    staging: wgc::id::BufferId,
+    size: wgt::BufferAddress,
+    comb_manager: &mut wgc::hub::IdentityManager,
+    copy: impl FnOnce(wgc::id::CommandEncoderId) -> Result<(), wgc::command::CopyError>,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    global.device_maintain_ids::<A>(device)?;
+    let (_, error) = global.device_create_buffer::<A>(
+        device,
+        &wgt::BufferDescriptor {
+            label: Some(Cow::Borrowed("readback")),
+            size,
+            usage: wgt::BufferUsages::MAP_READ | wgt::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        },
+        staging,
+    );
+    let result = match error {
+        Some(e) => Err(e.into()),
+        None => encode_readback::<A>(global, device, staging, size, comb_manager, copy),
+    };
+    global.buffer_drop::<A>(staging, true);
+    global.device_maintain_ids::<A>(device)?;
+    result
+}
+
+fn encode_readback<A: wgc::hub::HalApi>(
+    global: &wgc::hub::Global<IdentityPassThroughFactory>,
+    device: wgc::id::DeviceId,
+    staging: wgc::id::BufferId,
+    size: wgt::BufferAddress,
+    comb_manager: &mut wgc::hub::IdentityManager,
+    copy: impl FnOnce(wgc::id::CommandEncoderId) -> Result<(), wgc::command::CopyError>,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (encoder, error) = global.device_create_command_encoder::<A>(
+        device,
+        &wgt::CommandEncoderDescriptor { label: None },
+        comb_manager.alloc(device.backend()),
     );
+    if let Some(e) = error {
+        return Err(e.into());
+    }
+    if let Err(e) = copy(encoder) {
+        global.command_encoder_drop::<A>(encoder);
+        return Err(e.into());
+    }
+    let (cmdbuf, error) =
+        global.command_encoder_finish::<A>(encoder, &wgt::CommandBufferDescriptor { label: None });
+    if let Some(e) = error {
+        global.command_buffer_drop::<A>(cmdbuf);
+        return Err(e.into());
+    }
+    global.queue_submit::<A>(device, &[cmdbuf])?;
+    read_mapped::<A>(global, device, staging, 0..size)
+}
+
+fn encode_command<A: wgc::hub::HalApi>(
+    global: &wgc::hub::Global<IdentityPassThroughFactory>,
+    encoder: wgc::id::CommandEncoderId,
+    command: trace::Command,
+) -> Result<(), Box<dyn Error>> {
+    match command {
+        trace::Command::CopyBufferToBuffer {
+            src,
+            src_offset,
+            dst,
+            dst_offset,
+            size,
+        } => global.command_encoder_copy_buffer_to_buffer::<A>(
+            encoder, src, src_offset, dst, dst_offset, size,
+        )?,
+        trace::Command::CopyBufferToTexture { src, dst, size } => {
+            global.command_encoder_copy_buffer_to_texture::<A>(encoder, &src, &dst, &size)?
+        }
+        trace::Command::CopyTextureToBuffer { src, dst, size } => {
+            global.command_encoder_copy_texture_to_buffer::<A>(encoder, &src, &dst, &size)?
+        }
+        trace::Command::CopyTextureToTexture { src, dst, size } => {
+            global.command_encoder_copy_texture_to_texture::<A>(encoder, &src, &dst, &size)?
+        }
+        trace::Command::ClearBuffer { dst, offset, size } => {
+            global.command_encoder_clear_buffer::<A>(encoder, dst, offset, size)?
+        }
+        trace::Command::ClearImage {
+            dst,
+            subresource_range,
+        } => global.command_encoder_clear_image::<A>(encoder, dst, &subresource_range)?,
+        trace::Command::WriteTimestamp {
+            query_set_id,
+            query_index,
+        } => global.command_encoder_write_timestamp::<A>(encoder, query_set_id, query_index)?,
+        trace::Command::ResolveQuerySet {
+            query_set_id,
+            start_query,
+            query_count,
+            destination,
+            destination_offset,
+        } => global.command_encoder_resolve_query_set::<A>(
+            encoder,
+            query_set_id,
+            start_query,
+            query_count,
+            destination,
+            destination_offset,
+        )?,
+        trace::Command::RunComputePass { base } => {
+            global.command_encoder_run_compute_pass_impl::<A>(encoder, base.as_ref())?;
+        }
+        trace::Command::RunRenderPass {
+            base,
+            target_colors,
+            target_depth_stencil,
+        } => {
+            global.command_encoder_run_render_pass_impl::<A>(
+                encoder,
+                base.as_ref(),
+                &target_colors,
+                target_depth_stencil.as_ref(),
+            )?;
+        }
+    }
+    Ok(())
 }
 
 impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
@@ -54,94 +622,29 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
         &self,
         encoder: wgc::id::CommandEncoderId,
         commands: Vec<trace::Command>,
-    ) -> wgc::id::CommandBufferId {
+    ) -> Result<wgc::id::CommandBufferId, Box<dyn Error>> {
         for command in commands {
-            match command {
-                trace::Command::CopyBufferToBuffer {
-                    src,
-                    src_offset,
-                    dst,
-                    dst_offset,
-                    size,
-                } => self
-                    .command_encoder_copy_buffer_to_buffer::<A>(
-                        encoder, src, src_offset, dst, dst_offset, size,
-                    )
-                    .unwrap(),
-                trace::Command::CopyBufferToTexture { src, dst, size } => self
-                    .command_encoder_copy_buffer_to_texture::<A>(encoder, &src, &dst, &size)
-                    .unwrap(),
-                trace::Command::CopyTextureToBuffer { src, dst, size } => self
-                    .command_encoder_copy_texture_to_buffer::<A>(encoder, &src, &dst, &size)
-                    .unwrap(),
-                trace::Command::CopyTextureToTexture { src, dst, size } => self
-                    .command_encoder_copy_texture_to_texture::<A>(encoder, &src, &dst, &size)
-                    .unwrap(),
-                trace::Command::ClearBuffer { dst, offset, size } => self
-                    .command_encoder_clear_buffer::<A>(encoder, dst, offset, size)
-                    .unwrap(),
-                trace::Command::ClearImage {
-                    dst,
-                    subresource_range,
-                } => self
-                    .command_encoder_clear_image::<A>(encoder, dst, &subresource_range)
-                    .unwrap(),
-                trace::Command::WriteTimestamp {
-                    query_set_id,
-                    query_index,
-                } => self
-                    .command_encoder_write_timestamp::<A>(encoder, query_set_id, query_index)
-                    .unwrap(),
-                trace::Command::ResolveQuerySet {
-                    query_set_id,
-                    start_query,
-                    query_count,
-                    destination,
-                    destination_offset,
-                } => self
-                    .command_encoder_resolve_query_set::<A>(
-                        encoder,
-                        query_set_id,
-                        start_query,
-                        query_count,
-                        destination,
-                        destination_offset,
-                    )
-                    .unwrap(),
-                trace::Command::RunComputePass { base } => {
-                    self.command_encoder_run_compute_pass_impl::<A>(encoder, base.as_ref())
-                        .unwrap();
-                }
-                trace::Command::RunRenderPass {
-                    base,
-                    target_colors,
-                    target_depth_stencil,
-                } => {
-                    self.command_encoder_run_render_pass_impl::<A>(
-                        encoder,
-                        base.as_ref(),
-                        &target_colors,
-                        target_depth_stencil.as_ref(),
-                    )
-                    .unwrap();
-                }
+            if let Err(e) = encode_command::<A>(self, encoder, command) {
+                self.command_encoder_drop::<A>(encoder);
+                return Err(e);
             }
         }
         let (cmd_buf, error) = self
             .command_encoder_finish::<A>(encoder, &wgt::CommandBufferDescriptor { label: None });
         if let Some(e) = error {
-            panic!("{:?}", e);
+            self.command_buffer_drop::<A>(cmd_buf);
+            return Err(e.into());
         }
-        cmd_buf
+        Ok(cmd_buf)
     }
 
-    fn process<A: wgc::hub::HalApi>(
+    fn try_process<A: wgc::hub::HalApi>(
         &self,
         device: wgc::id::DeviceId,
         action: trace::Action,
         dir: &Path,
         comb_manager: &mut wgc::hub::IdentityManager,
-    ) {
+    ) -> Result<(), Box<dyn Error>> {
         use wgc::device::trace::Action;
         log::info!("action {:?}", action);
         //TODO: find a way to force ID perishing without excessive `maintain()` calls.
@@ -153,27 +656,27 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 panic!("Unexpected Surface action: winit feature is not enabled")
             }
             Action::CreateBuffer(id, desc) => {
-                self.device_maintain_ids::<A>(device).unwrap();
+                self.device_maintain_ids::<A>(device)?;
                 let (_, error) = self.device_create_buffer::<A>(device, &desc, id);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::FreeBuffer(id) => {
-                self.buffer_destroy::<A>(id).unwrap();
+                self.buffer_destroy::<A>(id)?;
             }
             Action::DestroyBuffer(id) => {
                 self.buffer_drop::<A>(id, true);
             }
             Action::CreateTexture(id, desc) => {
-                self.device_maintain_ids::<A>(device).unwrap();
+                self.device_maintain_ids::<A>(device)?;
                 let (_, error) = self.device_create_texture::<A>(device, &desc, id);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::FreeTexture(id) => {
-                self.texture_destroy::<A>(id).unwrap();
+                self.texture_destroy::<A>(id)?;
             }
             Action::DestroyTexture(id) => {
                 self.texture_drop::<A>(id, true);
@@ -183,56 +686,58 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 parent_id,
                 desc,
             } => {
-                self.device_maintain_ids::<A>(device).unwrap();
+                self.device_maintain_ids::<A>(device)?;
                 let (_, error) = self.texture_create_view::<A>(parent_id, &desc, id);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::DestroyTextureView(id) => {
-                self.texture_view_drop::<A>(id, true).unwrap();
+                self.texture_view_drop::<A>(id, true)?;
             }
             Action::CreateSampler(id, desc) => {
-                self.device_maintain_ids::<A>(device).unwrap();
+                self.device_maintain_ids::<A>(device)?;
                 let (_, error) = self.device_create_sampler::<A>(device, &desc, id);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::DestroySampler(id) => {
                 self.sampler_drop::<A>(id);
             }
             Action::GetSurfaceTexture { id, parent_id } => {
-                self.device_maintain_ids::<A>(device).unwrap();
-                self.surface_get_current_texture::<A>(parent_id, id)
-                    .unwrap()
-                    .texture_id
-                    .unwrap();
+                self.device_maintain_ids::<A>(device)?;
+                let output = self.surface_get_current_texture::<A>(parent_id, id)?;
+                if output.texture_id.is_none() {
+                    return Err(
+                        format!("Unable to get the surface texture: {:?}", output.status).into(),
+                    );
+                }
             }
             Action::CreateBindGroupLayout(id, desc) => {
                 let (_, error) = self.device_create_bind_group_layout::<A>(device, &desc, id);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::DestroyBindGroupLayout(id) => {
                 self.bind_group_layout_drop::<A>(id);
             }
             Action::CreatePipelineLayout(id, desc) => {
-                self.device_maintain_ids::<A>(device).unwrap();
+                self.device_maintain_ids::<A>(device)?;
                 let (_, error) = self.device_create_pipeline_layout::<A>(device, &desc, id);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::DestroyPipelineLayout(id) => {
                 self.pipeline_layout_drop::<A>(id);
             }
             Action::CreateBindGroup(id, desc) => {
-                self.device_maintain_ids::<A>(device).unwrap();
+                self.device_maintain_ids::<A>(device)?;
                 let (_, error) = self.device_create_bind_group::<A>(device, &desc, id);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::DestroyBindGroup(id) => {
@@ -240,18 +745,18 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
             }
             Action::CreateShaderModule { id, desc, data } => {
                 log::info!("Creating shader from {}", data);
-                let code = fs::read_to_string(dir.join(&data)).unwrap();
+                let code = fs::read_to_string(dir.join(&data))?;
                 let source = if data.ends_with(".wgsl") {
                     wgc::pipeline::ShaderModuleSource::Wgsl(Cow::Owned(code))
                 } else if data.ends_with(".ron") {
-                    let module = ron::de::from_str(&code).unwrap();
+                    let module = ron::de::from_str(&code)?;
                     wgc::pipeline::ShaderModuleSource::Naga(module)
                 } else {
                     panic!("Unknown shader {}", data);
                 };
                 let (_, error) = self.device_create_shader_module::<A>(device, &desc, source, id);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::DestroyShaderModule(id) => {
@@ -262,7 +767,7 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 desc,
                 implicit_context,
             } => {
-                self.device_maintain_ids::<A>(device).unwrap();
+                self.device_maintain_ids::<A>(device)?;
                 let implicit_ids =
                     implicit_context
                         .as_ref()
@@ -273,7 +778,7 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 let (_, error) =
                     self.device_create_compute_pipeline::<A>(device, &desc, id, implicit_ids);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::DestroyComputePipeline(id) => {
@@ -284,7 +789,7 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 desc,
                 implicit_context,
             } => {
-                self.device_maintain_ids::<A>(device).unwrap();
+                self.device_maintain_ids::<A>(device)?;
                 let implicit_ids =
                     implicit_context
                         .as_ref()
@@ -295,32 +800,31 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 let (_, error) =
                     self.device_create_render_pipeline::<A>(device, &desc, id, implicit_ids);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::DestroyRenderPipeline(id) => {
                 self.render_pipeline_drop::<A>(id);
             }
             Action::CreateRenderBundle { id, desc, base } => {
-                let bundle =
-                    wgc::command::RenderBundleEncoder::new(&desc, device, Some(base)).unwrap();
+                let bundle = wgc::command::RenderBundleEncoder::new(&desc, device, Some(base))?;
                 let (_, error) = self.render_bundle_encoder_finish::<A>(
                     bundle,
                     &wgt::RenderBundleDescriptor { label: desc.label },
                     id,
                 );
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::DestroyRenderBundle(id) => {
                 self.render_bundle_drop::<A>(id);
             }
             Action::CreateQuerySet { id, desc } => {
-                self.device_maintain_ids::<A>(device).unwrap();
+                self.device_maintain_ids::<A>(device)?;
                 let (_, error) = self.device_create_query_set::<A>(device, &desc, id);
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
             }
             Action::DestroyQuerySet(id) => {
@@ -332,15 +836,13 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 range,
                 queued,
             } => {
-                let bin = std::fs::read(dir.join(data)).unwrap();
+                let bin = std::fs::read(dir.join(data))?;
                 let size = (range.end - range.start) as usize;
                 if queued {
-                    self.queue_write_buffer::<A>(device, id, range.start, &bin)
-                        .unwrap();
+                    self.queue_write_buffer::<A>(device, id, range.start, &bin)?;
                 } else {
-                    self.device_wait_for_buffer::<A>(device, id).unwrap();
-                    self.device_set_buffer_sub_data::<A>(device, id, range.start, &bin[..size])
-                        .unwrap();
+                    self.device_wait_for_buffer::<A>(device, id)?;
+                    self.device_set_buffer_sub_data::<A>(device, id, range.start, &bin[..size])?;
                 }
             }
             Action::WriteTexture {
@@ -349,12 +851,11 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                 layout,
                 size,
             } => {
-                let bin = std::fs::read(dir.join(data)).unwrap();
-                self.queue_write_texture::<A>(device, &to, &bin, &layout, &size)
-                    .unwrap();
+                let bin = std::fs::read(dir.join(data))?;
+                self.queue_write_texture::<A>(device, &to, &bin, &layout, &size)?;
             }
             Action::Submit(_index, ref commands) if commands.is_empty() => {
-                self.queue_submit::<A>(device, &[]).unwrap();
+                self.queue_submit::<A>(device, &[])?;
             }
             Action::Submit(_index, commands) => {
                 let (encoder, error) = self.device_create_command_encoder::<A>(
@@ -363,11 +864,106 @@ impl GlobalPlay for wgc::hub::Global<IdentityPassThroughFactory> {
                     comb_manager.alloc(device.backend()),
                 );
                 if let Some(e) = error {
-                    panic!("{:?}", e);
+                    return Err(e.into());
                 }
-                let cmdbuf = self.encode_commands::<A>(encoder, commands);
-                self.queue_submit::<A>(device, &[cmdbuf]).unwrap();
+                let cmdbuf = self.encode_commands::<A>(encoder, commands)?;
+                self.queue_submit::<A>(device, &[cmdbuf])?;
             }
         }
+        Ok(())
+    }
+
+    fn read_buffer<A: wgc::hub::HalApi>(
+        &self,
+        device: wgc::id::DeviceId,
+        buffer: wgc::id::BufferId,
+        usage: wgt::BufferUsages,
+        range: Range<wgt::BufferAddress>,
+        staging: wgc::id::BufferId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        if usage.contains(wgt::BufferUsages::MAP_READ) {
+            return read_mapped::<A>(self, device, buffer, range);
+        }
+
+        let start = range.start - range.start % wgt::COPY_BUFFER_ALIGNMENT;
+        let size = wgt::math::align_to(range.end, wgt::COPY_BUFFER_ALIGNMENT) - start;
+        let data = read_staged::<A>(self, device, staging, size, comb_manager, |encoder| {
+            self.command_encoder_copy_buffer_to_buffer::<A>(
+                encoder, buffer, start, staging, 0, size,
+            )
+        })?;
+        let offset = (range.start - start) as usize;
+        Ok(data[offset..offset + (range.end - range.start) as usize].to_vec())
+    }
+
+    fn read_texture<A: wgc::hub::HalApi>(
+        &self,
+        device: wgc::id::DeviceId,
+        src: &wgc::command::ImageCopyTexture,
+        size: wgt::Extent3d,
+        format: wgt::TextureFormat,
+        staging: wgc::id::BufferId,
+        comb_manager: &mut wgc::hub::IdentityManager,
+    ) -> Result<Vec<u8>, Box<dyn Error>> {
+        // A copy can only select one aspect of a depth-stencil format.
+        let aspects = hal::FormatAspects::from(format) & hal::FormatAspects::from(src.aspect);
+        if aspects == hal::FormatAspects::DEPTH | hal::FormatAspects::STENCIL {
+            let mut data = Vec::new();
+            for &aspect in &[
+                wgt::TextureAspect::DepthOnly,
+                wgt::TextureAspect::StencilOnly,
+            ] {
+                let src = wgc::command::ImageCopyTexture { aspect, ..*src };
+                data.extend(self.read_texture::<A>(
+                    device,
+                    &src,
+                    size,
+                    format,
+                    staging,
+                    comb_manager,
+                )?);
+            }
+            return Ok(data);
+        }
+
+        let info = format.describe();
+        let block_size = match src.aspect {
+            wgt::TextureAspect::StencilOnly => 1,
+            _ => info.block_size as u32,
+        };
+        let (block_width, block_height) = info.block_dimensions;
+        let width_in_blocks = size.width / block_width as u32;
+        let height_in_blocks = size.height / block_height as u32;
+        let bytes_per_row = width_in_blocks * block_size;
+        let padded_bytes_per_row = wgt::math::align_to(
+            bytes_per_row as wgt::BufferAddress,
+            wgt::COPY_BYTES_PER_ROW_ALIGNMENT as wgt::BufferAddress,
+        ) as u32;
+        let buffer_size = padded_bytes_per_row as wgt::BufferAddress
+            * height_in_blocks as wgt::BufferAddress
+            * size.depth_or_array_layers as wgt::BufferAddress;
+
+        let dst = wgc::command::ImageCopyBuffer {
+            buffer: staging,
+            layout: wgt::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(padded_bytes_per_row),
+                rows_per_image: NonZeroU32::new(height_in_blocks),
+            },
+        };
+        let data = read_staged::<A>(
+            self,
+            device,
+            staging,
+            buffer_size,
+            comb_manager,
+            |encoder| self.command_encoder_copy_texture_to_buffer::<A>(encoder, src, &dst, &size),
+        )?;
+        Ok(data
+            .chunks(padded_bytes_per_row as usize)
+            .flat_map(|row| &row[..bytes_per_row as usize])
+            .cloned()
+            .collect())
     }
 }
@@ -0,0 +1,75 @@
+/*! Checks that device lost closures are released exactly once,
+using the empty backend.
+!*/
+
+use player::IdentityPassThroughFactory;
+use std::sync::Mutex;
+use wgc::{device::DeviceLostClosure, id};
+use wgt::{Backend, DeviceLostReason};
+
+type Empty = hal::api::Empty;
+type Recorder = Mutex<Vec<DeviceLostReason>>;
+
+unsafe extern "C" fn record_reason(
+    reason: DeviceLostReason,
+    _message: *const std::os::raw::c_char,
+    user_data: *mut u8,
+) {
+    let recorder = &*(user_data as *const Recorder);
+    recorder.lock().unwrap().push(reason);
+}
+
+fn create_device(name: &str) -> (wgc::hub::Global<IdentityPassThroughFactory>, id::DeviceId) {
+    let backend = Backend::Empty;
+    let instance = unsafe {
+        <<Empty as hal::Api>::Instance as hal::Instance<Empty>>::init(&hal::InstanceDescriptor {
+            name,
+            flags: hal::InstanceFlags::empty(),
+        })
+    }
+    .unwrap();
+    let exposed = unsafe { hal::Instance::enumerate_adapters(&instance) }
+        .pop()
+        .unwrap();
+    let global = unsafe {
+        wgc::hub::Global::from_hal_instance::<Empty>(name, IdentityPassThroughFactory, instance)
+    };
+    let adapter =
+        unsafe { global.create_adapter_from_hal(exposed, id::TypedId::zip(0, 1, backend)) };
+    let device = id::TypedId::zip(0, 1, backend);
+    let (_, error) = global.adapter_request_device::<Empty>(
+        adapter,
+        &wgt::DeviceDescriptor {
+            label: None,
+            features: wgt::Features::empty(),
+            limits: wgt::Limits::default(),
+        },
+        None,
+        device,
+    );
+    assert!(error.is_none());
+    (global, device)
+}
+
+#[test]
+fn replaced_and_dropped() {
+    let (global, device) = create_device("device_lost_replaced_and_dropped");
+    let first = Recorder::default();
+    let second = Recorder::default();
+
+    for recorder in [&first, &second] {
+        global
+            .device_set_device_lost_closure::<Empty>(
+                device,
+                DeviceLostClosure {
+                    callback: record_reason,
+                    user_data: recorder as *const Recorder as *mut u8,
+                },
+            )
+            .unwrap();
+    }
+    global.device_drop::<Empty>(device);
+
+    assert_eq!(*first.lock().unwrap(), [DeviceLostReason::ReplacedCallback]);
+    assert_eq!(*second.lock().unwrap(), [DeviceLostReason::Dropped]);
+}
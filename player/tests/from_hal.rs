@@ -0,0 +1,115 @@
+/*! Checks that buffers imported from HAL keep their contents,
+using the empty backend.
+!*/
+
+use player::IdentityPassThroughFactory;
+use wgc::id;
+use wgt::Backend;
+
+type Empty = hal::api::Empty;
+
+#[test]
+fn imported_buffer_keeps_contents() {
+    let backend = Backend::Empty;
+    let instance = unsafe {
+        <<Empty as hal::Api>::Instance as hal::Instance<Empty>>::init(&hal::InstanceDescriptor {
+            name: "from_hal",
+            flags: hal::InstanceFlags::empty(),
+        })
+    }
+    .unwrap();
+    let exposed = unsafe { hal::Instance::enumerate_adapters(&instance) }
+        .pop()
+        .unwrap();
+    let global = unsafe {
+        wgc::hub::Global::from_hal_instance::<Empty>(
+            "from_hal",
+            IdentityPassThroughFactory,
+            instance,
+        )
+    };
+    let adapter =
+        unsafe { global.create_adapter_from_hal(exposed, id::TypedId::zip(0, 1, backend)) };
+    let device = id::TypedId::zip(0, 1, backend);
+    let (_, error) = global.adapter_request_device::<Empty>(
+        adapter,
+        &wgt::DeviceDescriptor {
+            label: None,
+            features: wgt::Features::empty(),
+            limits: wgt::Limits::default(),
+        },
+        None,
+        device,
+    );
+    assert!(error.is_none());
+
+    let size = 256;
+    let hal_buffer = unsafe {
+        global.device_as_hal::<Empty, _, _>(device, |hal_device| {
+            use hal::Device as _;
+            let hal_device = hal_device.unwrap();
+            let hal_buffer = hal_device
+                .create_buffer(&hal::BufferDescriptor {
+                    label: None,
+                    size,
+                    usage: hal::BufferUses::MAP_READ | hal::BufferUses::COPY_DST,
+                    memory_flags: hal::MemoryFlags::empty(),
+                })
+                .unwrap();
+            let mapping = hal_device.map_buffer(&hal_buffer, 0..size).unwrap();
+            std::ptr::write_bytes(mapping.ptr.as_ptr(), 7, size as usize);
+            hal_device.unmap_buffer(&hal_buffer).unwrap();
+            hal_buffer
+        })
+    };
+
+    let buffer = id::TypedId::zip(0, 1, backend);
+    let (_, error) = unsafe {
+        global.create_buffer_from_hal::<Empty>(
+            hal_buffer,
+            device,
+            &wgt::BufferDescriptor {
+                label: None,
+                size,
+                usage: wgt::BufferUsages::MAP_READ | wgt::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            },
+            false,
+            buffer,
+        )
+    };
+    assert!(error.is_none());
+
+    unsafe extern "C" fn map_callback(status: wgc::resource::BufferMapAsyncStatus, _: *mut u8) {
+        assert!(matches!(
+            status,
+            wgc::resource::BufferMapAsyncStatus::Success
+        ));
+    }
+    global
+        .buffer_map_async::<Empty>(
+            buffer,
+            0..size,
+            wgc::resource::BufferMapOperation {
+                host: wgc::device::HostMap::Read,
+                callback: map_callback,
+                user_data: std::ptr::null_mut(),
+            },
+        )
+        .unwrap();
+    global.device_poll::<Empty>(device, true).unwrap();
+
+    let (ptr, len) = global
+        .buffer_get_mapped_range::<Empty>(buffer, 0, None)
+        .unwrap();
+    let data = unsafe { std::slice::from_raw_parts(ptr, len as usize) };
+    assert!(data.iter().all(|&byte| byte == 7));
+    global.buffer_unmap::<Empty>(buffer).unwrap();
+
+    global.buffer_drop::<Empty>(buffer, false);
+    global.device_poll::<Empty>(device, true).unwrap();
+    assert_eq!(
+        global.generate_report().empty.unwrap().buffers.num_occupied,
+        0
+    );
+}
@@ -0,0 +1,19 @@
+/*! Replays generated action sequences on the empty backend,
+making sure that the validation never panics.
+!*/
+
+use player::fuzz;
+
+#[test]
+fn fuzz_validation() {
+    env_logger::init();
+
+    let dir = std::env::temp_dir().join(format!("wgpu-fuzz-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    fuzz::write_files(&dir).unwrap();
+    for seed in 0..64 {
+        log::info!("Seed {}", seed);
+        fuzz::replay(seed, 200, &dir);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,121 @@
+/*! Checks that raw commands can be recorded into a command encoder,
+using the empty backend.
+!*/
+
+use player::IdentityPassThroughFactory;
+use wgc::id;
+use wgt::Backend;
+
+type Empty = hal::api::Empty;
+
+#[test]
+fn raw_commands_between_copies() {
+    let backend = Backend::Empty;
+    let instance = unsafe {
+        <<Empty as hal::Api>::Instance as hal::Instance<Empty>>::init(&hal::InstanceDescriptor {
+            name: "hal_encoder",
+            flags: hal::InstanceFlags::empty(),
+        })
+    }
+    .unwrap();
+    let exposed = unsafe { hal::Instance::enumerate_adapters(&instance) }
+        .pop()
+        .unwrap();
+    let global = unsafe {
+        wgc::hub::Global::from_hal_instance::<Empty>(
+            "hal_encoder",
+            IdentityPassThroughFactory,
+            instance,
+        )
+    };
+    let adapter =
+        unsafe { global.create_adapter_from_hal(exposed, id::TypedId::zip(0, 1, backend)) };
+    let device = id::TypedId::zip(0, 1, backend);
+    let (_, error) = global.adapter_request_device::<Empty>(
+        adapter,
+        &wgt::DeviceDescriptor {
+            label: None,
+            features: wgt::Features::empty(),
+            limits: wgt::Limits::default(),
+        },
+        None,
+        device,
+    );
+    assert!(error.is_none());
+
+    let mut buffers = Vec::new();
+    for (index, usage) in [
+        wgt::BufferUsages::COPY_SRC | wgt::BufferUsages::COPY_DST,
+        wgt::BufferUsages::COPY_SRC | wgt::BufferUsages::COPY_DST,
+        wgt::BufferUsages::empty(),
+    ]
+    .iter()
+    .enumerate()
+    {
+        let buffer = id::TypedId::zip(index as u32, 1, backend);
+        global.device_create_buffer::<Empty>(
+            device,
+            &wgt::BufferDescriptor {
+                label: None,
+                size: 256,
+                usage: *usage,
+                mapped_at_creation: false,
+            },
+            buffer,
+        );
+        buffers.push(buffer);
+    }
+    let (src, dst, invalid) = (buffers[0], buffers[1], buffers[2]);
+
+    let encoder = id::TypedId::zip(0, 1, backend);
+    let (_, error) = global.device_create_command_encoder::<Empty>(
+        device,
+        &wgt::CommandEncoderDescriptor { label: None },
+        encoder,
+    );
+    assert!(error.is_none());
+
+    let recorded = unsafe {
+        global.command_encoder_as_hal_mut::<Empty, _, _>(
+            encoder,
+            &[(dst, hal::BufferUses::COPY_DST)],
+            &[],
+            |hal_encoder| hal_encoder.is_some(),
+        )
+    };
+    assert!(recorded);
+    global
+        .command_encoder_copy_buffer_to_buffer::<Empty>(encoder, src, 0, dst, 0, 256)
+        .unwrap();
+
+    // Nothing is recorded if any of the declared resources is invalid.
+    let recorded = unsafe {
+        global.command_encoder_as_hal_mut::<Empty, _, _>(
+            encoder,
+            &[
+                (dst, hal::BufferUses::COPY_SRC),
+                (invalid, hal::BufferUses::COPY_DST),
+            ],
+            &[],
+            |hal_encoder| hal_encoder.is_some(),
+        )
+    };
+    assert!(!recorded);
+
+    let (command_buffer, error) = global
+        .command_encoder_finish::<Empty>(encoder, &wgt::CommandBufferDescriptor { label: None });
+    assert!(error.is_none());
+
+    // Raw commands can't be recorded once the encoder is finished.
+    let recorded = unsafe {
+        global.command_encoder_as_hal_mut::<Empty, _, _>(encoder, &[], &[], |hal_encoder| {
+            hal_encoder.is_some()
+        })
+    };
+    assert!(!recorded);
+
+    global
+        .queue_submit::<Empty>(device, &[command_buffer])
+        .unwrap();
+    global.device_poll::<Empty>(device, true).unwrap();
+}
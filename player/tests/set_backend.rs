@@ -0,0 +1,45 @@
+/*! Checks that moving a trace to another backend only touches the IDs.
+!*/
+
+use std::borrow::Cow;
+use wgc::{device::trace::Action, id::TypedId as _};
+use wgt::Backend;
+
+#[test]
+fn set_backend_keeps_labels() {
+    let label = "pass(1,1,Vulkan),Vulkan)";
+    let mut action = Action::CreateBindGroup(
+        wgc::id::Id::zip(1, 1, Backend::Vulkan),
+        wgc::binding_model::BindGroupDescriptor {
+            label: Some(Cow::Borrowed(label)),
+            layout: wgc::id::Id::zip(2, 1, Backend::Vulkan),
+            entries: Cow::Owned(vec![wgc::binding_model::BindGroupEntry {
+                binding: 0,
+                resource: wgc::binding_model::BindingResource::Buffer(
+                    wgc::binding_model::BufferBinding {
+                        buffer_id: wgc::id::Id::zip(3, 1, Backend::Vulkan),
+                        offset: 0,
+                        size: None,
+                    },
+                ),
+            }]),
+        },
+    );
+
+    player::set_backend(&mut action, Backend::Gl);
+
+    match action {
+        Action::CreateBindGroup(id, desc) => {
+            assert_eq!(id.unzip(), (1, 1, Backend::Gl));
+            assert_eq!(desc.label.as_deref(), Some(label));
+            assert_eq!(desc.layout.unzip(), (2, 1, Backend::Gl));
+            match desc.entries[0].resource {
+                wgc::binding_model::BindingResource::Buffer(ref binding) => {
+                    assert_eq!(binding.buffer_id.unzip(), (3, 1, Backend::Gl));
+                }
+                ref other => panic!("Unexpected resource {:?}", other),
+            }
+        }
+        other => panic!("Unexpected action {:?}", other),
+    }
+}
@@ -0,0 +1,185 @@
+/*! Checks that the queue writes manage their staging memory,
+using the empty backend.
+!*/
+
+use player::IdentityPassThroughFactory;
+use wgc::id;
+use wgt::Backend;
+
+type Empty = hal::api::Empty;
+
+fn create_device_and_buffer(
+    name: &str,
+) -> (
+    wgc::hub::Global<IdentityPassThroughFactory>,
+    id::DeviceId,
+    id::BufferId,
+) {
+    let backend = Backend::Empty;
+    let instance = unsafe {
+        <<Empty as hal::Api>::Instance as hal::Instance<Empty>>::init(&hal::InstanceDescriptor {
+            name,
+            flags: hal::InstanceFlags::empty(),
+        })
+    }
+    .unwrap();
+    let exposed = unsafe { hal::Instance::enumerate_adapters(&instance) }
+        .pop()
+        .unwrap();
+    let global = unsafe {
+        wgc::hub::Global::from_hal_instance::<Empty>(name, IdentityPassThroughFactory, instance)
+    };
+    let adapter =
+        unsafe { global.create_adapter_from_hal(exposed, id::TypedId::zip(0, 1, backend)) };
+    let device = id::TypedId::zip(0, 1, backend);
+    let (_, error) = global.adapter_request_device::<Empty>(
+        adapter,
+        &wgt::DeviceDescriptor {
+            label: None,
+            features: wgt::Features::empty(),
+            limits: wgt::Limits::default(),
+        },
+        None,
+        device,
+    );
+    assert!(error.is_none());
+
+    let buffer = id::TypedId::zip(0, 1, backend);
+    let (_, error) = global.device_create_buffer::<Empty>(
+        device,
+        &wgt::BufferDescriptor {
+            label: None,
+            size: 4 << 20,
+            usage: wgt::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        },
+        buffer,
+    );
+    assert!(error.is_none());
+
+    (global, device, buffer)
+}
+
+#[test]
+fn staging_chunks_are_recycled() {
+    let (global, device, buffer) = create_device_and_buffer("staging");
+
+    let small = [1u8; 256];
+    for _ in 0..10 {
+        for i in 0..100 {
+            global
+                .queue_write_buffer::<Empty>(device, buffer, i * 256, &small)
+                .unwrap();
+        }
+        global.queue_submit::<Empty>(device, &[]).unwrap();
+        global.device_poll::<Empty>(device, true).unwrap();
+    }
+
+    let report = global.generate_report().empty.unwrap().staging;
+    assert_eq!(report.num_allocations, 1000);
+    assert_eq!(report.num_chunks, 1);
+    assert_eq!(report.num_free_chunks, 1);
+    assert_eq!(report.num_buffers_created, 1);
+
+    // Large writes get a dedicated buffer, which isn't kept around.
+    let large = vec![2u8; 2 << 20];
+    global
+        .queue_write_buffer::<Empty>(device, buffer, 0, &large)
+        .unwrap();
+    global.queue_submit::<Empty>(device, &[]).unwrap();
+    global.device_poll::<Empty>(device, true).unwrap();
+
+    let report = global.generate_report().empty.unwrap().staging;
+    assert_eq!(report.num_chunks, 1);
+    assert_eq!(report.num_buffers_created, 2);
+}
+
+#[test]
+fn staging_chunks_are_trimmed() {
+    let (global, device, buffer) = create_device_and_buffer("staging_trimmed");
+
+    // Fill more chunks in a single submission than are kept around.
+    let chunk = vec![1u8; 1 << 20];
+    for i in 0..8 {
+        global
+            .queue_write_buffer::<Empty>(device, buffer, (i % 4) << 20, &chunk)
+            .unwrap();
+    }
+    global.queue_submit::<Empty>(device, &[]).unwrap();
+    global.device_poll::<Empty>(device, true).unwrap();
+
+    let report = global.generate_report().empty.unwrap().staging;
+    assert_eq!(report.num_buffers_created, 8);
+    assert_eq!(report.num_chunks, 4);
+    assert_eq!(report.num_free_chunks, 4);
+}
+
+#[test]
+fn staging_buffers_are_sub_allocated() {
+    let (global, device, buffer) = create_device_and_buffer("staging_buffers");
+    let size = wgt::BufferSize::new(1024).unwrap();
+
+    let (first, ptr) = global
+        .queue_create_staging_buffer::<Empty>(device, buffer, 0, size)
+        .unwrap();
+    let data = unsafe { std::slice::from_raw_parts_mut(ptr, size.get() as usize) };
+    assert!(data.iter().all(|&byte| byte == 0));
+    data.fill(3);
+    let (second, _) = global
+        .queue_create_staging_buffer::<Empty>(device, buffer, 1024, size)
+        .unwrap();
+
+    let report = global.generate_report().empty.unwrap().staging;
+    assert_eq!(report.num_buffers_created, 1);
+    assert_eq!(report.num_reserved_chunks, 1);
+
+    // The chunk is held until all of its staging buffers are written,
+    // the ones written before are copied to another chunk.
+    global
+        .queue_write_staging_buffer::<Empty>(device, buffer, 0, first)
+        .unwrap();
+    assert_eq!(
+        global
+            .generate_report()
+            .empty
+            .unwrap()
+            .staging
+            .num_reserved_chunks,
+        1
+    );
+    global
+        .queue_write_staging_buffer::<Empty>(device, buffer, 1024, second)
+        .unwrap();
+    assert_eq!(
+        global
+            .generate_report()
+            .empty
+            .unwrap()
+            .staging
+            .num_reserved_chunks,
+        0
+    );
+
+    // Invalid writes are caught when the staging buffer is created.
+    let error = global.queue_create_staging_buffer::<Empty>(device, buffer, 1, size);
+    assert!(error.is_err());
+    let error = global.queue_create_staging_buffer::<Empty>(device, buffer, 4 << 20, size);
+    assert!(error.is_err());
+    assert_eq!(
+        global
+            .generate_report()
+            .empty
+            .unwrap()
+            .staging
+            .num_reserved_chunks,
+        0
+    );
+
+    global.queue_submit::<Empty>(device, &[]).unwrap();
+    global.device_poll::<Empty>(device, true).unwrap();
+
+    let report = global.generate_report().empty.unwrap().staging;
+    assert_eq!(report.num_chunks, 2);
+    assert_eq!(report.num_free_chunks, 2);
+    assert_eq!(report.num_buffers_created, 2);
+}
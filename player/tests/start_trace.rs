@@ -0,0 +1,248 @@
+/*! Checks that traces can only be started on devices that allow it,
+using the empty backend, and that a started trace restores the contents
+of the resources, using the first available backend.
+!*/
+
+use player::{inspect::Resources, ActionReader, GlobalPlay, IdentityPassThroughFactory};
+use wgc::{
+    device::{DeviceTrace, StartTraceError},
+    id,
+};
+use wgt::Backend;
+
+type Empty = hal::api::Empty;
+
+fn create_device(
+    name: &str,
+    trace: Option<DeviceTrace>,
+) -> (wgc::hub::Global<IdentityPassThroughFactory>, id::DeviceId) {
+    let backend = Backend::Empty;
+    let instance = unsafe {
+        <<Empty as hal::Api>::Instance as hal::Instance<Empty>>::init(&hal::InstanceDescriptor {
+            name,
+            flags: hal::InstanceFlags::empty(),
+        })
+    }
+    .unwrap();
+    let exposed = unsafe { hal::Instance::enumerate_adapters(&instance) }
+        .pop()
+        .unwrap();
+    let global = unsafe {
+        wgc::hub::Global::from_hal_instance::<Empty>(name, IdentityPassThroughFactory, instance)
+    };
+    let adapter =
+        unsafe { global.create_adapter_from_hal(exposed, id::TypedId::zip(0, 1, backend)) };
+    let device = id::TypedId::zip(0, 1, backend);
+    let (_, error) = global.adapter_request_device::<Empty>(
+        adapter,
+        &wgt::DeviceDescriptor {
+            label: None,
+            features: wgt::Features::empty(),
+            limits: wgt::Limits::default(),
+        },
+        trace,
+        device,
+    );
+    assert!(error.is_none());
+    (global, device)
+}
+
+#[test]
+fn start_trace_not_active() {
+    let (global, device) = create_device("start_trace_not_active", None);
+    let dir = std::env::temp_dir().join("wgpu-start-trace-not-active");
+    let result = global.device_start_trace::<Empty>(device, dir.as_path().into());
+    assert!(matches!(result, Err(StartTraceError::NotActive)));
+    assert!(!dir.join("trace.ron").exists());
+}
+
+#[test]
+fn start_trace_recording() {
+    let record_dir = std::env::temp_dir().join("wgpu-start-trace-recording");
+    let _ = std::fs::remove_dir_all(&record_dir);
+    std::fs::create_dir_all(&record_dir).unwrap();
+    let (global, device) = create_device(
+        "start_trace_recording",
+        Some(DeviceTrace::Record(record_dir.as_path().into())),
+    );
+    let dir = std::env::temp_dir().join("wgpu-start-trace-recording-later");
+    let result = global.device_start_trace::<Empty>(device, dir.as_path().into());
+    assert!(matches!(result, Err(StartTraceError::NotActive)));
+    assert!(!dir.join("trace.ron").exists());
+    let _ = std::fs::remove_dir_all(&record_dir);
+}
+
+#[test]
+fn start_trace_on_demand() {
+    let (global, device) = create_device("start_trace_on_demand", Some(DeviceTrace::OnDemand));
+    let dir = std::env::temp_dir().join("wgpu-start-trace-on-demand");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+
+    global
+        .device_start_trace::<Empty>(device, dir.as_path().into())
+        .unwrap();
+    global.device_stop_trace::<Empty>(device).unwrap();
+    assert!(dir.join("trace.ron").exists());
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+fn request_adapter(global: &wgc::hub::Global<IdentityPassThroughFactory>) -> Option<id::AdapterId> {
+    global
+        .request_adapter(
+            &wgc::instance::RequestAdapterOptions {
+                power_preference: wgt::PowerPreference::LowPower,
+                compatible_surface: None,
+            },
+            wgc::instance::AdapterInputs::Mask(wgt::Backends::all(), |backend| {
+                id::TypedId::zip(0, 0, backend)
+            }),
+        )
+        .ok()
+}
+
+#[test]
+fn start_trace_replay() {
+    let global = wgc::hub::Global::new(
+        "start_trace_replay",
+        IdentityPassThroughFactory,
+        wgt::Backends::all(),
+    );
+    let adapter = match request_adapter(&global) {
+        Some(adapter) => adapter,
+        None => return,
+    };
+    let backend = adapter.backend();
+    let device = id::TypedId::zip(0, 0, backend);
+    let (_, error) = wgc::gfx_select!(adapter => global.adapter_request_device(
+        adapter,
+        &wgt::DeviceDescriptor {
+            label: None,
+            features: wgt::Features::empty(),
+            limits: wgt::Limits::downlevel_defaults(),
+        },
+        Some(DeviceTrace::OnDemand),
+        device
+    ));
+    assert!(error.is_none());
+
+    // Upload the contents before the trace is started.
+    let buffer = id::TypedId::zip(0, 1, backend);
+    let buffer_usage = wgt::BufferUsages::MAP_READ | wgt::BufferUsages::COPY_DST;
+    let (_, error) = wgc::gfx_select!(device => global.device_create_buffer(
+        device,
+        &wgt::BufferDescriptor {
+            label: None,
+            size: 256,
+            usage: buffer_usage,
+            mapped_at_creation: false,
+        },
+        buffer
+    ));
+    assert!(error.is_none());
+    let buffer_data = (0..=255).collect::<Vec<u8>>();
+    wgc::gfx_select!(device => global.queue_write_buffer(device, buffer, 0, &buffer_data)).unwrap();
+
+    let texture = id::TypedId::zip(0, 1, backend);
+    let size = wgt::Extent3d {
+        width: 4,
+        height: 4,
+        depth_or_array_layers: 1,
+    };
+    let format = wgt::TextureFormat::Rgba8Unorm;
+    let (_, error) = wgc::gfx_select!(device => global.device_create_texture(
+        device,
+        &wgt::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgt::TextureDimension::D2,
+            format,
+            usage: wgt::TextureUsages::COPY_DST | wgt::TextureUsages::COPY_SRC,
+        },
+        texture
+    ));
+    assert!(error.is_none());
+    let texels = (0..64).map(|i| i * 3).collect::<Vec<u8>>();
+    let copy = wgc::command::ImageCopyTexture {
+        texture,
+        mip_level: 0,
+        origin: wgt::Origin3d::ZERO,
+        aspect: wgt::TextureAspect::All,
+    };
+    wgc::gfx_select!(device => global.queue_write_texture(
+        device,
+        &copy,
+        &texels,
+        &wgt::ImageDataLayout {
+            offset: 0,
+            bytes_per_row: std::num::NonZeroU32::new(16),
+            rows_per_image: None,
+        },
+        &size
+    ))
+    .unwrap();
+    wgc::gfx_select!(device => global.queue_submit(device, &[])).unwrap();
+
+    let dir = std::env::temp_dir().join("wgpu-start-trace-replay");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    wgc::gfx_select!(device => global.device_start_trace(device, dir.as_path().into())).unwrap();
+    wgc::gfx_select!(device => global.device_stop_trace(device)).unwrap();
+    drop(global);
+
+    // Replay the trace on a fresh device, which starts out without any resources.
+    let global = wgc::hub::Global::new(
+        "start_trace_replay_play",
+        IdentityPassThroughFactory,
+        backend.into(),
+    );
+    let adapter = request_adapter(&global).unwrap();
+    let mut actions = ActionReader::open(&dir).unwrap();
+    let desc = match actions.next() {
+        Some(Ok(wgc::device::trace::Action::Init { desc, .. })) => desc,
+        _ => panic!("Expected Action::Init"),
+    };
+    let (_, error) = wgc::gfx_select!(adapter => global.adapter_request_device(
+        adapter,
+        &desc,
+        None,
+        device
+    ));
+    assert!(error.is_none());
+
+    let mut command_buffer_id_manager = wgc::hub::IdentityManager::default();
+    let mut resources = Resources::default();
+    for action in actions {
+        let action = action.unwrap();
+        resources.observe(&action);
+        wgc::gfx_select!(device => global.process(device, action, &dir, &mut command_buffer_id_manager));
+    }
+    // The trace was stopped before the uploads got submitted.
+    wgc::gfx_select!(device => global.queue_submit(device, &[])).unwrap();
+
+    let staging = resources.staging_buffer_id(backend);
+    let contents = wgc::gfx_select!(device => global.read_buffer(
+        device,
+        buffer,
+        buffer_usage,
+        0..256,
+        staging,
+        &mut command_buffer_id_manager
+    ))
+    .unwrap();
+    assert_eq!(contents, buffer_data);
+    let contents = wgc::gfx_select!(device => global.read_texture(
+        device,
+        &copy,
+        size,
+        format,
+        staging,
+        &mut command_buffer_id_manager
+    ))
+    .unwrap();
+    assert_eq!(contents, texels);
+
+    wgc::gfx_select!(device => global.clear_backend(()));
+}
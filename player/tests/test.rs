@@ -5,11 +5,14 @@
  *  Test requirements:
  *    - all IDs have the backend `Empty`
  *    - all expected buffers have `MAP_READ` usage
+ *    - all expected textures have `COPY_SRC` usage
  *    - last action is `Submit`
  *    - no swapchain use
 !*/
 
-use player::{GlobalPlay, IdentityPassThroughFactory};
+use player::{
+    compare::first_difference, inspect::Resources, GlobalPlay, IdentityPassThroughFactory,
+};
 use std::{
     fs::{read_to_string, File},
     io::{Read, Seek, SeekFrom},
@@ -48,10 +51,39 @@ struct Expectation {
     data: ExpectedData,
 }
 
+#[derive(serde::Deserialize)]
+enum ExpectedTexels {
+    /// Tightly packed texels, without the row padding.
+    Raw(Vec<u8>),
+    /// 8-bit RGBA image file.
+    Png(String),
+    /// A single texel, repeated over the whole region.
+    Color(Vec<u8>),
+}
+
+#[derive(serde::Deserialize)]
+struct TextureExpectation {
+    name: String,
+    texture: RawId,
+    #[serde(default)]
+    mip_level: u32,
+    #[serde(default)]
+    origin: wgt::Origin3d,
+    #[serde(default)]
+    aspect: wgt::TextureAspect,
+    size: wgt::Extent3d,
+    data: ExpectedTexels,
+    /// Maximum difference allowed in each byte, i.e. in each channel of 8-bit formats.
+    #[serde(default)]
+    tolerance: u8,
+}
+
 #[derive(serde::Deserialize)]
 struct Test<'a> {
     features: wgt::Features,
     expectations: Vec<Expectation>,
+    #[serde(default)]
+    texture_expectations: Vec<TextureExpectation>,
     actions: Vec<wgc::device::trace::Action<'a>>,
 }
 
@@ -100,8 +132,10 @@ impl Test<'_> {
         }
 
         let mut command_buffer_id_manager = wgc::hub::IdentityManager::default();
+        let mut resources = Resources::default();
         println!("\t\t\tRunning...");
         for action in self.actions {
+            resources.observe(&action);
             wgc::gfx_select!(device => global.process(device, action, dir, &mut command_buffer_id_manager));
         }
         println!("\t\t\tMapping...");
@@ -153,6 +187,58 @@ impl Test<'_> {
             }
         }
 
+        for expect in self.texture_expectations {
+            println!("\t\t\tChecking {}", expect.name);
+            let raw = player::inspect::RawId {
+                index: expect.texture.index,
+                epoch: expect.texture.epoch,
+            };
+            let format = resources.textures[&raw].format;
+            let src = wgc::command::ImageCopyTexture {
+                texture: raw.to_id(backend),
+                mip_level: expect.mip_level,
+                origin: expect.origin,
+                aspect: expect.aspect,
+            };
+            let contents = wgc::gfx_select!(device => global.read_texture(
+                device,
+                &src,
+                expect.size,
+                format,
+                resources.staging_buffer_id(backend),
+                &mut command_buffer_id_manager
+            ))
+            .unwrap();
+            let expected_data = match expect.data {
+                ExpectedTexels::Raw(vec) => vec,
+                ExpectedTexels::Png(name) => {
+                    let decoder = png::Decoder::new(File::open(dir.join(name)).unwrap());
+                    let (info, mut reader) = decoder.read_info().unwrap();
+                    assert_eq!(
+                        (info.color_type, info.bit_depth),
+                        (png::ColorType::RGBA, png::BitDepth::Eight),
+                        "Expected images need to be 8-bit RGBA"
+                    );
+                    let mut bin = vec![0; info.buffer_size()];
+                    reader.next_frame(&mut bin).unwrap();
+                    bin
+                }
+                ExpectedTexels::Color(texel) => texel.repeat(contents.len() / texel.len()),
+            };
+
+            assert_eq!(
+                expected_data.len(),
+                contents.len(),
+                "Expected data doesn't match the size of the region"
+            );
+            if let Some(diff) = first_difference(&contents, &expected_data, expect.tolerance) {
+                panic!(
+                    "Test expectation is not met!\nTexture byte {} was {} but expected {} (tolerance {})",
+                    diff.offset, diff.left, diff.right, expect.tolerance
+                );
+            }
+        }
+
         wgc::gfx_select!(device => global.clear_backend(()));
     }
 }
@@ -40,8 +40,8 @@ use crate::{
     },
     conv,
     device::{
-        AttachmentData, Device, DeviceError, MissingDownlevelFlags, RenderPassContext,
-        SHADER_STAGE_COUNT,
+        AttachmentData, Device, DeviceError, MissingDownlevelFlags, PushConstantWriter,
+        RenderPassContext, SHADER_STAGE_COUNT,
     },
     error::{ErrorFormatter, PrettyError},
     hub::{GlobalIdentityHandlerFactory, HalApi, Hub, Resource, Storage, Token},
@@ -240,7 +240,8 @@ impl RenderBundleEncoder {
                         .trackers
                         .render_pipes
                         .use_extend(&*pipeline_guard, pipeline_id, (), ())
-                        .unwrap();
+                        .map_err(|_| RenderCommandError::InvalidPipeline(pipeline_id))
+                        .map_pass_err(scope)?;
 
                     self.context
                         .check_compatible(&pipeline.pass_context)
@@ -279,7 +280,8 @@ impl RenderBundleEncoder {
                         .trackers
                         .buffers
                         .use_extend(&*buffer_guard, buffer_id, (), hal::BufferUses::INDEX)
-                        .unwrap();
+                        .map_err(|err| RenderCommandError::Buffer(buffer_id, err))
+                        .map_pass_err(scope)?;
                     check_buffer_usage(buffer.usage, wgt::BufferUsages::INDEX)
                         .map_pass_err(scope)?;
 
@@ -287,6 +289,14 @@ impl RenderBundleEncoder {
                         Some(s) => offset + s.get(),
                         None => buffer.size,
                     };
+                    if offset > end || end > buffer.size {
+                        return Err(RenderCommandError::BufferRangeOutOfBounds {
+                            start: offset,
+                            end,
+                            size: buffer.size,
+                        })
+                        .map_pass_err(scope);
+                    }
                     buffer_memory_init_actions.push(MemoryInitTrackerAction {
                         id: buffer_id,
                         range: offset..end,
@@ -306,7 +316,8 @@ impl RenderBundleEncoder {
                         .trackers
                         .buffers
                         .use_extend(&*buffer_guard, buffer_id, (), hal::BufferUses::VERTEX)
-                        .unwrap();
+                        .map_err(|err| RenderCommandError::Buffer(buffer_id, err))
+                        .map_pass_err(scope)?;
                     check_buffer_usage(buffer.usage, wgt::BufferUsages::VERTEX)
                         .map_pass_err(scope)?;
 
@@ -314,6 +325,14 @@ impl RenderBundleEncoder {
                         Some(s) => offset + s.get(),
                         None => buffer.size,
                     };
+                    if offset > end || end > buffer.size {
+                        return Err(RenderCommandError::BufferRangeOutOfBounds {
+                            start: offset,
+                            end,
+                            size: buffer.size,
+                        })
+                        .map_pass_err(scope);
+                    }
                     buffer_memory_init_actions.push(MemoryInitTrackerAction {
                         id: buffer_id,
                         range: offset..end,
@@ -388,6 +407,9 @@ impl RenderBundleEncoder {
                         pipeline: state.pipeline.last_state,
                     };
                     //TODO: validate that base_vertex + max_index() is within the provided range
+                    if state.index.buffer.is_none() {
+                        return Err(DrawError::MissingIndexBuffer).map_pass_err(scope);
+                    }
                     let vertex_limits = state.vertex_limits();
                     let index_limit = state.index.limit();
                     let last_index = first_index + index_count;
@@ -431,7 +453,8 @@ impl RenderBundleEncoder {
                         .trackers
                         .buffers
                         .use_extend(&*buffer_guard, buffer_id, (), hal::BufferUses::INDIRECT)
-                        .unwrap();
+                        .map_err(|err| RenderCommandError::Buffer(buffer_id, err))
+                        .map_pass_err(scope)?;
                     check_buffer_usage(buffer.usage, wgt::BufferUsages::INDIRECT)
                         .map_pass_err(scope)?;
 
@@ -495,13 +518,22 @@ impl RenderBundleEncoder {
                     commands.push(command);
                 }
                 RenderCommand::MultiDrawIndirect { .. }
-                | RenderCommand::MultiDrawIndirectCount { .. } => unimplemented!(),
-                RenderCommand::PushDebugGroup { color: _, len: _ } => unimplemented!(),
-                RenderCommand::InsertDebugMarker { color: _, len: _ } => unimplemented!(),
-                RenderCommand::PopDebugGroup => unimplemented!(),
+                | RenderCommand::MultiDrawIndirectCount { .. } => {
+                    return Err(RenderCommandError::Unimplemented("multi-draw-indirect"))
+                        .map_pass_err(PassErrorScope::Bundle);
+                }
+                RenderCommand::PushDebugGroup { .. }
+                | RenderCommand::InsertDebugMarker { .. }
+                | RenderCommand::PopDebugGroup => {
+                    return Err(RenderCommandError::Unimplemented("debug-markers"))
+                        .map_pass_err(PassErrorScope::Bundle);
+                }
                 RenderCommand::WriteTimestamp { .. }
                 | RenderCommand::BeginPipelineStatisticsQuery { .. }
-                | RenderCommand::EndPipelineStatisticsQuery => unimplemented!(),
+                | RenderCommand::EndPipelineStatisticsQuery => {
+                    return Err(RenderCommandError::Unimplemented("queries"))
+                        .map_pass_err(PassErrorScope::Bundle);
+                }
                 RenderCommand::ExecuteBundle(_)
                 | RenderCommand::SetBlendConstant(_)
                 | RenderCommand::SetStencilReference(_)
@@ -560,6 +592,8 @@ pub enum ExecutionError {
     DestroyedBuffer(id::BufferId),
     #[error("using {0} in a render bundle is not implemented")]
     Unimplemented(&'static str),
+    #[error(transparent)]
+    Device(#[from] DeviceError),
 }
 impl PrettyError for ExecutionError {
     fn fmt_pretty(&self, fmt: &mut ErrorFormatter) {
@@ -569,6 +603,7 @@ impl PrettyError for ExecutionError {
                 fmt.buffer_label(&id);
             }
             Self::Unimplemented(_reason) => {}
+            Self::Device(_) => {}
         };
     }
 }
@@ -614,6 +649,7 @@ impl RenderBundle {
         bind_group_guard: &Storage<crate::binding_model::BindGroup<A>, id::BindGroupId>,
         pipeline_guard: &Storage<crate::pipeline::RenderPipeline<A>, id::RenderPipelineId>,
         buffer_guard: &Storage<crate::resource::Buffer<A>, id::BufferId>,
+        mut push_constants: Option<&mut PushConstantWriter<A>>,
     ) -> Result<(), ExecutionError> {
         let mut offsets = self.base.dynamic_offsets.as_slice();
         let mut pipeline_layout_id = None::<id::Valid<id::PipelineLayoutId>>;
@@ -641,6 +677,11 @@ impl RenderBundle {
                     let pipeline = pipeline_guard.get(pipeline_id).unwrap();
                     raw.set_render_pipeline(&pipeline.raw);
 
+                    if pipeline_layout_id != Some(pipeline.layout_id.value) {
+                        if let Some(ref mut push_constants) = push_constants {
+                            push_constants.invalidate();
+                        }
+                    }
                     pipeline_layout_id = Some(pipeline.layout_id.value);
                 }
                 RenderCommand::SetIndexBuffer {
@@ -696,18 +737,29 @@ impl RenderBundle {
                         let data_slice = &self.base.push_constant_data
                             [(values_offset as usize)..values_end_offset];
 
-                        raw.set_push_constants(&pipeline_layout.raw, stages, offset, data_slice)
+                        match push_constants {
+                            Some(ref mut push_constants) => push_constants.set(offset, data_slice),
+                            None => raw.set_push_constants(
+                                &pipeline_layout.raw,
+                                stages,
+                                offset,
+                                data_slice,
+                            ),
+                        }
                     } else {
                         super::push_constant_clear(
                             offset,
                             size_bytes,
-                            |clear_offset, clear_data| {
-                                raw.set_push_constants(
+                            |clear_offset, clear_data| match push_constants {
+                                Some(ref mut push_constants) => {
+                                    push_constants.set(clear_offset, clear_data)
+                                }
+                                None => raw.set_push_constants(
                                     &pipeline_layout.raw,
                                     stages,
                                     clear_offset,
                                     clear_data,
-                                );
+                                ),
                             },
                         );
                     }
@@ -718,6 +770,10 @@ impl RenderBundle {
                     first_vertex,
                     first_instance,
                 } => {
+                    if let Some(ref mut push_constants) = push_constants {
+                        let pipeline_layout = &pipeline_layout_guard[pipeline_layout_id.unwrap()];
+                        push_constants.flush(raw, pipeline_layout)?;
+                    }
                     raw.draw(first_vertex, vertex_count, first_instance, instance_count);
                 }
                 RenderCommand::DrawIndexed {
@@ -727,6 +783,10 @@ impl RenderBundle {
                     base_vertex,
                     first_instance,
                 } => {
+                    if let Some(ref mut push_constants) = push_constants {
+                        let pipeline_layout = &pipeline_layout_guard[pipeline_layout_id.unwrap()];
+                        push_constants.flush(raw, pipeline_layout)?;
+                    }
                     raw.draw_indexed(
                         first_index,
                         index_count,
@@ -747,6 +807,10 @@ impl RenderBundle {
                         .raw
                         .as_ref()
                         .ok_or(ExecutionError::DestroyedBuffer(buffer_id))?;
+                    if let Some(ref mut push_constants) = push_constants {
+                        let pipeline_layout = &pipeline_layout_guard[pipeline_layout_id.unwrap()];
+                        push_constants.flush(raw, pipeline_layout)?;
+                    }
                     raw.draw_indirect(buffer, offset, 1);
                 }
                 RenderCommand::MultiDrawIndirect {
@@ -761,6 +825,10 @@ impl RenderBundle {
                         .raw
                         .as_ref()
                         .ok_or(ExecutionError::DestroyedBuffer(buffer_id))?;
+                    if let Some(ref mut push_constants) = push_constants {
+                        let pipeline_layout = &pipeline_layout_guard[pipeline_layout_id.unwrap()];
+                        push_constants.flush(raw, pipeline_layout)?;
+                    }
                     raw.draw_indexed_indirect(buffer, offset, 1);
                 }
                 RenderCommand::MultiDrawIndirect { .. }
@@ -195,6 +195,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             None => dst_texture.full_range.levels.end,
         };
         if dst_texture.full_range.levels.start > subresource_range.base_mip_level
+            || dst_texture.full_range.levels.end <= subresource_range.base_mip_level
             || dst_texture.full_range.levels.end < subresource_level_end
         {
             return Err(ClearError::InvalidTextureLevelRange {
@@ -209,6 +210,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             None => dst_texture.full_range.layers.end,
         };
         if dst_texture.full_range.layers.start > subresource_range.base_array_layer
+            || dst_texture.full_range.layers.end <= subresource_range.base_array_layer
             || dst_texture.full_range.layers.end < subresource_layer_end
         {
             return Err(ClearError::InvalidTextureLayerRange {
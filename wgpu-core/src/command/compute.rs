@@ -5,7 +5,7 @@ use crate::{
         CommandEncoderError, CommandEncoderStatus, MapPassErr, PassErrorScope, QueryUseError,
         StateChange,
     },
-    device::MissingDownlevelFlags,
+    device::{DeviceError, MissingDownlevelFlags, PushConstantWriter},
     error::{ErrorFormatter, PrettyError},
     hub::{Global, GlobalIdentityHandlerFactory, HalApi, Storage, Token},
     id,
@@ -160,6 +160,8 @@ pub enum ComputePassErrorInner {
     QueryUse(#[from] QueryUseError),
     #[error(transparent)]
     MissingDownlevelFlags(#[from] MissingDownlevelFlags),
+    #[error(transparent)]
+    Device(#[from] DeviceError),
 }
 
 impl PrettyError for ComputePassErrorInner {
@@ -322,6 +324,11 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let mut dynamic_offset_count = 0;
         let mut string_offset = 0;
         let mut active_query = None;
+        let push_constant_chunks = &mut cmd_buf.push_constant_chunks;
+        let mut push_constants = device
+            .push_constants
+            .as_ref()
+            .map(|emulation| PushConstantWriter::new(emulation, &device.raw, push_constant_chunks));
 
         let hal_desc = hal::ComputePassDescriptor { label: base.label };
         unsafe {
@@ -444,24 +451,28 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         }
 
                         // Clear push constant ranges
-                        let non_overlapping = super::bind::compute_nonoverlapping_ranges(
-                            &pipeline_layout.push_constant_ranges,
-                        );
-                        for range in non_overlapping {
-                            let offset = range.range.start;
-                            let size_bytes = range.range.end - offset;
-                            super::push_constant_clear(
-                                offset,
-                                size_bytes,
-                                |clear_offset, clear_data| unsafe {
-                                    raw.set_push_constants(
-                                        &pipeline_layout.raw,
-                                        wgt::ShaderStages::COMPUTE,
-                                        clear_offset,
-                                        clear_data,
-                                    );
-                                },
+                        if let Some(ref mut push_constants) = push_constants {
+                            push_constants.clear();
+                        } else {
+                            let non_overlapping = super::bind::compute_nonoverlapping_ranges(
+                                &pipeline_layout.push_constant_ranges,
                             );
+                            for range in non_overlapping {
+                                let offset = range.range.start;
+                                let size_bytes = range.range.end - offset;
+                                super::push_constant_clear(
+                                    offset,
+                                    size_bytes,
+                                    |clear_offset, clear_data| unsafe {
+                                        raw.set_push_constants(
+                                            &pipeline_layout.raw,
+                                            wgt::ShaderStages::COMPUTE,
+                                            clear_offset,
+                                            clear_data,
+                                        );
+                                    },
+                                );
+                            }
                         }
                     }
                 }
@@ -496,13 +507,16 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         )
                         .map_pass_err(scope)?;
 
-                    unsafe {
-                        raw.set_push_constants(
-                            &pipeline_layout.raw,
-                            wgt::ShaderStages::COMPUTE,
-                            offset,
-                            data_slice,
-                        );
+                    match push_constants {
+                        Some(ref mut push_constants) => push_constants.set(offset, data_slice),
+                        None => unsafe {
+                            raw.set_push_constants(
+                                &pipeline_layout.raw,
+                                wgt::ShaderStages::COMPUTE,
+                                offset,
+                                data_slice,
+                            );
+                        },
                     }
                 }
                 ComputeCommand::Dispatch(groups) => {
@@ -521,6 +535,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             &*texture_guard,
                         )
                         .map_pass_err(scope)?;
+                    if let Some(ref mut push_constants) = push_constants {
+                        let pipeline_layout =
+                            &pipeline_layout_guard[state.binder.pipeline_layout_id.unwrap()];
+                        push_constants
+                            .flush(raw, pipeline_layout)
+                            .map_pass_err(scope)?;
+                    }
                     unsafe {
                         raw.dispatch(groups);
                     }
@@ -584,6 +605,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             &*texture_guard,
                         )
                         .map_pass_err(scope)?;
+                    if let Some(ref mut push_constants) = push_constants {
+                        let pipeline_layout =
+                            &pipeline_layout_guard[state.binder.pipeline_layout_id.unwrap()];
+                        push_constants
+                            .flush(raw, pipeline_layout)
+                            .map_pass_err(scope)?;
+                    }
                     unsafe {
                         raw.dispatch_indirect(buf_raw, offset);
                     }
@@ -81,6 +81,12 @@ pub enum RenderCommandError {
     Buffer(id::BufferId, BufferError),
     #[error("buffer {0:?} is destroyed")]
     DestroyedBuffer(id::BufferId),
+    #[error("buffer range {start}..{end} is out of bounds for a buffer of size {size}")]
+    BufferRangeOutOfBounds {
+        start: BufferAddress,
+        end: BufferAddress,
+        size: BufferAddress,
+    },
     #[error(transparent)]
     MissingBufferUsage(#[from] MissingBufferUsageError),
     #[error(transparent)]
@@ -16,6 +16,7 @@ pub use self::transfer::*;
 
 use crate::error::{ErrorFormatter, PrettyError};
 use crate::{
+    device::PushConstantChunk,
     hub::{Global, GlobalIdentityHandlerFactory, HalApi, Storage, Token},
     id,
     memory_init_tracker::{MemoryInitKind, MemoryInitTrackerAction},
@@ -68,6 +69,7 @@ pub struct BakedCommands<A: hal::Api> {
     pub(crate) list: Vec<A::CommandBuffer>,
     pub(crate) trackers: TrackerSet,
     buffer_memory_init_actions: Vec<MemoryInitTrackerAction<id::BufferId>>,
+    pub(crate) push_constant_chunks: Vec<PushConstantChunk<A>>,
 }
 
 pub(crate) struct DestroyedBufferError(pub id::BufferId);
@@ -141,6 +143,8 @@ pub struct CommandBuffer<A: hal::Api> {
     pub(crate) device_id: Stored<id::DeviceId>,
     pub(crate) trackers: TrackerSet,
     buffer_memory_init_actions: Vec<MemoryInitTrackerAction<id::BufferId>>,
+    /// Uniform buffers holding the emulated push constants.
+    push_constant_chunks: Vec<PushConstantChunk<A>>,
     limits: wgt::Limits,
     support_fill_buffer_texture: bool,
     #[cfg(feature = "trace")]
@@ -168,6 +172,7 @@ impl<A: HalApi> CommandBuffer<A> {
             device_id,
             trackers: TrackerSet::new(A::VARIANT),
             buffer_memory_init_actions: Default::default(),
+            push_constant_chunks: Vec::new(),
             limits,
             support_fill_buffer_texture: features.contains(wgt::Features::CLEAR_COMMANDS),
             #[cfg(feature = "trace")]
@@ -234,6 +239,7 @@ impl<A: hal::Api> CommandBuffer<A> {
             list: self.encoder.list,
             trackers: self.trackers,
             buffer_memory_init_actions: self.buffer_memory_init_actions,
+            push_constant_chunks: self.push_constant_chunks,
         }
     }
 }
@@ -260,7 +266,7 @@ pub struct BasePassRef<'a, C> {
 }
 
 #[doc(hidden)]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(
     any(feature = "serial-pass", feature = "trace"),
     derive(serde::Serialize)
@@ -403,6 +409,101 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
         Ok(())
     }
+
+    /// Call `hal_command_encoder_callback` with the raw command encoder behind
+    /// `encoder_id`, open for recording, or `None` if the encoder is invalid or
+    /// finished, not from backend `A`, or any of the declared resources is
+    /// invalid or destroyed.
+    ///
+    /// `buffers` and `textures` declare the usages the raw commands need. The
+    /// resources are transitioned to them before the callback is called, and
+    /// the commands recorded after it synchronize against them.
+    ///
+    /// NB: Commands recorded through the raw encoder will not be recorded in the trace.
+    ///
+    /// # Safety
+    ///
+    /// - The raw commands must only use the declared resources, each declared
+    ///   once, in the declared usages, which must be allowed by the resource.
+    /// - The raw encoder must be left recording outside of any pass, and must
+    ///   not be ended, reset, or destroyed.
+    pub unsafe fn command_encoder_as_hal_mut<
+        A: HalApi,
+        F: FnOnce(Option<&mut A::CommandEncoder>) -> R,
+        R,
+    >(
+        &self,
+        encoder_id: id::CommandEncoderId,
+        buffers: &[(id::BufferId, hal::BufferUses)],
+        textures: &[(id::TextureId, hal::TextureUses)],
+        hal_command_encoder_callback: F,
+    ) -> R {
+        profiling::scope!("as_hal_mut", "CommandEncoder");
+
+        if encoder_id.backend() != A::VARIANT {
+            return hal_command_encoder_callback(None);
+        }
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+
+        let (mut cmd_buf_guard, mut token) = hub.command_buffers.write(&mut token);
+        let cmd_buf = match CommandBuffer::get_encoder_mut(&mut *cmd_buf_guard, encoder_id) {
+            Ok(cmd_buf) => cmd_buf,
+            Err(_) => return hal_command_encoder_callback(None),
+        };
+        let (buffer_guard, mut token) = hub.buffers.read(&mut token);
+        let (texture_guard, _) = hub.textures.read(&mut token);
+
+        // Check everything up front, so that nothing gets tracked
+        // unless the raw commands actually get recorded.
+        let buffers_valid = buffers
+            .iter()
+            .all(|&(id, _)| matches!(buffer_guard.get(id), Ok(buffer) if buffer.raw.is_some()));
+        let textures_valid = textures.iter().all(
+            |&(id, _)| matches!(texture_guard.get(id), Ok(texture) if texture.inner.as_raw().is_some()),
+        );
+        if !buffers_valid || !textures_valid {
+            return hal_command_encoder_callback(None);
+        }
+
+        let mut buffer_barriers = Vec::with_capacity(buffers.len());
+        for &(id, usage) in buffers {
+            let (buffer, pending) = cmd_buf
+                .trackers
+                .buffers
+                .use_replace(&*buffer_guard, id, (), usage)
+                .unwrap();
+            buffer_barriers.extend(pending.map(|pending| pending.into_hal(buffer)));
+            // The raw commands may read anything, so the whole buffer has to be initialized.
+            cmd_buf.buffer_memory_init_actions.extend(
+                buffer
+                    .initialization_status
+                    .check(0..buffer.size)
+                    .map(|range| MemoryInitTrackerAction {
+                        id,
+                        range,
+                        kind: MemoryInitKind::NeedsInitializedMemory,
+                    }),
+            );
+        }
+
+        let mut texture_barriers = Vec::with_capacity(textures.len());
+        for &(id, usage) in textures {
+            let selector = texture_guard[id::Valid(id)].full_range.clone();
+            let (texture, pending) = cmd_buf
+                .trackers
+                .textures
+                .use_replace(&*texture_guard, id, selector, usage)
+                .unwrap();
+            texture_barriers.extend(pending.map(|pending| pending.into_hal(texture)));
+        }
+
+        let cmd_buf_raw = cmd_buf.encoder.open();
+        cmd_buf_raw.transition_buffers(buffer_barriers.into_iter());
+        cmd_buf_raw.transition_textures(texture_barriers.into_iter());
+        hal_command_encoder_callback(Some(cmd_buf_raw))
+    }
 }
 
 fn push_constant_clear<PushFn>(offset: u32, size_bytes: u32, mut push_fn: PushFn)
@@ -358,6 +358,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         if !dst_buffer.usage.contains(wgt::BufferUsages::COPY_DST) {
             return Err(ResolveError::MissingBufferUsage.into());
         }
+        let dst_raw = dst_buffer
+            .raw
+            .as_ref()
+            .ok_or(QueryError::InvalidBuffer(destination))?;
 
         let end_query = start_query + query_count;
         if end_query > query_set.desc.count {
@@ -408,7 +412,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             raw_encoder.copy_query_results(
                 &query_set.raw,
                 start_query..end_query,
-                dst_buffer.raw.as_ref().unwrap(),
+                dst_raw,
                 destination_offset,
                 wgt::BufferSize::new_unchecked(stride as u64),
             );
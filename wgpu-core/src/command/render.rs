@@ -7,8 +7,8 @@ use crate::{
         StateChange,
     },
     device::{
-        AttachmentData, MissingDownlevelFlags, MissingFeatures, RenderPassCompatibilityError,
-        RenderPassContext,
+        AttachmentData, DeviceError, MissingDownlevelFlags, MissingFeatures, PushConstantWriter,
+        RenderPassCompatibilityError, RenderPassContext,
     },
     error::{ErrorFormatter, PrettyError},
     hub::{Global, GlobalIdentityHandlerFactory, HalApi, Storage, Token},
@@ -454,6 +454,8 @@ pub enum RenderPassErrorInner {
     Bind(#[from] BindError),
     #[error(transparent)]
     QueryUse(#[from] QueryUseError),
+    #[error(transparent)]
+    Device(#[from] DeviceError),
 }
 
 impl PrettyError for RenderPassErrorInner {
@@ -828,6 +830,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .map_pass_err(scope)?;
 
             let raw = &mut cmd_buf.encoder.raw;
+            let push_constant_chunks = &mut cmd_buf.push_constant_chunks;
+            let mut push_constants = device.push_constants.as_ref().map(|emulation| {
+                PushConstantWriter::new(emulation, &device.raw, push_constant_chunks)
+            });
 
             let mut state = State {
                 pipeline_flags: PipelineFlags::empty(),
@@ -993,24 +999,28 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             }
 
                             // Clear push constant ranges
-                            let non_overlapping = super::bind::compute_nonoverlapping_ranges(
-                                &pipeline_layout.push_constant_ranges,
-                            );
-                            for range in non_overlapping {
-                                let offset = range.range.start;
-                                let size_bytes = range.range.end - offset;
-                                super::push_constant_clear(
-                                    offset,
-                                    size_bytes,
-                                    |clear_offset, clear_data| unsafe {
-                                        raw.set_push_constants(
-                                            &pipeline_layout.raw,
-                                            range.stages,
-                                            clear_offset,
-                                            clear_data,
-                                        );
-                                    },
+                            if let Some(ref mut push_constants) = push_constants {
+                                push_constants.clear();
+                            } else {
+                                let non_overlapping = super::bind::compute_nonoverlapping_ranges(
+                                    &pipeline_layout.push_constant_ranges,
                                 );
+                                for range in non_overlapping {
+                                    let offset = range.range.start;
+                                    let size_bytes = range.range.end - offset;
+                                    super::push_constant_clear(
+                                        offset,
+                                        size_bytes,
+                                        |clear_offset, clear_data| unsafe {
+                                            raw.set_push_constants(
+                                                &pipeline_layout.raw,
+                                                range.stages,
+                                                clear_offset,
+                                                clear_data,
+                                            );
+                                        },
+                                    );
+                                }
                             }
                         }
 
@@ -1061,6 +1071,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             Some(s) => offset + s.get(),
                             None => buffer.size,
                         };
+                        if offset > end || end > buffer.size {
+                            return Err(RenderCommandError::BufferRangeOutOfBounds {
+                                start: offset,
+                                end,
+                                size: buffer.size,
+                            })
+                            .map_pass_err(scope);
+                        }
                         state.index.bound_buffer_view = Some((id::Valid(buffer_id), offset..end));
 
                         state.index.format = Some(index_format);
@@ -1113,12 +1131,20 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             .vertex
                             .inputs
                             .extend(iter::repeat(VertexBufferState::EMPTY).take(empty_slots));
-                        let vertex_state = &mut state.vertex.inputs[slot as usize];
-                        //TODO: where are we checking that the offset is in bound?
-                        vertex_state.total_size = match size {
-                            Some(s) => s.get(),
-                            None => buffer.size - offset,
+                        let end = match size {
+                            Some(s) => offset + s.get(),
+                            None => buffer.size,
                         };
+                        if offset > end || end > buffer.size {
+                            return Err(RenderCommandError::BufferRangeOutOfBounds {
+                                start: offset,
+                                end,
+                                size: buffer.size,
+                            })
+                            .map_pass_err(scope);
+                        }
+                        let vertex_state = &mut state.vertex.inputs[slot as usize];
+                        vertex_state.total_size = end - offset;
                         vertex_state.bound = true;
 
                         cmd_buf.buffer_memory_init_actions.extend(
@@ -1219,8 +1245,16 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             .map_err(RenderCommandError::from)
                             .map_pass_err(scope)?;
 
-                        unsafe {
-                            raw.set_push_constants(&pipeline_layout.raw, stages, offset, data_slice)
+                        match push_constants {
+                            Some(ref mut push_constants) => push_constants.set(offset, data_slice),
+                            None => unsafe {
+                                raw.set_push_constants(
+                                    &pipeline_layout.raw,
+                                    stages,
+                                    offset,
+                                    data_slice,
+                                )
+                            },
                         }
                     }
                     RenderCommand::SetScissor(ref rect) => {
@@ -1277,6 +1311,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             .map_pass_err(scope);
                         }
 
+                        if let Some(ref mut push_constants) = push_constants {
+                            let pipeline_layout =
+                                &pipeline_layout_guard[state.binder.pipeline_layout_id.unwrap()];
+                            push_constants
+                                .flush(raw, pipeline_layout)
+                                .map_pass_err(scope)?;
+                        }
                         unsafe {
                             raw.draw(first_vertex, vertex_count, first_instance, instance_count);
                         }
@@ -1317,6 +1358,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             .map_pass_err(scope);
                         }
 
+                        if let Some(ref mut push_constants) = push_constants {
+                            let pipeline_layout =
+                                &pipeline_layout_guard[state.binder.pipeline_layout_id.unwrap()];
+                            push_constants
+                                .flush(raw, pipeline_layout)
+                                .map_pass_err(scope)?;
+                        }
                         unsafe {
                             raw.draw_indexed(
                                 first_index,
@@ -1392,6 +1440,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 }),
                         );
 
+                        if let Some(ref mut push_constants) = push_constants {
+                            let pipeline_layout =
+                                &pipeline_layout_guard[state.binder.pipeline_layout_id.unwrap()];
+                            push_constants
+                                .flush(raw, pipeline_layout)
+                                .map_pass_err(scope)?;
+                        }
                         match indexed {
                             false => unsafe {
                                 raw.draw_indirect(indirect_raw, offset, actual_count);
@@ -1503,6 +1558,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 }),
                         );
 
+                        if let Some(ref mut push_constants) = push_constants {
+                            let pipeline_layout =
+                                &pipeline_layout_guard[state.binder.pipeline_layout_id.unwrap()];
+                            push_constants
+                                .flush(raw, pipeline_layout)
+                                .map_pass_err(scope)?;
+                        }
                         match indexed {
                             false => unsafe {
                                 raw.draw_indirect_count(
@@ -1661,15 +1723,17 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 &*bind_group_guard,
                                 &*pipeline_guard,
                                 &*buffer_guard,
+                                push_constants.as_mut(),
                             )
                         }
                         .map_err(|e| match e {
                             ExecutionError::DestroyedBuffer(id) => {
-                                RenderCommandError::DestroyedBuffer(id)
+                                RenderCommandError::DestroyedBuffer(id).into()
                             }
                             ExecutionError::Unimplemented(what) => {
-                                RenderCommandError::Unimplemented(what)
+                                RenderCommandError::Unimplemented(what).into()
                             }
+                            ExecutionError::Device(error) => RenderPassErrorInner::Device(error),
                         })
                         .map_pass_err(scope)?;
 
@@ -143,6 +143,13 @@ pub(crate) fn extract_texture_selector<A: hal::Api>(
         .get(copy_texture.texture)
         .map_err(|_| TransferError::InvalidTexture(copy_texture.texture))?;
 
+    if copy_texture.mip_level >= texture.desc.mip_level_count {
+        return Err(TransferError::InvalidTextureMipLevel {
+            level: copy_texture.mip_level,
+            total: texture.desc.mip_level_count,
+        });
+    }
+
     let format = texture.desc.format;
     let copy_aspect =
         hal::FormatAspects::from(format) & hal::FormatAspects::from(copy_texture.aspect);
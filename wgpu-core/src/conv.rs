@@ -94,6 +94,62 @@ pub fn map_texture_usage(
     u
 }
 
+pub fn map_texture_usage_from_hal(uses: hal::TextureUses) -> wgt::TextureUsages {
+    let mut u = wgt::TextureUsages::empty();
+    u.set(
+        wgt::TextureUsages::COPY_SRC,
+        uses.contains(hal::TextureUses::COPY_SRC),
+    );
+    u.set(
+        wgt::TextureUsages::COPY_DST,
+        uses.contains(hal::TextureUses::COPY_DST),
+    );
+    u.set(
+        wgt::TextureUsages::TEXTURE_BINDING,
+        uses.contains(hal::TextureUses::RESOURCE),
+    );
+    u.set(
+        wgt::TextureUsages::STORAGE_BINDING,
+        uses.contains(hal::TextureUses::STORAGE_READ | hal::TextureUses::STORAGE_WRITE),
+    );
+    u.set(
+        wgt::TextureUsages::RENDER_ATTACHMENT,
+        uses.intersects(hal::TextureUses::COLOR_TARGET | hal::TextureUses::DEPTH_STENCIL_WRITE),
+    );
+    u
+}
+
+pub fn map_composite_alpha_mode(mode: wgt::CompositeAlphaMode) -> hal::CompositeAlphaMode {
+    match mode {
+        wgt::CompositeAlphaMode::Opaque => hal::CompositeAlphaMode::Opaque,
+        wgt::CompositeAlphaMode::PreMultiplied => hal::CompositeAlphaMode::PreMultiplied,
+        wgt::CompositeAlphaMode::PostMultiplied => hal::CompositeAlphaMode::PostMultiplied,
+    }
+}
+
+pub fn map_composite_alpha_mode_from_hal(mode: hal::CompositeAlphaMode) -> wgt::CompositeAlphaMode {
+    match mode {
+        hal::CompositeAlphaMode::Opaque => wgt::CompositeAlphaMode::Opaque,
+        hal::CompositeAlphaMode::PreMultiplied => wgt::CompositeAlphaMode::PreMultiplied,
+        hal::CompositeAlphaMode::PostMultiplied => wgt::CompositeAlphaMode::PostMultiplied,
+    }
+}
+
+pub fn map_surface_capabilities(caps: hal::SurfaceCapabilities) -> wgt::SurfaceCapabilities {
+    wgt::SurfaceCapabilities {
+        formats: caps.formats,
+        current_extent: caps.current_extent,
+        extents: caps.extents,
+        usages: map_texture_usage_from_hal(caps.usage),
+        present_modes: caps.present_modes,
+        composite_alpha_modes: caps
+            .composite_alpha_modes
+            .into_iter()
+            .map(map_composite_alpha_mode_from_hal)
+            .collect(),
+    }
+}
+
 pub fn check_texture_dimension_size(
     dimension: wgt::TextureDimension,
     wgt::Extent3d {
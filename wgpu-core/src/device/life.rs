@@ -2,7 +2,9 @@
 use crate::device::trace;
 use crate::{
     device::{
+        push_constants::{PushConstantChunk, PushConstantEmulation},
         queue::{EncoderInFlight, SubmittedWorkDoneClosure, TempResource},
+        staging::{StagingBelt, StagingChunk},
         DeviceError,
     },
     hub::{GlobalIdentityHandlerFactory, HalApi, Hub, Token},
@@ -85,6 +87,7 @@ impl SuspectedResources {
 #[derive(Debug)]
 struct NonReferencedResources<A: hal::Api> {
     buffers: Vec<A::Buffer>,
+    external_buffers: Vec<A::Buffer>,
     textures: Vec<A::Texture>,
     // Note: we keep the associated ID here in order to be able to check
     // at any point what resources are used in a submission.
@@ -102,6 +105,7 @@ impl<A: hal::Api> NonReferencedResources<A> {
     fn new() -> Self {
         Self {
             buffers: Vec::new(),
+            external_buffers: Vec::new(),
             textures: Vec::new(),
             texture_views: Vec::new(),
             samplers: Vec::new(),
@@ -116,6 +120,7 @@ impl<A: hal::Api> NonReferencedResources<A> {
 
     fn extend(&mut self, other: Self) {
         self.buffers.extend(other.buffers);
+        self.external_buffers.extend(other.external_buffers);
         self.textures.extend(other.textures);
         self.texture_views.extend(other.texture_views);
         self.samplers.extend(other.samplers);
@@ -127,10 +132,20 @@ impl<A: hal::Api> NonReferencedResources<A> {
         assert!(other.pipeline_layouts.is_empty());
     }
 
+    fn add_temp(&mut self, temp_resource: TempResource<A>) {
+        match temp_resource {
+            TempResource::Buffer(raw) => self.buffers.push(raw),
+            TempResource::ExternalBuffer(raw) => self.external_buffers.push(raw),
+            TempResource::Texture(raw) => self.textures.push(raw),
+        }
+    }
+
     unsafe fn clean(&mut self, device: &A::Device) {
         for raw in self.buffers.drain(..) {
             device.destroy_buffer(raw);
         }
+        // Not ours to destroy, the GPU is just done with them.
+        self.external_buffers.clear();
         for raw in self.textures.drain(..) {
             device.destroy_texture(raw);
         }
@@ -166,6 +181,8 @@ struct ActiveSubmission<A: hal::Api> {
     last_resources: NonReferencedResources<A>,
     mapped: Vec<id::Valid<id::BufferId>>,
     encoders: Vec<EncoderInFlight<A>>,
+    staging_chunks: Vec<StagingChunk<A>>,
+    push_constant_chunks: Vec<PushConstantChunk<A>>,
     work_done_closures: SmallVec<[SubmittedWorkDoneClosure; 1]>,
 }
 
@@ -175,6 +192,8 @@ pub enum WaitIdleError {
     Device(#[from] DeviceError),
     #[error("GPU got stuck :(")]
     StuckGpu,
+    #[error("tried to wait on submission {0}, but the last submission is {1}")]
+    WrongSubmissionIndex(SubmissionIndex, SubmissionIndex),
 }
 
 /// A struct responsible for tracking resource lifetimes.
@@ -223,13 +242,12 @@ impl<A: hal::Api> LifetimeTracker<A> {
         index: SubmissionIndex,
         temp_resources: impl Iterator<Item = TempResource<A>>,
         encoders: Vec<EncoderInFlight<A>>,
+        staging_chunks: Vec<StagingChunk<A>>,
+        push_constant_chunks: Vec<PushConstantChunk<A>>,
     ) {
         let mut last_resources = NonReferencedResources::new();
         for res in temp_resources {
-            match res {
-                TempResource::Buffer(raw) => last_resources.buffers.push(raw),
-                TempResource::Texture(raw) => last_resources.textures.push(raw),
-            }
+            last_resources.add_temp(res);
         }
 
         self.active.alloc().init(ActiveSubmission {
@@ -237,6 +255,8 @@ impl<A: hal::Api> LifetimeTracker<A> {
             last_resources,
             mapped: Vec::new(),
             encoders,
+            staging_chunks,
+            push_constant_chunks,
             work_done_closures: SmallVec::new(),
         });
     }
@@ -264,6 +284,8 @@ impl<A: hal::Api> LifetimeTracker<A> {
         &mut self,
         last_done: SubmissionIndex,
         command_allocator: &Mutex<super::CommandAllocator<A>>,
+        staging_belt: &Mutex<StagingBelt<A>>,
+        push_constants: Option<&PushConstantEmulation<A>>,
     ) -> SmallVec<[SubmittedWorkDoneClosure; 1]> {
         profiling::scope!("triage_submissions");
 
@@ -284,6 +306,15 @@ impl<A: hal::Api> LifetimeTracker<A> {
                 let raw = unsafe { encoder.land() };
                 command_allocator.lock().release_encoder(raw);
             }
+            let mut staging_belt = staging_belt.lock();
+            for chunk in a.staging_chunks {
+                self.free_resources
+                    .buffers
+                    .extend(staging_belt.recycle(chunk));
+            }
+            if let Some(push_constants) = push_constants {
+                push_constants.recycle(a.push_constant_chunks);
+            }
             work_done_closures.extend(a.work_done_closures);
         }
         work_done_closures
@@ -301,15 +332,11 @@ impl<A: hal::Api> LifetimeTracker<A> {
         temp_resource: TempResource<A>,
         last_submit_index: SubmissionIndex,
     ) {
-        let resources = self
-            .active
+        self.active
             .iter_mut()
             .find(|a| a.index == last_submit_index)
-            .map_or(&mut self.free_resources, |a| &mut a.last_resources);
-        match temp_resource {
-            TempResource::Buffer(raw) => resources.buffers.push(raw),
-            TempResource::Texture(raw) => resources.textures.push(raw),
-        }
+            .map_or(&mut self.free_resources, |a| &mut a.last_resources)
+            .add_temp(temp_resource);
     }
 
     pub fn add_work_done_closure(&mut self, closure: SubmittedWorkDoneClosure) -> bool {
@@ -470,17 +497,19 @@ impl<A: HalApi> LifetimeTracker<A> {
                     }
                     log::debug!("Buffer {:?} is detached", id);
 
-                    if let Some(res) = hub.buffers.unregister_locked(id.0, &mut *guard) {
+                    if let Some(mut res) = hub.buffers.unregister_locked(id.0, &mut *guard) {
                         let submit_index = res.life_guard.submission_index.load(Ordering::Acquire);
+                        let raw = res.take_raw();
                         if let resource::BufferMapState::Init { stage_buffer, .. } = res.map_state {
                             self.free_resources.buffers.push(stage_buffer);
                         }
-                        self.active
-                            .iter_mut()
-                            .find(|a| a.index == submit_index)
-                            .map_or(&mut self.free_resources, |a| &mut a.last_resources)
-                            .buffers
-                            .extend(res.raw);
+                        if let Some(raw) = raw {
+                            self.active
+                                .iter_mut()
+                                .find(|a| a.index == submit_index)
+                                .map_or(&mut self.free_resources, |a| &mut a.last_resources)
+                                .add_temp(raw);
+                        }
                     }
                 }
             }
@@ -635,8 +664,9 @@ impl<A: HalApi> LifetimeTracker<A> {
     pub(super) fn handle_mapping<G: GlobalIdentityHandlerFactory>(
         &mut self,
         hub: &Hub<A, G>,
-        raw: &A::Device,
+        device: &super::Device<A>,
         trackers: &Mutex<TrackerSet>,
+        device_lost: bool,
         token: &mut Token<super::Device<A>>,
     ) -> Vec<super::BufferMapPendingClosure> {
         if self.ready_to_map.is_empty() {
@@ -652,11 +682,12 @@ impl<A: HalApi> LifetimeTracker<A> {
             {
                 buffer.map_state = resource::BufferMapState::Idle;
                 log::debug!("Mapping request is dropped because the buffer is destroyed.");
-                if let Some(buf) = hub
+                if let Some(raw) = hub
                     .buffers
                     .unregister_locked(buffer_id.0, &mut *buffer_guard)
+                    .and_then(|mut buf| buf.take_raw())
                 {
-                    self.free_resources.buffers.extend(buf.raw);
+                    self.free_resources.add_temp(raw);
                 }
             } else {
                 let mapping = match std::mem::replace(
@@ -674,11 +705,14 @@ impl<A: HalApi> LifetimeTracker<A> {
                     }
                     _ => panic!("No pending mapping."),
                 };
-                let status = if mapping.range.start != mapping.range.end {
+                let status = if device_lost {
+                    log::debug!("Mapping of buffer {:?} failed: device is lost", buffer_id);
+                    resource::BufferMapAsyncStatus::ContextLost
+                } else if mapping.range.start != mapping.range.end {
                     log::debug!("Buffer {:?} map state -> Active", buffer_id);
                     let host = mapping.op.host;
                     let size = mapping.range.end - mapping.range.start;
-                    match super::map_buffer(raw, buffer, mapping.range.start, size, host) {
+                    match super::map_buffer(device, buffer, mapping.range.start, size, host) {
                         Ok(ptr) => {
                             buffer.map_state = resource::BufferMapState::Active {
                                 ptr,
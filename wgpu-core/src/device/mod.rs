@@ -19,13 +19,26 @@ use smallvec::SmallVec;
 use thiserror::Error;
 use wgt::{BufferAddress, TextureFormat, TextureViewDimension};
 
-use std::{borrow::Cow, iter, marker::PhantomData, mem, ops::Range, ptr, sync::atomic::Ordering};
+use std::{
+    borrow::Cow,
+    iter,
+    marker::PhantomData,
+    mem,
+    ops::Range,
+    ptr,
+    sync::{atomic::Ordering, Arc},
+};
 
 mod life;
+pub(crate) mod push_constants;
 pub mod queue;
+mod staging;
 #[cfg(any(feature = "trace", feature = "replay"))]
 pub mod trace;
 
+pub(crate) use push_constants::{PushConstantChunk, PushConstantWriter};
+pub use staging::{StagingBuffer, StagingReport};
+
 pub const SHADER_STAGE_COUNT: usize = 3;
 const CLEANUP_WAIT_MS: u32 = 5000;
 
@@ -111,17 +124,85 @@ impl RenderPassContext {
 }
 
 pub type BufferMapPendingClosure = (resource::BufferMapOperation, resource::BufferMapAsyncStatus);
+pub type SubmittedWorkDonePendingClosure = (
+    queue::SubmittedWorkDoneClosure,
+    queue::SubmittedWorkDoneStatus,
+);
+
+pub type DeviceLostCallback = unsafe extern "C" fn(
+    reason: wgt::DeviceLostReason,
+    message: *const std::os::raw::c_char,
+    user_data: *mut u8,
+);
+
+/// Callback fired once when the device is lost, either by the backend or by being dropped.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct DeviceLostClosure {
+    pub callback: DeviceLostCallback,
+    pub user_data: *mut u8,
+}
+
+unsafe impl Send for DeviceLostClosure {}
+unsafe impl Sync for DeviceLostClosure {}
+
+impl DeviceLostClosure {
+    unsafe fn call(self, reason: wgt::DeviceLostReason, message: &str) {
+        let message = std::ffi::CString::new(message.replace('\0', "")).unwrap();
+        (self.callback)(reason, message.as_ptr(), self.user_data);
+    }
+}
+
+/// Signal raised whenever a device gets work that a [`Global::device_poll`] call
+/// would eventually complete, such as a submission or a buffer mapping.
+///
+/// Used to drive the device from a background thread without spinning.
+#[derive(Debug, Default)]
+pub struct WorkSignal {
+    /// Highest submission index to wait for, if any work is pending.
+    pending: Mutex<Option<SubmissionIndex>>,
+    condvar: parking_lot::Condvar,
+}
+
+impl WorkSignal {
+    fn notify(&self, submission_index: SubmissionIndex) {
+        let mut pending = self.pending.lock();
+        *pending = Some(pending.map_or(submission_index, |index| index.max(submission_index)));
+        self.condvar.notify_all();
+    }
+
+    /// Wait until some work is pending, or the timeout runs out.
+    ///
+    /// Returns the index of the submission to wait for before polling the device,
+    /// and clears the pending state.
+    pub fn wait(&self, timeout: std::time::Duration) -> Option<SubmissionIndex> {
+        let mut pending = self.pending.lock();
+        if pending.is_none() {
+            let _ = self.condvar.wait_for(&mut pending, timeout);
+        }
+        pending.take()
+    }
+
+    /// Wake up all the threads blocked in [`Self::wait`] without marking any work as pending.
+    pub fn wake(&self) {
+        self.condvar.notify_all();
+    }
+}
 
 #[derive(Default)]
 pub struct UserClosures {
     pub mappings: Vec<BufferMapPendingClosure>,
-    pub submissions: SmallVec<[queue::SubmittedWorkDoneClosure; 1]>,
+    pub submissions: SmallVec<[SubmittedWorkDonePendingClosure; 1]>,
+    pub device_lost: Option<(DeviceLostClosure, wgt::DeviceLostReason, String)>,
 }
 
 impl UserClosures {
     fn extend(&mut self, other: Self) {
         self.mappings.extend(other.mappings);
         self.submissions.extend(other.submissions);
+        if other.device_lost.is_some() {
+            self.device_lost = other.device_lost;
+        }
     }
 
     unsafe fn fire(self) {
@@ -130,22 +211,26 @@ impl UserClosures {
         for (operation, status) in self.mappings {
             (operation.callback)(status, operation.user_data);
         }
-        for closure in self.submissions {
-            (closure.callback)(closure.user_data);
+        for (closure, status) in self.submissions {
+            (closure.callback)(status, closure.user_data);
+        }
+        if let Some((closure, reason, message)) = self.device_lost {
+            closure.call(reason, &message);
         }
     }
 }
 
 fn map_buffer<A: hal::Api>(
-    raw: &A::Device,
+    device: &Device<A>,
     buffer: &mut resource::Buffer<A>,
     offset: BufferAddress,
     size: BufferAddress,
     kind: HostMap,
 ) -> Result<ptr::NonNull<u8>, resource::BufferAccessError> {
+    let raw = &device.raw;
     let mapping = unsafe {
         raw.map_buffer(buffer.raw.as_ref().unwrap(), offset..offset + size)
-            .map_err(DeviceError::from)?
+            .map_err(|e| device.handle_hal_error(e))?
     };
 
     buffer.sync_mapped_writes = match kind {
@@ -176,7 +261,7 @@ fn map_buffer<A: hal::Api>(
                 mapping
                     .ptr
                     .as_ptr()
-                    .offset(uninitialized_range.start as isize),
+                    .offset((uninitialized_range.start - offset) as isize),
                 0,
                 num_bytes as usize,
             )
@@ -247,6 +332,9 @@ pub struct Device<A: hal::Api> {
     //Note: The submission index here corresponds to the last submission that is done.
     pub(crate) life_guard: LifeGuard,
     command_allocator: Mutex<CommandAllocator<A>>,
+    staging_belt: Mutex<staging::StagingBelt<A>>,
+    /// Set if `Features::PUSH_CONSTANTS` is enabled, but the adapter doesn't have them.
+    pub(crate) push_constants: Option<push_constants::PushConstantEmulation<A>>,
     pub(crate) active_submission_index: SubmissionIndex,
     fence: A::Fence,
     /// Has to be locked temporarily only (locked last)
@@ -261,10 +349,65 @@ pub struct Device<A: hal::Api> {
     //TODO: move this behind another mutex. This would allow several methods to switch
     // to borrow Device immutably, such as `write_buffer`, `write_texture`, and `buffer_unmap`.
     pending_writes: queue::PendingWrites<A>,
+    /// Description of why the device was lost, `None` while it's still valid.
+    lost: Mutex<Option<String>>,
+    lost_closure: Mutex<Option<DeviceLostClosure>>,
+    work_signal: Arc<WorkSignal>,
     #[cfg(feature = "trace")]
     pub(crate) trace: Option<Mutex<trace::Trace>>,
 }
 
+/// Encoding of the actions in a trace.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TraceFormat {
+    /// Human-readable RON array, stored in `trace.ron`.
+    Ron,
+    /// Sequence of length-prefixed `bincode` records, stored in `trace.bin`.
+    Binary,
+}
+
+/// Describes where and how to record an API trace.
+#[derive(Clone, Copy, Debug)]
+pub struct TraceDescriptor<'a> {
+    /// Directory to put the trace file and the binary data into.
+    pub path: &'a std::path::Path,
+    pub format: TraceFormat,
+}
+
+/// Traces into the given directory in the RON format.
+impl<'a> From<&'a std::path::Path> for TraceDescriptor<'a> {
+    fn from(path: &'a std::path::Path) -> Self {
+        Self {
+            path,
+            format: TraceFormat::Ron,
+        }
+    }
+}
+
+/// Describes how the API calls of a new device can be traced.
+#[derive(Clone, Copy, Debug)]
+pub enum DeviceTrace<'a> {
+    /// Record a trace from the creation of the device on.
+    Record(TraceDescriptor<'a>),
+    /// Don't record anything yet, but allow a trace to be started later on.
+    ///
+    /// The device has to keep the contents of the resources readable for that.
+    OnDemand,
+}
+
+impl<'a> From<TraceDescriptor<'a>> for DeviceTrace<'a> {
+    fn from(desc: TraceDescriptor<'a>) -> Self {
+        Self::Record(desc)
+    }
+}
+
+/// Records a RON trace into the given directory.
+impl<'a> From<&'a std::path::Path> for DeviceTrace<'a> {
+    fn from(path: &'a std::path::Path) -> Self {
+        Self::Record(path.into())
+    }
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum CreateDeviceError {
     #[error("not enough memory left")]
@@ -272,6 +415,27 @@ pub enum CreateDeviceError {
 }
 
 impl<A: hal::Api> Device<A> {
+    pub(crate) fn is_lost(&self) -> bool {
+        self.lost.lock().is_some()
+    }
+
+    /// Mark the device as lost. Pending callbacks are failed by the next `maintain`.
+    fn lose(&self, message: &str) {
+        let mut lost = self.lost.lock();
+        if lost.is_none() {
+            log::error!("Device is lost: {}", message);
+            *lost = Some(message.to_string());
+        }
+    }
+
+    /// Convert a HAL error, marking the device as lost if that's what happened.
+    pub(crate) fn handle_hal_error(&self, error: hal::DeviceError) -> DeviceError {
+        if let hal::DeviceError::Lost = error {
+            self.lose("the backend reported the device as lost");
+        }
+        error.into()
+    }
+
     pub(crate) fn require_features(&self, feature: wgt::Features) -> Result<(), MissingFeatures> {
         if self.features.contains(feature) {
             Ok(())
@@ -299,11 +463,13 @@ impl<A: HalApi> Device<A> {
         adapter_id: Stored<id::AdapterId>,
         alignments: hal::Alignments,
         downlevel: wgt::DownlevelCapabilities,
+        adapter_limits: &wgt::Limits,
+        emulated_features: wgt::Features,
         desc: &DeviceDescriptor,
-        trace_path: Option<&std::path::Path>,
+        device_trace: Option<DeviceTrace>,
     ) -> Result<Self, CreateDeviceError> {
         #[cfg(not(feature = "trace"))]
-        if let Some(_) = trace_path {
+        if let Some(_) = device_trace {
             log::error!("Feature 'trace' is not enabled");
         }
         let fence =
@@ -316,6 +482,19 @@ impl<A: HalApi> Device<A> {
             .acquire_encoder(&open.device, &open.queue)
             .map_err(|_| CreateDeviceError::OutOfMemory)?;
         let pending_writes = queue::PendingWrites::new(pending_encoder);
+        let push_constants = if desc.features.contains(wgt::Features::PUSH_CONSTANTS)
+            && emulated_features.contains(wgt::Features::PUSH_CONSTANTS)
+        {
+            let emulation = push_constants::PushConstantEmulation::new(
+                &open.device,
+                adapter_limits,
+                &alignments,
+            )
+            .map_err(|_| CreateDeviceError::OutOfMemory)?;
+            Some(emulation)
+        } else {
+            None
+        };
 
         Ok(Self {
             raw: open.device,
@@ -323,33 +502,46 @@ impl<A: HalApi> Device<A> {
             queue: open.queue,
             life_guard: LifeGuard::new("<device>"),
             command_allocator: Mutex::new(com_alloc),
+            staging_belt: Mutex::new(staging::StagingBelt::new()),
+            push_constants,
             active_submission_index: 0,
             fence,
             trackers: Mutex::new(TrackerSet::new(A::VARIANT)),
             life_tracker: Mutex::new(life::LifetimeTracker::new()),
             temp_suspected: life::SuspectedResources::default(),
             #[cfg(feature = "trace")]
-            trace: trace_path.and_then(|path| match trace::Trace::new(path) {
-                Ok(mut trace) => {
-                    trace.add(trace::Action::Init {
+            trace: device_trace.map(|device_trace| {
+                let mut trace = trace::Trace::new(matches!(device_trace, DeviceTrace::OnDemand));
+                if let DeviceTrace::Record(trace_desc) = device_trace {
+                    let init = trace::Action::Init {
                         desc: desc.clone(),
                         backend: A::VARIANT,
-                    });
-                    Some(Mutex::new(trace))
-                }
-                Err(e) => {
-                    log::error!("Unable to start a trace in '{:?}': {:?}", path, e);
-                    None
+                    };
+                    if let Err(e) = trace.start(trace_desc, init, Vec::new()) {
+                        log::error!(
+                            "Unable to start a trace in '{:?}': {:?}",
+                            trace_desc.path,
+                            e
+                        );
+                    }
                 }
+                Mutex::new(trace)
             }),
             alignments,
             limits: desc.limits.clone(),
             features: desc.features,
             downlevel,
             pending_writes,
+            lost: Mutex::new(None),
+            lost_closure: Mutex::new(None),
+            work_signal: Arc::new(WorkSignal::default()),
         })
     }
 
+    pub(crate) fn staging_report(&self) -> StagingReport {
+        self.staging_belt.lock().report()
+    }
+
     fn lock_life<'this, 'token: 'this>(
         &'this self,
         //TODO: fix this - the token has to be borrowed for the lock
@@ -380,30 +572,57 @@ impl<A: HalApi> Device<A> {
         );
         life_tracker.triage_mapped(hub, token);
 
-        let last_done_index = if force_wait {
+        let fence_value = if self.is_lost() {
+            Err(hal::DeviceError::Lost)
+        } else if force_wait {
             let current_index = self.active_submission_index;
-            unsafe {
-                self.raw
-                    .wait(&self.fence, current_index, CLEANUP_WAIT_MS)
-                    .map_err(DeviceError::from)?
-            };
-            current_index
+            unsafe { self.raw.wait(&self.fence, current_index, CLEANUP_WAIT_MS) }
+                .map(|_| current_index)
         } else {
-            unsafe {
-                self.raw
-                    .get_fence_value(&self.fence)
-                    .map_err(DeviceError::from)?
+            unsafe { self.raw.get_fence_value(&self.fence) }
+        };
+        let (last_done_index, submission_status) = match fence_value {
+            Ok(index) => (index, queue::SubmittedWorkDoneStatus::Success),
+            Err(hal::DeviceError::Lost) => {
+                // Nothing in flight is ever going to finish, so retire everything
+                // and let the user know through the callbacks.
+                self.lose("the backend reported the device as lost");
+                (
+                    SubmissionIndex::MAX,
+                    queue::SubmittedWorkDoneStatus::DeviceLost,
+                )
             }
+            Err(error) => return Err(DeviceError::from(error).into()),
         };
-
-        let submission_closures =
-            life_tracker.triage_submissions(last_done_index, &self.command_allocator);
-        let mapping_closures = life_tracker.handle_mapping(hub, &self.raw, &self.trackers, token);
+        let lost = submission_status == queue::SubmittedWorkDoneStatus::DeviceLost;
+
+        let submission_closures = life_tracker
+            .triage_submissions(
+                last_done_index,
+                &self.command_allocator,
+                &self.staging_belt,
+                self.push_constants.as_ref(),
+            )
+            .into_iter()
+            .map(|closure| (closure, submission_status))
+            .collect();
+        let mapping_closures = life_tracker.handle_mapping(hub, self, &self.trackers, lost, token);
         life_tracker.cleanup(&self.raw);
 
+        let device_lost = if lost {
+            let message = self.lost.lock().clone().unwrap_or_default();
+            self.lost_closure
+                .lock()
+                .take()
+                .map(|closure| (closure, wgt::DeviceLostReason::Unknown, message))
+        } else {
+            None
+        };
+
         Ok(UserClosures {
             mappings: mapping_closures,
             submissions: submission_closures,
+            device_lost,
         })
     }
 
@@ -475,6 +694,15 @@ impl<A: HalApi> Device<A> {
         self.temp_suspected.clear();
     }
 
+    /// Whether a trace can be started at any time, which requires the
+    /// resources to be created with their contents readable.
+    #[cfg(feature = "trace")]
+    fn is_trace_on_demand(&self) -> bool {
+        self.trace
+            .as_ref()
+            .map_or(false, |trace| trace.lock().is_on_demand())
+    }
+
     fn create_buffer(
         &self,
         self_id: id::DeviceId,
@@ -501,6 +729,16 @@ impl<A: HalApi> Device<A> {
             // This is done on demand using fill_buffer which requires write transfer usage!
             usage |= hal::BufferUses::COPY_DST;
         }
+        // Allow the contents to be read back if a trace is started later on.
+        // Mappable buffers are read directly instead.
+        #[cfg(feature = "trace")]
+        if self.is_trace_on_demand()
+            && !desc
+                .usage
+                .intersects(wgt::BufferUsages::MAP_READ | wgt::BufferUsages::MAP_WRITE)
+        {
+            usage |= hal::BufferUses::COPY_SRC;
+        }
 
         let actual_size = if desc.size == 0 {
             wgt::COPY_BUFFER_ALIGNMENT
@@ -517,10 +755,12 @@ impl<A: HalApi> Device<A> {
             usage,
             memory_flags,
         };
-        let buffer = unsafe { self.raw.create_buffer(&hal_desc) }.map_err(DeviceError::from)?;
+        let buffer =
+            unsafe { self.raw.create_buffer(&hal_desc) }.map_err(|e| self.handle_hal_error(e))?;
 
         Ok(resource::Buffer {
             raw: Some(buffer),
+            owns_raw: true,
             device_id: Stored {
                 value: id::Valid(self_id),
                 ref_count: self.life_guard.add_ref(),
@@ -534,6 +774,43 @@ impl<A: HalApi> Device<A> {
         })
     }
 
+    fn create_buffer_from_hal(
+        &self,
+        hal_buffer: A::Buffer,
+        self_id: id::DeviceId,
+        desc: &resource::BufferDescriptor,
+        owns_raw: bool,
+    ) -> Result<resource::Buffer<A>, resource::CreateBufferError> {
+        debug_assert_eq!(self_id.backend(), A::VARIANT);
+
+        if desc.usage.is_empty() {
+            return Err(resource::CreateBufferError::EmptyUsage);
+        }
+        if desc.mapped_at_creation {
+            return Err(resource::CreateBufferError::MappedAtCreationFromHal);
+        }
+
+        // The contents are whatever the buffer was given before the import,
+        // so there is nothing for us to zero out.
+        let mut initialization_status = MemoryInitTracker::new(desc.size);
+        initialization_status.clear(0..desc.size);
+
+        Ok(resource::Buffer {
+            raw: Some(hal_buffer),
+            owns_raw,
+            device_id: Stored {
+                value: id::Valid(self_id),
+                ref_count: self.life_guard.add_ref(),
+            },
+            usage: desc.usage,
+            size: desc.size,
+            initialization_status,
+            sync_mapped_writes: None,
+            map_state: resource::BufferMapState::Idle,
+            life_guard: LifeGuard::new(desc.label.borrow_or_default()),
+        })
+    }
+
     fn create_texture_from_hal(
         &self,
         hal_texture: A::Texture,
@@ -611,7 +888,13 @@ impl<A: HalApi> Device<A> {
         adapter: &crate::instance::Adapter<A>,
         desc: &resource::TextureDescriptor,
     ) -> Result<resource::Texture<A>, resource::CreateTextureError> {
-        let hal_usage = conv::map_texture_usage(desc.usage, desc.format.into());
+        #[allow(unused_mut)]
+        let mut hal_usage = conv::map_texture_usage(desc.usage, desc.format.into());
+        // Allow the contents to be read back if a trace is started later on.
+        #[cfg(feature = "trace")]
+        if self.is_trace_on_demand() && trace::is_texture_snapshot_supported(desc) {
+            hal_usage |= hal::TextureUses::COPY_SRC;
+        }
         let hal_desc = hal::TextureDescriptor {
             label: desc.label.borrow_option(),
             size: desc.size,
@@ -625,7 +908,7 @@ impl<A: HalApi> Device<A> {
         let raw = unsafe {
             self.raw
                 .create_texture(&hal_desc)
-                .map_err(DeviceError::from)?
+                .map_err(|e| self.handle_hal_error(e))?
         };
 
         self.create_texture_from_hal(raw, self_id, adapter, desc)
@@ -681,9 +964,9 @@ impl<A: HalApi> Device<A> {
                 total: level_end,
             });
         }
-        if required_layer_count > layer_end {
+        if required_layer_count > layer_end || desc.range.base_array_layer >= layer_end {
             return Err(resource::CreateTextureViewError::TooManyArrayLayers {
-                requested: required_layer_count,
+                requested: required_layer_count.max(desc.range.base_array_layer + 1),
                 total: layer_end,
             });
         };
@@ -884,7 +1167,7 @@ impl<A: HalApi> Device<A> {
         let raw = unsafe {
             self.raw
                 .create_sampler(&hal_desc)
-                .map_err(DeviceError::from)?
+                .map_err(|e| self.handle_hal_error(e))?
         };
         Ok(resource::Sampler {
             raw,
@@ -905,7 +1188,7 @@ impl<A: HalApi> Device<A> {
         desc: &pipeline::ShaderModuleDescriptor<'a>,
         source: pipeline::ShaderModuleSource<'a>,
     ) -> Result<pipeline::ShaderModule<A>, pipeline::CreateShaderModuleError> {
-        let module = match source {
+        let mut module = match source {
             pipeline::ShaderModuleSource::Wgsl(code) => {
                 profiling::scope!("naga::wgsl::parse_str");
                 // TODO: refactor the corresponding Naga error to be owned, and then
@@ -943,9 +1226,19 @@ impl<A: HalApi> Device<A> {
             self.features
                 .contains(wgt::Features::SHADER_PRIMITIVE_INDEX),
         );
-        let info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), caps)
+        let mut info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), caps)
             .validate(&module)?;
         let interface = validation::Interface::new(&module, &info, self.features);
+
+        if let Some(ref push_constants) = self.push_constants {
+            // The interface keeps describing the push constants, but the backend
+            // gets them as a uniform buffer, which it has to be able to handle.
+            if push_constants.rewrite_module(&mut module) {
+                caps.remove(Caps::PUSH_CONSTANT);
+                info = naga::valid::Validator::new(naga::valid::ValidationFlags::all(), caps)
+                    .validate(&module)?;
+            }
+        }
         let hal_shader = hal::ShaderInput::Naga(hal::NagaShader { module, info });
 
         let hal_desc = hal::ShaderModuleDescriptor {
@@ -956,7 +1249,7 @@ impl<A: HalApi> Device<A> {
             Err(error) => {
                 return Err(match error {
                     hal::ShaderError::Device(error) => {
-                        pipeline::CreateShaderModuleError::Device(error.into())
+                        pipeline::CreateShaderModuleError::Device(self.handle_hal_error(error))
                     }
                     hal::ShaderError::Compilation(ref msg) => {
                         log::error!("Shader error: {}", msg);
@@ -995,7 +1288,7 @@ impl<A: HalApi> Device<A> {
             Err(error) => {
                 return Err(match error {
                     hal::ShaderError::Device(error) => {
-                        pipeline::CreateShaderModuleError::Device(error.into())
+                        pipeline::CreateShaderModuleError::Device(self.handle_hal_error(error))
                     }
                     hal::ShaderError::Compilation(ref msg) => {
                         log::error!("Shader error: {}", msg);
@@ -1165,7 +1458,7 @@ impl<A: HalApi> Device<A> {
         let raw = unsafe {
             self.raw
                 .create_bind_group_layout(&hal_desc)
-                .map_err(DeviceError::from)?
+                .map_err(|e| self.handle_hal_error(e))?
         };
 
         let mut count_validator = binding_model::BindingTypeMaxCountValidator::default();
@@ -1544,7 +1837,7 @@ impl<A: HalApi> Device<A> {
         let raw = unsafe {
             self.raw
                 .create_bind_group(&hal_desc)
-                .map_err(DeviceError::from)?
+                .map_err(|e| self.handle_hal_error(e))?
         };
 
         Ok(binding_model::BindGroup {
@@ -1744,22 +2037,32 @@ impl<A: HalApi> Device<A> {
             .validate(&self.limits)
             .map_err(Error::TooManyBindings)?;
 
-        let bgl_vec = desc
+        let mut bgl_vec = desc
             .bind_group_layouts
             .iter()
             .map(|&id| &bgl_guard.get(id).unwrap().raw)
             .collect::<Vec<_>>();
+        let mut push_constant_ranges = desc.push_constant_ranges.as_ref();
+        if let Some(ref push_constants) = self.push_constants {
+            if !push_constant_ranges.is_empty() {
+                push_constants
+                    .validate_layout(count_validator)
+                    .map_err(Error::TooManyBindings)?;
+                push_constants.extend_bind_group_layouts(&mut bgl_vec);
+                push_constant_ranges = &[];
+            }
+        }
         let hal_desc = hal::PipelineLayoutDescriptor {
             label: desc.label.borrow_option(),
             flags: hal::PipelineLayoutFlags::BASE_VERTEX_INSTANCE,
             bind_group_layouts: &bgl_vec,
-            push_constant_ranges: desc.push_constant_ranges.as_ref(),
+            push_constant_ranges,
         };
 
         let raw = unsafe {
             self.raw
                 .create_pipeline_layout(&hal_desc)
-                .map_err(DeviceError::from)?
+                .map_err(|e| self.handle_hal_error(e))?
         };
 
         Ok(binding_model::PipelineLayout {
@@ -1917,7 +2220,7 @@ impl<A: HalApi> Device<A> {
             unsafe { self.raw.create_compute_pipeline(&pipeline_desc) }.map_err(
                 |err| match err {
                     hal::PipelineError::Device(error) => {
-                        pipeline::CreateComputePipelineError::Device(error.into())
+                        pipeline::CreateComputePipelineError::Device(self.handle_hal_error(error))
                     }
                     hal::PipelineError::Linkage(_stages, msg) => {
                         pipeline::CreateComputePipelineError::Internal(msg)
@@ -2307,7 +2610,7 @@ impl<A: HalApi> Device<A> {
             unsafe { self.raw.create_render_pipeline(&pipeline_desc) }.map_err(
                 |err| match err {
                     hal::PipelineError::Device(error) => {
-                        pipeline::CreateRenderPipelineError::Device(error.into())
+                        pipeline::CreateRenderPipelineError::Device(self.handle_hal_error(error))
                     }
                     hal::PipelineError::Linkage(stage, msg) => {
                         pipeline::CreateRenderPipelineError::Internal { stage, error: msg }
@@ -2392,18 +2695,21 @@ impl<A: HalApi> Device<A> {
         let last_done_index = unsafe {
             self.raw
                 .get_fence_value(&self.fence)
-                .map_err(DeviceError::from)?
+                .map_err(|e| self.handle_hal_error(e))?
         };
         if last_done_index < submission_index {
             log::info!("Waiting for submission {:?}", submission_index);
             unsafe {
                 self.raw
                     .wait(&self.fence, submission_index, !0)
-                    .map_err(DeviceError::from)?
+                    .map_err(|e| self.handle_hal_error(e))?
             };
-            let closures = self
-                .lock_life(token)
-                .triage_submissions(submission_index, &self.command_allocator);
+            let closures = self.lock_life(token).triage_submissions(
+                submission_index,
+                &self.command_allocator,
+                &self.staging_belt,
+                self.push_constants.as_ref(),
+            );
             assert!(
                 closures.is_empty(),
                 "wait_for_submit is not expected to work with closures"
@@ -2424,8 +2730,11 @@ impl<A: HalApi> Device<A> {
             wgt::QueryType::Timestamp => {
                 self.require_features(wgt::Features::TIMESTAMP_QUERY)?;
             }
-            wgt::QueryType::PipelineStatistics(..) => {
+            wgt::QueryType::PipelineStatistics(statistics) => {
                 self.require_features(wgt::Features::PIPELINE_STATISTICS_QUERY)?;
+                if statistics.is_empty() {
+                    return Err(Error::NoPipelineStatistics);
+                }
             }
         }
 
@@ -2454,8 +2763,8 @@ impl<A: HalApi> Device<A> {
 }
 
 impl<A: hal::Api> Device<A> {
-    pub(crate) fn destroy_buffer(&self, buffer: resource::Buffer<A>) {
-        if let Some(raw) = buffer.raw {
+    pub(crate) fn destroy_buffer(&self, mut buffer: resource::Buffer<A>) {
+        if let Some(queue::TempResource::Buffer(raw)) = buffer.take_raw() {
             unsafe {
                 self.raw.destroy_buffer(raw);
             }
@@ -2464,6 +2773,9 @@ impl<A: hal::Api> Device<A> {
 
     pub(crate) fn destroy_command_buffer(&self, cmd_buf: command::CommandBuffer<A>) {
         let mut baked = cmd_buf.into_baked();
+        if let Some(ref push_constants) = self.push_constants {
+            push_constants.recycle(baked.push_constant_chunks.drain(..));
+        }
         unsafe {
             baked.encoder.reset_all(baked.list.into_iter());
         }
@@ -2480,7 +2792,12 @@ impl<A: hal::Api> Device<A> {
         if let Err(error) = unsafe { self.raw.wait(&self.fence, current_index, CLEANUP_WAIT_MS) } {
             log::error!("failed to wait for the device: {:?}", error);
         }
-        let _ = life_tracker.triage_submissions(current_index, &self.command_allocator);
+        let _ = life_tracker.triage_submissions(
+            current_index,
+            &self.command_allocator,
+            &self.staging_belt,
+            self.push_constants.as_ref(),
+        );
         life_tracker.cleanup(&self.raw);
         #[cfg(feature = "trace")]
         {
@@ -2491,6 +2808,10 @@ impl<A: hal::Api> Device<A> {
     pub(crate) fn dispose(self) {
         self.pending_writes.dispose(&self.raw);
         self.command_allocator.into_inner().dispose(&self.raw);
+        self.staging_belt.into_inner().dispose(&self.raw);
+        if let Some(push_constants) = self.push_constants {
+            push_constants.dispose(&self.raw);
+        }
         unsafe {
             self.raw.destroy_fence(self.fence);
             self.raw.exit(self.queue);
@@ -2510,6 +2831,18 @@ impl<A: hal::Api> crate::hub::Resource for Device<A> {
 #[error("device is invalid")]
 pub struct InvalidDevice;
 
+#[derive(Clone, Debug, Error)]
+pub enum StartTraceError {
+    #[error(transparent)]
+    Device(#[from] DeviceError),
+    #[error("unable to create the trace file")]
+    Io(#[source] Arc<std::io::Error>),
+    #[error("feature 'trace' is not enabled")]
+    NotEnabled,
+    #[error("tracing on demand is not enabled on this device, it has to be requested when the device is created")]
+    NotActive,
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum DeviceError {
     #[error("parent device is invalid")]
@@ -2605,6 +2938,26 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         surface.get_preferred_format(adapter)
     }
 
+    pub fn surface_get_capabilities<A: HalApi>(
+        &self,
+        surface_id: id::SurfaceId,
+        adapter_id: id::AdapterId,
+    ) -> Result<wgt::SurfaceCapabilities, instance::GetSurfaceCapabilitiesError> {
+        let hub = A::hub(self);
+        let mut token = Token::root();
+
+        let (surface_guard, mut token) = self.surfaces.read(&mut token);
+        let (adapter_guard, mut _token) = hub.adapters.read(&mut token);
+        let adapter = adapter_guard
+            .get(adapter_id)
+            .map_err(|_| instance::GetSurfaceCapabilitiesError::InvalidAdapter)?;
+        let surface = surface_guard
+            .get(surface_id)
+            .map_err(|_| instance::GetSurfaceCapabilitiesError::InvalidSurface)?;
+
+        surface.get_capabilities(adapter)
+    }
+
     pub fn device_features<A: HalApi>(
         &self,
         device_id: id::DeviceId,
@@ -2682,7 +3035,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             } else if desc.usage.contains(wgt::BufferUsages::MAP_WRITE) {
                 // buffer is mappable, so we are just doing that at start
                 let map_size = buffer.size;
-                let ptr = match map_buffer(&device.raw, &mut buffer, 0, map_size, HostMap::Write) {
+                let ptr = match map_buffer(device, &mut buffer, 0, map_size, HostMap::Write) {
                     Ok(ptr) => ptr,
                     Err(e) => {
                         let raw = buffer.raw.unwrap();
@@ -2728,7 +3081,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             queue::TempResource::Buffer(stage_buffer),
                             !0,
                         );
-                        break DeviceError::from(e).into();
+                        break device.handle_hal_error(e).into();
                     }
                 };
 
@@ -2807,6 +3160,17 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .get_mut(buffer_id)
             .map_err(|_| resource::BufferAccessError::Invalid)?;
         check_buffer_usage(buffer.usage, wgt::BufferUsages::MAP_WRITE)?;
+        let end = offset + data.len() as BufferAddress;
+        if end > buffer.size {
+            return Err(resource::BufferAccessError::OutOfBoundsOverrun {
+                index: end,
+                max: buffer.size,
+            });
+        }
+        match buffer.map_state {
+            resource::BufferMapState::Idle => (),
+            _ => return Err(resource::BufferAccessError::AlreadyMapped),
+        }
         //assert!(buffer isn't used by the GPU);
 
         #[cfg(feature = "trace")]
@@ -2821,12 +3185,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             });
         }
 
-        let raw_buf = buffer.raw.as_ref().unwrap();
+        let raw_buf = buffer
+            .raw
+            .as_ref()
+            .ok_or(resource::BufferAccessError::Destroyed)?;
         unsafe {
             let mapping = device
                 .raw
                 .map_buffer(raw_buf, offset..offset + data.len() as u64)
-                .map_err(DeviceError::from)?;
+                .map_err(|e| device.handle_hal_error(e))?;
             ptr::copy_nonoverlapping(data.as_ptr(), mapping.ptr.as_ptr(), data.len());
             if !mapping.is_coherent {
                 device
@@ -2836,7 +3203,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             device
                 .raw
                 .unmap_buffer(raw_buf)
-                .map_err(DeviceError::from)?;
+                .map_err(|e| device.handle_hal_error(e))?;
         }
 
         Ok(())
@@ -2864,14 +3231,28 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .get_mut(buffer_id)
             .map_err(|_| resource::BufferAccessError::Invalid)?;
         check_buffer_usage(buffer.usage, wgt::BufferUsages::MAP_READ)?;
+        let end = offset + data.len() as BufferAddress;
+        if end > buffer.size {
+            return Err(resource::BufferAccessError::OutOfBoundsOverrun {
+                index: end,
+                max: buffer.size,
+            });
+        }
+        match buffer.map_state {
+            resource::BufferMapState::Idle => (),
+            _ => return Err(resource::BufferAccessError::AlreadyMapped),
+        }
         //assert!(buffer isn't used by the GPU);
 
-        let raw_buf = buffer.raw.as_ref().unwrap();
+        let raw_buf = buffer
+            .raw
+            .as_ref()
+            .ok_or(resource::BufferAccessError::Destroyed)?;
         unsafe {
             let mapping = device
                 .raw
                 .map_buffer(raw_buf, offset..offset + data.len() as u64)
-                .map_err(DeviceError::from)?;
+                .map_err(|e| device.handle_hal_error(e))?;
             if !mapping.is_coherent {
                 device.raw.invalidate_mapped_ranges(
                     raw_buf,
@@ -2882,7 +3263,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             device
                 .raw
                 .unmap_buffer(raw_buf)
-                .map_err(DeviceError::from)?;
+                .map_err(|e| device.handle_hal_error(e))?;
         }
 
         Ok(())
@@ -2917,11 +3298,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             trace.lock().add(trace::Action::FreeBuffer(buffer_id));
         }
 
-        let raw = buffer
-            .raw
-            .take()
+        let temp = buffer
+            .take_raw()
             .ok_or(resource::DestroyError::AlreadyDestroyed)?;
-        let temp = queue::TempResource::Buffer(raw);
 
         if device.pending_writes.dst_buffers.contains(&buffer_id) {
             device.pending_writes.temp_resources.push(temp);
@@ -3036,6 +3415,76 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         (id, Some(error))
     }
 
+    /// Register `hal_buffer` as a buffer of `device_id`, with all of its
+    /// contents considered initialized.
+    ///
+    /// If `owns_raw` is false, wgpu never destroys `hal_buffer`. It's only
+    /// dropped once wgpu is done with it, and the underlying handle stays
+    /// the caller's to destroy.
+    ///
+    /// # Safety
+    ///
+    /// - `hal_buffer` must be created from `device_id` corresponding raw handle.
+    /// - `hal_buffer` must be created respecting `desc`, and support
+    ///   all the usages wgpu needs for `desc.usage`.
+    /// - If `owns_raw` is false, the underlying handle must outlive
+    ///   any use of the buffer by wgpu.
+    pub unsafe fn create_buffer_from_hal<A: HalApi>(
+        &self,
+        hal_buffer: A::Buffer,
+        device_id: id::DeviceId,
+        desc: &resource::BufferDescriptor,
+        owns_raw: bool,
+        id_in: Input<G, id::BufferId>,
+    ) -> (id::BufferId, Option<resource::CreateBufferError>) {
+        profiling::scope!("create_buffer", "Device");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let fid = hub.buffers.prepare(id_in);
+
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let error = loop {
+            let device = match device_guard.get(device_id) {
+                Ok(device) => device,
+                Err(_) => break DeviceError::Invalid.into(),
+            };
+
+            // NB: Any change done through the raw buffer handle will not be recorded in the replay
+            #[cfg(feature = "trace")]
+            if let Some(ref trace) = device.trace {
+                trace
+                    .lock()
+                    .add(trace::Action::CreateBuffer(fid.id(), desc.clone()));
+            }
+
+            let buffer = match device.create_buffer_from_hal(hal_buffer, device_id, desc, owns_raw)
+            {
+                Ok(buffer) => buffer,
+                Err(error) => break error,
+            };
+            let ref_count = buffer.life_guard.add_ref();
+
+            let id = fid.assign(buffer, &mut token);
+            log::info!("Created buffer {:?} from HAL with {:?}", id, desc);
+
+            device
+                .trackers
+                .lock()
+                .buffers
+                .init(
+                    id,
+                    ref_count,
+                    BufferState::with_usage(hal::BufferUses::empty()),
+                )
+                .unwrap();
+            return (id.0, None);
+        };
+
+        let id = fid.assign_error(desc.label.borrow_or_default(), &mut token);
+        (id, Some(error))
+    }
+
     /// # Safety
     ///
     /// - `hal_texture` must be created from `device_id` corresponding raw handle.
@@ -3095,6 +3544,133 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         (id, Some(error))
     }
 
+    /// Call `hal_buffer_callback` with the raw buffer behind `id`,
+    /// or `None` if the buffer is invalid, destroyed, or not from backend `A`.
+    ///
+    /// # Safety
+    ///
+    /// - The raw buffer must not be destroyed, or used in a way
+    ///   that conflicts with the usage tracked by wgpu.
+    pub unsafe fn buffer_as_hal<A: HalApi, F: FnOnce(Option<&A::Buffer>) -> R, R>(
+        &self,
+        id: id::BufferId,
+        hal_buffer_callback: F,
+    ) -> R {
+        profiling::scope!("as_hal", "Buffer");
+
+        if id.backend() != A::VARIANT {
+            return hal_buffer_callback(None);
+        }
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (guard, _) = hub.buffers.read(&mut token);
+        let buffer = guard.get(id).ok();
+        hal_buffer_callback(buffer.and_then(|buffer| buffer.raw.as_ref()))
+    }
+
+    /// Call `hal_texture_callback` with the raw texture behind `id`,
+    /// or `None` if the texture is invalid, destroyed, or not from backend `A`.
+    ///
+    /// # Safety
+    ///
+    /// - The raw texture must not be destroyed, or used in a way
+    ///   that conflicts with the usage tracked by wgpu.
+    pub unsafe fn texture_as_hal<A: HalApi, F: FnOnce(Option<&A::Texture>) -> R, R>(
+        &self,
+        id: id::TextureId,
+        hal_texture_callback: F,
+    ) -> R {
+        profiling::scope!("as_hal", "Texture");
+
+        if id.backend() != A::VARIANT {
+            return hal_texture_callback(None);
+        }
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (guard, _) = hub.textures.read(&mut token);
+        let texture = guard.get(id).ok();
+        hal_texture_callback(texture.and_then(|texture| texture.inner.as_raw()))
+    }
+
+    /// Call `hal_texture_view_callback` with the raw texture view behind `id`,
+    /// or `None` if the texture view is invalid or not from backend `A`.
+    ///
+    /// # Safety
+    ///
+    /// - The raw texture view must not be destroyed.
+    pub unsafe fn texture_view_as_hal<A: HalApi, F: FnOnce(Option<&A::TextureView>) -> R, R>(
+        &self,
+        id: id::TextureViewId,
+        hal_texture_view_callback: F,
+    ) -> R {
+        profiling::scope!("as_hal", "TextureView");
+
+        if id.backend() != A::VARIANT {
+            return hal_texture_view_callback(None);
+        }
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (guard, _) = hub.texture_views.read(&mut token);
+        let texture_view = guard.get(id).ok();
+        hal_texture_view_callback(texture_view.map(|texture_view| &texture_view.raw))
+    }
+
+    /// Call `hal_device_callback` with the raw device behind `id`,
+    /// or `None` if the device is invalid or not from backend `A`.
+    ///
+    /// # Safety
+    ///
+    /// - The raw device must not be destroyed, and the objects created
+    ///   with it must be destroyed before the device is dropped.
+    pub unsafe fn device_as_hal<A: HalApi, F: FnOnce(Option<&A::Device>) -> R, R>(
+        &self,
+        id: id::DeviceId,
+        hal_device_callback: F,
+    ) -> R {
+        profiling::scope!("as_hal", "Device");
+
+        if id.backend() != A::VARIANT {
+            return hal_device_callback(None);
+        }
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (guard, _) = hub.devices.read(&mut token);
+        let device = guard.get(id).ok();
+        hal_device_callback(device.map(|device| &device.raw))
+    }
+
+    /// Call `hal_queue_callback` with the raw queue behind `id`,
+    /// or `None` if the queue is invalid or not from backend `A`.
+    ///
+    /// The queue stays locked while the callback runs, so no other
+    /// submission can happen in the meantime.
+    ///
+    /// # Safety
+    ///
+    /// - Work submitted to the raw queue is not tracked by wgpu,
+    ///   so it must not conflict with the work submitted through wgpu.
+    pub unsafe fn queue_as_hal<A: HalApi, F: FnOnce(Option<&A::Queue>) -> R, R>(
+        &self,
+        id: id::QueueId,
+        hal_queue_callback: F,
+    ) -> R {
+        profiling::scope!("as_hal", "Queue");
+
+        if id.backend() != A::VARIANT {
+            return hal_queue_callback(None);
+        }
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (guard, _) = hub.devices.read(&mut token);
+        let device = guard.get(id).ok();
+        hal_queue_callback(device.map(|device| &device.queue))
+    }
+
     pub fn texture_label<A: HalApi>(&self, id: id::TextureId) -> String {
         A::hub(self).textures.label_for_resource(id)
     }
@@ -3670,13 +4246,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 let mut trace = trace.lock();
                 let data = match source {
                     pipeline::ShaderModuleSource::Wgsl(ref code) => {
-                        trace.make_binary("wgsl", code.as_bytes())
+                        trace.make_shader_source("wgsl", code.as_bytes())
                     }
                     pipeline::ShaderModuleSource::Naga(ref module) => {
                         let string =
                             ron::ser::to_string_pretty(module, ron::ser::PrettyConfig::default())
                                 .unwrap();
-                        trace.make_binary("ron", string.as_bytes())
+                        trace.make_shader_source("ron", string.as_bytes())
                     }
                 };
                 trace.add(trace::Action::CreateShaderModule {
@@ -3728,7 +4304,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             #[cfg(feature = "trace")]
             if let Some(ref trace) = device.trace {
                 let mut trace = trace.lock();
-                let data = trace.make_binary("spv", unsafe {
+                let data = trace.make_shader_source("spv", unsafe {
                     std::slice::from_raw_parts(source.as_ptr() as *const u8, source.len() * 4)
                 });
                 trace.add(trace::Action::CreateShaderModule {
@@ -3803,7 +4379,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 .acquire_encoder(&device.raw, &device.queue)
             {
                 Ok(raw) => raw,
-                Err(_) => break DeviceError::OutOfMemory,
+                Err(error) => break device.handle_hal_error(error),
             };
             let command_buffer = command::CommandBuffer::new(
                 encoder,
@@ -3812,7 +4388,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 device.downlevel.clone(),
                 device.features,
                 #[cfg(feature = "trace")]
-                device.trace.is_some(),
+                matches!(device.trace, Some(ref trace) if trace.lock().is_recording()),
                 &desc.label,
             );
 
@@ -4006,9 +4582,17 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
         let device_id = {
             let (mut query_set_guard, _) = hub.query_sets.write(&mut token);
-            let query_set = query_set_guard.get_mut(query_set_id).unwrap();
-            query_set.life_guard.ref_count.take();
-            query_set.device_id.value
+            match query_set_guard.get_mut(query_set_id) {
+                Ok(query_set) => {
+                    query_set.life_guard.ref_count.take();
+                    query_set.device_id.value
+                }
+                Err(InvalidId) => {
+                    hub.query_sets
+                        .unregister_locked(query_set_id, &mut *query_set_guard);
+                    return;
+                }
+            }
         };
 
         let (device_guard, mut token) = hub.devices.read(&mut token);
@@ -4303,46 +4887,6 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         use present::ConfigureSurfaceError as E;
         profiling::scope!("surface_configure");
 
-        fn validate_surface_configuraiton(
-            config: &mut hal::SurfaceConfiguration,
-            caps: &hal::SurfaceCapabilities,
-        ) -> Result<(), E> {
-            let width = config.extent.width;
-            let height = config.extent.height;
-            if width < caps.extents.start().width
-                || width > caps.extents.end().width
-                || height < caps.extents.start().height
-                || height > caps.extents.end().height
-            {
-                log::warn!(
-                    "Requested size {}x{} is outside of the supported range: {:?}",
-                    width,
-                    height,
-                    caps.extents
-                );
-            }
-            if !caps.present_modes.contains(&config.present_mode) {
-                log::warn!(
-                    "Surface does not support present mode: {:?}, falling back to FIFO",
-                    config.present_mode,
-                );
-                config.present_mode = wgt::PresentMode::Fifo;
-            }
-            if !caps.formats.contains(&config.format) {
-                return Err(E::UnsupportedFormat {
-                    requested: config.format,
-                    available: caps.formats.clone(),
-                });
-            }
-            if !caps.usage.contains(config.usage) {
-                return Err(E::UnsupportedUsage);
-            }
-            if width == 0 || height == 0 {
-                return Err(E::ZeroArea);
-            }
-            Ok(())
-        }
-
         log::info!("configuring surface with {:?}", config);
         let hub = A::hub(self);
         let mut token = Token::root();
@@ -4383,7 +4927,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             let mut hal_config = hal::SurfaceConfiguration {
                 swap_chain_size: num_frames,
                 present_mode: config.present_mode,
-                composite_alpha_mode: hal::CompositeAlphaMode::Opaque,
+                composite_alpha_mode: conv::map_composite_alpha_mode(config.composite_alpha_mode),
                 format: config.format,
                 extent: wgt::Extent3d {
                     width: config.width,
@@ -4393,7 +4937,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 usage: conv::map_texture_usage(config.usage, hal::FormatAspects::COLOR),
             };
 
-            if let Err(error) = validate_surface_configuraiton(&mut hal_config, &caps) {
+            if let Err(error) = present::validate_configuration(&mut hal_config, &caps) {
                 break error;
             }
 
@@ -4406,7 +4950,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Err(error) => {
                     break match error {
                         hal::SurfaceError::Outdated | hal::SurfaceError::Lost => E::InvalidSurface,
-                        hal::SurfaceError::Device(error) => E::Device(error.into()),
+                        hal::SurfaceError::Device(error) => {
+                            E::Device(device.handle_hal_error(error))
+                        }
                         hal::SurfaceError::Other(message) => {
                             log::error!("surface configuration failed: {}", message);
                             E::InvalidSurface
@@ -4478,6 +5024,55 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         Ok(())
     }
 
+    /// Wait until the submission with the given index is done, for at most `timeout_ms`,
+    /// and then process the completed work like [`Self::device_poll`] does.
+    ///
+    /// Returns `true` if the submission is done, or if it will never be done because
+    /// the device is lost.
+    ///
+    /// The device is locked while waiting, which blocks the submissions to it.
+    /// A `timeout_ms` of `0` only checks the submission without blocking.
+    pub fn device_wait_for_submission<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+        submission_index: SubmissionIndex,
+        timeout_ms: u32,
+    ) -> Result<bool, WaitIdleError> {
+        profiling::scope!("wait_for_submission", "Device");
+
+        let (closures, done) = {
+            let hub = A::hub(self);
+            let mut token = Token::root();
+            let (device_guard, mut token) = hub.devices.read(&mut token);
+            let device = device_guard
+                .get(device_id)
+                .map_err(|_| DeviceError::Invalid)?;
+            if submission_index > device.active_submission_index {
+                return Err(WaitIdleError::WrongSubmissionIndex(
+                    submission_index,
+                    device.active_submission_index,
+                ));
+            }
+            let done = if device.is_lost() {
+                true
+            } else {
+                match unsafe { device.raw.wait(&device.fence, submission_index, timeout_ms) } {
+                    Ok(done) => done,
+                    Err(hal::DeviceError::Lost) => {
+                        device.lose("the backend reported the device as lost");
+                        true
+                    }
+                    Err(error) => return Err(DeviceError::from(error).into()),
+                }
+            };
+            (device.maintain(hub, false, &mut token)?, done)
+        };
+        unsafe {
+            closures.fire();
+        }
+        Ok(done)
+    }
+
     fn poll_devices<A: HalApi>(
         &self,
         force_wait: bool,
@@ -4544,6 +5139,70 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
     }
 
+    /// Start recording an API trace of the device.
+    ///
+    /// The trace begins with the creation of the resources that are currently
+    /// alive, followed by the upload of their contents. This waits for the GPU
+    /// to finish all the work submitted so far.
+    pub fn device_start_trace<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+        desc: TraceDescriptor,
+    ) -> Result<(), StartTraceError> {
+        profiling::scope!("start_trace", "Device");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (mut device_guard, mut token) = hub.devices.write(&mut token);
+        let device = device_guard
+            .get_mut(device_id)
+            .map_err(|_| DeviceError::Invalid)?;
+
+        #[cfg(feature = "trace")]
+        {
+            if !device.is_trace_on_demand() {
+                return Err(StartTraceError::NotActive);
+            }
+            let contents = device.snapshot_contents(id::Valid(device_id), hub, &mut token)?;
+            let init = trace::Action::Init {
+                desc: DeviceDescriptor {
+                    label: None,
+                    features: device.features,
+                    limits: device.limits.clone(),
+                },
+                backend: A::VARIANT,
+            };
+            let mut trace = device.trace.as_ref().unwrap().lock();
+            trace.stop();
+            trace
+                .start(desc, init, contents)
+                .map_err(|e| StartTraceError::Io(Arc::new(e)))
+        }
+        #[cfg(not(feature = "trace"))]
+        {
+            let _ = (device, &mut token, desc);
+            Err(StartTraceError::NotEnabled)
+        }
+    }
+
+    /// Stop recording the API trace of the device, if any.
+    pub fn device_stop_trace<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+    ) -> Result<(), InvalidDevice> {
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+        #[cfg(feature = "trace")]
+        if let Some(ref trace) = device.trace {
+            trace.lock().stop();
+        }
+        #[cfg(not(feature = "trace"))]
+        let _ = device;
+        Ok(())
+    }
+
     pub fn device_drop<A: HalApi>(&self, device_id: id::DeviceId) {
         profiling::scope!("drop", "Device");
 
@@ -4561,8 +5220,56 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     .unregister(device.adapter_id.value.0, &mut token);
             }
 
+            let lost_closure = device.lost_closure.lock().take();
             device.dispose();
+
+            if let Some(closure) = lost_closure {
+                unsafe {
+                    closure.call(wgt::DeviceLostReason::Dropped, "device was dropped");
+                }
+            }
+        }
+    }
+
+    /// Get the signal raised when the device gets work to complete.
+    pub fn device_work_signal<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+    ) -> Result<Arc<WorkSignal>, InvalidDevice> {
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+        Ok(Arc::clone(&device.work_signal))
+    }
+
+    /// Set the closure to be called once the device is lost, replacing any previous one.
+    ///
+    /// If the device is dropped instead, the closure is called with
+    /// [`wgt::DeviceLostReason::Dropped`], and if it gets replaced,
+    /// it's called right away with [`wgt::DeviceLostReason::ReplacedCallback`].
+    pub fn device_set_device_lost_closure<A: HalApi>(
+        &self,
+        device_id: id::DeviceId,
+        closure: DeviceLostClosure,
+    ) -> Result<(), InvalidDevice> {
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (device_guard, _) = hub.devices.read(&mut token);
+        let device = device_guard.get(device_id).map_err(|_| InvalidDevice)?;
+        let old_closure = device.lost_closure.lock().replace(closure);
+        drop(device_guard);
+
+        // Let the previous closure release its user data.
+        if let Some(old_closure) = old_closure {
+            unsafe {
+                old_closure.call(
+                    wgt::DeviceLostReason::ReplacedCallback,
+                    "device lost closure was replaced",
+                );
+            }
         }
+        Ok(())
     }
 
     pub fn buffer_map_async<A: HalApi>(
@@ -4592,6 +5299,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 .map_err(|_| resource::BufferAccessError::Invalid)?;
 
             check_buffer_usage(buffer.usage, pub_usage)?;
+            if device_guard[buffer.device_id.value].is_lost() {
+                op.call_lost();
+                return Ok(());
+            }
             buffer.map_state = match buffer.map_state {
                 resource::BufferMapState::Init { .. } | resource::BufferMapState::Active { .. } => {
                     return Err(resource::BufferAccessError::AlreadyMapped);
@@ -4624,6 +5335,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         device
             .lock_life(&mut token)
             .map(id::Valid(buffer_id), ref_count);
+        device.work_signal.notify(0);
 
         Ok(())
     }
@@ -4682,7 +5394,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         max: range.end,
                     });
                 }
-                unsafe { Ok((ptr.as_ptr().offset(offset as isize), range_size)) }
+                // The pointer is at the start of the mapped range.
+                unsafe {
+                    Ok((
+                        ptr.as_ptr().offset((offset - range.start) as isize),
+                        range_size,
+                    ))
+                }
             }
             resource::BufferMapState::Idle | resource::BufferMapState::Waiting(_) => {
                 Err(resource::BufferAccessError::NotMapped)
@@ -4727,12 +5445,16 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     });
                 }
                 let _ = ptr;
-                if needs_flush {
-                    unsafe {
+                unsafe {
+                    if needs_flush {
                         device
                             .raw
                             .flush_mapped_ranges(&stage_buffer, iter::once(0..buffer.size));
                     }
+                    device
+                        .raw
+                        .unmap_buffer(&stage_buffer)
+                        .map_err(|e| device.handle_hal_error(e))?;
                 }
 
                 let raw_buf = buffer
@@ -4796,7 +5518,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     device
                         .raw
                         .unmap_buffer(buffer.raw.as_ref().unwrap())
-                        .map_err(DeviceError::from)?
+                        .map_err(|e| device.handle_hal_error(e))?
                 };
             }
         }
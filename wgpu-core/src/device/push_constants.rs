@@ -0,0 +1,665 @@
+use crate::{binding_model::PipelineLayout, device::DeviceError, FastHashMap};
+
+use super::staging;
+use hal::{CommandEncoder as _, Device as _};
+use parking_lot::Mutex;
+use wgt::{math::align_to, BufferAddress};
+
+use std::{mem, num::NonZeroU64, slice};
+
+/// Size of the emulated push constants, in bytes.
+const EMULATED_SIZE: u32 = 128;
+/// Size of the uniform buffers that the emulated push constants are sub-allocated from.
+const CHUNK_SIZE: BufferAddress = 1 << 16;
+
+/// Expose `Features::PUSH_CONSTANTS` on an adapter that can't do them natively,
+/// if there is room for the bind group they are emulated with.
+///
+/// The bind group is only reserved by the devices that enable the feature,
+/// see [`reserved_bind_groups`].
+///
+/// Returns `true` if the push constants are emulated.
+pub(crate) fn expose(features: &mut wgt::Features, limits: &mut wgt::Limits) -> bool {
+    if features.contains(wgt::Features::PUSH_CONSTANTS)
+        || limits.max_bind_groups < 2
+        || limits.max_uniform_buffers_per_shader_stage == 0
+        || limits.max_dynamic_uniform_buffers_per_pipeline_layout == 0
+    {
+        return false;
+    }
+    *features |= wgt::Features::PUSH_CONSTANTS;
+    limits.max_push_constant_size = EMULATED_SIZE.min(limits.max_uniform_buffer_binding_size);
+    true
+}
+
+/// Limit on the bind groups of a device with emulated push constants.
+///
+/// The last bind group of the adapter is reserved for the push constants.
+pub(crate) fn reserved_bind_groups(adapter_limits: &wgt::Limits) -> u32 {
+    adapter_limits.max_bind_groups - 1
+}
+
+/// Make the expressions of `function` that refer to the `wrapped` globals
+/// refer to the value inside of their wrapper instead.
+///
+/// The accesses to the values are put in front of the other expressions,
+/// which are otherwise kept in the same order, and emitted at the start of
+/// the function.
+fn wrap_accesses(function: &mut naga::Function, wrapped: &[naga::Handle<naga::GlobalVariable>]) {
+    use naga::{Expression as E, Handle};
+
+    let old_expressions = mem::take(&mut function.expressions);
+    let mut expressions = naga::Arena::new();
+    let globals = wrapped
+        .iter()
+        .map(|&var| expressions.append(E::GlobalVariable(var), naga::Span::Unknown))
+        .collect::<Vec<_>>();
+    let accesses_start = expressions.len();
+    let accesses = globals
+        .into_iter()
+        .map(|base| expressions.append(E::AccessIndex { base, index: 0 }, naga::Span::Unknown))
+        .collect::<Vec<_>>();
+    let accesses_range = expressions.range_from(accesses_start);
+    let offset = expressions.len();
+
+    // Ranges of handles can only be made by the arena, once it reaches their end.
+    let mut emit_ends = FastHashMap::<usize, Vec<usize>>::default();
+    collect_emits(&function.body, &mut emit_ends);
+    let mut emits = FastHashMap::default();
+
+    let mut handles: Vec<Handle<E>> = Vec::with_capacity(old_expressions.len());
+    for (old, expression) in old_expressions.iter() {
+        let mut expression = expression.clone();
+        remap_expression(&mut expression, &handles);
+        let new = expressions.append(expression, old_expressions.get_span(old).clone());
+        handles.push(match old_expressions[old] {
+            E::GlobalVariable(var) => match wrapped.iter().position(|&w| w == var) {
+                Some(index) => accesses[index],
+                None => new,
+            },
+            _ => new,
+        });
+        for &start in emit_ends.get(&(old.index() + 1)).into_iter().flatten() {
+            emits.insert(
+                (start, old.index() + 1),
+                expressions.range_from(start + offset),
+            );
+        }
+    }
+    function.expressions = expressions;
+
+    let remap = |handle: &mut Handle<E>| *handle = handles[handle.index()];
+    function.named_expressions = mem::take(&mut function.named_expressions)
+        .into_iter()
+        .map(|(mut handle, name)| {
+            remap(&mut handle);
+            (handle, name)
+        })
+        .collect();
+    let empty = function.expressions.range_from(function.expressions.len());
+    remap_block(&mut function.body, &handles, &emits, &empty);
+    let mut body = naga::Block::new();
+    body.push(naga::Statement::Emit(accesses_range), naga::Span::Unknown);
+    body.extend_block(mem::take(&mut function.body));
+    function.body = body;
+}
+
+/// Bounds of an emitted range, as indices of its first and past its last handle.
+fn range_bounds(range: &naga::Range<naga::Expression>) -> Option<(usize, usize)> {
+    let mut handles = range.clone();
+    let first = handles.next()?.index();
+    let last = handles.last().map_or(first, |handle| handle.index());
+    Some((first, last + 1))
+}
+
+fn collect_emits(block: &naga::Block, emit_ends: &mut FastHashMap<usize, Vec<usize>>) {
+    use naga::Statement as S;
+    for statement in block {
+        match *statement {
+            S::Emit(ref range) => {
+                if let Some((start, end)) = range_bounds(range) {
+                    emit_ends.entry(end).or_default().push(start);
+                }
+            }
+            S::Block(ref block) => collect_emits(block, emit_ends),
+            S::If {
+                ref accept,
+                ref reject,
+                ..
+            } => {
+                collect_emits(accept, emit_ends);
+                collect_emits(reject, emit_ends);
+            }
+            S::Switch {
+                ref cases,
+                ref default,
+                ..
+            } => {
+                for case in cases {
+                    collect_emits(&case.body, emit_ends);
+                }
+                collect_emits(default, emit_ends);
+            }
+            S::Loop {
+                ref body,
+                ref continuing,
+            } => {
+                collect_emits(body, emit_ends);
+                collect_emits(continuing, emit_ends);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn remap_expression(expression: &mut naga::Expression, handles: &[naga::Handle<naga::Expression>]) {
+    use naga::Expression as E;
+    let remap = |handle: &mut naga::Handle<E>| *handle = handles[handle.index()];
+    match *expression {
+        E::Access {
+            ref mut base,
+            ref mut index,
+        } => {
+            remap(base);
+            remap(index);
+        }
+        E::AccessIndex { ref mut base, .. } => remap(base),
+        E::Splat { ref mut value, .. } => remap(value),
+        E::Swizzle { ref mut vector, .. } => remap(vector),
+        E::Compose {
+            ref mut components, ..
+        } => components.iter_mut().for_each(remap),
+        E::Load { ref mut pointer } => remap(pointer),
+        E::ImageSample {
+            ref mut image,
+            ref mut sampler,
+            ref mut coordinate,
+            ref mut array_index,
+            ref mut level,
+            ref mut depth_ref,
+            ..
+        } => {
+            remap(image);
+            remap(sampler);
+            remap(coordinate);
+            array_index.iter_mut().for_each(remap);
+            match *level {
+                naga::SampleLevel::Auto | naga::SampleLevel::Zero => {}
+                naga::SampleLevel::Exact(ref mut handle)
+                | naga::SampleLevel::Bias(ref mut handle) => remap(handle),
+                naga::SampleLevel::Gradient {
+                    ref mut x,
+                    ref mut y,
+                } => {
+                    remap(x);
+                    remap(y);
+                }
+            }
+            depth_ref.iter_mut().for_each(remap);
+        }
+        E::ImageLoad {
+            ref mut image,
+            ref mut coordinate,
+            ref mut array_index,
+            ref mut index,
+        } => {
+            remap(image);
+            remap(coordinate);
+            array_index.iter_mut().for_each(remap);
+            index.iter_mut().for_each(remap);
+        }
+        E::ImageQuery {
+            ref mut image,
+            ref mut query,
+        } => {
+            remap(image);
+            if let naga::ImageQuery::Size { ref mut level } = *query {
+                level.iter_mut().for_each(remap);
+            }
+        }
+        E::Unary { ref mut expr, .. }
+        | E::Derivative { ref mut expr, .. }
+        | E::As { ref mut expr, .. } => remap(expr),
+        E::Binary {
+            ref mut left,
+            ref mut right,
+            ..
+        } => {
+            remap(left);
+            remap(right);
+        }
+        E::Select {
+            ref mut condition,
+            ref mut accept,
+            ref mut reject,
+        } => {
+            remap(condition);
+            remap(accept);
+            remap(reject);
+        }
+        E::Relational {
+            ref mut argument, ..
+        } => remap(argument),
+        E::Math {
+            ref mut arg,
+            ref mut arg1,
+            ref mut arg2,
+            ..
+        } => {
+            remap(arg);
+            arg1.iter_mut().for_each(remap);
+            arg2.iter_mut().for_each(remap);
+        }
+        E::ArrayLength(ref mut array) => remap(array),
+        E::Constant(_)
+        | E::FunctionArgument(_)
+        | E::GlobalVariable(_)
+        | E::LocalVariable(_)
+        | E::CallResult(_)
+        | E::AtomicResult { .. } => {}
+    }
+}
+
+fn remap_block(
+    block: &mut naga::Block,
+    handles: &[naga::Handle<naga::Expression>],
+    emits: &FastHashMap<(usize, usize), naga::Range<naga::Expression>>,
+    empty: &naga::Range<naga::Expression>,
+) {
+    use naga::Statement as S;
+    let remap = |handle: &mut naga::Handle<naga::Expression>| *handle = handles[handle.index()];
+    for statement in block.iter_mut() {
+        match *statement {
+            S::Emit(ref mut range) => {
+                *range = match range_bounds(range) {
+                    Some(bounds) => emits[&bounds].clone(),
+                    None => empty.clone(),
+                };
+            }
+            S::Block(ref mut block) => remap_block(block, handles, emits, empty),
+            S::If {
+                ref mut condition,
+                ref mut accept,
+                ref mut reject,
+            } => {
+                remap(condition);
+                remap_block(accept, handles, emits, empty);
+                remap_block(reject, handles, emits, empty);
+            }
+            S::Switch {
+                ref mut selector,
+                ref mut cases,
+                ref mut default,
+            } => {
+                remap(selector);
+                for case in cases.iter_mut() {
+                    remap_block(&mut case.body, handles, emits, empty);
+                }
+                remap_block(default, handles, emits, empty);
+            }
+            S::Loop {
+                ref mut body,
+                ref mut continuing,
+            } => {
+                remap_block(body, handles, emits, empty);
+                remap_block(continuing, handles, emits, empty);
+            }
+            S::Return { ref mut value } => value.iter_mut().for_each(remap),
+            S::Store {
+                ref mut pointer,
+                ref mut value,
+            } => {
+                remap(pointer);
+                remap(value);
+            }
+            S::ImageStore {
+                ref mut image,
+                ref mut coordinate,
+                ref mut array_index,
+                ref mut value,
+            } => {
+                remap(image);
+                remap(coordinate);
+                array_index.iter_mut().for_each(remap);
+                remap(value);
+            }
+            S::Atomic {
+                ref mut pointer,
+                ref mut fun,
+                ref mut value,
+                ref mut result,
+            } => {
+                remap(pointer);
+                if let naga::AtomicFunction::Exchange { ref mut compare } = *fun {
+                    compare.iter_mut().for_each(remap);
+                }
+                remap(value);
+                remap(result);
+            }
+            S::Call {
+                ref mut arguments,
+                ref mut result,
+                ..
+            } => {
+                arguments.iter_mut().for_each(remap);
+                result.iter_mut().for_each(remap);
+            }
+            S::Break | S::Continue | S::Kill | S::Barrier(_) => {}
+        }
+    }
+}
+
+/// Uniform buffer that the push constants of a command buffer are written to.
+pub(crate) struct PushConstantChunk<A: hal::Api> {
+    buffer: A::Buffer,
+    /// Binding of `buffer` in the hidden bind group, at a dynamic offset.
+    bind_group: A::BindGroup,
+    /// Start of the free space.
+    cursor: BufferAddress,
+}
+
+/// Emulation of the push constants, for the adapters that don't have them.
+///
+/// Shaders see the push constants as a uniform buffer at binding 0 of the bind
+/// group at `group_index`, which isn't available to the user. Every time the
+/// values change, they are written to a new part of a [`PushConstantChunk`],
+/// and the hidden bind group is set with the offset of that part.
+pub(crate) struct PushConstantEmulation<A: hal::Api> {
+    /// Index of the hidden bind group.
+    pub group_index: u32,
+    /// Limits of the adapter, which the hidden binding is counted against.
+    adapter_limits: wgt::Limits,
+    alignment: BufferAddress,
+    bind_group_layout: A::BindGroupLayout,
+    /// Layout of the bind groups between the ones of the user and the hidden one.
+    empty_bind_group_layout: A::BindGroupLayout,
+    /// Chunks that are not used by the GPU any more.
+    free_chunks: Mutex<Vec<PushConstantChunk<A>>>,
+}
+
+impl<A: hal::Api> PushConstantEmulation<A> {
+    fn entry() -> wgt::BindGroupLayoutEntry {
+        wgt::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgt::ShaderStages::all(),
+            ty: wgt::BindingType::Buffer {
+                ty: wgt::BufferBindingType::Uniform,
+                has_dynamic_offset: true,
+                min_binding_size: NonZeroU64::new(EMULATED_SIZE as u64),
+            },
+            count: None,
+        }
+    }
+
+    pub fn new(
+        device: &A::Device,
+        adapter_limits: &wgt::Limits,
+        alignments: &hal::Alignments,
+    ) -> Result<Self, hal::DeviceError> {
+        let bind_group_layout = unsafe {
+            device.create_bind_group_layout(&hal::BindGroupLayoutDescriptor {
+                label: Some("_PushConstants"),
+                entries: &[Self::entry()],
+            })
+        }?;
+        let empty_bind_group_layout = match unsafe {
+            device.create_bind_group_layout(&hal::BindGroupLayoutDescriptor {
+                label: Some("_Empty"),
+                entries: &[],
+            })
+        } {
+            Ok(layout) => layout,
+            Err(error) => {
+                unsafe { device.destroy_bind_group_layout(bind_group_layout) };
+                return Err(error);
+            }
+        };
+        Ok(Self {
+            group_index: reserved_bind_groups(adapter_limits),
+            adapter_limits: adapter_limits.clone(),
+            alignment: alignments.uniform_buffer_offset.get(),
+            bind_group_layout,
+            empty_bind_group_layout,
+            free_chunks: Mutex::new(Vec::new()),
+        })
+    }
+
+    /// Turn the push constants of `module` into the hidden uniform buffer.
+    ///
+    /// Returns `false` if the module has no push constants.
+    pub fn rewrite_module(&self, module: &mut naga::Module) -> bool {
+        let mut has_push_constants = false;
+        let mut wrapped = Vec::new();
+        for (handle, var) in module.global_variables.iter_mut() {
+            if var.class != naga::StorageClass::PushConstant {
+                continue;
+            }
+            var.class = naga::StorageClass::Uniform;
+            var.binding = Some(naga::ResourceBinding {
+                group: self.group_index,
+                binding: 0,
+            });
+            has_push_constants = true;
+            // Uniform buffers have to be blocks. Anything else is wrapped into
+            // a new one, since its type may be shared with other values.
+            if let naga::TypeInner::Struct {
+                top_level: true, ..
+            } = module.types[var.ty].inner
+            {
+                continue;
+            }
+            let span = module.types[var.ty].inner.span(&module.constants);
+            var.ty = module.types.append(
+                naga::Type {
+                    name: None,
+                    inner: naga::TypeInner::Struct {
+                        top_level: true,
+                        members: vec![naga::StructMember {
+                            name: Some("value".to_string()),
+                            ty: var.ty,
+                            binding: None,
+                            offset: 0,
+                        }],
+                        span,
+                    },
+                },
+                naga::Span::Unknown,
+            );
+            wrapped.push(handle);
+        }
+        if !wrapped.is_empty() {
+            for (_, function) in module.functions.iter_mut() {
+                wrap_accesses(function, &wrapped);
+            }
+            for entry_point in module.entry_points.iter_mut() {
+                wrap_accesses(&mut entry_point.function, &wrapped);
+            }
+        }
+        has_push_constants
+    }
+
+    /// Check that the hidden uniform buffer fits in the adapter limits,
+    /// along with the bindings of a pipeline layout.
+    pub fn validate_layout(
+        &self,
+        mut count_validator: crate::binding_model::BindingTypeMaxCountValidator,
+    ) -> Result<(), crate::binding_model::BindingTypeMaxCountError> {
+        count_validator.add_binding(&Self::entry());
+        count_validator.validate(&self.adapter_limits)
+    }
+
+    /// Append the layouts up to the hidden bind group to the ones of a pipeline layout.
+    pub fn extend_bind_group_layouts<'a>(&'a self, layouts: &mut Vec<&'a A::BindGroupLayout>) {
+        layouts.resize(self.group_index as usize, &self.empty_bind_group_layout);
+        layouts.push(&self.bind_group_layout);
+    }
+
+    fn create_chunk(&self, device: &A::Device) -> Result<PushConstantChunk<A>, DeviceError> {
+        profiling::scope!("create_chunk", "PushConstantEmulation");
+        let buffer = unsafe {
+            device.create_buffer(&hal::BufferDescriptor {
+                label: Some("_PushConstants"),
+                size: CHUNK_SIZE,
+                usage: hal::BufferUses::MAP_WRITE | hal::BufferUses::UNIFORM,
+                memory_flags: hal::MemoryFlags::TRANSIENT,
+            })
+        }?;
+        let bind_group = unsafe {
+            device.create_bind_group(&hal::BindGroupDescriptor {
+                label: Some("_PushConstants"),
+                layout: &self.bind_group_layout,
+                buffers: &[hal::BufferBinding {
+                    buffer: &buffer,
+                    offset: 0,
+                    size: NonZeroU64::new(EMULATED_SIZE as u64),
+                }],
+                samplers: &[],
+                textures: &[],
+                entries: &[hal::BindGroupEntry {
+                    binding: 0,
+                    resource_index: 0,
+                }],
+            })
+        };
+        match bind_group {
+            Ok(bind_group) => Ok(PushConstantChunk {
+                buffer,
+                bind_group,
+                cursor: 0,
+            }),
+            Err(error) => {
+                unsafe { device.destroy_buffer(buffer) };
+                Err(error.into())
+            }
+        }
+    }
+
+    /// Write `data` to the last chunk of a command buffer, or to a new one if
+    /// it's full, and return the chunk with the offset of the data.
+    fn write<'c>(
+        &self,
+        device: &A::Device,
+        chunks: &'c mut Vec<PushConstantChunk<A>>,
+        data: &[u32],
+    ) -> Result<(&'c PushConstantChunk<A>, wgt::DynamicOffset), DeviceError> {
+        let size = EMULATED_SIZE as BufferAddress;
+        let needs_chunk = match chunks.last() {
+            Some(chunk) => align_to(chunk.cursor, self.alignment) + size > CHUNK_SIZE,
+            None => true,
+        };
+        if needs_chunk {
+            let chunk = match self.free_chunks.lock().pop() {
+                Some(chunk) => chunk,
+                None => self.create_chunk(device)?,
+            };
+            chunks.push(chunk);
+        }
+
+        let chunk = chunks.last_mut().unwrap();
+        let offset = align_to(chunk.cursor, self.alignment);
+        chunk.cursor = offset + size;
+        let bytes = unsafe { slice::from_raw_parts(data.as_ptr() as *const u8, data.len() * 4) };
+        unsafe { staging::write_unmapped::<A>(device, &chunk.buffer, offset, bytes) }?;
+        Ok((chunk, offset as wgt::DynamicOffset))
+    }
+
+    /// Take back the chunks of a command buffer that isn't used by the GPU any more.
+    pub fn recycle(&self, chunks: impl IntoIterator<Item = PushConstantChunk<A>>) {
+        let mut free_chunks = self.free_chunks.lock();
+        for mut chunk in chunks {
+            chunk.cursor = 0;
+            free_chunks.push(chunk);
+        }
+    }
+
+    pub fn dispose(self, device: &A::Device) {
+        let free_chunks = self.free_chunks.into_inner();
+        log::info!("Destroying {} push constant chunks", free_chunks.len());
+        unsafe {
+            for chunk in free_chunks {
+                device.destroy_bind_group(chunk.bind_group);
+                device.destroy_buffer(chunk.buffer);
+            }
+            device.destroy_bind_group_layout(self.bind_group_layout);
+            device.destroy_bind_group_layout(self.empty_bind_group_layout);
+        }
+    }
+}
+
+/// Emulated push constants of a pass.
+///
+/// The values are only written out before the draws and dispatches that follow
+/// a change, so that consecutive updates end up in a single write.
+pub(crate) struct PushConstantWriter<'a, A: hal::Api> {
+    emulation: &'a PushConstantEmulation<A>,
+    device: &'a A::Device,
+    chunks: &'a mut Vec<PushConstantChunk<A>>,
+    data: [u32; EMULATED_SIZE as usize / 4],
+    dirty: bool,
+}
+
+impl<'a, A: hal::Api> PushConstantWriter<'a, A> {
+    pub fn new(
+        emulation: &'a PushConstantEmulation<A>,
+        device: &'a A::Device,
+        chunks: &'a mut Vec<PushConstantChunk<A>>,
+    ) -> Self {
+        Self {
+            emulation,
+            device,
+            chunks,
+            data: [0; EMULATED_SIZE as usize / 4],
+            dirty: false,
+        }
+    }
+
+    pub fn set(&mut self, offset: u32, data: &[u32]) {
+        let start = (offset / wgt::PUSH_CONSTANT_ALIGNMENT) as usize;
+        self.data[start..start + data.len()].copy_from_slice(data);
+        self.dirty = true;
+    }
+
+    /// Zero the values, when the pipeline layout changes.
+    pub fn clear(&mut self) {
+        self.data = [0; EMULATED_SIZE as usize / 4];
+        self.dirty = true;
+    }
+
+    /// Bind the values again, when the pipeline layout may have changed.
+    pub fn invalidate(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Write the values out and bind them, if they changed since the last time.
+    pub fn flush(
+        &mut self,
+        raw: &mut A::CommandEncoder,
+        layout: &PipelineLayout<A>,
+    ) -> Result<(), DeviceError> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.dirty = false;
+        // Layouts without push constants don't have the hidden bind group.
+        let size = match layout
+            .push_constant_ranges
+            .iter()
+            .map(|pc| pc.range.end)
+            .max()
+        {
+            Some(size) => size,
+            None => return Ok(()),
+        };
+        let data = &self.data[..(size / wgt::PUSH_CONSTANT_ALIGNMENT) as usize];
+        let (chunk, offset) = self.emulation.write(self.device, self.chunks, data)?;
+        unsafe {
+            raw.set_bind_group(
+                &layout.raw,
+                self.emulation.group_index,
+                &chunk.bind_group,
+                &[offset],
+            );
+        }
+        Ok(())
+    }
+}
@@ -6,17 +6,18 @@ use crate::{
         CommandBuffer, CopySide, ImageCopyTexture, TransferError,
     },
     conv,
-    device::{DeviceError, WaitIdleError},
+    device::{staging::StagingBuffer, DeviceError, WaitIdleError},
     hub::{Global, GlobalIdentityHandlerFactory, HalApi, Token},
     id,
     resource::{BufferAccessError, BufferMapState, TextureInner},
-    track, FastHashSet,
+    track, FastHashSet, SubmissionIndex,
 };
 
 use hal::{CommandEncoder as _, Device as _, Queue as _};
 use parking_lot::Mutex;
 use std::{iter, mem, num::NonZeroU32, ptr};
 use thiserror::Error;
+use wgt::math::align_to;
 
 /// Number of command buffers that we generate from the same pool
 /// for the write_xxx commands, before the pool is recycled.
@@ -25,7 +26,16 @@ use thiserror::Error;
 /// without a concrete moment of when it can be cleared.
 const WRITE_COMMAND_BUFFERS_PER_POOL: usize = 64;
 
-pub type OnSubmittedWorkDoneCallback = unsafe extern "C" fn(user_data: *mut u8);
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SubmittedWorkDoneStatus {
+    Success,
+    /// The device was lost before the work could be completed.
+    DeviceLost,
+}
+
+pub type OnSubmittedWorkDoneCallback =
+    unsafe extern "C" fn(status: SubmittedWorkDoneStatus, user_data: *mut u8);
 #[repr(C)]
 #[derive(Clone, Copy, Debug)]
 pub struct SubmittedWorkDoneClosure {
@@ -36,31 +46,11 @@ pub struct SubmittedWorkDoneClosure {
 unsafe impl Send for SubmittedWorkDoneClosure {}
 unsafe impl Sync for SubmittedWorkDoneClosure {}
 
-struct StagingData<A: hal::Api> {
-    buffer: A::Buffer,
-}
-
-impl<A: hal::Api> StagingData<A> {
-    unsafe fn write(
-        &self,
-        device: &A::Device,
-        offset: wgt::BufferAddress,
-        data: &[u8],
-    ) -> Result<(), hal::DeviceError> {
-        let mapping = device.map_buffer(&self.buffer, offset..offset + data.len() as u64)?;
-        ptr::copy_nonoverlapping(data.as_ptr(), mapping.ptr.as_ptr(), data.len());
-        if !mapping.is_coherent {
-            device
-                .flush_mapped_ranges(&self.buffer, iter::once(offset..offset + data.len() as u64));
-        }
-        device.unmap_buffer(&self.buffer)?;
-        Ok(())
-    }
-}
-
 #[derive(Debug)]
 pub enum TempResource<A: hal::Api> {
     Buffer(A::Buffer),
+    /// Buffer imported without ownership, released without being destroyed.
+    ExternalBuffer(A::Buffer),
     Texture(A::Texture),
 }
 
@@ -114,6 +104,7 @@ impl<A: hal::Api> PendingWrites<A> {
                 TempResource::Buffer(buffer) => unsafe {
                     device.destroy_buffer(buffer);
                 },
+                TempResource::ExternalBuffer(_) => {}
                 TempResource::Texture(texture) => unsafe {
                     device.destroy_texture(texture);
                 },
@@ -125,10 +116,6 @@ impl<A: hal::Api> PendingWrites<A> {
         self.temp_resources.push(resource);
     }
 
-    fn consume(&mut self, stage: StagingData<A>) {
-        self.temp_resources.push(TempResource::Buffer(stage.buffer));
-    }
-
     #[must_use]
     fn pre_submit(&mut self) -> Option<&A::CommandBuffer> {
         self.dst_buffers.clear();
@@ -186,17 +173,354 @@ impl<A: hal::Api> PendingWrites<A> {
     }
 }
 
-impl<A: hal::Api> super::Device<A> {
-    fn prepare_stage(&mut self, size: wgt::BufferAddress) -> Result<StagingData<A>, DeviceError> {
-        profiling::scope!("prepare_stage");
-        let stage_desc = hal::BufferDescriptor {
-            label: Some("_Staging"),
-            size,
-            usage: hal::BufferUses::MAP_WRITE | hal::BufferUses::COPY_SRC,
-            memory_flags: hal::MemoryFlags::TRANSIENT,
+/// Where the contents of a resource are read back from when a trace is started.
+#[cfg(feature = "trace")]
+enum Readback {
+    /// Mappable buffer, read directly.
+    Mapped { id: id::BufferId, size: u64 },
+    /// Buffer copied into a staging buffer.
+    StagedBuffer { id: id::BufferId, size: u64 },
+    /// Level of a texture, copied into a staging buffer.
+    StagedTexture {
+        to: ImageCopyTexture,
+        layout: wgt::ImageDataLayout,
+        size: wgt::Extent3d,
+        copy_size: hal::CopyExtent,
+        layer_count: u32,
+        stage_size: u64,
+    },
+}
+
+#[cfg(feature = "trace")]
+impl<A: HalApi> super::Device<A> {
+    /// Read back the contents of the live buffers and textures of this device,
+    /// so that a trace started now can recreate them.
+    ///
+    /// The pending writes are submitted along with the copies, and the call
+    /// waits for the GPU to finish.
+    pub(super) fn snapshot_contents<G: GlobalIdentityHandlerFactory>(
+        &mut self,
+        self_id: id::Valid<id::DeviceId>,
+        hub: &crate::hub::Hub<A, G>,
+        token: &mut Token<Self>,
+    ) -> Result<Vec<super::trace::ResourceContents>, DeviceError> {
+        use super::trace::ResourceContents;
+
+        profiling::scope!("snapshot_contents", "Device");
+        let submit_index = self.active_submission_index + 1;
+        let mut readbacks = Vec::new();
+        let mut stages = Vec::new();
+
+        let active_executions = {
+            let (buffer_guard, mut token) = hub.buffers.read(token);
+            let (texture_guard, _) = hub.textures.read(&mut token);
+
+            for (id, buffer) in buffer_guard.iter(A::VARIANT) {
+                if buffer.device_id.value != self_id || buffer.raw.is_none() || buffer.size == 0 {
+                    continue;
+                }
+                match buffer.map_state {
+                    BufferMapState::Idle => {}
+                    _ => {
+                        log::warn!("Contents of the mapped buffer {:?} are not captured", id);
+                        continue;
+                    }
+                }
+                let size = buffer.size;
+                readbacks.push(
+                    if buffer
+                        .usage
+                        .intersects(wgt::BufferUsages::MAP_READ | wgt::BufferUsages::MAP_WRITE)
+                    {
+                        Readback::Mapped { id, size }
+                    } else {
+                        Readback::StagedBuffer { id, size }
+                    },
+                );
+            }
+
+            for (id, texture) in texture_guard.iter(A::VARIANT) {
+                if texture.device_id.value != self_id || texture.inner.as_raw().is_none() {
+                    continue;
+                }
+                let desc = &texture.desc;
+                if !super::trace::is_texture_snapshot_supported(desc) {
+                    log::warn!("Contents of the texture {:?} are not captured", id);
+                    continue;
+                }
+                let format_desc = desc.format.describe();
+                let (block_width, block_height) = format_desc.block_dimensions;
+                let bytes_per_row_alignment = get_lowest_common_denom(
+                    self.alignments.buffer_copy_pitch.get() as u32,
+                    format_desc.block_size as u32,
+                );
+                for mip_level in 0..desc.mip_level_count {
+                    let size = desc
+                        .mip_level_size(mip_level)
+                        .unwrap()
+                        .physical_size(desc.format);
+                    let (layer_count, depth) = match desc.dimension {
+                        wgt::TextureDimension::D3 => (1, size.depth_or_array_layers),
+                        _ => (size.depth_or_array_layers, 1),
+                    };
+                    let block_rows = size.height / block_height as u32;
+                    let bytes_per_row = align_to(
+                        size.width / block_width as u32 * format_desc.block_size as u32,
+                        bytes_per_row_alignment,
+                    );
+                    readbacks.push(Readback::StagedTexture {
+                        to: ImageCopyTexture {
+                            texture: id,
+                            mip_level,
+                            origin: wgt::Origin3d::ZERO,
+                            aspect: wgt::TextureAspect::All,
+                        },
+                        layout: wgt::ImageDataLayout {
+                            offset: 0,
+                            bytes_per_row: NonZeroU32::new(bytes_per_row),
+                            rows_per_image: NonZeroU32::new(block_rows),
+                        },
+                        size,
+                        copy_size: hal::CopyExtent {
+                            width: size.width,
+                            height: size.height,
+                            depth,
+                        },
+                        layer_count,
+                        stage_size: bytes_per_row as u64
+                            * block_rows as u64
+                            * size.depth_or_array_layers as u64,
+                    });
+                }
+            }
+
+            for readback in readbacks.iter() {
+                let size = match *readback {
+                    Readback::Mapped { .. } => continue,
+                    Readback::StagedBuffer { size, .. } => size,
+                    Readback::StagedTexture { stage_size, .. } => stage_size,
+                };
+                let stage_desc = hal::BufferDescriptor {
+                    label: Some("_Snapshot"),
+                    size,
+                    usage: hal::BufferUses::MAP_READ | hal::BufferUses::COPY_DST,
+                    memory_flags: hal::MemoryFlags::TRANSIENT,
+                };
+                match unsafe { self.raw.create_buffer(&stage_desc) } {
+                    Ok(buffer) => stages.push(buffer),
+                    Err(e) => {
+                        for buffer in stages {
+                            unsafe { self.raw.destroy_buffer(buffer) };
+                        }
+                        return Err(e.into());
+                    }
+                }
+            }
+
+            let mut trackers = self.trackers.lock();
+            let encoder = self.pending_writes.activate();
+            let mut stage_iter = stages.iter();
+            for readback in readbacks.iter() {
+                match *readback {
+                    Readback::Mapped { .. } => {}
+                    Readback::StagedBuffer { id, size } => {
+                        let stage = stage_iter.next().unwrap();
+                        let (src, transition) = trackers
+                            .buffers
+                            .use_replace(&*buffer_guard, id, (), hal::BufferUses::COPY_SRC)
+                            .unwrap();
+                        src.life_guard.use_at(submit_index);
+                        let barriers = iter::once(hal::BufferBarrier {
+                            buffer: stage,
+                            usage: hal::BufferUses::empty()..hal::BufferUses::COPY_DST,
+                        })
+                        .chain(transition.map(|pending| pending.into_hal(src)));
+                        let region = hal::BufferCopy {
+                            src_offset: 0,
+                            dst_offset: 0,
+                            size: wgt::BufferSize::new(size).unwrap(),
+                        };
+                        unsafe {
+                            encoder.transition_buffers(barriers);
+                            encoder.copy_buffer_to_buffer(
+                                src.raw.as_ref().unwrap(),
+                                stage,
+                                iter::once(region),
+                            );
+                        }
+                    }
+                    Readback::StagedTexture {
+                        ref to,
+                        ref layout,
+                        copy_size,
+                        layer_count,
+                        ..
+                    } => {
+                        let stage = stage_iter.next().unwrap();
+                        let selector = track::TextureSelector {
+                            levels: to.mip_level..to.mip_level + 1,
+                            layers: 0..layer_count,
+                        };
+                        let (src, transition) = trackers
+                            .textures
+                            .use_replace(
+                                &*texture_guard,
+                                to.texture,
+                                selector,
+                                hal::TextureUses::COPY_SRC,
+                            )
+                            .unwrap();
+                        src.life_guard.use_at(submit_index);
+                        let bytes_per_layer = layout.bytes_per_row.unwrap().get() as u64
+                            * layout.rows_per_image.unwrap().get() as u64
+                            * copy_size.depth as u64;
+                        let regions = (0..layer_count).map(|array_layer| hal::BufferTextureCopy {
+                            buffer_layout: wgt::ImageDataLayout {
+                                offset: array_layer as u64 * bytes_per_layer,
+                                ..*layout
+                            },
+                            texture_base: hal::TextureCopyBase {
+                                mip_level: to.mip_level,
+                                array_layer,
+                                origin: wgt::Origin3d::ZERO,
+                                aspect: hal::FormatAspects::COLOR,
+                            },
+                            size: copy_size,
+                        });
+                        unsafe {
+                            encoder.transition_buffers(iter::once(hal::BufferBarrier {
+                                buffer: stage,
+                                usage: hal::BufferUses::empty()..hal::BufferUses::COPY_DST,
+                            }));
+                            encoder.transition_textures(
+                                transition.map(|pending| pending.into_hal(src)),
+                            );
+                            encoder.copy_texture_to_buffer(
+                                src.inner.as_raw().unwrap(),
+                                hal::TextureUses::COPY_SRC,
+                                stage,
+                                regions,
+                            );
+                        }
+                    }
+                }
+            }
+            unsafe {
+                encoder.transition_buffers(stages.iter().map(|stage| hal::BufferBarrier {
+                    buffer: stage,
+                    usage: hal::BufferUses::COPY_DST..hal::BufferUses::MAP_READ,
+                }));
+            }
+            drop(trackers);
+
+            self.active_submission_index = submit_index;
+            self.staging_belt
+                .lock()
+                .unmap(&self.raw)
+                .map_err(|e| self.handle_hal_error(e))?;
+            let super::Device {
+                ref mut pending_writes,
+                ref mut queue,
+                ref mut fence,
+                ..
+            } = *self;
+            let refs = pending_writes.pre_submit().into_iter().collect::<Vec<_>>();
+            let result = unsafe { queue.submit(&refs, Some((fence, submit_index))) };
+            result.map_err(|e| self.handle_hal_error(e))?;
+            self.pending_writes
+                .post_submit(&self.command_allocator, &self.raw, &self.queue)
+                .into_iter()
+                .collect()
         };
-        let buffer = unsafe { self.raw.create_buffer(&stage_desc)? };
-        Ok(StagingData { buffer })
+
+        let mut pending_write_resources = mem::take(&mut self.pending_writes.temp_resources);
+        let staging_chunks = self.staging_belt.lock().flush();
+        self.lock_life(token).track_submission(
+            submit_index,
+            pending_write_resources.drain(..),
+            active_executions,
+            staging_chunks,
+            Vec::new(),
+        );
+        self.pending_writes.temp_resources = pending_write_resources;
+
+        unsafe {
+            self.raw
+                .wait(&self.fence, submit_index, !0)
+                .map_err(|e| self.handle_hal_error(e))?;
+        }
+
+        let (buffer_guard, _) = hub.buffers.read(token);
+        let read = |buffer: &A::Buffer, size: u64| -> Result<Vec<u8>, DeviceError> {
+            unsafe {
+                let mapping = self
+                    .raw
+                    .map_buffer(buffer, 0..size)
+                    .map_err(|e| self.handle_hal_error(e))?;
+                if !mapping.is_coherent {
+                    self.raw
+                        .invalidate_mapped_ranges(buffer, iter::once(0..size));
+                }
+                let data = std::slice::from_raw_parts(mapping.ptr.as_ptr(), size as usize).to_vec();
+                self.raw
+                    .unmap_buffer(buffer)
+                    .map_err(|e| self.handle_hal_error(e))?;
+                Ok(data)
+            }
+        };
+        let mut contents = Vec::with_capacity(readbacks.len());
+        let mut stage_iter = stages.into_iter();
+        let mut result = Ok(());
+        for readback in readbacks {
+            let content = match readback {
+                Readback::Mapped { id, size } => {
+                    let buffer = &buffer_guard[id::Valid(id)];
+                    read(buffer.raw.as_ref().unwrap(), size).map(|data| ResourceContents::Buffer {
+                        id,
+                        data,
+                        queued: !buffer.usage.contains(wgt::BufferUsages::MAP_WRITE),
+                    })
+                }
+                Readback::StagedBuffer { id, size } => {
+                    let stage = stage_iter.next().unwrap();
+                    let data = read(&stage, size);
+                    unsafe { self.raw.destroy_buffer(stage) };
+                    data.map(|data| ResourceContents::Buffer {
+                        id,
+                        data,
+                        queued: true,
+                    })
+                }
+                Readback::StagedTexture {
+                    to,
+                    layout,
+                    size,
+                    stage_size,
+                    ..
+                } => {
+                    let stage = stage_iter.next().unwrap();
+                    let data = read(&stage, stage_size);
+                    unsafe { self.raw.destroy_buffer(stage) };
+                    data.map(|data| ResourceContents::Texture {
+                        to,
+                        layout,
+                        size,
+                        data,
+                    })
+                }
+            };
+            match content {
+                Ok(content) => contents.push(content),
+                Err(e) => {
+                    result = Err(e);
+                    break;
+                }
+            }
+        }
+        for stage in stage_iter {
+            unsafe { self.raw.destroy_buffer(stage) };
+        }
+        result.map(|()| contents)
     }
 }
 
@@ -204,12 +528,44 @@ impl<A: hal::Api> super::Device<A> {
 #[error("queue is invalid")]
 pub struct InvalidQueue;
 
+/// Check that `size` bytes can be written into `dst` at `offset` by the queue.
+fn validate_buffer_write<A: hal::Api>(
+    dst: &crate::resource::Buffer<A>,
+    buffer_id: id::BufferId,
+    offset: wgt::BufferAddress,
+    size: wgt::BufferAddress,
+) -> Result<(), TransferError> {
+    if !dst.usage.contains(wgt::BufferUsages::COPY_DST) {
+        return Err(TransferError::MissingCopyDstUsageFlag(
+            Some(buffer_id),
+            None,
+        ));
+    }
+    if size % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+        return Err(TransferError::UnalignedCopySize(size));
+    }
+    if offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
+        return Err(TransferError::UnalignedBufferOffset(offset));
+    }
+    if offset + size > dst.size {
+        return Err(TransferError::BufferOverrun {
+            start_offset: offset,
+            end_offset: offset + size,
+            buffer_size: dst.size,
+            side: CopySide::Destination,
+        });
+    }
+    Ok(())
+}
+
 #[derive(Clone, Debug, Error)]
 pub enum QueueWriteError {
     #[error(transparent)]
     Queue(#[from] DeviceError),
     #[error(transparent)]
     Transfer(#[from] TransferError),
+    #[error("staging buffer is invalid")]
+    InvalidStagingBuffer,
 }
 
 #[derive(Clone, Debug, Error)]
@@ -220,6 +576,8 @@ pub enum QueueSubmitError {
     DestroyedBuffer(id::BufferId),
     #[error("texture {0:?} is destroyed")]
     DestroyedTexture(id::TextureId),
+    #[error("buffer {0:?} is still mapped")]
+    BufferStillMapped(id::BufferId),
     #[error(transparent)]
     Unmap(#[from] BufferAccessError),
     #[error("surface output was dropped before the command buffer got submitted")]
@@ -268,8 +626,17 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             return Ok(());
         }
 
-        let stage = device.prepare_stage(data_size)?;
-        unsafe { stage.write(&device.raw, 0, data) }.map_err(DeviceError::from)?;
+        let mut staging_belt = device.staging_belt.lock();
+        let stage = staging_belt.allocate(
+            &device.raw,
+            data_size,
+            device
+                .alignments
+                .buffer_copy_offset
+                .get()
+                .max(wgt::COPY_BUFFER_ALIGNMENT),
+        )?;
+        unsafe { stage.write(&device.raw, 0, data) };
 
         let mut trackers = device.trackers.lock();
         let (dst, transition) = trackers
@@ -280,44 +647,165 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             .raw
             .as_ref()
             .ok_or(TransferError::InvalidBuffer(buffer_id))?;
-        if !dst.usage.contains(wgt::BufferUsages::COPY_DST) {
-            return Err(TransferError::MissingCopyDstUsageFlag(Some(buffer_id), None).into());
-        }
+        validate_buffer_write(dst, buffer_id, buffer_offset, data_size)?;
         dst.life_guard.use_at(device.active_submission_index + 1);
 
-        if data_size % wgt::COPY_BUFFER_ALIGNMENT != 0 {
-            return Err(TransferError::UnalignedCopySize(data_size).into());
+        let region = wgt::BufferSize::new(data.len() as u64).map(|size| hal::BufferCopy {
+            src_offset: stage.offset,
+            dst_offset: buffer_offset,
+            size,
+        });
+        let barriers = iter::once(hal::BufferBarrier {
+            buffer: stage.buffer,
+            usage: hal::BufferUses::MAP_WRITE..hal::BufferUses::COPY_SRC,
+        })
+        .chain(transition.map(|pending| pending.into_hal(dst)));
+        let encoder = device.pending_writes.activate();
+        unsafe {
+            encoder.transition_buffers(barriers);
+            encoder.copy_buffer_to_buffer(stage.buffer, dst_raw, region.into_iter());
+        }
+        drop(staging_belt);
+
+        device.pending_writes.dst_buffers.insert(buffer_id);
+
+        // Ensure the overwritten bytes are marked as initialized so they don't need to be nulled prior to mapping or binding.
+        {
+            drop(buffer_guard);
+            let (mut buffer_guard, _) = hub.buffers.write(&mut token);
+
+            let dst = buffer_guard.get_mut(buffer_id).unwrap();
+            dst.initialization_status
+                .clear(buffer_offset..(buffer_offset + data_size));
         }
-        if buffer_offset % wgt::COPY_BUFFER_ALIGNMENT != 0 {
-            return Err(TransferError::UnalignedBufferOffset(buffer_offset).into());
+
+        Ok(())
+    }
+
+    /// Create a staging buffer of `buffer_size` bytes, to be written into
+    /// `buffer_id` at `buffer_offset`, and return it along with the pointer
+    /// to its mapped, zero-initialized contents.
+    ///
+    /// The staging buffer is sub-allocated from the staging belt of the device.
+    /// The memory stays valid until it's passed to [`Global::queue_write_staging_buffer`].
+    pub fn queue_create_staging_buffer<A: HalApi>(
+        &self,
+        queue_id: id::QueueId,
+        buffer_id: id::BufferId,
+        buffer_offset: wgt::BufferAddress,
+        buffer_size: wgt::BufferSize,
+    ) -> Result<(StagingBuffer, *mut u8), QueueWriteError> {
+        profiling::scope!("create_staging_buffer", "Queue");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (device_guard, mut token) = hub.devices.read(&mut token);
+        let device = device_guard
+            .get(queue_id)
+            .map_err(|_| DeviceError::Invalid)?;
+        let (buffer_guard, _) = hub.buffers.read(&mut token);
+
+        let size = buffer_size.get();
+        let dst = buffer_guard
+            .get(buffer_id)
+            .map_err(|_| TransferError::InvalidBuffer(buffer_id))?;
+        if dst.raw.is_none() {
+            return Err(TransferError::InvalidBuffer(buffer_id).into());
         }
-        if buffer_offset + data_size > dst.size {
-            return Err(TransferError::BufferOverrun {
-                start_offset: buffer_offset,
-                end_offset: buffer_offset + data_size,
-                buffer_size: dst.size,
-                side: CopySide::Destination,
-            }
-            .into());
+        validate_buffer_write(dst, buffer_id, buffer_offset, size)?;
+
+        let staging_buffer = device.staging_belt.lock().reserve(
+            &device.raw,
+            size,
+            device
+                .alignments
+                .buffer_copy_offset
+                .get()
+                .max(wgt::COPY_BUFFER_ALIGNMENT),
+        )?;
+        let ptr = staging_buffer.ptr();
+        Ok((staging_buffer, ptr))
+    }
+
+    /// Schedule the copy of a staging buffer, created by
+    /// [`Global::queue_create_staging_buffer`], into `buffer_id` at `buffer_offset`.
+    ///
+    /// The staging buffer is consumed, and its memory can't be accessed any more.
+    pub fn queue_write_staging_buffer<A: HalApi>(
+        &self,
+        queue_id: id::QueueId,
+        buffer_id: id::BufferId,
+        buffer_offset: wgt::BufferAddress,
+        staging_buffer: StagingBuffer,
+    ) -> Result<(), QueueWriteError> {
+        profiling::scope!("write_staging_buffer", "Queue");
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (mut device_guard, mut token) = hub.devices.write(&mut token);
+        let device = device_guard
+            .get_mut(queue_id)
+            .map_err(|_| DeviceError::Invalid)?;
+        let (buffer_guard, _) = hub.buffers.read(&mut token);
+
+        let data_size = staging_buffer.size();
+
+        #[cfg(feature = "trace")]
+        if let Some(ref trace) = device.trace {
+            let mut trace = trace.lock();
+            let data_path = trace.make_binary("bin", unsafe { staging_buffer.data() });
+            trace.add(Action::WriteBuffer {
+                id: buffer_id,
+                data: data_path,
+                range: buffer_offset..buffer_offset + data_size,
+                queued: true,
+            });
         }
 
-        let region = wgt::BufferSize::new(data.len() as u64).map(|size| hal::BufferCopy {
-            src_offset: 0,
+        // Release the staging buffer first, so that it's given back to the belt
+        // even if the write turns out to be invalid.
+        let mut staging_belt = device.staging_belt.lock();
+        let stage = staging_belt
+            .release(
+                &device.raw,
+                staging_buffer,
+                device
+                    .alignments
+                    .buffer_copy_offset
+                    .get()
+                    .max(wgt::COPY_BUFFER_ALIGNMENT),
+            )
+            .ok_or(QueueWriteError::InvalidStagingBuffer)??;
+
+        let mut trackers = device.trackers.lock();
+        let (dst, transition) = trackers
+            .buffers
+            .use_replace(&*buffer_guard, buffer_id, (), hal::BufferUses::COPY_DST)
+            .map_err(TransferError::InvalidBuffer)?;
+        let dst_raw = dst
+            .raw
+            .as_ref()
+            .ok_or(TransferError::InvalidBuffer(buffer_id))?;
+        validate_buffer_write(dst, buffer_id, buffer_offset, data_size)?;
+        dst.life_guard.use_at(device.active_submission_index + 1);
+
+        let region = wgt::BufferSize::new(data_size).map(|size| hal::BufferCopy {
+            src_offset: stage.offset,
             dst_offset: buffer_offset,
             size,
         });
         let barriers = iter::once(hal::BufferBarrier {
-            buffer: &stage.buffer,
+            buffer: stage.buffer,
             usage: hal::BufferUses::MAP_WRITE..hal::BufferUses::COPY_SRC,
         })
         .chain(transition.map(|pending| pending.into_hal(dst)));
         let encoder = device.pending_writes.activate();
         unsafe {
             encoder.transition_buffers(barriers);
-            encoder.copy_buffer_to_buffer(&stage.buffer, dst_raw, region.into_iter());
+            encoder.copy_buffer_to_buffer(stage.buffer, dst_raw, region.into_iter());
         }
+        drop(staging_belt);
 
-        device.pending_writes.consume(stage);
         device.pending_writes.dst_buffers.insert(buffer_id);
 
         // Ensure the overwritten bytes are marked as initialized so they don't need to be nulled prior to mapping or binding.
@@ -408,7 +896,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         let block_rows_in_copy =
             (size.depth_or_array_layers - 1) * block_rows_per_image + height_blocks;
         let stage_size = stage_bytes_per_row as u64 * block_rows_in_copy as u64;
-        let stage = device.prepare_stage(stage_size)?;
+        let mut staging_belt = device.staging_belt.lock();
+        let stage = staging_belt.allocate(
+            &device.raw,
+            stage_size,
+            get_lowest_common_denom(
+                device.alignments.buffer_copy_offset.get() as u32,
+                format_desc.block_size as u32,
+            ) as wgt::BufferAddress,
+        )?;
 
         let mut trackers = device.trackers.lock();
         let (dst, transition) = trackers
@@ -440,15 +936,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             width_blocks * format_desc.block_size as u32
         };
 
-        let mapping = unsafe { device.raw.map_buffer(&stage.buffer, 0..stage_size) }
-            .map_err(DeviceError::from)?;
+        let stage_ptr = stage.ptr();
         unsafe {
             profiling::scope!("copy");
             if stage_bytes_per_row == bytes_per_row {
                 // Fast path if the data is already being aligned optimally.
                 ptr::copy_nonoverlapping(
                     data.as_ptr().offset(data_layout.offset as isize),
-                    mapping.ptr.as_ptr(),
+                    stage_ptr,
                     stage_size as usize,
                 );
             } else {
@@ -462,7 +957,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 data_layout.offset as isize
                                     + (rows_offset + row) as isize * bytes_per_row as isize,
                             ),
-                            mapping.ptr.as_ptr().offset(
+                            stage_ptr.offset(
                                 (rows_offset + row) as isize * stage_bytes_per_row as isize,
                             ),
                             copy_bytes_per_row,
@@ -471,24 +966,14 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 }
             }
         }
-        unsafe {
-            if !mapping.is_coherent {
-                device
-                    .raw
-                    .flush_mapped_ranges(&stage.buffer, iter::once(0..stage_size));
-            }
-            device
-                .raw
-                .unmap_buffer(&stage.buffer)
-                .map_err(DeviceError::from)?;
-        }
+        unsafe { stage.flush(&device.raw, 0..stage_size) };
 
         let regions = (0..array_layer_count).map(|rel_array_layer| {
             let mut texture_base = dst_base.clone();
             texture_base.array_layer += rel_array_layer;
             hal::BufferTextureCopy {
                 buffer_layout: wgt::ImageDataLayout {
-                    offset: rel_array_layer as u64 * bytes_per_array_layer,
+                    offset: stage.offset + rel_array_layer as u64 * bytes_per_array_layer,
                     bytes_per_row: NonZeroU32::new(stage_bytes_per_row),
                     rows_per_image: NonZeroU32::new(block_rows_per_image),
                 },
@@ -497,7 +982,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             }
         });
         let barrier = hal::BufferBarrier {
-            buffer: &stage.buffer,
+            buffer: stage.buffer,
             usage: hal::BufferUses::MAP_WRITE..hal::BufferUses::COPY_SRC,
         };
 
@@ -505,10 +990,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         unsafe {
             encoder.transition_buffers(iter::once(barrier));
             encoder.transition_textures(transition.map(|pending| pending.into_hal(dst)));
-            encoder.copy_buffer_to_texture(&stage.buffer, dst_raw, regions);
+            encoder.copy_buffer_to_texture(stage.buffer, dst_raw, regions);
         }
 
-        device.pending_writes.consume(stage);
+        drop(staging_belt);
         device
             .pending_writes
             .dst_textures
@@ -521,10 +1006,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         &self,
         queue_id: id::QueueId,
         command_buffer_ids: &[id::CommandBufferId],
-    ) -> Result<(), QueueSubmitError> {
+    ) -> Result<SubmissionIndex, QueueSubmitError> {
         profiling::scope!("submit", "Queue");
 
-        let callbacks = {
+        let (submit_index, callbacks) = {
             let hub = A::hub(self);
             let mut token = Token::root();
 
@@ -536,6 +1021,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             device.active_submission_index += 1;
             let submit_index = device.active_submission_index;
             let mut active_executions = Vec::new();
+            let mut push_constant_chunks = Vec::new();
             let mut used_surface_textures = track::ResourceTracker::new(A::VARIANT);
 
             {
@@ -573,10 +1059,17 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         };
                         #[cfg(feature = "trace")]
                         if let Some(ref trace) = device.trace {
-                            trace.lock().add(Action::Submit(
-                                submit_index,
-                                cmdbuf.commands.take().unwrap(),
-                            ));
+                            let mut trace = trace.lock();
+                            if trace.is_recording() {
+                                let commands = cmdbuf.commands.take().unwrap_or_else(|| {
+                                    log::warn!(
+                                        "Command buffer {:?} was encoded before the trace started",
+                                        cmb_id
+                                    );
+                                    Vec::new()
+                                });
+                                trace.add(Action::Submit(submit_index, commands));
+                            }
                         }
                         if !cmdbuf.is_finished() {
                             device.destroy_command_buffer(cmdbuf);
@@ -599,13 +1092,15 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 if let BufferMapState::Active { .. } = buffer.map_state {
                                     log::warn!("Dropped buffer has a pending mapping.");
                                     unsafe { device.raw.unmap_buffer(raw_buf) }
-                                        .map_err(DeviceError::from)?;
+                                        .map_err(|e| device.handle_hal_error(e))?;
                                 }
                                 device.temp_suspected.buffers.push(id);
                             } else {
                                 match buffer.map_state {
                                     BufferMapState::Idle => (),
-                                    _ => panic!("Buffer {:?} is still mapped", id),
+                                    _ => {
+                                        return Err(QueueSubmitError::BufferStillMapped(id.0));
+                                    }
                                 }
                             }
                         }
@@ -707,12 +1202,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                         }
 
                         let mut baked = cmdbuf.into_baked();
+                        push_constant_chunks.append(&mut baked.push_constant_chunks);
                         // execute resource transitions
                         unsafe {
                             baked
                                 .encoder
                                 .begin_encoding(Some("_Transit"))
-                                .map_err(DeviceError::from)?
+                                .map_err(|e| device.handle_hal_error(e))?
                         };
                         log::trace!("Stitching command buffer {:?} before submission", cmb_id);
                         baked
@@ -740,7 +1236,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                                 baked
                                     .encoder
                                     .begin_encoding(Some("_Present"))
-                                    .map_err(DeviceError::from)?
+                                    .map_err(|e| device.handle_hal_error(e))?
                             };
                             let texture_barriers = trackers
                                 .textures
@@ -767,6 +1263,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                     log::trace!("Device after submission {}: {:#?}", submit_index, trackers);
                 }
 
+                if let Err(error) = device.staging_belt.lock().unmap(&device.raw) {
+                    return Err(QueueSubmitError::Queue(device.handle_hal_error(error)));
+                }
                 let super::Device {
                     ref mut pending_writes,
                     ref mut queue,
@@ -782,10 +1281,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                             .flat_map(|pool_execution| pool_execution.cmd_buffers.iter()),
                     )
                     .collect::<Vec<_>>();
-                unsafe {
-                    queue
-                        .submit(&refs, Some((fence, submit_index)))
-                        .map_err(DeviceError::from)?;
+                let result = unsafe { queue.submit(&refs, Some((fence, submit_index))) };
+                if let Err(error) = result {
+                    return Err(QueueSubmitError::Queue(device.handle_hal_error(error)));
                 }
             }
 
@@ -800,10 +1298,13 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
 
             // this will register the new submission to the life time tracker
             let mut pending_write_resources = mem::take(&mut device.pending_writes.temp_resources);
+            let staging_chunks = device.staging_belt.lock().flush();
             device.lock_life(&mut token).track_submission(
                 submit_index,
                 pending_write_resources.drain(..),
                 active_executions,
+                staging_chunks,
+                push_constant_chunks,
             );
 
             // This will schedule destruction of all resources that are no longer needed
@@ -812,20 +1313,22 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(closures) => closures,
                 Err(WaitIdleError::Device(err)) => return Err(QueueSubmitError::Queue(err)),
                 Err(WaitIdleError::StuckGpu) => return Err(QueueSubmitError::StuckGpu),
+                Err(WaitIdleError::WrongSubmissionIndex(..)) => unreachable!(),
             };
 
             device.pending_writes.temp_resources = pending_write_resources;
             device.temp_suspected.clear();
             device.lock_life(&mut token).post_submit();
+            device.work_signal.notify(submit_index);
 
-            closures
+            (submit_index, closures)
         };
 
         // the closures should execute with nothing locked!
         unsafe {
             callbacks.fire();
         }
-        Ok(())
+        Ok(submit_index)
     }
 
     pub fn queue_get_timestamp_period<A: HalApi>(
@@ -847,18 +1350,26 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         closure: SubmittedWorkDoneClosure,
     ) -> Result<(), InvalidQueue> {
         //TODO: flush pending writes
-        let added = {
+        let status = {
             let hub = A::hub(self);
             let mut token = Token::root();
             let (device_guard, mut token) = hub.devices.read(&mut token);
             match device_guard.get(queue_id) {
-                Ok(device) => device.lock_life(&mut token).add_work_done_closure(closure),
+                Ok(device) if device.is_lost() => Some(SubmittedWorkDoneStatus::DeviceLost),
+                Ok(device) => {
+                    if device.lock_life(&mut token).add_work_done_closure(closure) {
+                        device.work_signal.notify(device.active_submission_index);
+                        None
+                    } else {
+                        Some(SubmittedWorkDoneStatus::Success)
+                    }
+                }
                 Err(_) => return Err(InvalidQueue),
             }
         };
-        if !added {
+        if let Some(status) = status {
             unsafe {
-                (closure.callback)(closure.user_data);
+                (closure.callback)(status, closure.user_data);
             }
         }
         Ok(())
@@ -887,13 +1398,6 @@ fn get_greatest_common_divisor(mut a: u32, mut b: u32) -> u32 {
     }
 }
 
-fn align_to(value: u32, alignment: u32) -> u32 {
-    match value % alignment {
-        0 => value,
-        other => value - other + alignment,
-    }
-}
-
 #[test]
 fn test_lcd() {
     assert_eq!(get_lowest_common_denom(2, 2), 2);
@@ -0,0 +1,436 @@
+use crate::device::DeviceError;
+
+use hal::Device as _;
+use wgt::{math::align_to, BufferAddress};
+
+use std::{iter, mem, ptr, ptr::NonNull};
+
+/// Size of the staging chunks that the queue writes are sub-allocated from.
+/// Larger writes get a dedicated staging buffer.
+pub(super) const CHUNK_SIZE: BufferAddress = 1 << 20;
+/// Number of free chunks kept around for the next writes.
+/// The chunks retired beyond that are destroyed.
+const MAX_FREE_CHUNKS: usize = 4;
+
+/// Copy `data` into `buffer` at `offset`, mapping it for the time of the copy.
+pub(super) unsafe fn write_unmapped<A: hal::Api>(
+    device: &A::Device,
+    buffer: &A::Buffer,
+    offset: BufferAddress,
+    data: &[u8],
+) -> Result<(), hal::DeviceError> {
+    let range = offset..offset + data.len() as BufferAddress;
+    let mapping = device.map_buffer(buffer, range.clone())?;
+    ptr::copy_nonoverlapping(data.as_ptr(), mapping.ptr.as_ptr(), data.len());
+    if !mapping.is_coherent {
+        device.flush_mapped_ranges(buffer, iter::once(range));
+    }
+    device.unmap_buffer(buffer)?;
+    Ok(())
+}
+
+/// Part of a staging chunk, reserved for a single queue write.
+pub(super) struct StagingSpace<'a, A: hal::Api> {
+    pub buffer: &'a A::Buffer,
+    pub offset: BufferAddress,
+    /// Mapping of the whole chunk.
+    mapping: &'a hal::BufferMapping,
+}
+
+impl<A: hal::Api> StagingSpace<'_, A> {
+    /// Pointer to the start of this space.
+    pub fn ptr(&self) -> *mut u8 {
+        unsafe { self.mapping.ptr.as_ptr().offset(self.offset as isize) }
+    }
+
+    /// Make the bytes written to `range`, relative to the start of
+    /// this space, visible to the device.
+    pub unsafe fn flush(&self, device: &A::Device, range: std::ops::Range<BufferAddress>) {
+        if !self.mapping.is_coherent {
+            let range = self.offset + range.start..self.offset + range.end;
+            device.flush_mapped_ranges(self.buffer, iter::once(range));
+        }
+    }
+
+    /// Copy `data` into this space, starting at `offset` relative to its start.
+    pub unsafe fn write(&self, device: &A::Device, offset: BufferAddress, data: &[u8]) {
+        ptr::copy_nonoverlapping(
+            data.as_ptr(),
+            self.ptr().offset(offset as isize),
+            data.len(),
+        );
+        self.flush(device, offset..offset + data.len() as BufferAddress);
+    }
+}
+
+/// Staging memory handed out by [`Global::queue_create_staging_buffer`],
+/// sub-allocated from the staging belt of the device.
+///
+/// It stays mapped until it's passed to [`Global::queue_write_staging_buffer`].
+///
+/// [`Global::queue_create_staging_buffer`]: crate::hub::Global::queue_create_staging_buffer
+/// [`Global::queue_write_staging_buffer`]: crate::hub::Global::queue_write_staging_buffer
+#[derive(Debug)]
+pub struct StagingBuffer {
+    /// Index of the chunk it's sub-allocated from.
+    chunk: u64,
+    offset: BufferAddress,
+    size: BufferAddress,
+    ptr: NonNull<u8>,
+}
+
+unsafe impl Send for StagingBuffer {}
+unsafe impl Sync for StagingBuffer {}
+
+impl StagingBuffer {
+    pub fn size(&self) -> BufferAddress {
+        self.size
+    }
+
+    /// Pointer to the mapped contents.
+    pub fn ptr(&self) -> *mut u8 {
+        self.ptr.as_ptr()
+    }
+
+    pub(super) unsafe fn data(&self) -> &[u8] {
+        std::slice::from_raw_parts(self.ptr.as_ptr(), self.size as usize)
+    }
+}
+
+/// Staging buffer that queue writes are sub-allocated from.
+///
+/// It stays mapped while it's written to, until the writes are submitted.
+pub(super) struct StagingChunk<A: hal::Api> {
+    buffer: A::Buffer,
+    /// Index of the chunk, unique within the belt.
+    index: u64,
+    size: BufferAddress,
+    /// Start of the free space.
+    cursor: BufferAddress,
+    /// Number of staging buffers sub-allocated from the chunk
+    /// that haven't been written yet.
+    reservations: usize,
+    mapping: Option<hal::BufferMapping>,
+}
+
+unsafe impl<A: hal::Api> Send for StagingChunk<A> {}
+unsafe impl<A: hal::Api> Sync for StagingChunk<A> {}
+
+impl<A: hal::Api> StagingChunk<A> {
+    fn map(&mut self, device: &A::Device) -> Result<&hal::BufferMapping, DeviceError> {
+        if self.mapping.is_none() {
+            let mapping = unsafe { device.map_buffer(&self.buffer, 0..self.size) }?;
+            self.mapping = Some(mapping);
+        }
+        Ok(self.mapping.as_ref().unwrap())
+    }
+
+    fn unmap(&mut self, device: &A::Device) -> Result<(), hal::DeviceError> {
+        if self.mapping.take().is_some() {
+            unsafe { device.unmap_buffer(&self.buffer) }?;
+        }
+        Ok(())
+    }
+}
+
+/// Statistics of the staging belt of a device.
+#[derive(Clone, Debug, Default)]
+pub struct StagingReport {
+    /// Size of a regular staging chunk.
+    pub chunk_size: BufferAddress,
+    /// Number of regular chunks alive, free or not.
+    pub num_chunks: usize,
+    /// Number of regular chunks ready to be reused.
+    pub num_free_chunks: usize,
+    /// Number of writes sub-allocated from the chunks since the device was created.
+    pub num_allocations: u64,
+    /// Number of chunks holding staging buffers that haven't been written yet.
+    pub num_reserved_chunks: usize,
+    /// Number of staging buffers created since the device was created,
+    /// including the dedicated ones for large writes.
+    pub num_buffers_created: u64,
+}
+
+impl StagingReport {
+    pub(crate) fn merge(&mut self, other: &Self) {
+        self.chunk_size = other.chunk_size;
+        self.num_chunks += other.num_chunks;
+        self.num_free_chunks += other.num_free_chunks;
+        self.num_allocations += other.num_allocations;
+        self.num_reserved_chunks += other.num_reserved_chunks;
+        self.num_buffers_created += other.num_buffers_created;
+    }
+}
+
+/// Per-device pool of staging chunks used by the queue writes.
+///
+/// Chunks written since the last submission are handed over to the
+/// `LifetimeTracker` with the submission, and come back here when it's retired.
+pub(super) struct StagingBelt<A: hal::Api> {
+    /// Chunks used by the pending writes. The last one is sub-allocated from.
+    active: Vec<StagingChunk<A>>,
+    /// Chunks that [`StagingBuffer`]s are sub-allocated from, until they are
+    /// all written. The last one is sub-allocated from.
+    ///
+    /// They can't be submitted before, as they have to stay mapped.
+    reserved: Vec<StagingChunk<A>>,
+    /// Chunks that are not used by the GPU any more.
+    free: Vec<StagingChunk<A>>,
+    num_chunks: usize,
+    num_allocations: u64,
+    num_buffers_created: u64,
+}
+
+impl<A: hal::Api> StagingBelt<A> {
+    pub fn new() -> Self {
+        Self {
+            active: Vec::new(),
+            reserved: Vec::new(),
+            free: Vec::new(),
+            num_chunks: 0,
+            num_allocations: 0,
+            num_buffers_created: 0,
+        }
+    }
+
+    fn create_chunk(
+        &mut self,
+        device: &A::Device,
+        size: BufferAddress,
+    ) -> Result<StagingChunk<A>, DeviceError> {
+        profiling::scope!("create_chunk", "StagingBelt");
+        let stage_desc = hal::BufferDescriptor {
+            label: Some("_Staging"),
+            size,
+            usage: hal::BufferUses::MAP_WRITE | hal::BufferUses::COPY_SRC,
+            memory_flags: hal::MemoryFlags::TRANSIENT,
+        };
+        let buffer = unsafe { device.create_buffer(&stage_desc) }?;
+        self.num_buffers_created += 1;
+        Ok(StagingChunk {
+            buffer,
+            index: self.num_buffers_created,
+            size,
+            cursor: 0,
+            reservations: 0,
+            mapping: None,
+        })
+    }
+
+    /// Find `size` bytes, starting at a multiple of `alignment`, at the end of
+    /// `chunks`, adding a chunk if needed.
+    ///
+    /// Returns the index of the chunk in `chunks` and the offset in the chunk.
+    fn sub_allocate(
+        &mut self,
+        device: &A::Device,
+        chunks: &mut Vec<StagingChunk<A>>,
+        size: BufferAddress,
+        alignment: BufferAddress,
+    ) -> Result<(usize, BufferAddress), DeviceError> {
+        if size > CHUNK_SIZE {
+            let mut chunk = self.create_chunk(device, size)?;
+            chunk.cursor = size;
+            // Keep the current chunk at the end, so that it's still sub-allocated from.
+            let index = chunks.len().saturating_sub(1);
+            chunks.insert(index, chunk);
+            return Ok((index, 0));
+        }
+
+        let fits = |chunk: &StagingChunk<A>| {
+            let offset = align_to(chunk.cursor, alignment);
+            offset + size <= chunk.size
+        };
+        let needs_chunk = match chunks.last() {
+            Some(chunk) => !fits(chunk),
+            None => true,
+        };
+        if needs_chunk {
+            let chunk = match self.free.pop() {
+                Some(chunk) => chunk,
+                None => {
+                    let chunk = self.create_chunk(device, CHUNK_SIZE)?;
+                    self.num_chunks += 1;
+                    chunk
+                }
+            };
+            chunks.push(chunk);
+        }
+
+        let index = chunks.len() - 1;
+        let chunk = &mut chunks[index];
+        let offset = align_to(chunk.cursor, alignment);
+        chunk.cursor = offset + size;
+        Ok((index, offset))
+    }
+
+    /// Reserve `size` bytes, starting at a multiple of `alignment`.
+    ///
+    /// Writes larger than [`CHUNK_SIZE`] get a dedicated chunk of their own,
+    /// which is destroyed instead of recycled.
+    pub fn allocate(
+        &mut self,
+        device: &A::Device,
+        size: BufferAddress,
+        alignment: BufferAddress,
+    ) -> Result<StagingSpace<'_, A>, DeviceError> {
+        self.num_allocations += 1;
+
+        let mut active = mem::take(&mut self.active);
+        let result = self.sub_allocate(device, &mut active, size, alignment);
+        self.active = active;
+        let (index, offset) = result?;
+
+        let chunk = &mut self.active[index];
+        chunk.map(device)?;
+        Ok(StagingSpace {
+            buffer: &chunk.buffer,
+            offset,
+            mapping: chunk.mapping.as_ref().unwrap(),
+        })
+    }
+
+    /// Reserve `size` zeroed bytes for a [`StagingBuffer`], starting at
+    /// a multiple of `alignment`.
+    ///
+    /// The chunk it comes from stays mapped, and out of the submissions,
+    /// until all of its staging buffers are released.
+    pub fn reserve(
+        &mut self,
+        device: &A::Device,
+        size: BufferAddress,
+        alignment: BufferAddress,
+    ) -> Result<StagingBuffer, DeviceError> {
+        self.num_allocations += 1;
+
+        let mut reserved = mem::take(&mut self.reserved);
+        let result = self.sub_allocate(device, &mut reserved, size, alignment);
+        self.reserved = reserved;
+        let (index, offset) = result?;
+
+        let chunk = &mut self.reserved[index];
+        let ptr = match chunk.map(device) {
+            Ok(mapping) => unsafe { mapping.ptr.as_ptr().offset(offset as isize) },
+            Err(e) => {
+                if chunk.reservations == 0 {
+                    // Nothing is left to write in the chunk, let it go
+                    // through a submission to be recycled.
+                    let chunk = self.reserved.remove(index);
+                    let index = self.active.len().saturating_sub(1);
+                    self.active.insert(index, chunk);
+                }
+                return Err(e);
+            }
+        };
+        // The contents are handed out as a slice, so they have to be initialized.
+        unsafe { ptr::write_bytes(ptr, 0, size as usize) };
+        chunk.reservations += 1;
+
+        Ok(StagingBuffer {
+            chunk: chunk.index,
+            offset,
+            size,
+            ptr: unsafe { NonNull::new_unchecked(ptr) },
+        })
+    }
+
+    /// Release a [`StagingBuffer`] and return the space its contents can be
+    /// copied from by the pending writes.
+    ///
+    /// If it was the last staging buffer of its chunk, the chunk joins the
+    /// pending writes. Otherwise the contents are copied to a regular space,
+    /// as the chunk can't be submitted while the other ones are written to.
+    ///
+    /// Returns `None` if the staging buffer doesn't come from this belt.
+    pub fn release(
+        &mut self,
+        device: &A::Device,
+        staging_buffer: StagingBuffer,
+        alignment: BufferAddress,
+    ) -> Option<Result<StagingSpace<'_, A>, DeviceError>> {
+        let index = self
+            .reserved
+            .iter()
+            .position(|chunk| chunk.index == staging_buffer.chunk)?;
+        let chunk = &mut self.reserved[index];
+        chunk.reservations -= 1;
+        if chunk.reservations != 0 {
+            let stage = match self.allocate(device, staging_buffer.size, alignment) {
+                Ok(stage) => stage,
+                Err(e) => return Some(Err(e)),
+            };
+            unsafe { stage.write(device, 0, staging_buffer.data()) };
+            return Some(Ok(stage));
+        }
+
+        let chunk = self.reserved.remove(index);
+        // Keep the current chunk at the end, so that it's still sub-allocated from.
+        let index = self.active.len().saturating_sub(1);
+        self.active.insert(index, chunk);
+        let chunk = &self.active[index];
+        let stage = StagingSpace {
+            buffer: &chunk.buffer,
+            offset: staging_buffer.offset,
+            mapping: chunk.mapping.as_ref().unwrap(),
+        };
+        unsafe { stage.flush(device, 0..staging_buffer.size) };
+        Some(Ok(stage))
+    }
+
+    /// Unmap the chunks used by the pending writes, before they are submitted.
+    pub fn unmap(&mut self, device: &A::Device) -> Result<(), hal::DeviceError> {
+        for chunk in self.active.iter_mut() {
+            chunk.unmap(device)?;
+        }
+        Ok(())
+    }
+
+    /// Take the chunks used by the pending writes, which have been submitted.
+    pub fn flush(&mut self) -> Vec<StagingChunk<A>> {
+        mem::take(&mut self.active)
+    }
+
+    /// Take back a chunk of a retired submission.
+    ///
+    /// Returns the buffer of a dedicated chunk, or of a chunk that isn't
+    /// needed any more, which has to be destroyed.
+    pub fn recycle(&mut self, mut chunk: StagingChunk<A>) -> Option<A::Buffer> {
+        if chunk.size > CHUNK_SIZE {
+            Some(chunk.buffer)
+        } else if self.free.len() >= MAX_FREE_CHUNKS {
+            self.num_chunks -= 1;
+            Some(chunk.buffer)
+        } else {
+            chunk.cursor = 0;
+            self.free.push(chunk);
+            None
+        }
+    }
+
+    pub fn report(&self) -> StagingReport {
+        StagingReport {
+            chunk_size: CHUNK_SIZE,
+            num_chunks: self.num_chunks,
+            num_free_chunks: self.free.len(),
+            num_allocations: self.num_allocations,
+            num_reserved_chunks: self.reserved.len(),
+            num_buffers_created: self.num_buffers_created,
+        }
+    }
+
+    pub fn dispose(self, device: &A::Device) {
+        log::info!(
+            "Destroying {} staging chunks",
+            self.active.len() + self.reserved.len() + self.free.len()
+        );
+        for mut chunk in self
+            .active
+            .into_iter()
+            .chain(self.reserved)
+            .chain(self.free)
+        {
+            let _ = chunk.unmap(device);
+            unsafe { device.destroy_buffer(chunk.buffer) };
+        }
+    }
+}
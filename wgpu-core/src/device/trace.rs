@@ -1,13 +1,37 @@
+#[cfg(feature = "trace")]
+use super::TraceDescriptor;
+use super::TraceFormat;
 use crate::id;
-use std::ops::Range;
 #[cfg(feature = "trace")]
-use std::{borrow::Cow, io::Write as _};
+use arrayvec::ArrayVec;
+#[cfg(feature = "replay")]
+use std::io::Read;
+#[cfg(feature = "trace")]
+use std::{borrow::Cow, io::Write};
+use std::{io, ops::Range};
 
 //TODO: consider a readable Id that doesn't include the backend
 
 type FileName = String;
 
 pub const FILE_NAME: &str = "trace.ron";
+pub const BINARY_FILE_NAME: &str = "trace.bin";
+/// Magic bytes at the start of a binary trace file.
+pub const BINARY_MAGIC: [u8; 8] = *b"wgpu-trc";
+/// Largest encoded action in a binary trace, in bytes.
+///
+/// The data of the buffer and texture writes is kept in separate files,
+/// so actions never get close to it, unless the trace is corrupted.
+pub const MAX_BINARY_ACTION_SIZE: u32 = 1 << 26;
+
+impl TraceFormat {
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Ron => FILE_NAME,
+            Self::Binary => BINARY_FILE_NAME,
+        }
+    }
+}
 
 #[cfg(feature = "trace")]
 pub(crate) fn new_render_bundle_encoder_descriptor<'a>(
@@ -31,7 +55,7 @@ pub(crate) fn new_render_bundle_encoder_descriptor<'a>(
 }
 
 #[allow(clippy::large_enum_variant)]
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "trace", derive(serde::Serialize))]
 #[cfg_attr(feature = "replay", derive(serde::Deserialize))]
 pub enum Action<'a> {
@@ -120,7 +144,7 @@ pub enum Action<'a> {
     Submit(crate::SubmissionIndex, Vec<Command>),
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 #[cfg_attr(feature = "trace", derive(serde::Serialize))]
 #[cfg_attr(feature = "replay", derive(serde::Deserialize))]
 pub enum Command {
@@ -176,51 +200,875 @@ pub enum Command {
     },
 }
 
+/// Error produced when reading a binary trace.
+#[cfg(feature = "replay")]
+#[derive(Debug, thiserror::Error)]
+pub enum BinaryTraceError {
+    #[error("not a binary trace")]
+    InvalidMagic,
+    #[error(
+        "action of {0} bytes is larger than the limit of {} bytes",
+        MAX_BINARY_ACTION_SIZE
+    )]
+    ActionTooLarge(u32),
+    #[error("trace ends in the middle of an action")]
+    Truncated,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    Decode(#[from] bincode::Error),
+}
+
+/// Streaming reader of the actions in a binary trace.
+///
+/// Actions are decoded one at a time, so the trace doesn't have to fit in memory.
+#[cfg(feature = "replay")]
+#[derive(Debug)]
+pub struct BinaryReader<R> {
+    reader: R,
+    buffer: Vec<u8>,
+}
+
+#[cfg(feature = "replay")]
+impl<R: Read> BinaryReader<R> {
+    pub fn new(mut reader: R) -> Result<Self, BinaryTraceError> {
+        let mut magic = [0; 8];
+        reader.read_exact(&mut magic)?;
+        if magic != BINARY_MAGIC {
+            return Err(BinaryTraceError::InvalidMagic);
+        }
+        Ok(Self {
+            reader,
+            buffer: Vec::new(),
+        })
+    }
+
+    /// Read the next action, returning `None` at the end of the trace.
+    ///
+    /// Only a trace that ends between two actions ends cleanly.
+    pub fn read_action(&mut self) -> Result<Option<Action<'static>>, BinaryTraceError> {
+        let mut length = [0; 4];
+        let mut filled = 0;
+        while filled < length.len() {
+            match self.reader.read(&mut length[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(BinaryTraceError::Truncated),
+                Ok(count) => filled += count,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let length = u32::from_le_bytes(length);
+        if length > MAX_BINARY_ACTION_SIZE {
+            return Err(BinaryTraceError::ActionTooLarge(length));
+        }
+        self.buffer.resize(length as usize, 0);
+        self.reader
+            .read_exact(&mut self.buffer)
+            .map_err(|e| match e.kind() {
+                io::ErrorKind::UnexpectedEof => BinaryTraceError::Truncated,
+                _ => e.into(),
+            })?;
+        Ok(Some(bincode::deserialize(&self.buffer)?))
+    }
+}
+
+#[cfg(feature = "replay")]
+impl<R: Read> Iterator for BinaryReader<R> {
+    type Item = Result<Action<'static>, BinaryTraceError>;
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_action().transpose()
+    }
+}
+
+/// Encoder of actions into a trace file of the given format.
+///
+/// The RON array is closed when the writer is dropped.
 #[cfg(feature = "trace")]
 #[derive(Debug)]
-pub struct Trace {
-    path: std::path::PathBuf,
-    file: std::fs::File,
-    config: ron::ser::PrettyConfig,
-    binary_id: usize,
+pub struct TraceWriter<W: Write> {
+    writer: W,
+    format: TraceFormat,
 }
 
 #[cfg(feature = "trace")]
-impl Trace {
-    pub fn new(path: &std::path::Path) -> Result<Self, std::io::Error> {
-        log::info!("Tracing into '{:?}'", path);
-        let mut file = std::fs::File::create(path.join(FILE_NAME))?;
-        file.write_all(b"[\n")?;
-        Ok(Self {
-            path: path.to_path_buf(),
-            file,
-            config: ron::ser::PrettyConfig::default(),
-            binary_id: 0,
+impl<W: Write> TraceWriter<W> {
+    pub fn new(mut writer: W, format: TraceFormat) -> Result<Self, io::Error> {
+        match format {
+            TraceFormat::Ron => writer.write_all(b"[\n")?,
+            TraceFormat::Binary => writer.write_all(&BINARY_MAGIC)?,
+        }
+        Ok(Self { writer, format })
+    }
+
+    pub fn write(&mut self, action: &Action) -> Result<(), io::Error> {
+        match self.format {
+            TraceFormat::Ron => self.write_ron(&encode_ron(action)?),
+            TraceFormat::Binary => self.write_binary(&encode_binary(action)?),
+        }
+    }
+
+    fn write_ron(&mut self, string: &str) -> Result<(), io::Error> {
+        writeln!(self.writer, "{},", string)
+    }
+
+    fn write_binary(&mut self, payload: &[u8]) -> Result<(), io::Error> {
+        if payload.len() > MAX_BINARY_ACTION_SIZE as usize {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "action is too large for a binary trace",
+            ));
+        }
+        // Write the length prefix together with the payload, so that
+        // an interrupted trace doesn't end with a dangling length.
+        let mut record = Vec::with_capacity(4 + payload.len());
+        record.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        record.extend_from_slice(payload);
+        self.writer.write_all(&record)
+    }
+}
+
+#[cfg(feature = "trace")]
+fn encode_ron(action: &Action) -> Result<String, io::Error> {
+    ron::ser::to_string_pretty(action, ron::ser::PrettyConfig::default())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(feature = "trace")]
+fn encode_binary(action: &Action) -> Result<Vec<u8>, io::Error> {
+    bincode::serialize(action).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+#[cfg(feature = "trace")]
+impl<W: Write> Drop for TraceWriter<W> {
+    fn drop(&mut self) {
+        if self.format == TraceFormat::Ron {
+            let _ = self.writer.write_all(b"]");
+        }
+    }
+}
+
+/// Resource created by an action, as far as the book-keeping of the live set goes.
+#[cfg(feature = "trace")]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+enum ResourceKey {
+    Surface(id::SurfaceId),
+    Buffer(id::BufferId),
+    Texture(id::TextureId),
+    TextureView(id::TextureViewId),
+    Sampler(id::SamplerId),
+    BindGroupLayout(id::BindGroupLayoutId),
+    PipelineLayout(id::PipelineLayoutId),
+    BindGroup(id::BindGroupId),
+    ShaderModule(id::ShaderModuleId),
+    ComputePipeline(id::ComputePipelineId),
+    RenderPipeline(id::RenderPipelineId),
+    RenderBundle(id::RenderBundleId),
+    QuerySet(id::QuerySetId),
+}
+
+#[cfg(feature = "trace")]
+type CreatedKeys = ArrayVec<ResourceKey, { hal::MAX_BIND_GROUPS + 2 }>;
+
+#[cfg(feature = "trace")]
+impl Action<'_> {
+    /// Resources brought to life by this action.
+    fn created_keys(&self) -> CreatedKeys {
+        fn implicit(keys: &mut CreatedKeys, context: &Option<super::ImplicitPipelineContext>) {
+            if let Some(ref context) = *context {
+                keys.push(ResourceKey::PipelineLayout(context.root_id));
+                keys.extend(
+                    context
+                        .group_ids
+                        .iter()
+                        .map(|&id| ResourceKey::BindGroupLayout(id)),
+                );
+            }
+        }
+
+        let mut keys = CreatedKeys::new();
+        match *self {
+            Action::ConfigureSurface(id, _) => keys.push(ResourceKey::Surface(id)),
+            Action::CreateBuffer(id, _) => keys.push(ResourceKey::Buffer(id)),
+            Action::CreateTexture(id, _) => keys.push(ResourceKey::Texture(id)),
+            Action::CreateTextureView { id, .. } => keys.push(ResourceKey::TextureView(id)),
+            Action::CreateSampler(id, _) => keys.push(ResourceKey::Sampler(id)),
+            Action::CreateBindGroupLayout(id, _) => keys.push(ResourceKey::BindGroupLayout(id)),
+            Action::CreatePipelineLayout(id, _) => keys.push(ResourceKey::PipelineLayout(id)),
+            Action::CreateBindGroup(id, _) => keys.push(ResourceKey::BindGroup(id)),
+            Action::CreateShaderModule { id, .. } => keys.push(ResourceKey::ShaderModule(id)),
+            Action::CreateComputePipeline {
+                id,
+                ref implicit_context,
+                ..
+            } => {
+                keys.push(ResourceKey::ComputePipeline(id));
+                implicit(&mut keys, implicit_context);
+            }
+            Action::CreateRenderPipeline {
+                id,
+                ref implicit_context,
+                ..
+            } => {
+                keys.push(ResourceKey::RenderPipeline(id));
+                implicit(&mut keys, implicit_context);
+            }
+            Action::CreateRenderBundle { id, .. } => keys.push(ResourceKey::RenderBundle(id)),
+            Action::CreateQuerySet { id, .. } => keys.push(ResourceKey::QuerySet(id)),
+            _ => {}
+        }
+        keys
+    }
+
+    /// Resource affected by this action, and whether its ID stays alive.
+    fn destroyed_key(&self) -> Option<(ResourceKey, bool)> {
+        Some(match *self {
+            Action::FreeBuffer(id) => (ResourceKey::Buffer(id), true),
+            Action::DestroyBuffer(id) => (ResourceKey::Buffer(id), false),
+            Action::FreeTexture(id) => (ResourceKey::Texture(id), true),
+            Action::DestroyTexture(id) => (ResourceKey::Texture(id), false),
+            Action::DestroyTextureView(id) => (ResourceKey::TextureView(id), false),
+            Action::DestroySampler(id) => (ResourceKey::Sampler(id), false),
+            Action::DestroyBindGroupLayout(id) => (ResourceKey::BindGroupLayout(id), false),
+            Action::DestroyPipelineLayout(id) => (ResourceKey::PipelineLayout(id), false),
+            Action::DestroyBindGroup(id) => (ResourceKey::BindGroup(id), false),
+            Action::DestroyShaderModule(id) => (ResourceKey::ShaderModule(id), false),
+            Action::DestroyComputePipeline(id) => (ResourceKey::ComputePipeline(id), false),
+            Action::DestroyRenderPipeline(id) => (ResourceKey::RenderPipeline(id), false),
+            Action::DestroyRenderBundle(id) => (ResourceKey::RenderBundle(id), false),
+            Action::DestroyQuerySet(id) => (ResourceKey::QuerySet(id), false),
+            _ => return None,
         })
     }
 
-    pub fn make_binary(&mut self, kind: &str, data: &[u8]) -> String {
+    /// Copy of the action that doesn't borrow anything, so that it can be
+    /// kept around by [`LiveResources`].
+    ///
+    /// Returns `None` for the actions that don't create or destroy resources.
+    fn to_static(&self) -> Option<Action<'static>> {
+        use crate::{binding_model as bm, command, pipeline, resource};
+
+        fn label(label: &crate::Label) -> crate::Label<'static> {
+            label.as_ref().map(|label| Cow::Owned(label.to_string()))
+        }
+        fn slice<T: Clone>(slice: &[T]) -> Cow<'static, [T]> {
+            Cow::Owned(slice.to_vec())
+        }
+        fn stage(
+            stage: &pipeline::ProgrammableStageDescriptor,
+        ) -> pipeline::ProgrammableStageDescriptor<'static> {
+            pipeline::ProgrammableStageDescriptor {
+                module: stage.module,
+                entry_point: Cow::Owned(stage.entry_point.to_string()),
+            }
+        }
+
+        Some(match *self {
+            Action::ConfigureSurface(id, ref config) => {
+                Action::ConfigureSurface(id, config.clone())
+            }
+            Action::CreateBuffer(id, ref desc) => Action::CreateBuffer(id, desc.map_label(label)),
+            Action::CreateTexture(id, ref desc) => Action::CreateTexture(id, desc.map_label(label)),
+            Action::CreateTextureView {
+                id,
+                parent_id,
+                ref desc,
+            } => Action::CreateTextureView {
+                id,
+                parent_id,
+                desc: resource::TextureViewDescriptor {
+                    label: label(&desc.label),
+                    format: desc.format,
+                    dimension: desc.dimension,
+                    range: desc.range.clone(),
+                },
+            },
+            Action::CreateSampler(id, ref desc) => Action::CreateSampler(
+                id,
+                resource::SamplerDescriptor {
+                    label: label(&desc.label),
+                    address_modes: desc.address_modes,
+                    mag_filter: desc.mag_filter,
+                    min_filter: desc.min_filter,
+                    mipmap_filter: desc.mipmap_filter,
+                    lod_min_clamp: desc.lod_min_clamp,
+                    lod_max_clamp: desc.lod_max_clamp,
+                    compare: desc.compare,
+                    anisotropy_clamp: desc.anisotropy_clamp,
+                    border_color: desc.border_color,
+                },
+            ),
+            Action::CreateBindGroupLayout(id, ref desc) => Action::CreateBindGroupLayout(
+                id,
+                bm::BindGroupLayoutDescriptor {
+                    label: label(&desc.label),
+                    entries: slice(&desc.entries),
+                },
+            ),
+            Action::CreatePipelineLayout(id, ref desc) => Action::CreatePipelineLayout(
+                id,
+                bm::PipelineLayoutDescriptor {
+                    label: label(&desc.label),
+                    bind_group_layouts: slice(&desc.bind_group_layouts),
+                    push_constant_ranges: slice(&desc.push_constant_ranges),
+                },
+            ),
+            Action::CreateBindGroup(id, ref desc) => Action::CreateBindGroup(
+                id,
+                bm::BindGroupDescriptor {
+                    label: label(&desc.label),
+                    layout: desc.layout,
+                    entries: desc
+                        .entries
+                        .iter()
+                        .map(|entry| bm::BindGroupEntry {
+                            binding: entry.binding,
+                            resource: match entry.resource {
+                                bm::BindingResource::Buffer(ref binding) => {
+                                    bm::BindingResource::Buffer(binding.clone())
+                                }
+                                bm::BindingResource::BufferArray(ref bindings) => {
+                                    bm::BindingResource::BufferArray(slice(bindings))
+                                }
+                                bm::BindingResource::Sampler(id) => {
+                                    bm::BindingResource::Sampler(id)
+                                }
+                                bm::BindingResource::TextureView(id) => {
+                                    bm::BindingResource::TextureView(id)
+                                }
+                                bm::BindingResource::TextureViewArray(ref ids) => {
+                                    bm::BindingResource::TextureViewArray(slice(ids))
+                                }
+                            },
+                        })
+                        .collect(),
+                },
+            ),
+            Action::CreateShaderModule {
+                id,
+                ref desc,
+                ref data,
+            } => Action::CreateShaderModule {
+                id,
+                desc: pipeline::ShaderModuleDescriptor {
+                    label: label(&desc.label),
+                },
+                data: data.clone(),
+            },
+            Action::CreateComputePipeline {
+                id,
+                ref desc,
+                ref implicit_context,
+            } => Action::CreateComputePipeline {
+                id,
+                desc: pipeline::ComputePipelineDescriptor {
+                    label: label(&desc.label),
+                    layout: desc.layout,
+                    stage: stage(&desc.stage),
+                },
+                implicit_context: implicit_context.clone(),
+            },
+            Action::CreateRenderPipeline {
+                id,
+                ref desc,
+                ref implicit_context,
+            } => Action::CreateRenderPipeline {
+                id,
+                desc: pipeline::RenderPipelineDescriptor {
+                    label: label(&desc.label),
+                    layout: desc.layout,
+                    vertex: pipeline::VertexState {
+                        stage: stage(&desc.vertex.stage),
+                        buffers: desc
+                            .vertex
+                            .buffers
+                            .iter()
+                            .map(|buffer| pipeline::VertexBufferLayout {
+                                array_stride: buffer.array_stride,
+                                step_mode: buffer.step_mode,
+                                attributes: slice(&buffer.attributes),
+                            })
+                            .collect(),
+                    },
+                    primitive: desc.primitive,
+                    depth_stencil: desc.depth_stencil.clone(),
+                    multisample: desc.multisample,
+                    fragment: desc
+                        .fragment
+                        .as_ref()
+                        .map(|fragment| pipeline::FragmentState {
+                            stage: stage(&fragment.stage),
+                            targets: slice(&fragment.targets),
+                        }),
+                },
+                implicit_context: implicit_context.clone(),
+            },
+            Action::CreateRenderBundle {
+                id,
+                ref desc,
+                ref base,
+            } => Action::CreateRenderBundle {
+                id,
+                desc: command::RenderBundleEncoderDescriptor {
+                    label: label(&desc.label),
+                    color_formats: slice(&desc.color_formats),
+                    depth_stencil: desc.depth_stencil,
+                    sample_count: desc.sample_count,
+                },
+                base: base.clone(),
+            },
+            Action::CreateQuerySet { id, ref desc } => Action::CreateQuerySet {
+                id,
+                desc: desc.map_label(label),
+            },
+            Action::FreeBuffer(id) => Action::FreeBuffer(id),
+            Action::DestroyBuffer(id) => Action::DestroyBuffer(id),
+            Action::FreeTexture(id) => Action::FreeTexture(id),
+            Action::DestroyTexture(id) => Action::DestroyTexture(id),
+            Action::DestroyTextureView(id) => Action::DestroyTextureView(id),
+            Action::DestroySampler(id) => Action::DestroySampler(id),
+            Action::DestroyBindGroupLayout(id) => Action::DestroyBindGroupLayout(id),
+            Action::DestroyPipelineLayout(id) => Action::DestroyPipelineLayout(id),
+            Action::DestroyBindGroup(id) => Action::DestroyBindGroup(id),
+            Action::DestroyShaderModule(id) => Action::DestroyShaderModule(id),
+            Action::DestroyComputePipeline(id) => Action::DestroyComputePipeline(id),
+            Action::DestroyRenderPipeline(id) => Action::DestroyRenderPipeline(id),
+            Action::DestroyRenderBundle(id) => Action::DestroyRenderBundle(id),
+            Action::DestroyQuerySet(id) => Action::DestroyQuerySet(id),
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+struct LiveEntry {
+    /// The creation action, as written at the start of a trace.
+    action: Action<'static>,
+    /// Name and contents of the data file referenced by the action.
+    data: Option<(FileName, Vec<u8>)>,
+    /// Number of the created resources that are still alive.
+    alive: usize,
+    /// Actions that freed or destroyed some of the created resources.
+    followups: Vec<Action<'static>>,
+}
+
+/// Creation actions of the resources that are still alive, in creation order.
+///
+/// This is what gets written at the start of a trace that is begun
+/// in the middle of the device's life.
+#[cfg(feature = "trace")]
+#[derive(Debug, Default)]
+struct LiveResources {
+    next_index: usize,
+    entries: std::collections::BTreeMap<usize, LiveEntry>,
+    keys: crate::FastHashMap<ResourceKey, usize>,
+}
+
+#[cfg(feature = "trace")]
+impl LiveResources {
+    fn observe(&mut self, action: &Action, source: Option<(String, Vec<u8>)>) {
+        let created = action.created_keys();
+        if !created.is_empty() {
+            let index = self.next_index;
+            let data = source.map(|(kind, data)| (format!("live{}.{}", index, kind), data));
+            let creation = match creation_action(action, data.as_ref().map(|data| &data.0)) {
+                Some(creation) => creation,
+                None => return,
+            };
+            self.next_index += 1;
+            for &key in created.iter() {
+                if let Some(old) = self.keys.insert(key, index) {
+                    self.release(old);
+                }
+            }
+            self.entries.insert(
+                index,
+                LiveEntry {
+                    action: creation,
+                    data,
+                    alive: created.len(),
+                    followups: Vec::new(),
+                },
+            );
+        } else if let Some((key, keep)) = action.destroyed_key() {
+            let index = if keep {
+                self.keys.get(&key).cloned()
+            } else {
+                self.keys.remove(&key)
+            };
+            if let Some(index) = index {
+                if let Some(followup) = action.to_static() {
+                    self.entries
+                        .get_mut(&index)
+                        .unwrap()
+                        .followups
+                        .push(followup);
+                }
+                if !keep {
+                    self.release(index);
+                }
+            }
+        }
+    }
+
+    fn release(&mut self, index: usize) {
+        let entry = self.entries.get_mut(&index).unwrap();
+        entry.alive -= 1;
+        if entry.alive == 0 {
+            self.entries.remove(&index);
+        }
+    }
+}
+
+/// Copy a creation action the way it's written at the start of a trace,
+/// where the contents of the resources are uploaded after their creation,
+/// and the data file of a shader module is written as `data_name`.
+#[cfg(feature = "trace")]
+fn creation_action(action: &Action, data_name: Option<&FileName>) -> Option<Action<'static>> {
+    let mut action = action.to_static()?;
+    match action {
+        // The buffers need to be writable and must not start mapped.
+        Action::CreateBuffer(_, ref mut desc) => {
+            if !desc.usage.contains(wgt::BufferUsages::MAP_WRITE) {
+                desc.usage |= wgt::BufferUsages::COPY_DST;
+            }
+            desc.mapped_at_creation = false;
+        }
+        Action::CreateTexture(_, ref mut desc) if is_texture_snapshot_supported(desc) => {
+            desc.usage |= wgt::TextureUsages::COPY_DST;
+        }
+        Action::CreateShaderModule { ref mut data, .. } => {
+            if let Some(data_name) = data_name {
+                *data = data_name.clone();
+            }
+        }
+        _ => {}
+    }
+    Some(action)
+}
+
+/// Returns true if the contents of textures with this descriptor
+/// are captured when a trace is started.
+#[cfg(feature = "trace")]
+pub(crate) fn is_texture_snapshot_supported<L>(desc: &wgt::TextureDescriptor<L>) -> bool {
+    desc.sample_count == 1
+        && hal::FormatAspects::from(desc.format) == hal::FormatAspects::COLOR
+        && crate::conv::is_valid_copy_src_texture_format(desc.format)
+        && crate::conv::is_valid_copy_dst_texture_format(desc.format)
+}
+
+/// Contents of a resource, read back when a trace is started.
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+pub(crate) enum ResourceContents {
+    Buffer {
+        id: id::BufferId,
+        data: Vec<u8>,
+        queued: bool,
+    },
+    Texture {
+        to: crate::command::ImageCopyTexture,
+        layout: wgt::ImageDataLayout,
+        size: wgt::Extent3d,
+        data: Vec<u8>,
+    },
+}
+
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+struct Recording {
+    path: std::path::PathBuf,
+    writer: TraceWriter<std::fs::File>,
+    binary_id: usize,
+}
+
+#[cfg(feature = "trace")]
+impl Recording {
+    fn make_binary(&mut self, kind: &str, data: &[u8]) -> FileName {
         self.binary_id += 1;
         let name = format!("data{}.{}", self.binary_id, kind);
         let _ = std::fs::write(self.path.join(&name), data);
         name
     }
 
-    pub(crate) fn add(&mut self, action: Action) {
-        match ron::ser::to_string_pretty(&action, self.config.clone()) {
-            Ok(string) => {
-                let _ = writeln!(self.file, "{},", string);
+    fn write(&mut self, action: &Action) {
+        if let Err(e) = self.writer.write(action) {
+            log::warn!("Trace serialization failure: {:?}", e);
+        }
+    }
+}
+
+/// API trace of a device.
+///
+/// The actions are only written out while a trace is being recorded.
+/// On devices that allow starting a trace later on, the creation of
+/// resources is also followed for the whole life of the device.
+#[cfg(feature = "trace")]
+#[derive(Debug)]
+pub struct Trace {
+    recording: Option<Recording>,
+    on_demand: bool,
+    live: LiveResources,
+    source: Option<(String, Vec<u8>)>,
+}
+
+#[cfg(feature = "trace")]
+impl Trace {
+    pub(crate) fn new(on_demand: bool) -> Self {
+        Self {
+            recording: None,
+            on_demand,
+            live: LiveResources::default(),
+            source: None,
+        }
+    }
+
+    /// Whether a trace can be started at any time, see [`DeviceTrace::OnDemand`].
+    ///
+    /// [`DeviceTrace::OnDemand`]: super::DeviceTrace::OnDemand
+    pub(crate) fn is_on_demand(&self) -> bool {
+        self.on_demand
+    }
+
+    /// Start recording into the directory described by `desc`.
+    ///
+    /// The trace begins with `init`, followed by the creation of all the
+    /// resources that are alive, the upload of their `contents`, and
+    /// the destruction of resources that are only kept alive by others.
+    pub(crate) fn start(
+        &mut self,
+        desc: TraceDescriptor,
+        init: Action,
+        contents: Vec<ResourceContents>,
+    ) -> Result<(), io::Error> {
+        log::info!("Tracing into '{:?}' as {:?}", desc.path, desc.format);
+        let file = std::fs::File::create(desc.path.join(desc.format.file_name()))?;
+        let mut recording = Recording {
+            path: desc.path.to_path_buf(),
+            writer: TraceWriter::new(file, desc.format)?,
+            binary_id: 0,
+        };
+        recording.write(&init);
+
+        for entry in self.live.entries.values() {
+            if let Some((ref name, ref data)) = entry.data {
+                let _ = std::fs::write(recording.path.join(name), data);
             }
-            Err(e) => {
-                log::warn!("RON serialization failure: {:?}", e);
+            recording.write(&entry.action);
+        }
+
+        for content in contents {
+            let action = match content {
+                ResourceContents::Buffer { id, data, queued } => Action::WriteBuffer {
+                    id,
+                    data: recording.make_binary("bin", &data),
+                    range: 0..data.len() as wgt::BufferAddress,
+                    queued,
+                },
+                ResourceContents::Texture {
+                    to,
+                    layout,
+                    size,
+                    data,
+                } => Action::WriteTexture {
+                    to,
+                    data: recording.make_binary("bin", &data),
+                    layout,
+                    size,
+                },
+            };
+            recording.write(&action);
+        }
+
+        for entry in self.live.entries.values() {
+            for followup in entry.followups.iter() {
+                recording.write(followup);
             }
         }
+
+        self.recording = Some(recording);
+        Ok(())
+    }
+
+    /// Stop recording, closing the trace file.
+    pub(crate) fn stop(&mut self) {
+        if let Some(recording) = self.recording.take() {
+            log::info!("Stopped tracing into '{:?}'", recording.path);
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    pub fn make_binary(&mut self, kind: &str, data: &[u8]) -> FileName {
+        match self.recording {
+            Some(ref mut recording) => recording.make_binary(kind, data),
+            None => FileName::new(),
+        }
+    }
+
+    /// Like `make_binary`, but the data is also kept for as long as the
+    /// shader module created by the next action is alive.
+    pub(crate) fn make_shader_source(&mut self, kind: &str, data: &[u8]) -> FileName {
+        if self.on_demand {
+            self.source = Some((kind.to_string(), data.to_vec()));
+        }
+        self.make_binary(kind, data)
+    }
+
+    pub(crate) fn add(&mut self, action: Action) {
+        if self.on_demand {
+            self.live.observe(&action, self.source.take());
+        }
+        if let Some(ref mut recording) = self.recording {
+            recording.write(&action);
+        }
     }
 }
 
-#[cfg(feature = "trace")]
-impl Drop for Trace {
-    fn drop(&mut self) {
-        let _ = self.file.write_all(b"]");
+#[cfg(all(test, feature = "trace"))]
+mod test {
+    use super::*;
+    use crate::id::TypedId as _;
+
+    #[cfg(feature = "replay")]
+    #[test]
+    fn binary_round_trip() {
+        let buffer_id = id::BufferId::zip(3, 1, wgt::Backend::Empty);
+        let actions = [
+            Action::CreateBuffer(
+                buffer_id,
+                crate::resource::BufferDescriptor {
+                    label: Some(Cow::Borrowed("staging")),
+                    size: 256,
+                    usage: wgt::BufferUsages::MAP_READ | wgt::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                },
+            ),
+            Action::Submit(1, Vec::new()),
+            Action::DestroyBuffer(buffer_id),
+        ];
+
+        let mut data = Vec::new();
+        {
+            let mut writer = TraceWriter::new(&mut data, TraceFormat::Binary).unwrap();
+            for action in actions.iter() {
+                writer.write(action).unwrap();
+            }
+        }
+
+        let read = BinaryReader::new(&data[..])
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(format!("{:?}", read), format!("{:?}", actions));
+        assert!(matches!(
+            BinaryReader::new(&b"[\nCreateBuffer("[..]),
+            Err(BinaryTraceError::InvalidMagic)
+        ));
+    }
+
+    #[cfg(feature = "replay")]
+    #[test]
+    fn binary_corrupted() {
+        let mut data = Vec::new();
+        {
+            let mut writer = TraceWriter::new(&mut data, TraceFormat::Binary).unwrap();
+            writer.write(&Action::Submit(1, Vec::new())).unwrap();
+        }
+        let read_all = |data: &[u8]| {
+            BinaryReader::new(data)
+                .unwrap()
+                .collect::<Result<Vec<_>, _>>()
+        };
+        assert_eq!(read_all(&data).unwrap().len(), 1);
+
+        // Cut in the length prefix, and in the payload.
+        for &end in [BINARY_MAGIC.len() + 2, data.len() - 1].iter() {
+            assert!(matches!(
+                read_all(&data[..end]),
+                Err(BinaryTraceError::Truncated)
+            ));
+        }
+
+        let mut huge = BINARY_MAGIC.to_vec();
+        huge.extend_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            read_all(&huge),
+            Err(BinaryTraceError::ActionTooLarge(u32::MAX))
+        ));
+    }
+
+    #[test]
+    fn live_resources() {
+        let buffer_desc = crate::resource::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgt::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        };
+        let kept = id::BufferId::zip(0, 1, wgt::Backend::Empty);
+        let dropped = id::BufferId::zip(1, 1, wgt::Backend::Empty);
+        let pipeline = id::ComputePipelineId::zip(0, 1, wgt::Backend::Empty);
+        let layout = id::PipelineLayoutId::zip(0, 1, wgt::Backend::Empty);
+        let group = id::BindGroupLayoutId::zip(0, 1, wgt::Backend::Empty);
+
+        let mut recorded = Trace::new(false);
+        recorded.add(Action::CreateBuffer(kept, buffer_desc.clone()));
+        assert!(recorded.live.entries.is_empty());
+
+        let mut trace = Trace::new(true);
+        trace.add(Action::CreateBuffer(kept, buffer_desc.clone()));
+        trace.add(Action::CreateBuffer(dropped, buffer_desc));
+        trace.add(Action::FreeBuffer(kept));
+        trace.add(Action::DestroyBuffer(dropped));
+        trace.add(Action::CreateComputePipeline {
+            id: pipeline,
+            desc: crate::pipeline::ComputePipelineDescriptor {
+                label: None,
+                layout: None,
+                stage: crate::pipeline::ProgrammableStageDescriptor {
+                    module: id::ShaderModuleId::zip(0, 1, wgt::Backend::Empty),
+                    entry_point: Cow::Borrowed("main"),
+                },
+            },
+            implicit_context: Some(crate::device::ImplicitPipelineContext {
+                root_id: layout,
+                group_ids: std::iter::once(group).collect(),
+            }),
+        });
+        // The implicit layouts outlive the pipeline.
+        trace.add(Action::DestroyComputePipeline(pipeline));
+        trace.add(Action::DestroyPipelineLayout(layout));
+
+        let live = trace
+            .live
+            .entries
+            .values()
+            .map(|entry| {
+                let followups = entry
+                    .followups
+                    .iter()
+                    .map(|f| encode_ron(f).unwrap())
+                    .collect::<Vec<_>>();
+                (entry.alive, followups)
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            live,
+            [
+                (1, vec![encode_ron(&Action::FreeBuffer(kept)).unwrap()]),
+                (
+                    1,
+                    vec![
+                        encode_ron(&Action::DestroyComputePipeline(pipeline)).unwrap(),
+                        encode_ron(&Action::DestroyPipelineLayout(layout)).unwrap(),
+                    ]
+                ),
+            ]
+        );
+        assert!(!trace.is_recording());
     }
 }
@@ -1,7 +1,7 @@
 use crate::{
     binding_model::{BindGroup, BindGroupLayout, PipelineLayout},
     command::{CommandBuffer, RenderBundle},
-    device::Device,
+    device::{Device, StagingReport},
     id,
     instance::{Adapter, HalSurface, Instance, Surface},
     pipeline::{ComputePipeline, RenderPipeline, ShaderModule},
@@ -566,6 +566,8 @@ pub struct HubReport {
     pub textures: StorageReport,
     pub texture_views: StorageReport,
     pub samplers: StorageReport,
+    /// Staging memory of all the devices, used by the queue writes.
+    pub staging: StagingReport,
 }
 
 impl HubReport {
@@ -781,6 +783,13 @@ impl<A: HalApi, F: GlobalIdentityHandlerFactory> Hub<A, F> {
             textures: self.textures.data.read().generate_report(),
             texture_views: self.texture_views.data.read().generate_report(),
             samplers: self.samplers.data.read().generate_report(),
+            staging: {
+                let mut report = StagingReport::default();
+                for (_, device) in self.devices.data.read().iter(A::VARIANT) {
+                    report.merge(&device.staging_report());
+                }
+                report
+            },
         }
     }
 }
@@ -796,6 +805,8 @@ pub struct Hubs<F: GlobalIdentityHandlerFactory> {
     dx11: Hub<hal::api::Dx11, F>,
     #[cfg(gl)]
     gl: Hub<hal::api::Gles, F>,
+    #[cfg(feature = "empty")]
+    empty: Hub<hal::api::Empty, F>,
 }
 
 impl<F: GlobalIdentityHandlerFactory> Hubs<F> {
@@ -811,6 +822,8 @@ impl<F: GlobalIdentityHandlerFactory> Hubs<F> {
             dx11: Hub::new(factory),
             #[cfg(gl)]
             gl: Hub::new(factory),
+            #[cfg(feature = "empty")]
+            empty: Hub::new(factory),
         }
     }
 }
@@ -828,6 +841,8 @@ pub struct GlobalReport {
     pub dx11: Option<HubReport>,
     #[cfg(gl)]
     pub gl: Option<HubReport>,
+    #[cfg(feature = "empty")]
+    pub empty: Option<HubReport>,
 }
 
 pub struct Global<G: GlobalIdentityHandlerFactory> {
@@ -846,6 +861,23 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
     }
 
+    /// Create a global like [`Global::new`], with the Vulkan instance
+    /// getting the extra layers and extensions of `vulkan_extras`.
+    #[cfg(vulkan)]
+    pub fn new_with_vulkan_extras(
+        name: &str,
+        factory: G,
+        backends: wgt::Backends,
+        vulkan_extras: &hal::vulkan::InstanceExtras,
+    ) -> Self {
+        profiling::scope!("new", "Global");
+        Self {
+            instance: Instance::new_with_vulkan_extras(name, backends, vulkan_extras),
+            surfaces: Registry::without_backend(&factory, "Surface"),
+            hubs: Hubs::new(&factory),
+        }
+    }
+
     /// # Safety
     ///
     /// Refer to the creation of wgpu-hal Instance for every backend.
@@ -902,6 +934,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             } else {
                 None
             },
+            // The empty backend has no instance, its adapters are only made from HAL.
+            #[cfg(feature = "empty")]
+            empty: Some(self.hubs.empty.generate_report()).filter(|report| !report.is_empty()),
         }
     }
 }
@@ -933,6 +968,10 @@ impl<G: GlobalIdentityHandlerFactory> Drop for Global<G> {
         {
             self.hubs.gl.clear(&mut *surface_guard, true);
         }
+        #[cfg(feature = "empty")]
+        {
+            self.hubs.empty.clear(&mut *surface_guard, true);
+        }
 
         // destroy surfaces
         for element in surface_guard.map.drain(..) {
@@ -1050,6 +1089,26 @@ impl HalApi for hal::api::Gles {
     }
 }
 
+#[cfg(feature = "empty")]
+impl HalApi for hal::api::Empty {
+    const VARIANT: Backend = Backend::Empty;
+    fn create_instance_from_hal(name: &str, _hal_instance: Self::Instance) -> Instance {
+        Instance {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+    fn hub<G: GlobalIdentityHandlerFactory>(global: &Global<G>) -> &Hub<Self, G> {
+        &global.hubs.empty
+    }
+    fn get_surface(_surface: &Surface) -> &HalSurface<Self> {
+        panic!("The empty backend has no surfaces")
+    }
+    fn get_surface_mut(_surface: &mut Surface) -> &mut HalSurface<Self> {
+        panic!("The empty backend has no surfaces")
+    }
+}
+
 #[cfg(test)]
 fn _test_send_sync(global: &Global<IdentityManagerFactory>) {
     fn test_internal<T: Send + Sync>(_: T) {}
@@ -1,5 +1,6 @@
 use crate::{
-    device::{Device, DeviceDescriptor},
+    conv,
+    device::{Device, DeviceDescriptor, DeviceTrace},
     hub::{Global, GlobalIdentityHandlerFactory, HalApi, Input, Token},
     id::{AdapterId, DeviceId, SurfaceId, Valid},
     present::Presentation,
@@ -90,20 +91,23 @@ pub struct Instance {
     pub gl: Option<HalInstance<hal::api::Gles>>,
 }
 
+fn hal_instance_descriptor() -> hal::InstanceDescriptor<'static> {
+    let mut flags = hal::InstanceFlags::empty();
+    if cfg!(debug_assertions) {
+        flags |= hal::InstanceFlags::VALIDATION;
+        flags |= hal::InstanceFlags::DEBUG;
+    }
+    hal::InstanceDescriptor {
+        name: "wgpu",
+        flags,
+    }
+}
+
 impl Instance {
     pub fn new(name: &str, backends: Backends) -> Self {
         fn init<A: HalApi>(mask: Backends) -> Option<A::Instance> {
             if mask.contains(A::VARIANT.into()) {
-                let mut flags = hal::InstanceFlags::empty();
-                if cfg!(debug_assertions) {
-                    flags |= hal::InstanceFlags::VALIDATION;
-                    flags |= hal::InstanceFlags::DEBUG;
-                }
-                let hal_desc = hal::InstanceDescriptor {
-                    name: "wgpu",
-                    flags,
-                };
-                unsafe { hal::Instance::init(&hal_desc).ok() }
+                unsafe { hal::Instance::init(&hal_instance_descriptor()).ok() }
             } else {
                 None
             }
@@ -124,6 +128,24 @@ impl Instance {
         }
     }
 
+    /// Create an instance like [`Instance::new`], with the Vulkan
+    /// instance getting the extra layers and extensions of `vulkan_extras`.
+    #[cfg(vulkan)]
+    pub fn new_with_vulkan_extras(
+        name: &str,
+        backends: Backends,
+        vulkan_extras: &hal::vulkan::InstanceExtras,
+    ) -> Self {
+        let mut instance = Self::new(name, backends - Backends::VULKAN);
+        if backends.contains(Backends::VULKAN) {
+            instance.vulkan = unsafe {
+                hal::vulkan::Instance::init_with_extras(&hal_instance_descriptor(), vulkan_extras)
+                    .ok()
+            };
+        }
+        instance
+    }
+
     pub(crate) fn destroy_surface(&self, surface: Surface) {
         backends_map! {
             let map = |(surface_backend, self_backend)| {
@@ -204,17 +226,40 @@ impl Surface {
             .find(|preferred| caps.formats.contains(preferred))
             .ok_or(GetSurfacePreferredFormatError::NotFound)
     }
+
+    pub fn get_capabilities<A: HalApi>(
+        &self,
+        adapter: &Adapter<A>,
+    ) -> Result<wgt::SurfaceCapabilities, GetSurfaceCapabilitiesError> {
+        let suf = A::get_surface(self);
+        let caps = unsafe {
+            adapter
+                .raw
+                .adapter
+                .surface_capabilities(&suf.raw)
+                .ok_or(GetSurfaceCapabilitiesError::UnsupportedQueueFamily)?
+        };
+
+        Ok(conv::map_surface_capabilities(caps))
+    }
 }
 
 pub struct Adapter<A: hal::Api> {
     pub(crate) raw: hal::ExposedAdapter<A>,
+    /// Features that wgpu-core emulates on top of the ones of `raw`.
+    emulated_features: wgt::Features,
     life_guard: LifeGuard,
 }
 
 impl<A: HalApi> Adapter<A> {
-    fn new(raw: hal::ExposedAdapter<A>) -> Self {
+    fn new(mut raw: hal::ExposedAdapter<A>) -> Self {
+        let mut emulated_features = wgt::Features::empty();
+        if crate::device::push_constants::expose(&mut raw.features, &mut raw.capabilities.limits) {
+            emulated_features |= wgt::Features::PUSH_CONSTANTS;
+        }
         Self {
             raw,
+            emulated_features,
             life_guard: LifeGuard::new("<Adapter>"),
         }
     }
@@ -275,7 +320,7 @@ impl<A: HalApi> Adapter<A> {
         self_id: AdapterId,
         open: hal::OpenDevice<A>,
         desc: &DeviceDescriptor,
-        trace_path: Option<&std::path::Path>,
+        trace: Option<DeviceTrace>,
     ) -> Result<Device<A>, RequestDeviceError> {
         // Verify all features were exposed by the adapter
         if !self.raw.features.contains(desc.features) {
@@ -321,6 +366,21 @@ impl<A: HalApi> Adapter<A> {
         if let Some(failed) = check_limits(&desc.limits, &caps.limits).pop() {
             return Err(RequestDeviceError::LimitsExceeded(failed));
         }
+        // Emulated push constants take the last bind group.
+        if desc.features.contains(wgt::Features::PUSH_CONSTANTS)
+            && self
+                .emulated_features
+                .contains(wgt::Features::PUSH_CONSTANTS)
+        {
+            let allowed = crate::device::push_constants::reserved_bind_groups(&caps.limits);
+            if desc.limits.max_bind_groups > allowed {
+                return Err(RequestDeviceError::LimitsExceeded(FailedLimit {
+                    name: "max_bind_groups",
+                    requested: desc.limits.max_bind_groups,
+                    allowed,
+                }));
+            }
+        }
 
         Device::new(
             open,
@@ -330,24 +390,34 @@ impl<A: HalApi> Adapter<A> {
             },
             caps.alignments.clone(),
             caps.downlevel.clone(),
+            &caps.limits,
+            self.emulated_features,
             desc,
-            trace_path,
+            trace,
         )
         .or(Err(RequestDeviceError::OutOfMemory))
     }
 
+    /// Create a device, with `open` opening the HAL device for the requested
+    /// features that aren't emulated.
     fn create_device(
         &self,
         self_id: AdapterId,
         desc: &DeviceDescriptor,
-        trace_path: Option<&std::path::Path>,
+        trace: Option<DeviceTrace>,
+        open: impl FnOnce(&A::Adapter, wgt::Features) -> Result<hal::OpenDevice<A>, hal::DeviceError>,
     ) -> Result<Device<A>, RequestDeviceError> {
-        let open = unsafe { self.raw.adapter.open(desc.features) }.map_err(|err| match err {
-            hal::DeviceError::Lost => RequestDeviceError::DeviceLost,
-            hal::DeviceError::OutOfMemory => RequestDeviceError::OutOfMemory,
-        })?;
+        let open = open(&self.raw.adapter, desc.features - self.emulated_features)
+            .map_err(map_open_error)?;
+
+        self.create_device_from_hal(self_id, open, desc, trace)
+    }
+}
 
-        self.create_device_from_hal(self_id, open, desc, trace_path)
+fn map_open_error(error: hal::DeviceError) -> RequestDeviceError {
+    match error {
+        hal::DeviceError::Lost => RequestDeviceError::DeviceLost,
+        hal::DeviceError::OutOfMemory => RequestDeviceError::OutOfMemory,
     }
 }
 
@@ -379,6 +449,16 @@ pub enum GetSurfacePreferredFormatError {
     UnsupportedQueueFamily,
 }
 
+#[derive(Clone, Debug, Error)]
+pub enum GetSurfaceCapabilitiesError {
+    #[error("invalid adapter")]
+    InvalidAdapter,
+    #[error("invalid surface")]
+    InvalidSurface,
+    #[error("surface does not support the adapter's queue family")]
+    UnsupportedQueueFamily,
+}
+
 #[derive(Clone, Debug, Error)]
 /// Error when requesting a device from the adaptor
 pub enum RequestDeviceError {
@@ -727,10 +807,36 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             Backend::Dx11 => fid.assign(Adapter::new(hal_adapter), &mut token).0,
             #[cfg(gl)]
             Backend::Gl => fid.assign(Adapter::new(hal_adapter), &mut token).0,
+            #[cfg(feature = "empty")]
+            Backend::Empty => fid.assign(Adapter::new(hal_adapter), &mut token).0,
             _ => unreachable!(),
         }
     }
 
+    /// Call `hal_adapter_callback` with the raw adapter behind `id`,
+    /// or `None` if the adapter is invalid or not from backend `A`.
+    ///
+    /// # Safety
+    ///
+    /// - The raw adapter must not be manually destroyed.
+    pub unsafe fn adapter_as_hal<A: HalApi, F: FnOnce(Option<&A::Adapter>) -> R, R>(
+        &self,
+        id: AdapterId,
+        hal_adapter_callback: F,
+    ) -> R {
+        profiling::scope!("as_hal", "Adapter");
+
+        if id.backend() != A::VARIANT {
+            return hal_adapter_callback(None);
+        }
+
+        let hub = A::hub(self);
+        let mut token = Token::root();
+        let (guard, _) = hub.adapters.read(&mut token);
+        let adapter = guard.get(id).ok();
+        hal_adapter_callback(adapter.map(|adapter| &adapter.raw.adapter))
+    }
+
     pub fn adapter_get_info<A: HalApi>(
         &self,
         adapter_id: AdapterId,
@@ -820,11 +926,51 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         &self,
         adapter_id: AdapterId,
         desc: &DeviceDescriptor,
-        trace_path: Option<&std::path::Path>,
+        trace: Option<DeviceTrace>,
+        id_in: Input<G, DeviceId>,
+    ) -> (DeviceId, Option<RequestDeviceError>) {
+        profiling::scope!("request_device", "Adapter");
+        self.request_device::<A>(adapter_id, desc, trace, id_in, |adapter, features| unsafe {
+            hal::Adapter::open(adapter, features)
+        })
+    }
+
+    /// Request a device like [`Global::adapter_request_device`], with the Vulkan
+    /// device getting the extra extensions and features of `vulkan_extras`.
+    ///
+    /// # Safety
+    ///
+    /// - `vulkan_extras` must respect the safety requirements of
+    ///   [`hal::vulkan::Adapter::open_with_extras`].
+    #[cfg(vulkan)]
+    pub unsafe fn adapter_request_device_with_vulkan_extras(
+        &self,
+        adapter_id: AdapterId,
+        desc: &DeviceDescriptor,
+        vulkan_extras: hal::vulkan::DeviceExtras,
+        trace: Option<DeviceTrace>,
         id_in: Input<G, DeviceId>,
     ) -> (DeviceId, Option<RequestDeviceError>) {
         profiling::scope!("request_device", "Adapter");
+        self.request_device::<hal::api::Vulkan>(
+            adapter_id,
+            desc,
+            trace,
+            id_in,
+            |adapter, features| adapter.open_with_extras(features, vulkan_extras),
+        )
+    }
 
+    /// Shared part of the `adapter_request_device` variants, with `open`
+    /// opening the HAL device of the adapter.
+    fn request_device<A: HalApi>(
+        &self,
+        adapter_id: AdapterId,
+        desc: &DeviceDescriptor,
+        trace: Option<DeviceTrace>,
+        id_in: Input<G, DeviceId>,
+        open: impl FnOnce(&A::Adapter, wgt::Features) -> Result<hal::OpenDevice<A>, hal::DeviceError>,
+    ) -> (DeviceId, Option<RequestDeviceError>) {
         let hub = A::hub(self);
         let mut token = Token::root();
         let fid = hub.devices.prepare(id_in);
@@ -835,7 +981,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(adapter) => adapter,
                 Err(_) => break RequestDeviceError::InvalidAdapter,
             };
-            let device = match adapter.create_device(adapter_id, desc, trace_path) {
+            let device = match adapter.create_device(adapter_id, desc, trace, open) {
                 Ok(device) => device,
                 Err(e) => break e,
             };
@@ -856,7 +1002,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         adapter_id: AdapterId,
         hal_device: hal::OpenDevice<A>,
         desc: &DeviceDescriptor,
-        trace_path: Option<&std::path::Path>,
+        trace: Option<DeviceTrace>,
         id_in: Input<G, DeviceId>,
     ) -> (DeviceId, Option<RequestDeviceError>) {
         profiling::scope!("request_device", "Adapter");
@@ -871,11 +1017,10 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 Ok(adapter) => adapter,
                 Err(_) => break RequestDeviceError::InvalidAdapter,
             };
-            let device =
-                match adapter.create_device_from_hal(adapter_id, hal_device, desc, trace_path) {
-                    Ok(device) => device,
-                    Err(e) => break e,
-                };
+            let device = match adapter.create_device_from_hal(adapter_id, hal_device, desc, trace) {
+                Ok(device) => device,
+                Err(e) => break e,
+            };
             let id = fid.assign(device, &mut token);
             return (id.0, None);
         };
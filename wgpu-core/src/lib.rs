@@ -51,7 +51,7 @@ use atomic::{AtomicU64, AtomicUsize, Ordering};
 
 use std::{borrow::Cow, os::raw::c_char, ptr, sync::atomic};
 
-type SubmissionIndex = hal::FenceValue;
+pub type SubmissionIndex = hal::FenceValue;
 type Index = u32;
 type Epoch = u32;
 
@@ -75,6 +75,67 @@ pub enum ConfigureSurfaceError {
     },
     #[error("requested usage is not supported")]
     UnsupportedUsage,
+    #[error("requested composite alpha mode {requested:?} is not in list of supported modes: {available:?}")]
+    UnsupportedCompositeAlphaMode {
+        requested: wgt::CompositeAlphaMode,
+        available: Vec<wgt::CompositeAlphaMode>,
+    },
+}
+
+/// Check `config` against the capabilities of the surface, falling back to FIFO
+/// if the present mode isn't supported.
+pub(crate) fn validate_configuration(
+    config: &mut hal::SurfaceConfiguration,
+    caps: &hal::SurfaceCapabilities,
+) -> Result<(), ConfigureSurfaceError> {
+    let width = config.extent.width;
+    let height = config.extent.height;
+    if width < caps.extents.start().width
+        || width > caps.extents.end().width
+        || height < caps.extents.start().height
+        || height > caps.extents.end().height
+    {
+        log::warn!(
+            "Requested size {}x{} is outside of the supported range: {:?}",
+            width,
+            height,
+            caps.extents
+        );
+    }
+    if !caps.present_modes.contains(&config.present_mode) {
+        log::warn!(
+            "Surface does not support present mode: {:?}, falling back to FIFO",
+            config.present_mode,
+        );
+        config.present_mode = wgt::PresentMode::Fifo;
+    }
+    if !caps.formats.contains(&config.format) {
+        return Err(ConfigureSurfaceError::UnsupportedFormat {
+            requested: config.format,
+            available: caps.formats.clone(),
+        });
+    }
+    if !caps.usage.contains(config.usage) {
+        return Err(ConfigureSurfaceError::UnsupportedUsage);
+    }
+    if !caps
+        .composite_alpha_modes
+        .contains(&config.composite_alpha_mode)
+    {
+        return Err(ConfigureSurfaceError::UnsupportedCompositeAlphaMode {
+            requested: conv::map_composite_alpha_mode_from_hal(config.composite_alpha_mode),
+            available: caps
+                .composite_alpha_modes
+                .iter()
+                .cloned()
+                .map(conv::map_composite_alpha_mode_from_hal)
+                .collect(),
+        });
+    }
+    if width == 0 || height == 0 {
+        return Err(ConfigureSurfaceError::ZeroArea);
+    }
+    Ok(())
 }
 
 #[repr(C)]
@@ -182,7 +243,7 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
                 match err {
                     hal::SurfaceError::Lost => Status::Lost,
                     hal::SurfaceError::Device(err) => {
-                        return Err(DeviceError::from(err).into());
+                        return Err(device.handle_hal_error(err).into());
                     }
                     hal::SurfaceError::Outdated => Status::Outdated,
                     hal::SurfaceError::Other(msg) => {
@@ -252,7 +313,9 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
             Ok(()) => Ok(Status::Good),
             Err(err) => match err {
                 hal::SurfaceError::Lost => Ok(Status::Lost),
-                hal::SurfaceError::Device(err) => Err(SurfaceError::from(DeviceError::from(err))),
+                hal::SurfaceError::Device(err) => {
+                    Err(SurfaceError::from(device.handle_hal_error(err)))
+                }
                 hal::SurfaceError::Outdated => Ok(Status::Outdated),
                 hal::SurfaceError::Other(msg) => {
                     log::error!("acquire error: {}", msg);
@@ -262,3 +325,59 @@ impl<G: GlobalIdentityHandlerFactory> Global<G> {
         }
     }
 }
+
+#[test]
+fn configure_with_unsupported_composite_alpha_mode() {
+    let caps = hal::SurfaceCapabilities {
+        formats: vec![wgt::TextureFormat::Bgra8UnormSrgb],
+        swap_chain_sizes: 2..=3,
+        current_extent: None,
+        extents: wgt::Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        }..=wgt::Extent3d {
+            width: 4096,
+            height: 4096,
+            depth_or_array_layers: 1,
+        },
+        usage: hal::TextureUses::COLOR_TARGET | hal::TextureUses::COPY_SRC,
+        present_modes: vec![wgt::PresentMode::Fifo],
+        composite_alpha_modes: vec![hal::CompositeAlphaMode::Opaque],
+    };
+    let surface_caps = conv::map_surface_capabilities(caps.clone());
+    assert_eq!(
+        surface_caps.usages,
+        wgt::TextureUsages::RENDER_ATTACHMENT | wgt::TextureUsages::COPY_SRC
+    );
+    assert_eq!(
+        surface_caps.composite_alpha_modes,
+        [wgt::CompositeAlphaMode::Opaque]
+    );
+
+    let mut config = hal::SurfaceConfiguration {
+        swap_chain_size: DESIRED_NUM_FRAMES,
+        present_mode: wgt::PresentMode::Fifo,
+        composite_alpha_mode: hal::CompositeAlphaMode::PostMultiplied,
+        format: wgt::TextureFormat::Bgra8UnormSrgb,
+        extent: wgt::Extent3d {
+            width: 64,
+            height: 64,
+            depth_or_array_layers: 1,
+        },
+        usage: hal::TextureUses::COLOR_TARGET,
+    };
+    match validate_configuration(&mut config, &caps) {
+        Err(ConfigureSurfaceError::UnsupportedCompositeAlphaMode {
+            requested,
+            available,
+        }) => {
+            assert_eq!(requested, wgt::CompositeAlphaMode::PostMultiplied);
+            assert_eq!(available, surface_caps.composite_alpha_modes);
+        }
+        other => panic!("Unexpected configuration result: {:?}", other),
+    }
+
+    config.composite_alpha_mode = hal::CompositeAlphaMode::Opaque;
+    assert!(validate_configuration(&mut config, &caps).is_ok());
+}
@@ -1,5 +1,5 @@
 use crate::{
-    device::{DeviceError, HostMap, MissingFeatures},
+    device::{queue::TempResource, DeviceError, HostMap, MissingFeatures},
     hub::Resource,
     id::{DeviceId, SurfaceId, TextureId, Valid},
     memory_init_tracker::MemoryInitTracker,
@@ -66,6 +66,13 @@ impl BufferMapOperation {
             (self.callback)(BufferMapAsyncStatus::Error, self.user_data);
         }
     }
+
+    pub(crate) fn call_lost(self) {
+        log::warn!("wgpu_buffer_map_async failed: device is lost");
+        unsafe {
+            (self.callback)(BufferMapAsyncStatus::ContextLost, self.user_data);
+        }
+    }
 }
 
 #[derive(Clone, Debug, Error)]
@@ -117,6 +124,9 @@ pub type BufferDescriptor<'a> = wgt::BufferDescriptor<Label<'a>>;
 #[derive(Debug)]
 pub struct Buffer<A: hal::Api> {
     pub(crate) raw: Option<A::Buffer>,
+    /// Whether `raw` gets destroyed along with the buffer. Only false
+    /// for buffers imported from HAL without taking ownership.
+    pub(crate) owns_raw: bool,
     pub(crate) device_id: Stored<DeviceId>,
     pub(crate) usage: wgt::BufferUsages,
     pub(crate) size: wgt::BufferAddress,
@@ -138,6 +148,23 @@ pub enum CreateBufferError {
     EmptyUsage,
     #[error("`MAP` usage can only be combined with the opposite `COPY`, requested {0:?}")]
     UsageMismatch(wgt::BufferUsages),
+    #[error("buffers created from a HAL buffer cannot be mapped at creation")]
+    MappedAtCreationFromHal,
+}
+
+impl<A: hal::Api> Buffer<A> {
+    /// Take out the raw buffer, to be released once the GPU is done with it.
+    ///
+    /// A raw buffer we don't own is only dropped then, leaving
+    /// the underlying handle to whoever imported it.
+    pub(crate) fn take_raw(&mut self) -> Option<TempResource<A>> {
+        let raw = self.raw.take()?;
+        Some(if self.owns_raw {
+            TempResource::Buffer(raw)
+        } else {
+            TempResource::ExternalBuffer(raw)
+        })
+    }
 }
 
 impl<A: hal::Api> Resource for Buffer<A> {
@@ -433,6 +460,8 @@ pub enum CreateQuerySetError {
     ZeroCount,
     #[error("{count} is too many queries for a single QuerySet. QuerySets cannot be made more than {maximum} queries.")]
     TooManyQueries { count: u32, maximum: u32 },
+    #[error("QuerySets of pipeline statistics need at least one statistic")]
+    NoPipelineStatistics,
     #[error(transparent)]
     MissingFeatures(#[from] MissingFeatures),
 }
@@ -1,6 +1,6 @@
 #![allow(unused_variables)]
 
-use std::ops::Range;
+use std::{cell::UnsafeCell, fmt, ops::Range, ptr::NonNull};
 
 #[derive(Clone)]
 pub struct Api;
@@ -9,6 +9,28 @@ pub struct Encoder;
 #[derive(Debug)]
 pub struct Resource;
 
+/// Buffer that has host memory behind it if it's mappable,
+/// so that the mapped contents can be read and written.
+pub struct Buffer {
+    data: Box<[UnsafeCell<u8>]>,
+}
+
+// Mapped memory is only accessed through the pointers handed out by `map_buffer`.
+unsafe impl Sync for Buffer {}
+
+impl fmt::Debug for Buffer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Buffer")
+            .field("host_size", &self.data.len())
+            .finish()
+    }
+}
+
+#[derive(Debug)]
+pub struct Fence {
+    value: crate::FenceValue,
+}
+
 type DeviceResult<T> = Result<T, crate::DeviceError>;
 
 impl crate::Api for Api {
@@ -21,13 +43,13 @@ impl crate::Api for Api {
     type CommandEncoder = Encoder;
     type CommandBuffer = Resource;
 
-    type Buffer = Resource;
+    type Buffer = Buffer;
     type Texture = Resource;
     type SurfaceTexture = Resource;
     type TextureView = Resource;
     type Sampler = Resource;
     type QuerySet = Resource;
-    type Fence = Resource;
+    type Fence = Fence;
 
     type BindGroupLayout = Resource;
     type BindGroup = Resource;
@@ -49,7 +71,48 @@ impl crate::Instance<Api> for Context {
     }
     unsafe fn destroy_surface(&self, surface: Context) {}
     unsafe fn enumerate_adapters(&self) -> Vec<crate::ExposedAdapter<Api>> {
-        Vec::new()
+        let alignment = wgt::BufferSize::new(1).unwrap();
+        vec![crate::ExposedAdapter {
+            adapter: Context,
+            info: wgt::AdapterInfo {
+                name: String::from("Empty"),
+                vendor: 0,
+                device: 0,
+                device_type: wgt::DeviceType::Cpu,
+                backend: wgt::Backend::Empty,
+            },
+            // What a typical desktop adapter supports, so that the missing
+            // features are validated as well.
+            features: wgt::Features::DEPTH_CLAMPING
+                | wgt::Features::TEXTURE_COMPRESSION_BC
+                | wgt::Features::TIMESTAMP_QUERY
+                | wgt::Features::PIPELINE_STATISTICS_QUERY
+                | wgt::Features::MAPPABLE_PRIMARY_BUFFERS
+                | wgt::Features::TEXTURE_BINDING_ARRAY
+                | wgt::Features::BUFFER_BINDING_ARRAY
+                | wgt::Features::STORAGE_RESOURCE_BINDING_ARRAY
+                | wgt::Features::SAMPLED_TEXTURE_AND_STORAGE_BUFFER_ARRAY_NON_UNIFORM_INDEXING
+                | wgt::Features::UNSIZED_BINDING_ARRAY
+                | wgt::Features::MULTI_DRAW_INDIRECT
+                | wgt::Features::MULTI_DRAW_INDIRECT_COUNT
+                | wgt::Features::PUSH_CONSTANTS
+                | wgt::Features::ADDRESS_MODE_CLAMP_TO_BORDER
+                | wgt::Features::NON_FILL_POLYGON_MODE
+                | wgt::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+                | wgt::Features::SHADER_FLOAT64
+                | wgt::Features::VERTEX_WRITABLE_STORAGE
+                | wgt::Features::CLEAR_COMMANDS,
+            capabilities: crate::Capabilities {
+                limits: wgt::Limits::default(),
+                alignments: crate::Alignments {
+                    buffer_copy_offset: alignment,
+                    buffer_copy_pitch: alignment,
+                    uniform_buffer_offset: alignment,
+                    storage_buffer_offset: alignment,
+                },
+                downlevel: wgt::DownlevelCapabilities::default(),
+            },
+        }]
     }
 }
 
@@ -75,13 +138,16 @@ impl crate::Surface<Api> for Context {
 
 impl crate::Adapter<Api> for Context {
     unsafe fn open(&self, features: wgt::Features) -> DeviceResult<crate::OpenDevice<Api>> {
-        Err(crate::DeviceError::Lost)
+        Ok(crate::OpenDevice {
+            device: Context,
+            queue: Context,
+        })
     }
     unsafe fn texture_format_capabilities(
         &self,
         format: wgt::TextureFormat,
     ) -> crate::TextureFormatCapabilities {
-        crate::TextureFormatCapabilities::empty()
+        crate::TextureFormatCapabilities::all()
     }
     unsafe fn surface_capabilities(&self, surface: &Context) -> Option<crate::SurfaceCapabilities> {
         None
@@ -92,8 +158,11 @@ impl crate::Queue<Api> for Context {
     unsafe fn submit(
         &mut self,
         command_buffers: &[&Resource],
-        signal_fence: Option<(&mut Resource, crate::FenceValue)>,
+        signal_fence: Option<(&mut Fence, crate::FenceValue)>,
     ) -> DeviceResult<()> {
+        if let Some((fence, value)) = signal_fence {
+            fence.value = value;
+        }
         Ok(())
     }
     unsafe fn present(
@@ -107,22 +176,40 @@ impl crate::Queue<Api> for Context {
 
 impl crate::Device<Api> for Context {
     unsafe fn exit(self, queue: Context) {}
-    unsafe fn create_buffer(&self, desc: &crate::BufferDescriptor) -> DeviceResult<Resource> {
-        Ok(Resource)
-    }
-    unsafe fn destroy_buffer(&self, buffer: Resource) {}
+    unsafe fn create_buffer(&self, desc: &crate::BufferDescriptor) -> DeviceResult<Buffer> {
+        let host_size = if desc
+            .usage
+            .intersects(crate::BufferUses::MAP_READ | crate::BufferUses::MAP_WRITE)
+        {
+            desc.size
+        } else {
+            0
+        };
+        Ok(Buffer {
+            data: (0..host_size).map(|_| UnsafeCell::new(0)).collect(),
+        })
+    }
+    unsafe fn destroy_buffer(&self, buffer: Buffer) {}
     unsafe fn map_buffer(
         &self,
-        buffer: &Resource,
+        buffer: &Buffer,
         range: crate::MemoryRange,
     ) -> DeviceResult<crate::BufferMapping> {
-        Err(crate::DeviceError::Lost)
-    }
-    unsafe fn unmap_buffer(&self, buffer: &Resource) -> DeviceResult<()> {
+        if range.end > buffer.data.len() as wgt::BufferAddress {
+            return Err(crate::DeviceError::Lost);
+        }
+        // `UnsafeCell<u8>` has the same layout as `u8`
+        let ptr = (buffer.data.as_ptr() as *mut u8).add(range.start as usize);
+        Ok(crate::BufferMapping {
+            ptr: NonNull::new(ptr).unwrap(),
+            is_coherent: true,
+        })
+    }
+    unsafe fn unmap_buffer(&self, buffer: &Buffer) -> DeviceResult<()> {
         Ok(())
     }
-    unsafe fn flush_mapped_ranges<I>(&self, buffer: &Resource, ranges: I) {}
-    unsafe fn invalidate_mapped_ranges<I>(&self, buffer: &Resource, ranges: I) {}
+    unsafe fn flush_mapped_ranges<I>(&self, buffer: &Buffer, ranges: I) {}
+    unsafe fn invalidate_mapped_ranges<I>(&self, buffer: &Buffer, ranges: I) {}
 
     unsafe fn create_texture(&self, desc: &crate::TextureDescriptor) -> DeviceResult<Resource> {
         Ok(Resource)
@@ -201,20 +288,20 @@ impl crate::Device<Api> for Context {
         Ok(Resource)
     }
     unsafe fn destroy_query_set(&self, set: Resource) {}
-    unsafe fn create_fence(&self) -> DeviceResult<Resource> {
-        Ok(Resource)
+    unsafe fn create_fence(&self) -> DeviceResult<Fence> {
+        Ok(Fence { value: 0 })
     }
-    unsafe fn destroy_fence(&self, fence: Resource) {}
-    unsafe fn get_fence_value(&self, fence: &Resource) -> DeviceResult<crate::FenceValue> {
-        Ok(0)
+    unsafe fn destroy_fence(&self, fence: Fence) {}
+    unsafe fn get_fence_value(&self, fence: &Fence) -> DeviceResult<crate::FenceValue> {
+        Ok(fence.value)
     }
     unsafe fn wait(
         &self,
-        fence: &Resource,
+        fence: &Fence,
         value: crate::FenceValue,
         timeout_ms: u32,
     ) -> DeviceResult<bool> {
-        Ok(true)
+        Ok(fence.value >= value)
     }
 
     unsafe fn start_capture(&self) -> bool {
@@ -245,9 +332,9 @@ impl crate::CommandEncoder<Api> for Encoder {
     {
     }
 
-    unsafe fn fill_buffer(&mut self, buffer: &Resource, range: crate::MemoryRange, value: u8) {}
+    unsafe fn fill_buffer(&mut self, buffer: &Buffer, range: crate::MemoryRange, value: u8) {}
 
-    unsafe fn copy_buffer_to_buffer<T>(&mut self, src: &Resource, dst: &Resource, regions: T) {}
+    unsafe fn copy_buffer_to_buffer<T>(&mut self, src: &Buffer, dst: &Buffer, regions: T) {}
 
     unsafe fn copy_texture_to_texture<T>(
         &mut self,
@@ -258,13 +345,13 @@ impl crate::CommandEncoder<Api> for Encoder {
     ) {
     }
 
-    unsafe fn copy_buffer_to_texture<T>(&mut self, src: &Resource, dst: &Resource, regions: T) {}
+    unsafe fn copy_buffer_to_texture<T>(&mut self, src: &Buffer, dst: &Resource, regions: T) {}
 
     unsafe fn copy_texture_to_buffer<T>(
         &mut self,
         src: &Resource,
         src_usage: crate::TextureUses,
-        dst: &Resource,
+        dst: &Buffer,
         regions: T,
     ) {
     }
@@ -277,7 +364,7 @@ impl crate::CommandEncoder<Api> for Encoder {
         &mut self,
         set: &Resource,
         range: Range<u32>,
-        buffer: &Resource,
+        buffer: &Buffer,
         offset: wgt::BufferAddress,
         stride: wgt::BufferSize,
     ) {
@@ -343,32 +430,32 @@ impl crate::CommandEncoder<Api> for Encoder {
     }
     unsafe fn draw_indirect(
         &mut self,
-        buffer: &Resource,
+        buffer: &Buffer,
         offset: wgt::BufferAddress,
         draw_count: u32,
     ) {
     }
     unsafe fn draw_indexed_indirect(
         &mut self,
-        buffer: &Resource,
+        buffer: &Buffer,
         offset: wgt::BufferAddress,
         draw_count: u32,
     ) {
     }
     unsafe fn draw_indirect_count(
         &mut self,
-        buffer: &Resource,
+        buffer: &Buffer,
         offset: wgt::BufferAddress,
-        count_buffer: &Resource,
+        count_buffer: &Buffer,
         count_offset: wgt::BufferAddress,
         max_count: u32,
     ) {
     }
     unsafe fn draw_indexed_indirect_count(
         &mut self,
-        buffer: &Resource,
+        buffer: &Buffer,
         offset: wgt::BufferAddress,
-        count_buffer: &Resource,
+        count_buffer: &Buffer,
         count_offset: wgt::BufferAddress,
         max_count: u32,
     ) {
@@ -382,5 +469,5 @@ impl crate::CommandEncoder<Api> for Encoder {
     unsafe fn set_compute_pipeline(&mut self, pipeline: &Resource) {}
 
     unsafe fn dispatch(&mut self, count: [u32; 3]) {}
-    unsafe fn dispatch_indirect(&mut self, buffer: &Resource, offset: wgt::BufferAddress) {}
+    unsafe fn dispatch_indirect(&mut self, buffer: &Buffer, offset: wgt::BufferAddress) {}
 }
@@ -1,5 +1,5 @@
 use glow::HasContext;
-use std::sync::Arc;
+use std::{mem, sync::Arc};
 
 // https://webgl2fundamentals.org/webgl/lessons/webgl-data-textures.html
 
@@ -163,6 +163,28 @@ impl super::Adapter {
         }
     }
 
+    unsafe fn load_timer_query(
+        context: &super::AdapterContext,
+        suffix: &str,
+    ) -> Option<super::TimerQueryFunctions> {
+        let gen_queries = context.get_proc_address(&format!("glGenQueries{}", suffix))?;
+        let delete_queries = context.get_proc_address(&format!("glDeleteQueries{}", suffix))?;
+        let query_counter = context.get_proc_address(&format!("glQueryCounter{}", suffix))?;
+        let get_query_object_u64 =
+            context.get_proc_address(&format!("glGetQueryObjectui64v{}", suffix))?;
+        Some(super::TimerQueryFunctions {
+            gen_queries: mem::transmute::<extern "C" fn(), super::GenQueriesFun>(gen_queries),
+            delete_queries: mem::transmute::<extern "C" fn(), super::DeleteQueriesFun>(
+                delete_queries,
+            ),
+            query_counter: mem::transmute::<extern "C" fn(), super::QueryCounterFun>(query_counter),
+            get_query_object_u64: mem::transmute::<extern "C" fn(), super::GetQueryObjectU64Fun>(
+                get_query_object_u64,
+            ),
+            can_be_disjoint: suffix == "EXT",
+        })
+    }
+
     pub(super) unsafe fn expose(
         context: super::AdapterContext,
     ) -> Option<crate::ExposedAdapter<super::Api>> {
@@ -233,6 +255,8 @@ impl super::Adapter {
         let mut features = wgt::Features::empty()
             | wgt::Features::TEXTURE_COMPRESSION_ETC2
             | wgt::Features::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+        // Emulated with a draw per command, which needs indirect draws.
+        features.set(wgt::Features::MULTI_DRAW_INDIRECT, ver >= (3, 1));
         features.set(
             wgt::Features::DEPTH_CLAMPING,
             extensions.contains("GL_EXT_depth_clamp"),
@@ -244,6 +268,21 @@ impl super::Adapter {
                 && vertex_shader_storage_textures != 0,
         );
 
+        // Check the extensions first, as `eglGetProcAddress` may return
+        // non-null pointers for entry points that aren't supported.
+        let timer_query = if extensions.contains("GL_ARB_timer_query") {
+            Self::load_timer_query(&context, "")
+        } else if extensions.contains("GL_EXT_disjoint_timer_query") {
+            Self::load_timer_query(&context, "EXT")
+        } else {
+            None
+        };
+        features.set(wgt::Features::TIMESTAMP_QUERY, timer_query.is_some());
+        features.set(
+            wgt::Features::PIPELINE_STATISTICS_QUERY,
+            extensions.contains("GL_ARB_pipeline_statistics_query"),
+        );
+
         let mut downlevel_flags = wgt::DownlevelFlags::empty()
             | wgt::DownlevelFlags::DEVICE_LOCAL_IMAGE_COPIES
             | wgt::DownlevelFlags::NON_POWER_OF_TWO_MIPMAPPED_TEXTURES
@@ -280,6 +319,8 @@ impl super::Adapter {
         let max_uniform_buffers_per_shader_stage =
             gl.get_parameter_i32(glow::MAX_VERTEX_UNIFORM_BLOCKS)
                 .min(gl.get_parameter_i32(glow::MAX_FRAGMENT_UNIFORM_BLOCKS)) as u32;
+        let max_uniform_buffer_bindings =
+            gl.get_parameter_i32(glow::MAX_UNIFORM_BUFFER_BINDINGS) as u32;
 
         let limits = wgt::Limits {
             max_texture_dimension_1d: max_texture_size,
@@ -323,6 +364,11 @@ impl super::Adapter {
             super::PrivateCapabilities::VERTEX_BUFFER_LAYOUT,
             ver >= (3, 1),
         );
+        private_caps.set(
+            super::PrivateCapabilities::BUFFER_STORAGE,
+            extensions.contains("GL_EXT_buffer_storage")
+                || extensions.contains("GL_ARB_buffer_storage"),
+        );
 
         let mut workarounds = super::Workarounds::empty();
         let r = renderer.to_lowercase();
@@ -350,8 +396,10 @@ impl super::Adapter {
                 shared: Arc::new(super::AdapterShared {
                     context,
                     private_caps,
+                    timer_query,
                     workarounds,
                     shading_language_version,
+                    max_uniform_buffer_bindings,
                 }),
             },
             info: Self::make_info(vendor, renderer),
@@ -453,6 +501,7 @@ impl crate::Adapter<super::Api> for super::Adapter {
                 shader_clear_program_color_uniform_location,
                 zero_buffer,
                 temp_query_results: Vec::new(),
+                disjoint_timestamps: Default::default(),
                 draw_buffer_count: 1,
             },
         })
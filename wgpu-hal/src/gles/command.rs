@@ -36,6 +36,7 @@ impl super::CommandBuffer {
         self.commands.clear();
         self.data_bytes.clear();
         self.queries.clear();
+        self.timestamps.clear();
     }
 
     fn add_marker(&mut self, marker: &str) -> Range<u32> {
@@ -333,16 +334,22 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
     }
 
     unsafe fn begin_query(&mut self, set: &super::QuerySet, index: u32) {
-        let query = set.queries[index as usize];
-        self.cmd_buffer
-            .commands
-            .push(C::BeginQuery(query, set.target));
+        let start = index as usize * set.targets.len();
+        for (&query, &target) in set.queries[start..].iter().zip(set.targets.iter()) {
+            self.cmd_buffer.commands.push(C::BeginQuery(query, target));
+        }
     }
     unsafe fn end_query(&mut self, set: &super::QuerySet, _index: u32) {
-        self.cmd_buffer.commands.push(C::EndQuery(set.target));
+        for &target in set.targets.iter() {
+            self.cmd_buffer.commands.push(C::EndQuery(target));
+        }
     }
-    unsafe fn write_timestamp(&mut self, _set: &super::QuerySet, _index: u32) {
-        unimplemented!()
+    unsafe fn write_timestamp(&mut self, set: &super::QuerySet, index: u32) {
+        // Timestamp sets only have names if the timer queries are supported.
+        match set.timestamps.get(index as usize) {
+            Some(&query) => self.cmd_buffer.commands.push(C::TimestampQuery(query)),
+            None => log::error!("Timestamp queries are not supported"),
+        }
     }
     unsafe fn reset_queries(&mut self, _set: &super::QuerySet, _range: Range<u32>) {
         //TODO: what do we do here?
@@ -355,13 +362,24 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         offset: wgt::BufferAddress,
         _stride: wgt::BufferSize,
     ) {
-        let start = self.cmd_buffer.queries.len();
-        self.cmd_buffer
-            .queries
-            .extend_from_slice(&set.queries[range.start as usize..range.end as usize]);
-        let query_range = start as u32..self.cmd_buffer.queries.len() as u32;
+        let timestamps = !set.timestamps.is_empty();
+        let query_range = if timestamps {
+            let start = self.cmd_buffer.timestamps.len();
+            self.cmd_buffer
+                .timestamps
+                .extend_from_slice(&set.timestamps[range.start as usize..range.end as usize]);
+            start as u32..self.cmd_buffer.timestamps.len() as u32
+        } else {
+            let start = self.cmd_buffer.queries.len();
+            let per_query = set.targets.len();
+            self.cmd_buffer.queries.extend_from_slice(
+                &set.queries[range.start as usize * per_query..range.end as usize * per_query],
+            );
+            start as u32..self.cmd_buffer.queries.len() as u32
+        };
         self.cmd_buffer.commands.push(C::CopyQueryResults {
             query_range,
+            timestamps,
             dst: buffer.raw,
             dst_target: buffer.target,
             dst_offset: offset,
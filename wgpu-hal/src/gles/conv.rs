@@ -349,3 +349,37 @@ pub(super) fn map_storage_access(access: wgt::StorageTextureAccess) -> u32 {
         wgt::StorageTextureAccess::ReadWrite => glow::READ_WRITE,
     }
 }
+
+pub(super) fn map_query_targets(
+    ty: wgt::QueryType,
+) -> arrayvec::ArrayVec<u32, { super::MAX_QUERY_TARGETS }> {
+    use wgt::PipelineStatisticsTypes as Pst;
+    match ty {
+        wgt::QueryType::Occlusion => [glow::ANY_SAMPLES_PASSED].iter().copied().collect(),
+        // Counters are listed in the order their results are written out.
+        wgt::QueryType::PipelineStatistics(types) => [
+            (
+                Pst::VERTEX_SHADER_INVOCATIONS,
+                glow::VERTEX_SHADER_INVOCATIONS,
+            ),
+            (Pst::CLIPPER_INVOCATIONS, glow::CLIPPING_INPUT_PRIMITIVES),
+            (
+                Pst::CLIPPER_PRIMITIVES_OUT,
+                glow::CLIPPING_OUTPUT_PRIMITIVES,
+            ),
+            (
+                Pst::FRAGMENT_SHADER_INVOCATIONS,
+                glow::FRAGMENT_SHADER_INVOCATIONS,
+            ),
+            (
+                Pst::COMPUTE_SHADER_INVOCATIONS,
+                glow::COMPUTE_SHADER_INVOCATIONS,
+            ),
+        ]
+        .iter()
+        .filter(|&&(flag, _)| types.contains(flag))
+        .map(|&(_, target)| target)
+        .collect(),
+        wgt::QueryType::Timestamp => [glow::TIMESTAMP].iter().copied().collect(),
+    }
+}
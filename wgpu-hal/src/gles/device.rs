@@ -1,6 +1,7 @@
 use super::conv;
 use crate::auxil::map_naga_stage;
 use glow::HasContext;
+use parking_lot::Mutex;
 use std::{convert::TryInto, iter, mem, ptr, sync::Arc};
 
 type ShaderStage<'a> = (
@@ -13,15 +14,17 @@ struct CompilationContext<'a> {
     layout: &'a super::PipelineLayout,
     sampler_map: &'a mut super::SamplerBindMap,
     name_binding_map: &'a mut NameBindingMap,
+    max_uniform_buffer_bindings: u32,
 }
 
 impl CompilationContext<'_> {
     fn consume_reflection(
         self,
+        naga_stage: naga::ShaderStage,
         module: &naga::Module,
         ep_info: &naga::valid::FunctionInfo,
         reflection_info: naga::back::glsl::ReflectionInfo,
-    ) {
+    ) -> Result<(), crate::PipelineError> {
         for (handle, var) in module.global_variables.iter() {
             if ep_info[handle].is_empty() {
                 continue;
@@ -39,6 +42,21 @@ impl CompilationContext<'_> {
                 Some(name) => name.clone(),
                 None => continue,
             };
+
+            // All the stages share the uniform buffer binding points, and the layout
+            // hands them out without looking at the visibility.
+            if let super::BindingRegister::UniformBuffers = register {
+                if slot as u32 >= self.max_uniform_buffer_bindings {
+                    return Err(crate::PipelineError::Linkage(
+                        map_naga_stage(naga_stage),
+                        format!(
+                            "uniform block {} needs slot {}, but only {} are available",
+                            name, slot, self.max_uniform_buffer_bindings
+                        ),
+                    ));
+                }
+            }
+
             log::debug!(
                 "Rebind buffer: {:?} -> {}, register={:?}, slot={}",
                 var.name.as_ref(),
@@ -73,6 +91,7 @@ impl CompilationContext<'_> {
                 self.sampler_map[texture_linear_index as usize] = Some(sampler_linear_index);
             }
         }
+        Ok(())
     }
 }
 
@@ -157,10 +176,11 @@ impl super::Device {
         log::debug!("Naga generated shader:\n{}", output);
 
         context.consume_reflection(
+            naga_stage,
             &shader.module,
             shader.info.get_entry_point(entry_point_index),
             reflection_info,
-        );
+        )?;
 
         unsafe { Self::compile_shader(gl, &output, naga_stage, stage.module.label.as_deref()) }
     }
@@ -190,6 +210,7 @@ impl super::Device {
                 layout,
                 sampler_map: &mut sampler_map,
                 name_binding_map: &mut name_binding_map,
+                max_uniform_buffer_bindings: self.shared.max_uniform_buffer_bindings,
             };
 
             let shader = Self::create_shader(gl, naga_stage, stage, context)?;
@@ -320,23 +341,24 @@ impl crate::Device<super::Api> for super::Device {
         let is_host_visible = desc
             .usage
             .intersects(crate::BufferUses::MAP_READ | crate::BufferUses::MAP_WRITE);
-        let is_coherent = desc
-            .memory_flags
-            .contains(crate::MemoryFlags::PREFER_COHERENT);
-        let mut map_flags = 0;
+        let has_storage = self
+            .shared
+            .private_caps
+            .contains(super::PrivateCapabilities::BUFFER_STORAGE);
 
-        if is_host_visible {
-            map_flags |= glow::MAP_PERSISTENT_BIT;
-            if is_coherent {
-                map_flags |= glow::MAP_COHERENT_BIT;
-            }
-        }
+        let mut map_flags = 0;
         if desc.usage.contains(crate::BufferUses::MAP_READ) {
             map_flags |= glow::MAP_READ_BIT;
         }
         if desc.usage.contains(crate::BufferUses::MAP_WRITE) {
             map_flags |= glow::MAP_WRITE_BIT;
         }
+        // Host visible buffers stay mapped for their whole lifetime if we can,
+        // relying on the fences to know when the GPU is done with them.
+        let is_persistent = is_host_visible && has_storage;
+        if is_persistent {
+            map_flags |= glow::MAP_PERSISTENT_BIT | glow::MAP_COHERENT_BIT;
+        }
 
         let raw = gl.create_buffer().unwrap();
         gl.bind_buffer(target, Some(raw));
@@ -344,10 +366,33 @@ impl crate::Device<super::Api> for super::Device {
             .size
             .try_into()
             .map_err(|_| crate::DeviceError::OutOfMemory)?;
-        gl.buffer_storage(target, raw_size, None, map_flags);
+        if has_storage {
+            // Query results are written with `glBufferSubData`.
+            let storage_flags = if desc.usage.contains(crate::BufferUses::COPY_DST) {
+                map_flags | glow::DYNAMIC_STORAGE_BIT
+            } else {
+                map_flags
+            };
+            gl.buffer_storage(target, raw_size, None, storage_flags);
+        } else {
+            let usage = if desc.usage.contains(crate::BufferUses::MAP_READ) {
+                glow::STREAM_READ
+            } else if desc.usage.contains(crate::BufferUses::MAP_WRITE) {
+                glow::DYNAMIC_DRAW
+            } else {
+                glow::STATIC_DRAW
+            };
+            gl.buffer_data_size(target, raw_size, usage);
+        }
+        let persistent_map = if is_persistent {
+            let ptr = gl.map_buffer_range(target, 0, raw_size, map_flags);
+            Some(ptr::NonNull::new(ptr).ok_or(crate::DeviceError::OutOfMemory)?)
+        } else {
+            None
+        };
         gl.bind_buffer(target, None);
 
-        if !is_coherent && desc.usage.contains(crate::BufferUses::MAP_WRITE) {
+        if !is_persistent && desc.usage.contains(crate::BufferUses::MAP_WRITE) {
             map_flags |= glow::MAP_FLUSH_EXPLICIT_BIT;
         }
         //TODO: do we need `glow::MAP_UNSYNCHRONIZED_BIT`?
@@ -363,6 +408,8 @@ impl crate::Device<super::Api> for super::Device {
             target,
             size: desc.size,
             map_flags,
+            map_offset: Mutex::new(0),
+            persistent_map,
         })
     }
     unsafe fn destroy_buffer(&self, buffer: super::Buffer) {
@@ -375,10 +422,14 @@ impl crate::Device<super::Api> for super::Device {
         buffer: &super::Buffer,
         range: crate::MemoryRange,
     ) -> Result<crate::BufferMapping, crate::DeviceError> {
-        let gl = &self.shared.context.lock();
-
-        let is_coherent = buffer.map_flags & glow::MAP_COHERENT_BIT != 0;
+        if let Some(ptr) = buffer.persistent_map {
+            return Ok(crate::BufferMapping {
+                ptr: ptr::NonNull::new_unchecked(ptr.as_ptr().add(range.start as usize)),
+                is_coherent: true,
+            });
+        }
 
+        let gl = &self.shared.context.lock();
         gl.bind_buffer(buffer.target, Some(buffer.raw));
         let ptr = gl.map_buffer_range(
             buffer.target,
@@ -387,13 +438,17 @@ impl crate::Device<super::Api> for super::Device {
             buffer.map_flags,
         );
         gl.bind_buffer(buffer.target, None);
+        *buffer.map_offset.lock() = range.start;
 
         Ok(crate::BufferMapping {
             ptr: ptr::NonNull::new(ptr).ok_or(crate::DeviceError::Lost)?,
-            is_coherent,
+            is_coherent: false,
         })
     }
     unsafe fn unmap_buffer(&self, buffer: &super::Buffer) -> Result<(), crate::DeviceError> {
+        if buffer.persistent_map.is_some() {
+            return Ok(());
+        }
         let gl = &self.shared.context.lock();
         gl.bind_buffer(buffer.target, Some(buffer.raw));
         gl.unmap_buffer(buffer.target);
@@ -404,18 +459,22 @@ impl crate::Device<super::Api> for super::Device {
     where
         I: Iterator<Item = crate::MemoryRange>,
     {
+        if buffer.map_flags & glow::MAP_FLUSH_EXPLICIT_BIT == 0 {
+            return;
+        }
         let gl = &self.shared.context.lock();
+        let map_offset = *buffer.map_offset.lock();
         gl.bind_buffer(buffer.target, Some(buffer.raw));
         for range in ranges {
             gl.flush_mapped_buffer_range(
                 buffer.target,
-                range.start as i32,
+                (range.start - map_offset) as i32,
                 (range.end - range.start) as i32,
             );
         }
     }
     unsafe fn invalidate_mapped_ranges<I>(&self, _buffer: &super::Buffer, _ranges: I) {
-        //TODO: do we need to do anything?
+        // Mappings without `MAP_PERSISTENT_BIT` are up to date when they are created.
     }
 
     unsafe fn create_texture(
@@ -959,27 +1018,54 @@ impl crate::Device<super::Api> for super::Device {
         let gl = &self.shared.context.lock();
         let mut temp_string = String::new();
 
-        let mut queries = Vec::with_capacity(desc.count as usize);
-        for i in 0..desc.count {
-            let query = gl
-                .create_query()
-                .map_err(|_| crate::DeviceError::OutOfMemory)?;
+        let targets = conv::map_query_targets(desc.ty);
+        let mut queries = Vec::new();
+        let mut timestamps = Vec::new();
+        if let wgt::QueryType::Timestamp = desc.ty {
+            let functions = match self.shared.timer_query {
+                Some(ref functions) => functions,
+                None => {
+                    log::error!("Timestamp queries are not supported");
+                    return Err(crate::DeviceError::OutOfMemory);
+                }
+            };
+            timestamps.resize(desc.count as usize, 0);
+            (functions.gen_queries)(desc.count as i32, timestamps.as_mut_ptr());
+            if timestamps.contains(&0) {
+                (functions.delete_queries)(desc.count as i32, timestamps.as_ptr());
+                return Err(crate::DeviceError::OutOfMemory);
+            }
             if gl.supports_debug() {
                 if let Some(label) = desc.label {
-                    temp_string.clear();
-                    let _ = write!(temp_string, "{}[{}]", label, i);
-                    gl.object_label(glow::QUERY, mem::transmute(query), Some(&temp_string));
+                    for (i, &query) in timestamps.iter().enumerate() {
+                        temp_string.clear();
+                        let _ = write!(temp_string, "{}[{}]", label, i);
+                        gl.object_label(glow::QUERY, query, Some(&temp_string));
+                    }
+                }
+            }
+        } else {
+            let count = desc.count as usize * targets.len();
+            queries.reserve(count);
+            for i in 0..count {
+                let query = gl
+                    .create_query()
+                    .map_err(|_| crate::DeviceError::OutOfMemory)?;
+                if gl.supports_debug() {
+                    if let Some(label) = desc.label {
+                        temp_string.clear();
+                        let _ = write!(temp_string, "{}[{}]", label, i);
+                        gl.object_label(glow::QUERY, mem::transmute(query), Some(&temp_string));
+                    }
                 }
+                queries.push(query);
             }
-            queries.push(query);
         }
 
         Ok(super::QuerySet {
             queries: queries.into_boxed_slice(),
-            target: match desc.ty {
-                wgt::QueryType::Occlusion => glow::ANY_SAMPLES_PASSED,
-                _ => unimplemented!(),
-            },
+            timestamps: timestamps.into_boxed_slice(),
+            targets,
         })
     }
     unsafe fn destroy_query_set(&self, set: super::QuerySet) {
@@ -987,6 +1073,11 @@ impl crate::Device<super::Api> for super::Device {
         for &query in set.queries.iter() {
             gl.delete_query(query);
         }
+        if let Some(ref functions) = self.shared.timer_query {
+            if !set.timestamps.is_empty() {
+                (functions.delete_queries)(set.timestamps.len() as i32, set.timestamps.as_ptr());
+            }
+        }
     }
     unsafe fn create_fence(&self) -> Result<super::Fence, crate::DeviceError> {
         Ok(super::Fence {
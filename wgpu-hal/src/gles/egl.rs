@@ -2,7 +2,7 @@ use glow::HasContext;
 use parking_lot::{Mutex, MutexGuard};
 use raw_window_handle::{HasRawWindowHandle, RawWindowHandle};
 
-use std::{ffi::CStr, os::raw, ptr, sync::Arc, time::Duration};
+use std::{ffi::CStr, iter, os::raw, ptr, sync::Arc, time::Duration};
 
 /// The amount of time to wait while trying to obtain a lock to the adapter context
 const CONTEXT_LOCK_TIMEOUT_SECS: u64 = 1;
@@ -12,6 +12,8 @@ const EGL_CONTEXT_OPENGL_DEBUG_BIT_KHR: i32 = 0x0001;
 const EGL_CONTEXT_OPENGL_ROBUST_ACCESS_EXT: i32 = 0x30BF;
 const EGL_PLATFORM_WAYLAND_KHR: u32 = 0x31D8;
 const EGL_PLATFORM_X11_KHR: u32 = 0x31D5;
+const EGL_PLATFORM_SURFACELESS_MESA: u32 = 0x31DD;
+const EGL_PLATFORM_DEVICE_EXT: u32 = 0x313F;
 const EGL_GL_COLORSPACE_KHR: u32 = 0x309D;
 const EGL_GL_COLORSPACE_SRGB_KHR: u32 = 0x3089;
 
@@ -51,6 +53,12 @@ extern "C" {
     ) -> i32;
 }
 
+type EglQueryDevicesFun = unsafe extern "system" fn(
+    max_devices: egl::Int,
+    devices: *mut *mut raw::c_void,
+    num_devices: *mut egl::Int,
+) -> egl::Boolean;
+
 type EglLabel = *const raw::c_void;
 
 #[allow(clippy::upper_case_acronyms)]
@@ -131,6 +139,85 @@ fn test_wayland_display() -> Option<libloading::Library> {
     Some(library)
 }
 
+/// Get a display for each EGL device, including render-only GPUs and software rasterizers.
+unsafe fn open_device_displays(
+    egl: &egl::DynamicInstance<egl::EGL1_4>,
+    client_ext_str: &str,
+) -> Vec<egl::Display> {
+    let egl1_5 = match egl.upcast::<egl::EGL1_5>() {
+        Some(egl) if client_ext_str.contains("EGL_EXT_platform_device") => egl,
+        _ => return Vec::new(),
+    };
+    let query_devices: EglQueryDevicesFun = match egl.get_proc_address("eglQueryDevicesEXT") {
+        Some(function) => std::mem::transmute::<extern "C" fn(), EglQueryDevicesFun>(function),
+        None => return Vec::new(),
+    };
+
+    log::info!("Enumerating EGL devices");
+    let mut count = 0;
+    if query_devices(0, ptr::null_mut(), &mut count) == egl::FALSE {
+        log::warn!("Error in eglQueryDevicesEXT");
+        return Vec::new();
+    }
+    let mut devices = vec![ptr::null_mut(); count as usize];
+    if query_devices(count, devices.as_mut_ptr(), &mut count) == egl::FALSE {
+        log::warn!("Error in eglQueryDevicesEXT");
+        return Vec::new();
+    }
+    devices.truncate(count as usize);
+
+    let display_attributes = [egl::ATTRIB_NONE];
+    devices
+        .into_iter()
+        .filter_map(|device| {
+            egl1_5
+                .get_platform_display(EGL_PLATFORM_DEVICE_EXT, device, &display_attributes)
+                .map_err(|e| log::warn!("Error in get_platform_display: {:?}", e))
+                .ok()
+        })
+        .collect()
+}
+
+/// Open the displays to use without a window system: one for each EGL device,
+/// or the surfaceless or the default display if there are no devices.
+unsafe fn open_headless(
+    flags: crate::InstanceFlags,
+    egl: Arc<egl::DynamicInstance<egl::EGL1_4>>,
+    client_ext_str: &str,
+) -> Result<Vec<Inner>, crate::InstanceError> {
+    let mut inners = Vec::new();
+    for display in open_device_displays(&egl, client_ext_str) {
+        match Inner::create(flags, Arc::clone(&egl), display) {
+            Ok(inner) => inners.push(inner),
+            Err(_) => log::warn!("Skipping EGL device display {:?}", display),
+        }
+    }
+    if !inners.is_empty() {
+        log::info!("Using device platform");
+        return Ok(inners);
+    }
+
+    let display = if let (true, Some(egl1_5)) = (
+        client_ext_str.contains("EGL_MESA_platform_surfaceless"),
+        egl.upcast::<egl::EGL1_5>(),
+    ) {
+        log::info!("Using surfaceless platform");
+        let display_attributes = [egl::ATTRIB_NONE];
+        egl1_5
+            .get_platform_display(
+                EGL_PLATFORM_SURFACELESS_MESA,
+                egl::DEFAULT_DISPLAY,
+                &display_attributes,
+            )
+            .unwrap()
+    } else {
+        log::info!("Using default platform");
+        egl.get_display(egl::DEFAULT_DISPLAY).unwrap()
+    };
+    inners.push(Inner::create(flags, egl, display)?);
+    Ok(inners)
+}
+
 /// Choose GLES framebuffer configuration.
 fn choose_config(
     egl: &egl::DynamicInstance<egl::EGL1_4>,
@@ -156,6 +243,10 @@ fn choose_config(
         for &(_, tier_attr) in tiers[..=tier_max].iter() {
             attributes.extend_from_slice(tier_attr);
         }
+        if tier_max == 0 {
+            // The surface type defaults to windows, which are not there without a window system.
+            attributes.extend_from_slice(&[egl::SURFACE_TYPE, egl::PBUFFER_BIT]);
+        }
         attributes.push(egl::NONE);
 
         match egl.choose_first_config(display, &attributes) {
@@ -285,6 +376,11 @@ impl AdapterContext {
             .expect("Could not lock adapter context. This is most-likely a deadlcok.")
     }
 
+    /// Get the address of a GL entry point that [`glow::Context`] doesn't wrap.
+    pub(super) fn get_proc_address(&self, name: &str) -> Option<extern "C" fn()> {
+        self.egl.get_proc_address(name)
+    }
+
     /// Obtain a lock to the EGL context and get handle to the [`glow::Context`] that can be used to
     /// do rendering.
     #[track_caller]
@@ -471,11 +567,60 @@ pub struct Instance {
     wsi_library: Option<Arc<libloading::Library>>,
     flags: crate::InstanceFlags,
     inner: Mutex<Inner>,
+    /// Displays of the other EGL devices, when there is no window system.
+    other_inners: Vec<Inner>,
 }
 
 unsafe impl Send for Instance {}
 unsafe impl Sync for Instance {}
 
+impl Instance {
+    unsafe fn expose_adapter(&self, inner: &Inner) -> Option<crate::ExposedAdapter<super::Api>> {
+        inner
+            .egl
+            .make_current(
+                inner.display,
+                inner.pbuffer,
+                inner.pbuffer,
+                Some(inner.context),
+            )
+            .unwrap();
+
+        let gl = glow::Context::from_loader_function(|name| {
+            inner
+                .egl
+                .get_proc_address(name)
+                .map_or(ptr::null(), |p| p as *const _)
+        });
+
+        if self.flags.contains(crate::InstanceFlags::DEBUG) && gl.supports_debug() {
+            log::info!(
+                "Max label length: {}",
+                gl.get_parameter_i32(glow::MAX_LABEL_LENGTH)
+            );
+        }
+
+        if self.flags.contains(crate::InstanceFlags::VALIDATION) && gl.supports_debug() {
+            log::info!("Enabling GLES debug output");
+            gl.enable(glow::DEBUG_OUTPUT);
+            gl.debug_message_callback(gl_debug_message_callback);
+        }
+
+        inner
+            .egl
+            .make_current(inner.display, None, None, None)
+            .unwrap();
+
+        super::Adapter::expose(AdapterContext {
+            glow_context: Mutex::new(gl),
+            egl: inner.egl.clone(),
+            egl_display: inner.display,
+            egl_context: inner.context,
+            egl_pbuffer: inner.pbuffer,
+        })
+    }
+}
+
 impl crate::Instance<super::Api> for Instance {
     unsafe fn init(desc: &crate::InstanceDescriptor) -> Result<Self, crate::InstanceError> {
         let egl = match egl::DynamicInstance::<egl::EGL1_4>::load_required() {
@@ -511,31 +656,6 @@ impl crate::Instance<super::Api> for Instance {
             None
         };
 
-        let display = if let (Some(library), Some(egl)) =
-            (wayland_library, egl.upcast::<egl::EGL1_5>())
-        {
-            log::info!("Using Wayland platform");
-            let display_attributes = [egl::ATTRIB_NONE];
-            wsi_library = Some(Arc::new(library));
-            egl.get_platform_display(
-                EGL_PLATFORM_WAYLAND_KHR,
-                egl::DEFAULT_DISPLAY,
-                &display_attributes,
-            )
-            .unwrap()
-        } else if let (Some((display, library)), Some(egl)) =
-            (x11_display_library, egl.upcast::<egl::EGL1_5>())
-        {
-            log::info!("Using X11 platform");
-            let display_attributes = [egl::ATTRIB_NONE];
-            wsi_library = Some(Arc::new(library));
-            egl.get_platform_display(EGL_PLATFORM_X11_KHR, display.as_ptr(), &display_attributes)
-                .unwrap()
-        } else {
-            log::info!("Using default platform");
-            egl.get_display(egl::DEFAULT_DISPLAY).unwrap()
-        };
-
         if desc.flags.contains(crate::InstanceFlags::VALIDATION)
             && client_ext_str.contains(&"EGL_KHR_debug")
         {
@@ -556,12 +676,42 @@ impl crate::Instance<super::Api> for Instance {
             (function)(Some(egl_debug_proc), attributes.as_ptr());
         }
 
-        let inner = Inner::create(desc.flags, egl, display)?;
+        let (inner, other_inners) = if let (Some(library), Some(egl1_5)) =
+            (wayland_library, egl.upcast::<egl::EGL1_5>())
+        {
+            log::info!("Using Wayland platform");
+            let display_attributes = [egl::ATTRIB_NONE];
+            wsi_library = Some(Arc::new(library));
+            let display = egl1_5
+                .get_platform_display(
+                    EGL_PLATFORM_WAYLAND_KHR,
+                    egl::DEFAULT_DISPLAY,
+                    &display_attributes,
+                )
+                .unwrap();
+            (Inner::create(desc.flags, egl, display)?, Vec::new())
+        } else if let (Some((display, library)), Some(egl1_5)) =
+            (x11_display_library, egl.upcast::<egl::EGL1_5>())
+        {
+            log::info!("Using X11 platform");
+            let display_attributes = [egl::ATTRIB_NONE];
+            wsi_library = Some(Arc::new(library));
+            let display = egl1_5
+                .get_platform_display(EGL_PLATFORM_X11_KHR, display.as_ptr(), &display_attributes)
+                .unwrap();
+            (Inner::create(desc.flags, egl, display)?, Vec::new())
+        } else {
+            // Without a window system, each EGL device is exposed as its own adapter.
+            let mut inners = open_headless(desc.flags, egl, &client_ext_str)?;
+            let inner = inners.remove(0);
+            (inner, inners)
+        };
 
         Ok(Instance {
             wsi_library,
             flags: desc.flags,
             inner: Mutex::new(inner),
+            other_inners,
         })
     }
 
@@ -664,50 +814,10 @@ impl crate::Instance<super::Api> for Instance {
 
     unsafe fn enumerate_adapters(&self) -> Vec<crate::ExposedAdapter<super::Api>> {
         let inner = self.inner.lock();
-        inner
-            .egl
-            .make_current(
-                inner.display,
-                inner.pbuffer,
-                inner.pbuffer,
-                Some(inner.context),
-            )
-            .unwrap();
-
-        let gl = glow::Context::from_loader_function(|name| {
-            inner
-                .egl
-                .get_proc_address(name)
-                .map_or(ptr::null(), |p| p as *const _)
-        });
-
-        if self.flags.contains(crate::InstanceFlags::DEBUG) && gl.supports_debug() {
-            log::info!(
-                "Max label length: {}",
-                gl.get_parameter_i32(glow::MAX_LABEL_LENGTH)
-            );
-        }
-
-        if self.flags.contains(crate::InstanceFlags::VALIDATION) && gl.supports_debug() {
-            log::info!("Enabling GLES debug output");
-            gl.enable(glow::DEBUG_OUTPUT);
-            gl.debug_message_callback(gl_debug_message_callback);
-        }
-
-        inner
-            .egl
-            .make_current(inner.display, None, None, None)
-            .unwrap();
-
-        super::Adapter::expose(AdapterContext {
-            glow_context: Mutex::new(gl),
-            egl: inner.egl.clone(),
-            egl_display: inner.display,
-            egl_context: inner.context,
-            egl_pbuffer: inner.pbuffer,
-        })
-        .into_iter()
-        .collect()
+        iter::once(&*inner)
+            .chain(self.other_inners.iter())
+            .filter_map(|inner| self.expose_adapter(inner))
+            .collect()
     }
 }
 
@@ -992,3 +1102,48 @@ impl crate::Surface<super::Api> for Surface {
     }
     unsafe fn discard_texture(&mut self, _texture: super::Texture) {}
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Adapter as _, Device as _, Instance as _};
+
+    /// Expose the adapters of the headless displays, and open a device on each of them.
+    unsafe fn open_headless_devices(client_ext_str: &str) -> usize {
+        let egl = Arc::new(egl::DynamicInstance::<egl::EGL1_4>::load_required().unwrap());
+        let mut inners = open_headless(crate::InstanceFlags::empty(), egl, client_ext_str).unwrap();
+        let inner = inners.remove(0);
+        let instance = Instance {
+            wsi_library: None,
+            flags: crate::InstanceFlags::empty(),
+            inner: Mutex::new(inner),
+            other_inners: inners,
+        };
+        let adapters = instance.enumerate_adapters();
+        for exposed in adapters.iter() {
+            let open = exposed.adapter.open(wgt::Features::empty()).unwrap();
+            open.device.exit(open.queue);
+        }
+        adapters.len()
+    }
+
+    #[test]
+    fn headless_platforms() {
+        let egl = unsafe { egl::DynamicInstance::<egl::EGL1_4>::load_required() }.unwrap();
+        let client_ext_str = egl
+            .query_string(None, egl::EXTENSIONS)
+            .map(|ext| ext.to_string_lossy().into_owned())
+            .unwrap_or_default();
+
+        if client_ext_str.contains("EGL_EXT_platform_device") {
+            let num_devices = unsafe { open_device_displays(&egl, &client_ext_str) }.len();
+            assert_eq!(
+                unsafe { open_headless_devices(&client_ext_str) },
+                num_devices.max(1)
+            );
+        }
+        // Without devices to enumerate, a single surfaceless or default display is used.
+        let without_devices = client_ext_str.replace("EGL_EXT_platform_device", "");
+        assert_eq!(unsafe { open_headless_devices(&without_devices) }, 1);
+    }
+}
@@ -71,8 +71,9 @@ use self::egl::{AdapterContext, Instance, Surface};
 use arrayvec::ArrayVec;
 
 use glow::HasContext;
+use parking_lot::Mutex;
 
-use std::{ops::Range, sync::Arc};
+use std::{ops::Range, ptr, sync::Arc};
 
 #[derive(Clone)]
 pub struct Api;
@@ -82,6 +83,8 @@ pub struct Api;
 const MAX_TEXTURE_SLOTS: usize = 16;
 const MAX_SAMPLERS: usize = 16;
 const MAX_VERTEX_ATTRIBUTES: usize = 16;
+/// One GL query per counter of `wgt::PipelineStatisticsTypes`.
+const MAX_QUERY_TARGETS: usize = 5;
 const ZERO_BUFFER_SIZE: usize = 256 << 10;
 
 impl crate::Api for Api {
@@ -122,6 +125,8 @@ bitflags::bitflags! {
         const MEMORY_BARRIERS = 1 << 2;
         /// Vertex buffer layouts separate from the data.
         const VERTEX_BUFFER_LAYOUT = 1 << 3;
+        /// Immutable buffer storage, which can stay mapped while in use by the GPU.
+        const BUFFER_STORAGE = 1 << 4;
     }
 }
 
@@ -160,11 +165,38 @@ struct TextureFormatDesc {
     data_type: u32,
 }
 
+type GenQueriesFun = unsafe extern "system" fn(n: i32, ids: *mut u32);
+type DeleteQueriesFun = unsafe extern "system" fn(n: i32, ids: *const u32);
+type QueryCounterFun = unsafe extern "system" fn(query: u32, target: u32);
+type GetQueryObjectU64Fun = unsafe extern "system" fn(query: u32, pname: u32, params: *mut u64);
+
+/// `GL_GPU_DISJOINT_EXT`, which `glow` doesn't define.
+const GPU_DISJOINT_EXT: u32 = 0x8FBB;
+
+/// Entry points of `GL_ARB_timer_query` or `GL_EXT_disjoint_timer_query`,
+/// which `glow` doesn't expose.
+///
+/// The timestamp queries are created through them as well, since
+/// `glow::Query` can't be turned back into the name they take.
+#[derive(Clone, Copy)]
+struct TimerQueryFunctions {
+    gen_queries: GenQueriesFun,
+    delete_queries: DeleteQueriesFun,
+    query_counter: QueryCounterFun,
+    get_query_object_u64: GetQueryObjectU64Fun,
+    /// Whether timings can be invalidated by a disjoint operation,
+    /// which `GPU_DISJOINT_EXT` reports.
+    can_be_disjoint: bool,
+}
+
 struct AdapterShared {
     context: AdapterContext,
     private_caps: PrivateCapabilities,
+    timer_query: Option<TimerQueryFunctions>,
     workarounds: Workarounds,
     shading_language_version: naga::back::glsl::Version,
+    /// Number of uniform buffer binding points, shared by all the stages.
+    max_uniform_buffer_bindings: u32,
 }
 
 pub struct Adapter {
@@ -178,6 +210,13 @@ pub struct Device {
     render_doc: crate::auxil::renderdoc::RenderDoc,
 }
 
+impl Device {
+    /// Get the context that the device renders with, to interoperate with raw GL.
+    pub fn context(&self) -> &AdapterContext {
+        &self.shared.context
+    }
+}
+
 pub struct Queue {
     shared: Arc<AdapterShared>,
     features: wgt::Features,
@@ -192,6 +231,8 @@ pub struct Queue {
     /// zeroes by copying from it.
     zero_buffer: glow::Buffer,
     temp_query_results: Vec<u64>,
+    /// Timestamp queries that were written across a disjoint operation.
+    disjoint_timestamps: fxhash::FxHashSet<u32>,
     draw_buffer_count: u8,
 }
 
@@ -201,6 +242,21 @@ pub struct Buffer {
     target: BindTarget,
     size: wgt::BufferAddress,
     map_flags: u32,
+    /// Start of the mapped range, which flushed ranges are relative to.
+    map_offset: Mutex<wgt::BufferAddress>,
+    /// Persistent mapping of the whole buffer, if it's host visible
+    /// and [`PrivateCapabilities::BUFFER_STORAGE`] is supported.
+    persistent_map: Option<ptr::NonNull<u8>>,
+}
+
+unsafe impl Send for Buffer {}
+unsafe impl Sync for Buffer {}
+
+impl Buffer {
+    /// Get the raw GL buffer.
+    pub fn raw_handle(&self) -> glow::Buffer {
+        self.raw
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -403,8 +459,11 @@ pub struct ComputePipeline {
 
 #[derive(Debug)]
 pub struct QuerySet {
+    /// GL queries backing the set, `targets.len()` of them per query.
     queries: Box<[glow::Query]>,
-    target: BindTarget,
+    /// Names of the queries backing a timestamp set, one per query.
+    timestamps: Box<[u32]>,
+    targets: ArrayVec<BindTarget, MAX_QUERY_TARGETS>,
 }
 
 #[derive(Debug)]
@@ -566,8 +625,11 @@ enum Command {
     SetIndexBuffer(glow::Buffer),
     BeginQuery(glow::Query, BindTarget),
     EndQuery(BindTarget),
+    TimestampQuery(u32),
+    /// Copies the results of `queries`, or of `timestamps` if set.
     CopyQueryResults {
         query_range: Range<u32>,
+        timestamps: bool,
         dst: glow::Buffer,
         dst_target: BindTarget,
         dst_offset: wgt::BufferAddress,
@@ -660,6 +722,7 @@ pub struct CommandBuffer {
     commands: Vec<Command>,
     data_bytes: Vec<u8>,
     queries: Vec<glow::Query>,
+    timestamps: Vec<u32>,
 }
 
 //TODO: we would have something like `Arc<typed_arena::Arena>`
@@ -107,6 +107,7 @@ impl super::Queue {
         command: &C,
         data_bytes: &[u8],
         queries: &[glow::Query],
+        timestamps: &[u32],
     ) {
         match *command {
             C::Draw {
@@ -313,7 +314,7 @@ impl super::Queue {
                     let unpack_data =
                         glow::PixelUnpackData::BufferOffset(copy.buffer_layout.offset as u32);
                     match dst_target {
-                        glow::TEXTURE_3D | glow::TEXTURE_2D_ARRAY => {
+                        glow::TEXTURE_3D => {
                             gl.tex_sub_image_3d(
                                 dst_target,
                                 copy.texture_base.mip_level as i32,
@@ -328,6 +329,21 @@ impl super::Queue {
                                 unpack_data,
                             );
                         }
+                        glow::TEXTURE_2D_ARRAY => {
+                            gl.tex_sub_image_3d(
+                                dst_target,
+                                copy.texture_base.mip_level as i32,
+                                copy.texture_base.origin.x as i32,
+                                copy.texture_base.origin.y as i32,
+                                copy.texture_base.array_layer as i32,
+                                copy.size.width as i32,
+                                copy.size.height as i32,
+                                copy.size.depth as i32,
+                                format_desc.external,
+                                format_desc.data_type,
+                                unpack_data,
+                            );
+                        }
                         glow::TEXTURE_2D => {
                             gl.tex_sub_image_2d(
                                 dst_target,
@@ -470,20 +486,43 @@ impl super::Queue {
                 gl.pixel_store_i32(glow::PACK_ROW_LENGTH, row_texels as i32);
                 gl.bind_buffer(glow::PIXEL_PACK_BUFFER, Some(dst));
 
+                // Depth and stencil can only be read from their own attachment.
+                let (attachment, external, data_type) = match copy.texture_base.aspect {
+                    crate::FormatAspects::DEPTH => (
+                        glow::DEPTH_ATTACHMENT,
+                        format_desc.external,
+                        format_desc.data_type,
+                    ),
+                    crate::FormatAspects::STENCIL => (
+                        glow::STENCIL_ATTACHMENT,
+                        glow::STENCIL_INDEX,
+                        glow::UNSIGNED_BYTE,
+                    ),
+                    _ => (
+                        glow::COLOR_ATTACHMENT0,
+                        format_desc.external,
+                        format_desc.data_type,
+                    ),
+                };
+
                 gl.bind_framebuffer(glow::READ_FRAMEBUFFER, Some(self.copy_fbo));
                 if is_3d_target(src_target) {
+                    let layer = match src_target {
+                        glow::TEXTURE_3D => copy.texture_base.origin.z,
+                        _ => copy.texture_base.array_layer,
+                    };
                     //TODO: handle GLES without framebuffer_texture_3d
                     gl.framebuffer_texture_layer(
                         glow::READ_FRAMEBUFFER,
-                        glow::COLOR_ATTACHMENT0,
+                        attachment,
                         Some(src),
                         copy.texture_base.mip_level as i32,
-                        copy.texture_base.array_layer as i32,
+                        layer as i32,
                     );
                 } else {
                     gl.framebuffer_texture_2d(
                         glow::READ_FRAMEBUFFER,
-                        glow::COLOR_ATTACHMENT0,
+                        attachment,
                         src_target,
                         Some(src),
                         copy.texture_base.mip_level as i32,
@@ -494,10 +533,20 @@ impl super::Queue {
                     copy.texture_base.origin.y as i32,
                     copy.size.width as i32,
                     copy.size.height as i32,
-                    format_desc.external,
-                    format_desc.data_type,
+                    external,
+                    data_type,
                     glow::PixelPackData::BufferOffset(copy.buffer_layout.offset as u32),
                 );
+                if attachment != glow::COLOR_ATTACHMENT0 {
+                    // Don't leave it around for the color copies.
+                    gl.framebuffer_texture_2d(
+                        glow::READ_FRAMEBUFFER,
+                        attachment,
+                        glow::TEXTURE_2D,
+                        None,
+                        0,
+                    );
+                }
             }
             C::SetIndexBuffer(buffer) => {
                 gl.bind_buffer(glow::ELEMENT_ARRAY_BUFFER, Some(buffer));
@@ -508,8 +557,68 @@ impl super::Queue {
             C::EndQuery(target) => {
                 gl.end_query(target);
             }
+            C::TimestampQuery(query) => {
+                // The set only has names if the timer queries are supported.
+                if let Some(ref functions) = self.shared.timer_query {
+                    (functions.query_counter)(query, glow::TIMESTAMP);
+                    // Timestamps taken across a disjoint operation, like a change
+                    // of the GPU frequency, can't be compared with the others.
+                    // Checking the flag also resets it.
+                    if functions.can_be_disjoint
+                        && gl.get_parameter_i32(super::GPU_DISJOINT_EXT) != 0
+                    {
+                        self.disjoint_timestamps.insert(query);
+                    } else {
+                        self.disjoint_timestamps.remove(&query);
+                    }
+                }
+            }
+            C::CopyQueryResults {
+                ref query_range,
+                timestamps: true,
+                dst,
+                dst_target,
+                dst_offset,
+            } => {
+                let functions = match self.shared.timer_query {
+                    Some(ref functions) => functions,
+                    None => return,
+                };
+                let range = query_range.start as usize..query_range.end as usize;
+                gl.bind_buffer(dst_target, Some(dst));
+                // Disjoint timestamps are unavailable, so their results are left
+                // untouched, and only the runs of the others are written.
+                let disjoint_timestamps = &self.disjoint_timestamps;
+                let results = &mut self.temp_query_results;
+                let mut offset = dst_offset;
+                for run in timestamps[range].split(|query| {
+                    let is_disjoint = disjoint_timestamps.contains(query);
+                    if is_disjoint {
+                        log::warn!("Timestamp {} was taken across a disjoint operation", query);
+                    }
+                    is_disjoint
+                }) {
+                    results.clear();
+                    for &query in run.iter() {
+                        let mut result = 0;
+                        (functions.get_query_object_u64)(query, glow::QUERY_RESULT, &mut result);
+                        results.push(result);
+                    }
+                    if !run.is_empty() {
+                        let query_data = slice::from_raw_parts(
+                            results.as_ptr() as *const u8,
+                            results.len() * mem::size_of::<u64>(),
+                        );
+                        gl.buffer_sub_data_u8_slice(dst_target, offset as i32, query_data);
+                    }
+                    // Skip the disjoint timestamp after the run as well.
+                    offset += (run.len() + 1) as wgt::BufferAddress
+                        * mem::size_of::<u64>() as wgt::BufferAddress;
+                }
+            }
             C::CopyQueryResults {
                 ref query_range,
+                timestamps: false,
                 dst,
                 dst_target,
                 dst_offset,
@@ -946,7 +1055,13 @@ impl crate::Queue<super::Api> for super::Queue {
                 gl.push_debug_group(glow::DEBUG_SOURCE_APPLICATION, DEBUG_ID, label);
             }
             for command in cmd_buf.commands.iter() {
-                self.process(gl, command, &cmd_buf.data_bytes, &cmd_buf.queries);
+                self.process(
+                    gl,
+                    command,
+                    &cmd_buf.data_bytes,
+                    &cmd_buf.queries,
+                    &cmd_buf.timestamps,
+                );
             }
             if cmd_buf.label.is_some() {
                 gl.pop_debug_group();
@@ -67,7 +67,8 @@ impl PhysicalDeviceFeatures {
                 )
                 //.dual_src_blend(requested_features.contains(wgt::Features::DUAL_SRC_BLENDING))
                 .multi_draw_indirect(
-                    requested_features.contains(wgt::Features::MULTI_DRAW_INDIRECT),
+                    requested_features.contains(wgt::Features::MULTI_DRAW_INDIRECT)
+                        && private_caps.multi_draw_indirect,
                 )
                 .depth_clamp(requested_features.contains(wgt::Features::DEPTH_CLAMPING))
                 .fill_mode_non_solid(
@@ -242,7 +243,9 @@ impl PhysicalDeviceFeatures {
             | F::ADDRESS_MODE_CLAMP_TO_BORDER
             | F::TIMESTAMP_QUERY
             | F::PIPELINE_STATISTICS_QUERY
-            | F::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES;
+            | F::TEXTURE_ADAPTER_SPECIFIC_FORMAT_FEATURES
+            // Split into single draws when `multiDrawIndirect` isn't supported.
+            | F::MULTI_DRAW_INDIRECT;
         let mut dl_flags = Df::all();
 
         dl_flags.set(Df::CUBE_ARRAY_TEXTURES, self.core.image_cube_array != 0);
@@ -253,7 +256,6 @@ impl PhysicalDeviceFeatures {
         );
 
         //if self.core.dual_src_blend != 0
-        features.set(F::MULTI_DRAW_INDIRECT, self.core.multi_draw_indirect != 0);
         features.set(F::DEPTH_CLAMPING, self.core.depth_clamp != 0);
         features.set(F::NON_FILL_POLYGON_MODE, self.core.fill_mode_non_solid != 0);
         //if self.core.depth_bounds != 0 {
@@ -697,6 +699,7 @@ impl super::Instance {
                     .contains(vk::FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT)
             },
             non_coherent_map_mask: phd_capabilities.properties.limits.non_coherent_atom_size - 1,
+            multi_draw_indirect: phd_features.core.multi_draw_indirect != 0,
         };
 
         let capabilities = crate::Capabilities {
@@ -777,6 +780,25 @@ impl super::Adapter {
         enabled_extensions: &[&'static CStr],
         family_index: u32,
         queue_index: u32,
+    ) -> Result<crate::OpenDevice<super::Api>, crate::DeviceError> {
+        self.device_from_raw_with_caps(
+            raw_device,
+            enabled_extensions,
+            family_index,
+            queue_index,
+            self.private_caps.clone(),
+        )
+    }
+
+    /// Like [`Self::device_from_raw`], with the capabilities of the device
+    /// narrowed down to the features it was created with.
+    unsafe fn device_from_raw_with_caps(
+        &self,
+        raw_device: ash::Device,
+        enabled_extensions: &[&'static CStr],
+        family_index: u32,
+        queue_index: u32,
+        private_caps: super::PrivateCapabilities,
     ) -> Result<crate::OpenDevice<super::Api>, crate::DeviceError> {
         let mem_properties = self
             .instance
@@ -791,6 +813,16 @@ impl super::Adapter {
                 u
             }
         });
+        let device_local_memory_types = memory_types.iter().enumerate().fold(0, |u, (i, mem)| {
+            if mem
+                .property_flags
+                .contains(vk::MemoryPropertyFlags::DEVICE_LOCAL)
+            {
+                u | (1 << i)
+            } else {
+                u
+            }
+        });
 
         let swapchain_fn = khr::Swapchain::new(&self.instance.raw, &raw_device);
 
@@ -816,6 +848,21 @@ impl super::Adapter {
             None
         };
 
+        let external_memory_fd_fn = if enabled_extensions.contains(&khr::ExternalMemoryFd::name()) {
+            Some(khr::ExternalMemoryFd::new(&self.instance.raw, &raw_device))
+        } else {
+            None
+        };
+        let external_semaphore_fd_fn =
+            if enabled_extensions.contains(&khr::ExternalSemaphoreFd::name()) {
+                Some(khr::ExternalSemaphoreFd::new(
+                    &self.instance.raw,
+                    &raw_device,
+                ))
+            } else {
+                None
+            };
+
         let naga_options = {
             use naga::back::spv;
             let capabilities = [
@@ -840,7 +887,7 @@ impl super::Adapter {
             }
         };
 
-        log::info!("Private capabilities: {:?}", self.private_caps);
+        log::info!("Private capabilities: {:?}", private_caps);
         let raw_queue = raw_device.get_device_queue(family_index, queue_index);
 
         let shared = Arc::new(super::DeviceShared {
@@ -849,13 +896,16 @@ impl super::Adapter {
             extension_fns: super::DeviceExtensionFunctions {
                 draw_indirect_count: indirect_count_fn,
                 timeline_semaphore: timeline_semaphore_fn,
+                external_memory_fd: external_memory_fd_fn,
+                external_semaphore_fd: external_semaphore_fd_fn,
             },
             vendor_id: self.phd_capabilities.properties.vendor_id,
             downlevel_flags: self.downlevel_flags,
-            private_caps: self.private_caps.clone(),
+            private_caps,
             _timestamp_period: self.phd_capabilities.properties.limits.timestamp_period,
             render_passes: Mutex::new(Default::default()),
             framebuffers: Mutex::new(Default::default()),
+            enabled_extensions: enabled_extensions.to_vec(),
         });
         let queue = super::Queue {
             raw: raw_queue,
@@ -866,6 +916,8 @@ impl super::Adapter {
                 .raw
                 .create_semaphore(&vk::SemaphoreCreateInfo::builder(), None)?,
             relay_active: false,
+            external_waits: Mutex::new(Vec::new()),
+            external_signals: Mutex::new(Vec::new()),
         };
 
         let mem_allocator = {
@@ -902,6 +954,7 @@ impl super::Adapter {
             mem_allocator: Mutex::new(mem_allocator),
             desc_allocator: Mutex::new(desc_allocator),
             valid_ash_memory_types,
+            device_local_memory_types,
             naga_options,
             #[cfg(feature = "renderdoc")]
             render_doc: Default::default(),
@@ -909,16 +962,46 @@ impl super::Adapter {
 
         Ok(crate::OpenDevice { device, queue })
     }
-}
 
-impl crate::Adapter<super::Api> for super::Adapter {
-    unsafe fn open(
+    /// Open a device like [`crate::Adapter::open`], enabling
+    /// the extensions and features of `extras` as well.
+    ///
+    /// # Safety
+    ///
+    /// - The extra extensions and features must not change the behavior the backend relies on.
+    /// - The structures chained by the features callback must be valid, must extend
+    ///   `VkDeviceCreateInfo`, and must not be of a type the backend chains already.
+    pub unsafe fn open_with_extras(
         &self,
         features: wgt::Features,
+        extras: super::DeviceExtras,
     ) -> Result<crate::OpenDevice<super::Api>, crate::DeviceError> {
-        let enabled_extensions = self.required_device_extensions(features);
+        let mut enabled_extensions = self.required_device_extensions(features);
+        for &extension in extras.extensions.iter() {
+            if enabled_extensions.contains(&extension) {
+                continue;
+            }
+            if self.phd_capabilities.supports_extension(extension) {
+                enabled_extensions.push(extension);
+            } else {
+                log::warn!("Missing extension: {:?}", extension);
+            }
+        }
         let mut enabled_phd_features = self.physical_device_features(&enabled_extensions, features);
 
+        // Only the core features are handed out, and the structures chained to them
+        // are spliced into the device creation chain, since they all extend it as well.
+        let mut extra_p_next = ptr::null_mut();
+        if let Some(callback) = extras.features_callback {
+            let mut features2 = vk::PhysicalDeviceFeatures2 {
+                features: enabled_phd_features.core,
+                ..Default::default()
+            };
+            callback(&mut features2);
+            enabled_phd_features.core = features2.features;
+            extra_p_next = features2.p_next;
+        }
+
         let family_index = 0; //TODO
         let family_info = vk::DeviceQueueCreateInfo::builder()
             .queue_family_index(family_index)
@@ -937,18 +1020,42 @@ impl crate::Adapter<super::Api> for super::Adapter {
         let pre_info = vk::DeviceCreateInfo::builder()
             .queue_create_infos(&family_infos)
             .enabled_extension_names(&str_pointers);
-        let info = enabled_phd_features
+        let mut info = enabled_phd_features
             .add_to_device_create_builder(pre_info)
             .build();
+        if !extra_p_next.is_null() {
+            let mut last = extra_p_next as *mut vk::BaseOutStructure;
+            while !(*last).p_next.is_null() {
+                last = (*last).p_next;
+            }
+            (*last).p_next = info.p_next as *mut vk::BaseOutStructure;
+            info.p_next = extra_p_next;
+        }
         let raw_device = self.instance.raw.create_device(self.raw, &info, None)?;
 
-        self.device_from_raw(
+        // The features callback may have disabled `multiDrawIndirect`,
+        // in which case the draws are split up like without it.
+        let private_caps = super::PrivateCapabilities {
+            multi_draw_indirect: enabled_phd_features.core.multi_draw_indirect != 0,
+            ..self.private_caps.clone()
+        };
+        self.device_from_raw_with_caps(
             raw_device,
             &enabled_extensions,
             family_info.queue_family_index,
             0,
+            private_caps,
         )
     }
+}
+
+impl crate::Adapter<super::Api> for super::Adapter {
+    unsafe fn open(
+        &self,
+        features: wgt::Features,
+    ) -> Result<crate::OpenDevice<super::Api>, crate::DeviceError> {
+        self.open_with_extras(features, super::DeviceExtras::default())
+    }
 
     unsafe fn texture_format_capabilities(
         &self,
@@ -606,13 +606,22 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         offset: wgt::BufferAddress,
         draw_count: u32,
     ) {
-        self.device.raw.cmd_draw_indirect(
-            self.active,
-            buffer.raw,
-            offset,
-            draw_count,
-            mem::size_of::<wgt::DrawIndirectArgs>() as u32,
-        );
+        let stride = mem::size_of::<wgt::DrawIndirectArgs>() as u32;
+        if self.device.private_caps.multi_draw_indirect {
+            self.device
+                .raw
+                .cmd_draw_indirect(self.active, buffer.raw, offset, draw_count, stride);
+        } else {
+            for draw in 0..draw_count {
+                self.device.raw.cmd_draw_indirect(
+                    self.active,
+                    buffer.raw,
+                    offset + draw as wgt::BufferAddress * stride as wgt::BufferAddress,
+                    1,
+                    stride,
+                );
+            }
+        }
     }
     unsafe fn draw_indexed_indirect(
         &mut self,
@@ -620,13 +629,26 @@ impl crate::CommandEncoder<super::Api> for super::CommandEncoder {
         offset: wgt::BufferAddress,
         draw_count: u32,
     ) {
-        self.device.raw.cmd_draw_indexed_indirect(
-            self.active,
-            buffer.raw,
-            offset,
-            draw_count,
-            mem::size_of::<wgt::DrawIndexedIndirectArgs>() as u32,
-        );
+        let stride = mem::size_of::<wgt::DrawIndexedIndirectArgs>() as u32;
+        if self.device.private_caps.multi_draw_indirect {
+            self.device.raw.cmd_draw_indexed_indirect(
+                self.active,
+                buffer.raw,
+                offset,
+                draw_count,
+                stride,
+            );
+        } else {
+            for draw in 0..draw_count {
+                self.device.raw.cmd_draw_indexed_indirect(
+                    self.active,
+                    buffer.raw,
+                    offset + draw as wgt::BufferAddress * stride as wgt::BufferAddress,
+                    1,
+                    stride,
+                );
+            }
+        }
     }
     unsafe fn draw_indirect_count(
         &mut self,
@@ -5,7 +5,16 @@ use ash::{extensions::khr, vk};
 use inplace_it::inplace_or_alloc_from_iter;
 use parking_lot::Mutex;
 
-use std::{borrow::Cow, collections::hash_map::Entry, ffi::CString, ptr, sync::Arc};
+use std::{
+    borrow::Cow,
+    collections::hash_map::Entry,
+    ffi::{CStr, CString},
+    ptr,
+    sync::Arc,
+};
+
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
 
 impl super::DeviceShared {
     pub(super) unsafe fn set_object_name(
@@ -532,6 +541,55 @@ impl super::Device {
         })
     }
 
+    /// Create the image of a texture, without any memory bound to it.
+    unsafe fn create_image(
+        &self,
+        desc: &crate::TextureDescriptor,
+        tiling: vk::ImageTiling,
+        external_handle_types: vk::ExternalMemoryHandleTypeFlags,
+    ) -> Result<(vk::Image, vk::ImageCreateFlags), crate::DeviceError> {
+        let (depth, array_layer_count) = match desc.dimension {
+            wgt::TextureDimension::D3 => (desc.size.depth_or_array_layers, 1),
+            _ => (1, desc.size.depth_or_array_layers),
+        };
+
+        let mut raw_flags = vk::ImageCreateFlags::empty();
+        if desc.dimension == wgt::TextureDimension::D2 && desc.size.depth_or_array_layers % 6 == 0 {
+            raw_flags |= vk::ImageCreateFlags::CUBE_COMPATIBLE;
+        }
+
+        let mut external_info =
+            vk::ExternalMemoryImageCreateInfo::builder().handle_types(external_handle_types);
+        let mut vk_info = vk::ImageCreateInfo::builder()
+            .flags(raw_flags)
+            .image_type(conv::map_texture_dimension(desc.dimension))
+            .format(self.shared.private_caps.map_texture_format(desc.format))
+            .extent(vk::Extent3D {
+                width: desc.size.width,
+                height: desc.size.height,
+                depth,
+            })
+            .mip_levels(desc.mip_level_count)
+            .array_layers(array_layer_count)
+            .samples(vk::SampleCountFlags::from_raw(desc.sample_count))
+            .tiling(tiling)
+            .usage(conv::map_texture_usage(desc.usage))
+            .sharing_mode(vk::SharingMode::EXCLUSIVE)
+            .initial_layout(vk::ImageLayout::UNDEFINED);
+
+        if !external_handle_types.is_empty() {
+            vk_info = vk_info.push_next(&mut external_info);
+        }
+
+        let raw = self.shared.raw.create_image(&vk_info, None)?;
+        Ok((raw, raw_flags))
+    }
+
+    /// The device extensions that are enabled.
+    pub fn enabled_extensions(&self) -> &[&'static CStr] {
+        &self.shared.enabled_extensions
+    }
+
     /// The image handle and its memory are not handled by wgpu-hal
     ///
     /// # Safety
@@ -552,8 +610,265 @@ impl super::Device {
             aspects: crate::FormatAspects::from(desc.format),
             format_info: desc.format.describe(),
             raw_flags: vk::ImageCreateFlags::empty(),
+            external_memory: None,
         }
     }
+
+    /// Create a texture with dedicated memory that can be shared with
+    /// other APIs and processes through [`super::Texture::export_fd`].
+    ///
+    /// # Panics
+    ///
+    /// - If `VK_KHR_external_memory_fd` is not enabled, or `VK_EXT_external_memory_dma_buf`
+    ///   isn't for [`super::ExternalMemoryType::DmaBuf`].
+    #[cfg(unix)]
+    pub unsafe fn create_exportable_texture(
+        &self,
+        desc: &crate::TextureDescriptor,
+        ty: super::ExternalMemoryType,
+    ) -> Result<super::Texture, crate::DeviceError> {
+        self.create_external_texture(desc, ty, None)
+    }
+
+    /// Create a texture on top of the memory exported through `fd`,
+    /// which is owned by the texture memory on success.
+    ///
+    /// The texture starts out in the `UNDEFINED` layout, like any other.
+    ///
+    /// # Safety
+    ///
+    /// - The memory must be exported for an image created with the same `desc` and `ty`
+    ///   on a device with the same driver and UUID.
+    ///
+    /// # Panics
+    ///
+    /// - If `VK_KHR_external_memory_fd` is not enabled, or `VK_EXT_external_memory_dma_buf`
+    ///   isn't for [`super::ExternalMemoryType::DmaBuf`].
+    #[cfg(unix)]
+    pub unsafe fn create_texture_from_external_memory(
+        &self,
+        desc: &crate::TextureDescriptor,
+        ty: super::ExternalMemoryType,
+        fd: RawFd,
+    ) -> Result<super::Texture, crate::DeviceError> {
+        self.create_external_texture(desc, ty, Some(fd))
+    }
+
+    #[cfg(unix)]
+    unsafe fn create_external_texture(
+        &self,
+        desc: &crate::TextureDescriptor,
+        ty: super::ExternalMemoryType,
+        import_fd: Option<RawFd>,
+    ) -> Result<super::Texture, crate::DeviceError> {
+        let functor = self
+            .shared
+            .extension_fns
+            .external_memory_fd
+            .as_ref()
+            .expect("VK_KHR_external_memory_fd is not enabled");
+        if ty == super::ExternalMemoryType::DmaBuf {
+            assert!(
+                self.shared
+                    .enabled_extensions
+                    .contains(&vk::ExtExternalMemoryDmaBufFn::name()),
+                "VK_EXT_external_memory_dma_buf is not enabled"
+            );
+        }
+        let handle_type = ty.to_vk();
+
+        // Without `VK_EXT_image_drm_format_modifier`, linear tiling is
+        // the only layout that can be agreed on with the other side of a dma-buf.
+        let tiling = match ty {
+            super::ExternalMemoryType::OpaqueFd => vk::ImageTiling::OPTIMAL,
+            super::ExternalMemoryType::DmaBuf => vk::ImageTiling::LINEAR,
+        };
+        let (raw, raw_flags) = self.create_image(desc, tiling, handle_type)?;
+
+        let allocate = || {
+            let req = self.shared.raw.get_image_memory_requirements(raw);
+            let mut memory_types = req.memory_type_bits & self.valid_ash_memory_types;
+            if let (super::ExternalMemoryType::DmaBuf, Some(fd)) = (ty, import_fd) {
+                memory_types &= functor
+                    .get_memory_fd_properties_khr(handle_type, fd)?
+                    .memory_type_bits;
+            }
+            // The lowest type is picked, so that both sides of an opaque fd pick the same.
+            let device_local_types = memory_types & self.device_local_memory_types;
+            if device_local_types != 0 {
+                memory_types = device_local_types;
+            }
+            if memory_types == 0 {
+                return Err(vk::Result::ERROR_INVALID_EXTERNAL_HANDLE);
+            }
+
+            let mut dedicated_info = vk::MemoryDedicatedAllocateInfo::builder().image(raw);
+            let mut export_info = vk::ExportMemoryAllocateInfo::builder().handle_types(handle_type);
+            let mut import_info;
+            let mut vk_info = vk::MemoryAllocateInfo::builder()
+                .allocation_size(req.size)
+                .memory_type_index(memory_types.trailing_zeros())
+                .push_next(&mut dedicated_info);
+            vk_info = match import_fd {
+                Some(fd) => {
+                    import_info = vk::ImportMemoryFdInfoKHR::builder()
+                        .handle_type(handle_type)
+                        .fd(fd);
+                    vk_info.push_next(&mut import_info)
+                }
+                None => vk_info.push_next(&mut export_info),
+            };
+
+            let memory = self.shared.raw.allocate_memory(&vk_info, None)?;
+            if let Err(error) = self.shared.raw.bind_image_memory(raw, memory, 0) {
+                self.shared.raw.free_memory(memory, None);
+                return Err(error);
+            }
+            Ok(memory)
+        };
+        let memory = match allocate() {
+            Ok(memory) => memory,
+            Err(error) => {
+                self.shared.raw.destroy_image(raw, None);
+                return Err(error.into());
+            }
+        };
+
+        if let Some(label) = desc.label {
+            self.shared
+                .set_object_name(vk::ObjectType::IMAGE, raw, label);
+        }
+
+        Ok(super::Texture {
+            raw,
+            drop_guard: None,
+            block: None,
+            usage: desc.usage,
+            aspects: crate::FormatAspects::from(desc.format),
+            format_info: desc.format.describe(),
+            raw_flags,
+            external_memory: Some(super::ExternalMemory { raw: memory, ty }),
+        })
+    }
+
+    /// Create a binary semaphore that can be shared with
+    /// other APIs and processes through [`super::ExternalSemaphore::export_fd`].
+    ///
+    /// # Panics
+    ///
+    /// - If `VK_KHR_external_semaphore_fd` is not enabled.
+    #[cfg(unix)]
+    pub unsafe fn create_exportable_semaphore(
+        &self,
+        ty: super::ExternalSemaphoreType,
+    ) -> Result<super::ExternalSemaphore, crate::DeviceError> {
+        assert!(
+            self.shared.extension_fns.external_semaphore_fd.is_some(),
+            "VK_KHR_external_semaphore_fd is not enabled"
+        );
+        let mut export_info = vk::ExportSemaphoreCreateInfo::builder().handle_types(ty.to_vk());
+        let vk_info = vk::SemaphoreCreateInfo::builder().push_next(&mut export_info);
+        let raw = self.shared.raw.create_semaphore(&vk_info, None)?;
+        Ok(super::ExternalSemaphore { raw, ty })
+    }
+
+    /// Create a binary semaphore out of the payload exported through `fd`,
+    /// which is owned by the semaphore on success.
+    ///
+    /// # Panics
+    ///
+    /// - If `VK_KHR_external_semaphore_fd` is not enabled.
+    #[cfg(unix)]
+    pub unsafe fn import_semaphore_fd(
+        &self,
+        ty: super::ExternalSemaphoreType,
+        fd: RawFd,
+    ) -> Result<super::ExternalSemaphore, crate::DeviceError> {
+        let functor = self
+            .shared
+            .extension_fns
+            .external_semaphore_fd
+            .as_ref()
+            .expect("VK_KHR_external_semaphore_fd is not enabled");
+        let raw = self
+            .shared
+            .raw
+            .create_semaphore(&vk::SemaphoreCreateInfo::builder(), None)?;
+        // Sync files can only be imported temporarily.
+        let flags = match ty {
+            super::ExternalSemaphoreType::OpaqueFd => vk::SemaphoreImportFlags::empty(),
+            super::ExternalSemaphoreType::SyncFd => vk::SemaphoreImportFlags::TEMPORARY,
+        };
+        let vk_info = vk::ImportSemaphoreFdInfoKHR::builder()
+            .semaphore(raw)
+            .flags(flags)
+            .handle_type(ty.to_vk())
+            .fd(fd);
+        if let Err(error) = functor.import_semaphore_fd(&vk_info) {
+            self.shared.raw.destroy_semaphore(raw, None);
+            return Err(error.into());
+        }
+        Ok(super::ExternalSemaphore { raw, ty })
+    }
+
+    /// # Safety
+    ///
+    /// - The semaphore must not be in use by any pending submission.
+    pub unsafe fn destroy_external_semaphore(&self, semaphore: super::ExternalSemaphore) {
+        self.shared.raw.destroy_semaphore(semaphore.raw, None);
+    }
+}
+
+#[cfg(unix)]
+impl super::Texture {
+    /// Export a new file descriptor referring to the memory of this texture,
+    /// which the caller takes ownership of.
+    ///
+    /// # Safety
+    ///
+    /// - The texture must be created by [`super::Device::create_exportable_texture`]
+    ///   on `device`.
+    pub unsafe fn export_fd(&self, device: &super::Device) -> Result<RawFd, crate::DeviceError> {
+        let memory = self
+            .external_memory
+            .as_ref()
+            .expect("Texture memory is not external");
+        let vk_info = vk::MemoryGetFdInfoKHR::builder()
+            .memory(memory.raw)
+            .handle_type(memory.ty.to_vk());
+        let functor = device
+            .shared
+            .extension_fns
+            .external_memory_fd
+            .as_ref()
+            .unwrap();
+        Ok(functor.get_memory_fd(&vk_info)?)
+    }
+}
+
+#[cfg(unix)]
+impl super::ExternalSemaphore {
+    /// Export a new file descriptor referring to the payload of this semaphore,
+    /// which the caller takes ownership of.
+    ///
+    /// # Safety
+    ///
+    /// - The semaphore must be created by [`super::Device::create_exportable_semaphore`]
+    ///   on `device`.
+    /// - For [`super::ExternalSemaphoreType::SyncFd`], the semaphore must be signalled,
+    ///   or have a signal operation pending, and exporting it unsignals it.
+    pub unsafe fn export_fd(&self, device: &super::Device) -> Result<RawFd, crate::DeviceError> {
+        let vk_info = vk::SemaphoreGetFdInfoKHR::builder()
+            .semaphore(self.raw)
+            .handle_type(self.ty.to_vk());
+        let functor = device
+            .shared
+            .extension_fns
+            .external_semaphore_fd
+            .as_ref()
+            .unwrap();
+        Ok(functor.get_semaphore_fd(&vk_info)?)
+    }
 }
 
 impl crate::Device<super::Api> for super::Device {
@@ -676,34 +991,11 @@ impl crate::Device<super::Api> for super::Device {
         &self,
         desc: &crate::TextureDescriptor,
     ) -> Result<super::Texture, crate::DeviceError> {
-        let (depth, array_layer_count) = match desc.dimension {
-            wgt::TextureDimension::D3 => (desc.size.depth_or_array_layers, 1),
-            _ => (1, desc.size.depth_or_array_layers),
-        };
-
-        let mut raw_flags = vk::ImageCreateFlags::empty();
-        if desc.dimension == wgt::TextureDimension::D2 && desc.size.depth_or_array_layers % 6 == 0 {
-            raw_flags |= vk::ImageCreateFlags::CUBE_COMPATIBLE;
-        }
-
-        let vk_info = vk::ImageCreateInfo::builder()
-            .flags(raw_flags)
-            .image_type(conv::map_texture_dimension(desc.dimension))
-            .format(self.shared.private_caps.map_texture_format(desc.format))
-            .extent(vk::Extent3D {
-                width: desc.size.width,
-                height: desc.size.height,
-                depth,
-            })
-            .mip_levels(desc.mip_level_count)
-            .array_layers(array_layer_count)
-            .samples(vk::SampleCountFlags::from_raw(desc.sample_count))
-            .tiling(vk::ImageTiling::OPTIMAL)
-            .usage(conv::map_texture_usage(desc.usage))
-            .sharing_mode(vk::SharingMode::EXCLUSIVE)
-            .initial_layout(vk::ImageLayout::UNDEFINED);
-
-        let raw = self.shared.raw.create_image(&vk_info, None)?;
+        let (raw, raw_flags) = self.create_image(
+            desc,
+            vk::ImageTiling::OPTIMAL,
+            vk::ExternalMemoryHandleTypeFlags::empty(),
+        )?;
         let req = self.shared.raw.get_image_memory_requirements(raw);
 
         let block = self.mem_allocator.lock().alloc(
@@ -733,6 +1025,7 @@ impl crate::Device<super::Api> for super::Device {
             aspects: crate::FormatAspects::from(desc.format),
             format_info: desc.format.describe(),
             raw_flags,
+            external_memory: None,
         })
     }
     unsafe fn destroy_texture(&self, texture: super::Texture) {
@@ -742,6 +1035,9 @@ impl crate::Device<super::Api> for super::Device {
         if let Some(block) = texture.block {
             self.mem_allocator.lock().dealloc(&*self.shared, block);
         }
+        if let Some(memory) = texture.external_memory {
+            self.shared.raw.free_memory(memory.raw, None);
+        }
     }
 
     unsafe fn create_texture_view(
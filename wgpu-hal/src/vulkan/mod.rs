@@ -31,7 +31,7 @@ mod conv;
 mod device;
 mod instance;
 
-use std::{borrow::Borrow, ffi::CStr, sync::Arc};
+use std::{borrow::Borrow, ffi::CStr, fmt, mem, sync::Arc};
 
 use arrayvec::ArrayVec;
 use ash::{
@@ -45,6 +45,85 @@ const MAX_TOTAL_ATTACHMENTS: usize = crate::MAX_COLOR_TARGETS * 2 + 1;
 
 pub type DropGuard = Box<dyn std::any::Any + Send + Sync>;
 
+/// Layers and extensions to enable on the instance,
+/// on top of the ones the backend needs.
+///
+/// The ones that are not available are skipped with a warning,
+/// so check [`Instance::extensions`] for what was actually enabled.
+#[derive(Clone, Debug, Default)]
+pub struct InstanceExtras {
+    pub layers: Vec<&'static CStr>,
+    pub extensions: Vec<&'static CStr>,
+}
+
+/// Called with the core features about to be enabled, to enable more of
+/// them, or to chain more feature structures through `p_next`.
+pub type FeaturesCallback<'a> = Box<dyn FnOnce(&mut vk::PhysicalDeviceFeatures2) + 'a>;
+
+/// Extensions and features to enable on the device,
+/// on top of the ones the backend needs.
+///
+/// The extensions that are not supported are skipped with a warning,
+/// so check [`Device::enabled_extensions`] for what was actually enabled.
+#[derive(Default)]
+pub struct DeviceExtras<'a> {
+    pub extensions: Vec<&'static CStr>,
+    pub features_callback: Option<FeaturesCallback<'a>>,
+}
+
+impl fmt::Debug for DeviceExtras<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DeviceExtras")
+            .field("extensions", &self.extensions)
+            .field("features_callback", &self.features_callback.is_some())
+            .finish()
+    }
+}
+
+/// Kind of file descriptor that texture memory is shared through.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExternalMemoryType {
+    /// Opaque handle, only meaningful to the same driver and device.
+    /// Requires `VK_KHR_external_memory_fd`.
+    OpaqueFd,
+    /// Linux dma-buf, shareable with other APIs and processes.
+    /// Requires `VK_EXT_external_memory_dma_buf` as well.
+    ///
+    /// Images sharing memory this way have linear tiling.
+    DmaBuf,
+}
+
+#[cfg(unix)]
+impl ExternalMemoryType {
+    fn to_vk(self) -> vk::ExternalMemoryHandleTypeFlags {
+        match self {
+            Self::OpaqueFd => vk::ExternalMemoryHandleTypeFlags::OPAQUE_FD,
+            Self::DmaBuf => vk::ExternalMemoryHandleTypeFlags::DMA_BUF_EXT,
+        }
+    }
+}
+
+/// Kind of file descriptor that a semaphore is shared through.
+/// Requires `VK_KHR_external_semaphore_fd`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ExternalSemaphoreType {
+    /// Opaque handle, only meaningful to the same driver and device.
+    OpaqueFd,
+    /// Linux sync file, as used by the implicit synchronization of dma-bufs.
+    /// Its payload is consumed by the first wait on it.
+    SyncFd,
+}
+
+#[cfg(unix)]
+impl ExternalSemaphoreType {
+    fn to_vk(self) -> vk::ExternalSemaphoreHandleTypeFlags {
+        match self {
+            Self::OpaqueFd => vk::ExternalSemaphoreHandleTypeFlags::OPAQUE_FD,
+            Self::SyncFd => vk::ExternalSemaphoreHandleTypeFlags::SYNC_FD,
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Api;
 
@@ -143,6 +222,8 @@ enum ExtensionFn<T> {
 struct DeviceExtensionFunctions {
     draw_indirect_count: Option<ExtensionFn<khr::DrawIndirectCount>>,
     timeline_semaphore: Option<ExtensionFn<khr::TimelineSemaphore>>,
+    external_memory_fd: Option<khr::ExternalMemoryFd>,
+    external_semaphore_fd: Option<khr::ExternalSemaphoreFd>,
 }
 
 /// Set of internal capabilities, which don't show up in the exposed
@@ -159,6 +240,8 @@ struct PrivateCapabilities {
     texture_d24: bool,
     texture_d24_s8: bool,
     non_coherent_map_mask: wgt::BufferAddress,
+    /// Multiple draws per indirect command. Without it, multi-draws are split into single draws.
+    multi_draw_indirect: bool,
 }
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -224,6 +307,7 @@ struct DeviceShared {
     private_caps: PrivateCapabilities,
     render_passes: Mutex<fxhash::FxHashMap<RenderPassKey, vk::RenderPass>>,
     framebuffers: Mutex<fxhash::FxHashMap<FramebufferKey, vk::Framebuffer>>,
+    enabled_extensions: Vec<&'static CStr>,
 }
 
 pub struct Device {
@@ -232,6 +316,7 @@ pub struct Device {
     desc_allocator:
         Mutex<gpu_descriptor::DescriptorAllocator<vk::DescriptorPool, vk::DescriptorSet>>,
     valid_ash_memory_types: u32,
+    device_local_memory_types: u32,
     naga_options: naga::back::spv::Options,
     #[cfg(feature = "renderdoc")]
     render_doc: crate::auxil::renderdoc::RenderDoc,
@@ -248,6 +333,9 @@ pub struct Queue {
     /// by last submission and waited by the present.
     relay_semaphore: vk::Semaphore,
     relay_active: bool,
+    /// External semaphores to wait on and to signal by the next submission.
+    external_waits: Mutex<Vec<vk::Semaphore>>,
+    external_signals: Mutex<Vec<vk::Semaphore>>,
 }
 
 #[derive(Debug)]
@@ -265,6 +353,22 @@ pub struct Texture {
     aspects: crate::FormatAspects,
     format_info: wgt::TextureFormatInfo,
     raw_flags: vk::ImageCreateFlags,
+    external_memory: Option<ExternalMemory>,
+}
+
+/// Dedicated memory of a texture that is shared with the outside.
+#[derive(Debug)]
+struct ExternalMemory {
+    raw: vk::DeviceMemory,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    ty: ExternalMemoryType,
+}
+
+#[derive(Debug)]
+pub struct ExternalSemaphore {
+    raw: vk::Semaphore,
+    #[cfg_attr(not(unix), allow(dead_code))]
+    ty: ExternalSemaphoreType,
 }
 
 #[derive(Debug)]
@@ -438,6 +542,30 @@ impl Fence {
     }
 }
 
+impl Queue {
+    /// Make the next submission wait on `semaphore` before executing.
+    ///
+    /// # Safety
+    ///
+    /// - `semaphore` must be signalled, or have a signal operation pending,
+    ///   by the time of the next submission
+    /// - `semaphore` must outlive the wait
+    pub unsafe fn wait_external_semaphore(&self, semaphore: &ExternalSemaphore) {
+        self.external_waits.lock().push(semaphore.raw);
+    }
+
+    /// Make the next submission signal `semaphore` once it's done executing.
+    ///
+    /// # Safety
+    ///
+    /// - `semaphore` must not be signalled, nor have a signal operation pending,
+    ///   by the time of the next submission
+    /// - `semaphore` must outlive the signal
+    pub unsafe fn signal_external_semaphore(&self, semaphore: &ExternalSemaphore) {
+        self.external_signals.lock().push(semaphore.raw);
+    }
+}
+
 impl crate::Queue<Api> for Queue {
     unsafe fn submit(
         &mut self,
@@ -453,18 +581,17 @@ impl crate::Queue<Api> for Queue {
 
         let mut fence_raw = vk::Fence::null();
         let mut vk_timeline_info;
-        let mut semaphores = [self.relay_semaphore, vk::Semaphore::null()];
-        let signal_values;
+        let mut signal_semaphores = vec![self.relay_semaphore];
+        let mut signal_values = vec![!0];
+        let mut has_timeline = false;
 
         if let Some((fence, value)) = signal_fence {
             fence.maintain(&self.device.raw)?;
             match *fence {
                 Fence::TimelineSemaphore(raw) => {
-                    signal_values = [!0, value];
-                    semaphores[1] = raw;
-                    vk_timeline_info = vk::TimelineSemaphoreSubmitInfo::builder()
-                        .signal_semaphore_values(&signal_values);
-                    vk_info = vk_info.push_next(&mut vk_timeline_info);
+                    signal_semaphores.push(raw);
+                    signal_values.push(value);
+                    has_timeline = true;
                 }
                 Fence::FencePool {
                     ref mut active,
@@ -483,19 +610,27 @@ impl crate::Queue<Api> for Queue {
             }
         }
 
-        let wait_stage_mask = [vk::PipelineStageFlags::TOP_OF_PIPE];
+        signal_semaphores.append(&mut self.external_signals.lock());
+        if has_timeline {
+            // The values of binary semaphores are ignored, but they still need one.
+            signal_values.resize(signal_semaphores.len(), !0);
+            vk_timeline_info =
+                vk::TimelineSemaphoreSubmitInfo::builder().signal_semaphore_values(&signal_values);
+            vk_info = vk_info.push_next(&mut vk_timeline_info);
+        }
+
+        let mut wait_semaphores = mem::take(&mut *self.external_waits.lock());
+        let mut wait_stage_masks =
+            vec![vk::PipelineStageFlags::ALL_COMMANDS; wait_semaphores.len()];
         if self.relay_active {
-            vk_info = vk_info
-                .wait_semaphores(&semaphores[..1])
-                .wait_dst_stage_mask(&wait_stage_mask);
+            wait_semaphores.push(self.relay_semaphore);
+            wait_stage_masks.push(vk::PipelineStageFlags::TOP_OF_PIPE);
         }
         self.relay_active = true;
-        let signal_count = if semaphores[1] == vk::Semaphore::null() {
-            1
-        } else {
-            2
-        };
-        vk_info = vk_info.signal_semaphores(&semaphores[..signal_count]);
+        vk_info = vk_info
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_stage_masks)
+            .signal_semaphores(&signal_semaphores);
 
         self.device
             .raw
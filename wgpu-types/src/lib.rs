@@ -15,7 +15,12 @@
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
-use std::{num::NonZeroU32, ops::Range};
+use std::{
+    num::NonZeroU32,
+    ops::{Range, RangeInclusive},
+};
+
+pub mod math;
 
 /// Integral type used for buffer offsets.
 pub type BufferAddress = u64;
@@ -205,6 +210,7 @@ bitflags::bitflags! {
         /// Supported Platforms:
         /// - Vulkan (works)
         /// - DX12 (works)
+        /// - OpenGL (with `GL_ARB_timer_query` or `GL_EXT_disjoint_timer_query`)
         ///
         /// This is a web and native feature.
         const TIMESTAMP_QUERY = 1 << 2;
@@ -220,6 +226,7 @@ bitflags::bitflags! {
         /// Supported Platforms:
         /// - Vulkan (works)
         /// - DX12 (works)
+        /// - OpenGL (with `GL_ARB_pipeline_statistics_query`)
         ///
         /// This is a web and native feature.
         const PIPELINE_STATISTICS_QUERY = 1 << 3;
@@ -351,7 +358,9 @@ bitflags::bitflags! {
         ///
         /// Supported platforms:
         /// - DX12
-        /// - Vulkan
+        /// - Vulkan (emulated with single draws without `multiDrawIndirect`)
+        /// - Metal (emulated with single draws)
+        /// - OpenGL (emulated with single draws)
         ///
         /// This is a native only feature.
         const MULTI_DRAW_INDIRECT = 1 << 23;
@@ -853,6 +862,20 @@ impl<L> DeviceDescriptor<L> {
     }
 }
 
+/// Reason for a [`Device`] to be lost.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum DeviceLostReason {
+    /// The device was lost by the backend, e.g. after a driver crash or a GPU reset.
+    Unknown = 0,
+    /// The device was dropped by the user.
+    Dropped = 1,
+    /// The callback was replaced by another one before the device was lost.
+    ReplacedCallback = 2,
+}
+
 bitflags::bitflags! {
     /// Describes the shader stages that a binding will be visible from.
     ///
@@ -2342,6 +2365,49 @@ pub enum PresentMode {
     Fifo = 2,
 }
 
+/// Specifies how the alpha channel of the surface textures is handled
+/// when they are composited with the rest of the screen.
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub enum CompositeAlphaMode {
+    /// The alpha channel, if it exists, is ignored, and the textures are treated
+    /// as if they had a constant alpha of 1.0.
+    Opaque = 0,
+    /// The alpha channel is respected. The color channels are expected to be
+    /// already multiplied by the alpha channel by the application.
+    PreMultiplied = 1,
+    /// The alpha channel is respected. The color channels are multiplied by
+    /// the alpha channel by the compositor.
+    PostMultiplied = 2,
+}
+
+impl Default for CompositeAlphaMode {
+    fn default() -> Self {
+        Self::Opaque
+    }
+}
+
+/// Capabilities of a [`Surface`] when used with a specific adapter.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "trace", derive(Serialize))]
+#[cfg_attr(feature = "replay", derive(Deserialize))]
+pub struct SurfaceCapabilities {
+    /// Supported formats of the surface textures. Never empty.
+    pub formats: Vec<TextureFormat>,
+    /// Current extent of the surface, if known.
+    pub current_extent: Option<Extent3d>,
+    /// Range of the supported extents.
+    pub extents: RangeInclusive<Extent3d>,
+    /// Supported usages of the surface textures. Always contains `RENDER_ATTACHMENT`.
+    pub usages: TextureUsages,
+    /// Supported presentation modes. Never empty.
+    pub present_modes: Vec<PresentMode>,
+    /// Supported alpha composition modes. Never empty.
+    pub composite_alpha_modes: Vec<CompositeAlphaMode>,
+}
+
 bitflags::bitflags! {
     /// Different ways that you can use a texture.
     ///
@@ -2384,6 +2450,10 @@ pub struct SurfaceConfiguration {
     /// Presentation mode of the swap chain. FIFO is the only guaranteed to be supported, though
     /// other formats will automatically fall back to FIFO.
     pub present_mode: PresentMode,
+    /// Alpha composition mode of the swap chain. Must be one of the
+    /// `SurfaceCapabilities::composite_alpha_modes`. Only `Opaque` is guaranteed to be supported.
+    #[cfg_attr(feature = "replay", serde(default))]
+    pub composite_alpha_mode: CompositeAlphaMode,
 }
 
 /// Status of the recieved surface image.
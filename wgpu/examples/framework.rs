@@ -148,7 +148,10 @@ async fn setup<E: Example>(title: &str) -> Setup {
                 features: (optional_features & adapter_features) | required_features,
                 limits: needed_limits,
             },
-            trace_dir.ok().as_ref().map(std::path::Path::new),
+            trace_dir
+                .ok()
+                .as_ref()
+                .map(|dir| std::path::Path::new(dir).into()),
         )
         .await
         .expect("Unable to find a suitable GPU adapter!");
@@ -184,6 +187,7 @@ fn start<E: Example>(
         width: size.width,
         height: size.height,
         present_mode: wgpu::PresentMode::Mailbox,
+        composite_alpha_mode: wgpu::CompositeAlphaMode::Opaque,
     };
     surface.configure(&device, &config);
 
@@ -440,6 +444,7 @@ pub fn test<E: Example>(mut params: FrameworkRefTest) {
                     width: params.width,
                     height: params.height,
                     present_mode: wgpu::PresentMode::Fifo,
+                    composite_alpha_mode: wgpu::CompositeAlphaMode::Opaque,
                 },
                 &ctx.adapter,
                 &ctx.device,
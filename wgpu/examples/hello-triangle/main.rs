@@ -70,6 +70,7 @@ async fn run(event_loop: EventLoop<()>, window: Window) {
         width: size.width,
         height: size.height,
         present_mode: wgpu::PresentMode::Mailbox,
+        composite_alpha_mode: wgpu::CompositeAlphaMode::Opaque,
     };
 
     surface.configure(&device, &config);
@@ -35,6 +35,7 @@ impl ViewportDesc {
             width: size.width,
             height: size.height,
             present_mode: wgpu::PresentMode::Fifo,
+            composite_alpha_mode: wgpu::CompositeAlphaMode::Opaque,
         };
 
         self.surface.configure(device, &config);
@@ -18,10 +18,22 @@ use std::{
     marker::PhantomData,
     ops::Range,
     slice,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
 };
 
 const LABEL: &str = "label";
+/// How long the background poller blocks at most before checking if it has to stop.
+const POLLER_TIMEOUT: Duration = Duration::from_millis(100);
+/// How long the background poller sleeps between checks of a pending submission.
+///
+/// The fence is only checked without blocking, since waiting on it would keep
+/// the device locked, and stall the submissions in the meantime.
+const POLLER_INTERVAL: Duration = Duration::from_millis(1);
 
 pub struct Context(wgc::hub::Global<wgc::hub::IdentityManagerFactory>);
 
@@ -46,6 +58,19 @@ impl Context {
         ))
     }
 
+    #[cfg(any(windows, all(unix, not(target_os = "ios"), not(target_os = "macos"))))]
+    pub fn new_with_vulkan_extras(
+        backends: wgt::Backends,
+        vulkan_extras: &hal::vulkan::InstanceExtras,
+    ) -> Self {
+        Self(wgc::hub::Global::new_with_vulkan_extras(
+            "wgpu",
+            wgc::hub::IdentityManagerFactory,
+            backends,
+            vulkan_extras,
+        ))
+    }
+
     pub(crate) fn global(&self) -> &wgc::hub::Global<wgc::hub::IdentityManagerFactory> {
         &self.0
     }
@@ -69,25 +94,112 @@ impl Context {
         adapter: &wgc::id::AdapterId,
         hal_device: hal::OpenDevice<A>,
         desc: &crate::DeviceDescriptor,
-        trace_dir: Option<&std::path::Path>,
-    ) -> Result<(Device, wgc::id::QueueId), crate::RequestDeviceError> {
+        trace: Option<crate::DeviceTrace<'_>>,
+    ) -> Result<(Device, Queue), crate::RequestDeviceError> {
         let global = &self.0;
         let (device_id, error) = global.create_device_from_hal(
             *adapter,
             hal_device,
             &desc.map_label(|l| l.map(Borrowed)),
-            trace_dir,
+            trace.map(map_device_trace),
             PhantomData,
         );
         if let Some(err) = error {
             self.handle_error_fatal(err, "Adapter::create_device_from_hal");
         }
+        let error_sink = Arc::new(Mutex::new(ErrorSinkRaw::new()));
+        let device = Device {
+            id: device_id,
+            error_sink: Arc::clone(&error_sink),
+            features: desc.features,
+            poller: Mutex::new(None),
+        };
+        let queue = Queue {
+            id: device_id,
+            error_sink,
+        };
+        Ok((device, queue))
+    }
+
+    pub unsafe fn create_buffer_from_hal<A: wgc::hub::HalApi>(
+        &self,
+        hal_buffer: A::Buffer,
+        device: &Device,
+        desc: &crate::BufferDescriptor,
+        owned: bool,
+    ) -> Buffer {
+        let global = &self.0;
+        let (id, error) = global.create_buffer_from_hal::<A>(
+            hal_buffer,
+            device.id,
+            &desc.map_label(|l| l.map(Borrowed)),
+            owned,
+            PhantomData,
+        );
+        if let Some(cause) = error {
+            self.handle_error(
+                &device.error_sink,
+                cause,
+                LABEL,
+                desc.label,
+                "Device::create_buffer_from_hal",
+            );
+        }
+        Buffer {
+            id,
+            error_sink: Arc::clone(&device.error_sink),
+        }
+    }
+
+    #[cfg(any(windows, all(unix, not(target_os = "ios"), not(target_os = "macos"))))]
+    pub unsafe fn adapter_request_device_with_vulkan_extras(
+        &self,
+        adapter: &wgc::id::AdapterId,
+        desc: &crate::DeviceDescriptor,
+        vulkan_extras: hal::vulkan::DeviceExtras,
+        trace: Option<crate::DeviceTrace<'_>>,
+    ) -> Result<(Device, Queue), crate::RequestDeviceError> {
+        let global = &self.0;
+        let (device_id, error) = if adapter.backend() == wgt::Backend::Vulkan {
+            global.adapter_request_device_with_vulkan_extras(
+                *adapter,
+                &desc.map_label(|l| l.map(Borrowed)),
+                vulkan_extras,
+                trace.map(map_device_trace),
+                PhantomData,
+            )
+        } else {
+            wgc::gfx_select!(*adapter => global.adapter_request_device(
+                *adapter,
+                &desc.map_label(|l| l.map(Borrowed)),
+                trace.map(map_device_trace),
+                PhantomData
+            ))
+        };
+        Self::requested_device(device_id, error, desc)
+    }
+
+    fn requested_device(
+        device_id: wgc::id::DeviceId,
+        error: Option<wgc::instance::RequestDeviceError>,
+        desc: &crate::DeviceDescriptor,
+    ) -> Result<(Device, Queue), crate::RequestDeviceError> {
+        if let Some(err) = error {
+            log::error!("Error in Adapter::request_device: {}", err);
+            return Err(crate::RequestDeviceError);
+        }
+        let error_sink = Arc::new(Mutex::new(ErrorSinkRaw::new()));
         let device = Device {
             id: device_id,
-            error_sink: Arc::new(Mutex::new(ErrorSinkRaw::new())),
+            error_sink: Arc::clone(&error_sink),
             features: desc.features,
+            poller: Mutex::new(None),
+        };
+        let queue = Queue {
+            id: device_id,
+            error_sink,
         };
-        Ok((device, device_id))
+        Ok((device, queue))
     }
 
     pub unsafe fn create_texture_from_hal<A: wgc::hub::HalApi>(
@@ -118,6 +230,90 @@ impl Context {
         }
     }
 
+    pub unsafe fn adapter_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Adapter>) -> R, R>(
+        &self,
+        adapter: wgc::id::AdapterId,
+        hal_adapter_callback: F,
+    ) -> R {
+        self.0
+            .adapter_as_hal::<A, F, R>(adapter, hal_adapter_callback)
+    }
+
+    pub unsafe fn device_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Device>) -> R, R>(
+        &self,
+        device: &Device,
+        hal_device_callback: F,
+    ) -> R {
+        self.0
+            .device_as_hal::<A, F, R>(device.id, hal_device_callback)
+    }
+
+    pub unsafe fn queue_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Queue>) -> R, R>(
+        &self,
+        queue: &Queue,
+        hal_queue_callback: F,
+    ) -> R {
+        self.0.queue_as_hal::<A, F, R>(queue.id, hal_queue_callback)
+    }
+
+    pub unsafe fn buffer_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Buffer>) -> R, R>(
+        &self,
+        buffer: &Buffer,
+        hal_buffer_callback: F,
+    ) -> R {
+        self.0
+            .buffer_as_hal::<A, F, R>(buffer.id, hal_buffer_callback)
+    }
+
+    pub unsafe fn texture_as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Texture>) -> R, R>(
+        &self,
+        texture: &Texture,
+        hal_texture_callback: F,
+    ) -> R {
+        self.0
+            .texture_as_hal::<A, F, R>(texture.id, hal_texture_callback)
+    }
+
+    pub unsafe fn texture_view_as_hal<
+        A: wgc::hub::HalApi,
+        F: FnOnce(Option<&A::TextureView>) -> R,
+        R,
+    >(
+        &self,
+        texture_view: wgc::id::TextureViewId,
+        hal_texture_view_callback: F,
+    ) -> R {
+        self.0
+            .texture_view_as_hal::<A, F, R>(texture_view, hal_texture_view_callback)
+    }
+
+    pub unsafe fn command_encoder_as_hal_mut<
+        A: wgc::hub::HalApi,
+        F: FnOnce(Option<&mut A::CommandEncoder>) -> R,
+        R,
+    >(
+        &self,
+        encoder: &CommandEncoder,
+        buffers: &[(&crate::Buffer, hal::BufferUses)],
+        textures: &[(&crate::Texture, hal::TextureUses)],
+        hal_command_encoder_callback: F,
+    ) -> R {
+        let buffers = buffers
+            .iter()
+            .map(|&(buffer, usage)| (buffer.id.id, usage))
+            .collect::<Vec<_>>();
+        let textures = textures
+            .iter()
+            .map(|&(texture, usage)| (texture.id.id, usage))
+            .collect::<Vec<_>>();
+        self.0.command_encoder_as_hal_mut::<A, F, R>(
+            encoder.id,
+            &buffers,
+            &textures,
+            hal_command_encoder_callback,
+        )
+    }
+
     pub fn generate_report(&self) -> wgc::hub::GlobalReport {
         self.0.generate_report()
     }
@@ -152,10 +348,9 @@ impl Context {
         let sink = sink_mutex.lock();
         let mut source_opt: Option<&(dyn Error + 'static)> = Some(&error);
         while let Some(source) = source_opt {
-            if let Some(wgc::device::DeviceError::OutOfMemory) =
-                source.downcast_ref::<wgc::device::DeviceError>()
-            {
-                return sink.handle_error(crate::Error::OutOfMemoryError {
+            if is_out_of_memory(source) {
+                return sink.handle_error(crate::Error::OutOfMemory {
+                    description: self.format_error("Out of Memory", &error),
                     source: Box::new(error),
                 });
             }
@@ -164,7 +359,7 @@ impl Context {
 
         // Otherwise, it is a validation error
         sink.handle_error(crate::Error::ValidationError {
-            description: self.format_error(&error),
+            description: self.format_error("Validation Error", &error),
             source: Box::new(error),
         });
     }
@@ -186,7 +381,7 @@ impl Context {
         panic!("Error in {}: {}", string, cause);
     }
 
-    fn format_error(&self, err: &(impl Error + 'static)) -> String {
+    fn format_error(&self, kind: &str, err: &(impl Error + 'static)) -> String {
         let global = self.global();
         let mut err_descs = vec![];
 
@@ -202,10 +397,37 @@ impl Context {
             source_opt = source.source();
         }
 
-        format!("Validation Error\n\nCaused by:\n{}", err_descs.join(""))
+        format!("{}\n\nCaused by:\n{}", kind, err_descs.join(""))
     }
 }
 
+/// Checks whether an error in the chain reports running out of memory.
+///
+/// The creation and queue errors wrap `DeviceError` transparently,
+/// so walking `source()` alone skips it and they have to be matched here.
+fn is_out_of_memory(error: &(dyn Error + 'static)) -> bool {
+    use wgc::device::{
+        queue::{QueueSubmitError, QueueWriteError},
+        DeviceError,
+    };
+    use wgc::resource::{CreateBufferError, CreateTextureError};
+
+    let device_error = if let Some(e) = error.downcast_ref::<DeviceError>() {
+        e
+    } else if let Some(CreateBufferError::Device(e)) = error.downcast_ref() {
+        e
+    } else if let Some(CreateTextureError::Device(e)) = error.downcast_ref() {
+        e
+    } else if let Some(QueueWriteError::Queue(e)) = error.downcast_ref() {
+        e
+    } else if let Some(QueueSubmitError::Queue(e)) = error.downcast_ref() {
+        e
+    } else {
+        return false;
+    };
+    matches!(device_error, DeviceError::OutOfMemory)
+}
+
 mod pass_impl {
     use super::Context;
     use smallvec::SmallVec;
@@ -620,6 +842,29 @@ mod pass_impl {
     }
 }
 
+fn map_trace_format(format: crate::TraceFormat) -> wgc::device::TraceFormat {
+    match format {
+        crate::TraceFormat::Ron => wgc::device::TraceFormat::Ron,
+        crate::TraceFormat::Binary => wgc::device::TraceFormat::Binary,
+    }
+}
+
+fn map_trace_descriptor(desc: crate::TraceDescriptor) -> wgc::device::TraceDescriptor {
+    wgc::device::TraceDescriptor {
+        path: desc.path,
+        format: map_trace_format(desc.format),
+    }
+}
+
+fn map_device_trace(trace: crate::DeviceTrace) -> wgc::device::DeviceTrace {
+    match trace {
+        crate::DeviceTrace::Record(desc) => {
+            wgc::device::DeviceTrace::Record(map_trace_descriptor(desc))
+        }
+        crate::DeviceTrace::OnDemand => wgc::device::DeviceTrace::OnDemand,
+    }
+}
+
 fn map_buffer_copy_view(view: crate::ImageCopyBuffer) -> wgc::command::ImageCopyBuffer {
     wgc::command::ImageCopyBuffer {
         buffer: view.buffer.id.id,
@@ -688,6 +933,89 @@ pub struct Device {
     id: wgc::id::DeviceId,
     error_sink: ErrorSink,
     features: Features,
+    poller: Mutex<Option<Poller>>,
+}
+
+/// Background thread polling a device whenever it gets work to complete.
+///
+/// The thread is stopped when this is dropped.
+#[derive(Debug)]
+struct Poller {
+    stop: Arc<AtomicBool>,
+    signal: Arc<wgc::device::WorkSignal>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl Poller {
+    fn spawn(context: Arc<Context>, device_id: wgc::id::DeviceId) -> Self {
+        let global = &context.0;
+        let signal = match wgc::gfx_select!(device_id => global.device_work_signal(device_id)) {
+            Ok(signal) => signal,
+            Err(err) => context.handle_error_fatal(err, "Device::start_background_polling"),
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_stop = Arc::clone(&stop);
+        let thread_signal = Arc::clone(&signal);
+        let thread = thread::Builder::new()
+            .name("wgpu device poller".to_string())
+            .spawn(move || {
+                let global = &context.0;
+                while !thread_stop.load(Ordering::Acquire) {
+                    let mut index = match thread_signal.wait(POLLER_TIMEOUT) {
+                        Some(index) => index,
+                        None => continue,
+                    };
+                    // Keep checking until the work is done, or until we are told to stop.
+                    loop {
+                        match wgc::gfx_select!(device_id => global.device_wait_for_submission(
+                            device_id,
+                            index,
+                            0
+                        )) {
+                            Ok(true) => break,
+                            Ok(false) if !thread_stop.load(Ordering::Acquire) => {
+                                // Newer work may come in while the device isn't locked.
+                                if let Some(newer) = thread_signal.wait(POLLER_INTERVAL) {
+                                    index = index.max(newer);
+                                }
+                            }
+                            Ok(false) => return,
+                            Err(err) => {
+                                log::error!("Background polling of the device failed: {}", err);
+                                return;
+                            }
+                        }
+                    }
+                }
+            })
+            .unwrap();
+
+        Self {
+            stop,
+            signal,
+            thread: Some(thread),
+        }
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Release);
+        self.signal.wake();
+        if let Some(thread) = self.thread.take() {
+            // The device may be dropped from a callback running on the poller itself.
+            if thread.thread().id() != thread::current().id() {
+                let _ = thread.join();
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Queue {
+    id: wgc::id::QueueId,
+    error_sink: ErrorSink,
 }
 
 #[derive(Debug)]
@@ -712,7 +1040,7 @@ pub(crate) struct CommandEncoder {
 impl crate::Context for Context {
     type AdapterId = wgc::id::AdapterId;
     type DeviceId = Device;
-    type QueueId = wgc::id::QueueId;
+    type QueueId = Queue;
     type ShaderModuleId = wgc::id::ShaderModuleId;
     type BindGroupLayoutId = wgc::id::BindGroupLayoutId;
     type BindGroupId = wgc::id::BindGroupId;
@@ -731,6 +1059,7 @@ impl crate::Context for Context {
     type RenderBundleEncoderId = wgc::command::RenderBundleEncoder;
     type RenderBundleId = wgc::id::RenderBundleId;
     type SurfaceId = Surface;
+    type SubmissionIndex = wgc::SubmissionIndex;
 
     type SurfaceOutputDetail = SurfaceOutputDetail;
 
@@ -785,25 +1114,16 @@ impl crate::Context for Context {
         &self,
         adapter: &Self::AdapterId,
         desc: &crate::DeviceDescriptor,
-        trace_dir: Option<&std::path::Path>,
+        trace: Option<crate::DeviceTrace<'_>>,
     ) -> Self::RequestDeviceFuture {
         let global = &self.0;
         let (device_id, error) = wgc::gfx_select!(*adapter => global.adapter_request_device(
             *adapter,
             &desc.map_label(|l| l.map(Borrowed)),
-            trace_dir,
+            trace.map(map_device_trace),
             PhantomData
         ));
-        if let Some(err) = error {
-            log::error!("Error in Adapter::request_device: {}", err);
-            return ready(Err(crate::RequestDeviceError));
-        }
-        let device = Device {
-            id: device_id,
-            error_sink: Arc::new(Mutex::new(ErrorSinkRaw::new())),
-            features: desc.features,
-        };
-        ready(Ok((device, device_id)))
+        ready(Self::requested_device(device_id, error, desc))
     }
 
     fn adapter_is_surface_supported(
@@ -878,6 +1198,19 @@ impl crate::Context for Context {
         }
     }
 
+    fn surface_get_capabilities(
+        &self,
+        surface: &Self::SurfaceId,
+        adapter: &Self::AdapterId,
+    ) -> Option<wgt::SurfaceCapabilities> {
+        let global = &self.0;
+        match wgc::gfx_select!(adapter => global.surface_get_capabilities(surface.id, *adapter)) {
+            Ok(caps) => Some(caps),
+            Err(wgc::instance::GetSurfaceCapabilitiesError::UnsupportedQueueFamily) => None,
+            Err(err) => self.handle_error_fatal(err, "Surface::get_capabilities"),
+        }
+    }
+
     fn surface_configure(
         &self,
         surface: &Self::SurfaceId,
@@ -1469,20 +1802,46 @@ impl crate::Context for Context {
         }
     }
 
-    fn device_poll(&self, device: &Self::DeviceId, maintain: crate::Maintain) {
+    fn device_poll(&self, device: &Self::DeviceId, maintain: crate::Maintain) -> bool {
         let global = &self.0;
-        match wgc::gfx_select!(device.id => global.device_poll(
-            device.id,
-            match maintain {
-                crate::Maintain::Poll => false,
-                crate::Maintain::Wait => true,
+        let result = match maintain {
+            crate::Maintain::Poll => {
+                wgc::gfx_select!(device.id => global.device_poll(device.id, false)).map(|()| true)
             }
-        )) {
-            Ok(()) => (),
-            Err(err) => self.handle_error_fatal(err, "Device::poll"),
+            crate::Maintain::Wait => {
+                wgc::gfx_select!(device.id => global.device_poll(device.id, true)).map(|()| true)
+            }
+            crate::Maintain::WaitForSubmissionIndex(index, timeout) => {
+                let timeout_ms = timeout.map_or(!0, |t| t.as_millis().min(!0u32 as u128) as u32);
+                wgc::gfx_select!(device.id => global.device_wait_for_submission(
+                    device.id,
+                    index.0,
+                    timeout_ms
+                ))
+            }
+        };
+        match result {
+            Ok(done) => done,
+            Err(err) => {
+                self.handle_error_nolabel(&device.error_sink, err, "Device::poll");
+                true
+            }
+        }
+    }
+
+    fn device_start_background_polling(self: Arc<Self>, device: &Self::DeviceId) {
+        let mut poller = device.poller.lock();
+        if poller.is_none() {
+            *poller = Some(Poller::spawn(self, device.id));
         }
     }
 
+    fn device_stop_background_polling(&self, device: &Self::DeviceId) {
+        // Dropping the poller joins the thread, so don't hold the lock meanwhile.
+        let poller = device.poller.lock().take();
+        drop(poller);
+    }
+
     fn device_on_uncaptured_error(
         &self,
         device: &Self::DeviceId,
@@ -1492,6 +1851,40 @@ impl crate::Context for Context {
         error_sink.uncaptured_handler = Box::new(handler);
     }
 
+    fn device_set_device_lost_callback(
+        &self,
+        device: &Self::DeviceId,
+        callback: impl FnOnce(wgt::DeviceLostReason, String) + Send + 'static,
+    ) {
+        type BoxedCallback = Box<dyn FnOnce(wgt::DeviceLostReason, String) + Send>;
+
+        unsafe extern "C" fn device_lost_callback_wrapper(
+            reason: wgt::DeviceLostReason,
+            message: *const std::os::raw::c_char,
+            user_data: *mut u8,
+        ) {
+            let callback = Box::from_raw(user_data as *mut BoxedCallback);
+            let message = std::ffi::CStr::from_ptr(message)
+                .to_string_lossy()
+                .into_owned();
+            callback(reason, message);
+        }
+
+        let boxed: Box<BoxedCallback> = Box::new(Box::new(callback));
+        let closure = wgc::device::DeviceLostClosure {
+            callback: device_lost_callback_wrapper,
+            user_data: Box::into_raw(boxed) as _,
+        };
+
+        let global = &self.0;
+        let res = wgc::gfx_select!(device.id => global.device_set_device_lost_closure(device.id, closure));
+        if let Err(cause) = res {
+            // The closure was never handed over, so free it here.
+            drop(unsafe { Box::from_raw(closure.user_data as *mut BoxedCallback) });
+            self.handle_error_fatal(cause, "Device::set_device_lost_callback");
+        }
+    }
+
     fn buffer_map_async(
         &self,
         buffer: &Self::BufferId,
@@ -2033,10 +2426,63 @@ impl crate::Context for Context {
     ) {
         let global = &self.0;
         match wgc::gfx_select!(
-            *queue => global.queue_write_buffer(*queue, buffer.id, offset, data)
+            queue.id => global.queue_write_buffer(queue.id, buffer.id, offset, data)
         ) {
             Ok(()) => (),
-            Err(err) => self.handle_error_fatal(err, "Queue::write_buffer"),
+            Err(err) => self.handle_error_nolabel(&queue.error_sink, err, "Queue::write_buffer"),
+        }
+    }
+
+    fn queue_create_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        size: wgt::BufferSize,
+    ) -> QueueWriteBuffer {
+        let global = &self.0;
+        match wgc::gfx_select!(
+            queue.id => global.queue_create_staging_buffer(queue.id, buffer.id, offset, size)
+        ) {
+            Ok((staging_buffer, ptr)) => QueueWriteBuffer {
+                staging_buffer: Some(staging_buffer),
+                ptr,
+                size: size.get() as usize,
+                _fallback: Vec::new(),
+            },
+            Err(err) => {
+                self.handle_error_nolabel(&queue.error_sink, err, "Queue::write_buffer_with");
+                // Hand out host memory instead, which is then discarded.
+                let mut fallback = vec![0; size.get() as usize];
+                QueueWriteBuffer {
+                    staging_buffer: None,
+                    ptr: fallback.as_mut_ptr(),
+                    size: fallback.len(),
+                    _fallback: fallback,
+                }
+            }
+        }
+    }
+
+    fn queue_write_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        staging_buffer: QueueWriteBuffer,
+    ) {
+        let staging_buffer = match staging_buffer.staging_buffer {
+            Some(staging_buffer) => staging_buffer,
+            None => return,
+        };
+        let global = &self.0;
+        match wgc::gfx_select!(
+            queue.id => global.queue_write_staging_buffer(queue.id, buffer.id, offset, staging_buffer)
+        ) {
+            Ok(()) => (),
+            Err(err) => {
+                self.handle_error_nolabel(&queue.error_sink, err, "Queue::write_buffer_with")
+            }
         }
     }
 
@@ -2049,15 +2495,15 @@ impl crate::Context for Context {
         size: wgt::Extent3d,
     ) {
         let global = &self.0;
-        match wgc::gfx_select!(*queue => global.queue_write_texture(
-            *queue,
+        match wgc::gfx_select!(queue.id => global.queue_write_texture(
+            queue.id,
             &map_texture_copy_view(texture),
             data,
             &data_layout,
             &size
         )) {
             Ok(()) => (),
-            Err(err) => self.handle_error_fatal(err, "Queue::write_texture"),
+            Err(err) => self.handle_error_nolabel(&queue.error_sink, err, "Queue::write_texture"),
         }
     }
 
@@ -2065,20 +2511,28 @@ impl crate::Context for Context {
         &self,
         queue: &Self::QueueId,
         command_buffers: I,
-    ) {
+    ) -> Self::SubmissionIndex {
         let temp_command_buffers = command_buffers.collect::<SmallVec<[_; 4]>>();
 
         let global = &self.0;
-        match wgc::gfx_select!(*queue => global.queue_submit(*queue, &temp_command_buffers)) {
-            Ok(()) => (),
-            Err(err) => self.handle_error_fatal(err, "Queue::submit"),
+        match wgc::gfx_select!(queue.id => global.queue_submit(queue.id, &temp_command_buffers)) {
+            Ok(index) => index,
+            // The device lost callback lets the user know about this one.
+            Err(wgc::device::queue::QueueSubmitError::Queue(wgc::device::DeviceError::Lost)) => {
+                log::warn!("Queue::submit is ignored: device is lost");
+                0
+            }
+            Err(err) => {
+                self.handle_error_nolabel(&queue.error_sink, err, "Queue::submit");
+                0
+            }
         }
     }
 
     fn queue_get_timestamp_period(&self, queue: &Self::QueueId) -> f32 {
         let global = &self.0;
-        let res = wgc::gfx_select!(queue => global.queue_get_timestamp_period(
-            *queue
+        let res = wgc::gfx_select!(queue.id => global.queue_get_timestamp_period(
+            queue.id
         ));
         match res {
             Ok(v) => v,
@@ -2094,7 +2548,10 @@ impl crate::Context for Context {
     ) -> Self::OnSubmittedWorkDoneFuture {
         let (future, completion) = native_gpu_future::new_gpu_future();
 
-        extern "C" fn submitted_work_done_future_wrapper(user_data: *mut u8) {
+        extern "C" fn submitted_work_done_future_wrapper(
+            _status: wgc::device::queue::SubmittedWorkDoneStatus,
+            user_data: *mut u8,
+        ) {
             let completion =
                 unsafe { native_gpu_future::GpuFutureCompletion::from_raw(user_data as _) };
             completion.complete(())
@@ -2106,7 +2563,8 @@ impl crate::Context for Context {
         };
 
         let global = &self.0;
-        let res = wgc::gfx_select!(queue => global.queue_on_submitted_work_done(*queue, closure));
+        let res =
+            wgc::gfx_select!(queue.id => global.queue_on_submitted_work_done(queue.id, closure));
         if let Err(cause) = res {
             self.handle_error_fatal(cause, "Queue::on_submitted_work_done");
         }
@@ -2122,6 +2580,29 @@ impl crate::Context for Context {
         let global = &self.0;
         wgc::gfx_select!(device.id => global.device_stop_capture(device.id));
     }
+
+    fn device_start_trace(&self, device: &Self::DeviceId, desc: crate::TraceDescriptor<'_>) {
+        let global = &self.0;
+        let desc = map_trace_descriptor(desc);
+        if let Err(cause) =
+            wgc::gfx_select!(device.id => global.device_start_trace(device.id, desc))
+        {
+            self.handle_error(
+                &device.error_sink,
+                cause,
+                LABEL,
+                None,
+                "Device::start_trace",
+            );
+        }
+    }
+
+    fn device_stop_trace(&self, device: &Self::DeviceId) {
+        let global = &self.0;
+        if let Err(cause) = wgc::gfx_select!(device.id => global.device_stop_trace(device.id)) {
+            self.handle_error_fatal(cause, "Device::stop_trace");
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -2184,3 +2665,27 @@ impl Drop for BufferMappedRange {
         // implements `Drop`, to match the web backend
     }
 }
+
+/// Staging memory handed out by `Queue::write_buffer_with`.
+#[derive(Debug)]
+pub struct QueueWriteBuffer {
+    /// `None` if the staging buffer couldn't be created, in which case
+    /// `ptr` points into `_fallback`.
+    staging_buffer: Option<wgc::device::StagingBuffer>,
+    ptr: *mut u8,
+    size: usize,
+    _fallback: Vec<u8>,
+}
+
+unsafe impl Send for QueueWriteBuffer {}
+unsafe impl Sync for QueueWriteBuffer {}
+
+impl crate::BufferMappedRangeSlice for QueueWriteBuffer {
+    fn slice(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(self.ptr, self.size) }
+    }
+
+    fn slice_mut(&mut self) -> &mut [u8] {
+        unsafe { slice::from_raw_parts_mut(self.ptr, self.size) }
+    }
+}
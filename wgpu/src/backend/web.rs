@@ -909,6 +909,7 @@ impl crate::Context for Context {
     type RenderBundleEncoderId = RenderBundleEncoder;
     type RenderBundleId = Sendable<web_sys::GpuRenderBundle>;
     type SurfaceId = Sendable<web_sys::GpuCanvasContext>;
+    type SubmissionIndex = ();
 
     type SurfaceOutputDetail = SurfaceOutputDetail;
 
@@ -994,11 +995,11 @@ impl crate::Context for Context {
         &self,
         adapter: &Self::AdapterId,
         desc: &crate::DeviceDescriptor,
-        trace_dir: Option<&std::path::Path>,
+        trace: Option<crate::DeviceTrace<'_>>,
     ) -> Self::RequestDeviceFuture {
         use web_sys::GpuFeatureName as Gfn;
 
-        if trace_dir.is_some() {
+        if trace.is_some() {
             //Error: Tracing isn't supported on the Web target
         }
 
@@ -1113,6 +1114,31 @@ impl crate::Context for Context {
         Some(format)
     }
 
+    fn surface_get_capabilities(
+        &self,
+        surface: &Self::SurfaceId,
+        adapter: &Self::AdapterId,
+    ) -> Option<wgt::SurfaceCapabilities> {
+        // The swap chain only reports its preferred format.
+        let max_dimension = wgt::Limits::default().max_texture_dimension_2d;
+        Some(wgt::SurfaceCapabilities {
+            formats: vec![self.surface_get_preferred_format(surface, adapter)?],
+            current_extent: None,
+            extents: wgt::Extent3d {
+                width: 1,
+                height: 1,
+                depth_or_array_layers: 1,
+            }..=wgt::Extent3d {
+                width: max_dimension,
+                height: max_dimension,
+                depth_or_array_layers: 1,
+            },
+            usages: wgt::TextureUsages::RENDER_ATTACHMENT,
+            present_modes: vec![wgt::PresentMode::Fifo],
+            composite_alpha_modes: vec![wgt::CompositeAlphaMode::Opaque],
+        })
+    }
+
     fn surface_configure(
         &self,
         surface: &Self::SurfaceId,
@@ -1551,10 +1577,17 @@ impl crate::Context for Context {
         // Device is dropped automatically
     }
 
-    fn device_poll(&self, _device: &Self::DeviceId, _maintain: crate::Maintain) {
+    fn device_poll(&self, _device: &Self::DeviceId, _maintain: crate::Maintain) -> bool {
+        // Device is polled automatically
+        true
+    }
+
+    fn device_start_background_polling(self: std::sync::Arc<Self>, _device: &Self::DeviceId) {
         // Device is polled automatically
     }
 
+    fn device_stop_background_polling(&self, _device: &Self::DeviceId) {}
+
     fn device_on_uncaptured_error(
         &self,
         _device: &Self::DeviceId,
@@ -1563,6 +1596,14 @@ impl crate::Context for Context {
         // TODO:
     }
 
+    fn device_set_device_lost_callback(
+        &self,
+        _device: &Self::DeviceId,
+        _callback: impl FnOnce(wgt::DeviceLostReason, String) + Send + 'static,
+    ) {
+        // TODO: hook up `GPUDevice.lost`
+    }
+
     fn buffer_map_async(
         &self,
         buffer: &Self::BufferId,
@@ -1989,6 +2030,26 @@ impl crate::Context for Context {
             );
     }
 
+    fn queue_create_staging_buffer(
+        &self,
+        _queue: &Self::QueueId,
+        _buffer: &Self::BufferId,
+        _offset: wgt::BufferAddress,
+        size: wgt::BufferSize,
+    ) -> QueueWriteBuffer {
+        QueueWriteBuffer(vec![0; size.get() as usize].into_boxed_slice())
+    }
+
+    fn queue_write_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: wgt::BufferAddress,
+        staging_buffer: QueueWriteBuffer,
+    ) {
+        self.queue_write_buffer(queue, buffer, offset, &staging_buffer.0)
+    }
+
     fn queue_write_texture(
         &self,
         queue: &Self::QueueId,
@@ -2028,7 +2089,7 @@ impl crate::Context for Context {
         &self,
         queue: &Self::QueueId,
         command_buffers: I,
-    ) {
+    ) -> Self::SubmissionIndex {
         let temp_command_buffers = command_buffers.map(|i| i.0).collect::<js_sys::Array>();
 
         queue.0.submit(&temp_command_buffers);
@@ -2047,6 +2108,8 @@ impl crate::Context for Context {
 
     fn device_start_capture(&self, _device: &Self::DeviceId) {}
     fn device_stop_capture(&self, _device: &Self::DeviceId) {}
+    fn device_start_trace(&self, _device: &Self::DeviceId, _desc: crate::TraceDescriptor<'_>) {}
+    fn device_stop_trace(&self, _device: &Self::DeviceId) {}
 }
 
 pub(crate) type SurfaceOutputDetail = ();
@@ -2067,6 +2130,23 @@ impl crate::BufferMappedRangeSlice for BufferMappedRange {
     }
 }
 
+/// Staging memory handed out by `Queue::write_buffer_with`.
+///
+/// The browser API has no way to write into the staging memory directly,
+/// so this is a plain allocation, passed to `writeBuffer` afterwards.
+#[derive(Debug)]
+pub struct QueueWriteBuffer(Box<[u8]>);
+
+impl crate::BufferMappedRangeSlice for QueueWriteBuffer {
+    fn slice(&self) -> &[u8] {
+        &self.0
+    }
+
+    fn slice_mut(&mut self) -> &mut [u8] {
+        &mut self.0
+    }
+}
+
 impl Drop for BufferMappedRange {
     fn drop(&mut self) {
         // Copy from the temporary mapping back into the array buffer that was
@@ -28,20 +28,21 @@ pub use wgt::{
     AdapterInfo, AddressMode, Backend, Backends, BindGroupLayoutEntry, BindingType, BlendComponent,
     BlendFactor, BlendOperation, BlendState, BufferAddress, BufferBindingType, BufferSize,
     BufferUsages, Color, ColorTargetState, ColorWrites, CommandBufferDescriptor, CompareFunction,
-    DepthBiasState, DepthStencilState, DeviceType, DownlevelCapabilities, DownlevelFlags,
-    DynamicOffset, Extent3d, Face, Features, FilterMode, FrontFace, ImageDataLayout,
-    ImageSubresourceRange, IndexFormat, Limits, MultisampleState, Origin3d,
-    PipelineStatisticsTypes, PolygonMode, PowerPreference, PresentMode, PrimitiveState,
+    CompositeAlphaMode, DepthBiasState, DepthStencilState, DeviceLostReason, DeviceType,
+    DownlevelCapabilities, DownlevelFlags, DynamicOffset, Extent3d, Face, Features, FilterMode,
+    FrontFace, ImageDataLayout, ImageSubresourceRange, IndexFormat, Limits, MultisampleState,
+    Origin3d, PipelineStatisticsTypes, PolygonMode, PowerPreference, PresentMode, PrimitiveState,
     PrimitiveTopology, PushConstantRange, QueryType, RenderBundleDepthStencil, SamplerBorderColor,
     ShaderLocation, ShaderModel, ShaderStages, StencilFaceState, StencilOperation, StencilState,
-    StorageTextureAccess, SurfaceConfiguration, SurfaceStatus, TextureAspect, TextureDimension,
-    TextureFormat, TextureFormatFeatureFlags, TextureFormatFeatures, TextureSampleType,
-    TextureUsages, TextureViewDimension, VertexAttribute, VertexFormat, VertexStepMode,
-    BIND_BUFFER_ALIGNMENT, COPY_BUFFER_ALIGNMENT, COPY_BYTES_PER_ROW_ALIGNMENT, MAP_ALIGNMENT,
-    PUSH_CONSTANT_ALIGNMENT, QUERY_SET_MAX_QUERIES, QUERY_SIZE, VERTEX_STRIDE_ALIGNMENT,
+    StorageTextureAccess, SurfaceCapabilities, SurfaceConfiguration, SurfaceStatus, TextureAspect,
+    TextureDimension, TextureFormat, TextureFormatFeatureFlags, TextureFormatFeatures,
+    TextureSampleType, TextureUsages, TextureViewDimension, VertexAttribute, VertexFormat,
+    VertexStepMode, BIND_BUFFER_ALIGNMENT, COPY_BUFFER_ALIGNMENT, COPY_BYTES_PER_ROW_ALIGNMENT,
+    MAP_ALIGNMENT, PUSH_CONSTANT_ALIGNMENT, QUERY_SET_MAX_QUERIES, QUERY_SIZE,
+    VERTEX_STRIDE_ALIGNMENT,
 };
 
-use backend::{BufferMappedRange, Context as C};
+use backend::{BufferMappedRange, Context as C, QueueWriteBuffer};
 
 trait ComputePassInner<Ctx: Context> {
     fn set_pipeline(&mut self, pipeline: &Ctx::ComputePipelineId);
@@ -174,6 +175,7 @@ trait Context: Debug + Send + Sized + Sync {
     type RenderBundleEncoderId: Debug + RenderInner<Self>;
     type RenderBundleId: Debug + Send + Sync + 'static;
     type SurfaceId: Debug + Send + Sync + 'static;
+    type SubmissionIndex: Debug + Copy + Eq + Send + Sync + 'static;
 
     type SurfaceOutputDetail: Send;
 
@@ -196,7 +198,7 @@ trait Context: Debug + Send + Sized + Sync {
         &self,
         adapter: &Self::AdapterId,
         desc: &DeviceDescriptor,
-        trace_dir: Option<&std::path::Path>,
+        trace: Option<DeviceTrace<'_>>,
     ) -> Self::RequestDeviceFuture;
     fn instance_poll_all_devices(&self, force_wait: bool);
     fn adapter_is_surface_supported(
@@ -219,6 +221,11 @@ trait Context: Debug + Send + Sized + Sync {
         surface: &Self::SurfaceId,
         adapter: &Self::AdapterId,
     ) -> Option<TextureFormat>;
+    fn surface_get_capabilities(
+        &self,
+        surface: &Self::SurfaceId,
+        adapter: &Self::AdapterId,
+    ) -> Option<SurfaceCapabilities>;
     fn surface_configure(
         &self,
         surface: &Self::SurfaceId,
@@ -304,12 +311,19 @@ trait Context: Debug + Send + Sized + Sync {
         desc: &RenderBundleEncoderDescriptor,
     ) -> Self::RenderBundleEncoderId;
     fn device_drop(&self, device: &Self::DeviceId);
-    fn device_poll(&self, device: &Self::DeviceId, maintain: Maintain);
+    fn device_poll(&self, device: &Self::DeviceId, maintain: Maintain) -> bool;
+    fn device_start_background_polling(self: Arc<Self>, device: &Self::DeviceId);
+    fn device_stop_background_polling(&self, device: &Self::DeviceId);
     fn device_on_uncaptured_error(
         &self,
         device: &Self::DeviceId,
         handler: impl UncapturedErrorHandler,
     );
+    fn device_set_device_lost_callback(
+        &self,
+        device: &Self::DeviceId,
+        callback: impl FnOnce(DeviceLostReason, String) + Send + 'static,
+    );
 
     fn buffer_map_async(
         &self,
@@ -458,6 +472,20 @@ trait Context: Debug + Send + Sized + Sync {
         offset: BufferAddress,
         data: &[u8],
     );
+    fn queue_create_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: BufferAddress,
+        size: BufferSize,
+    ) -> QueueWriteBuffer;
+    fn queue_write_staging_buffer(
+        &self,
+        queue: &Self::QueueId,
+        buffer: &Self::BufferId,
+        offset: BufferAddress,
+        staging_buffer: QueueWriteBuffer,
+    );
     fn queue_write_texture(
         &self,
         queue: &Self::QueueId,
@@ -470,7 +498,7 @@ trait Context: Debug + Send + Sized + Sync {
         &self,
         queue: &Self::QueueId,
         command_buffers: I,
-    );
+    ) -> Self::SubmissionIndex;
     fn queue_get_timestamp_period(&self, queue: &Self::QueueId) -> f32;
     fn queue_on_submitted_work_done(
         &self,
@@ -479,6 +507,8 @@ trait Context: Debug + Send + Sized + Sync {
 
     fn device_start_capture(&self, device: &Self::DeviceId);
     fn device_stop_capture(&self, device: &Self::DeviceId);
+    fn device_start_trace(&self, device: &Self::DeviceId, desc: TraceDescriptor<'_>);
+    fn device_stop_trace(&self, device: &Self::DeviceId);
 }
 
 /// Context for all other wgpu objects. Instance of wgpu.
@@ -524,12 +554,73 @@ pub struct Device {
     id: <C as Context>::DeviceId,
 }
 
+/// Identifier of a submission, returned by [`Queue::submit`].
+///
+/// Can be passed to [`Device::poll`] to wait for this particular submission to finish.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct SubmissionIndex(<C as Context>::SubmissionIndex);
+
+/// Encoding of the actions in an API trace.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TraceFormat {
+    /// Human-readable RON, stored in `trace.ron`.
+    Ron,
+    /// Length-prefixed binary records, stored in `trace.bin`,
+    /// which are more compact and can be replayed as they are read.
+    Binary,
+}
+
+/// Describes where and how to record an API trace.
+#[derive(Clone, Copy, Debug)]
+pub struct TraceDescriptor<'a> {
+    /// Directory to put the trace file and the binary data into.
+    pub path: &'a std::path::Path,
+    /// Encoding of the trace file.
+    pub format: TraceFormat,
+}
+
+/// Traces into the given directory in the RON format.
+impl<'a> From<&'a std::path::Path> for TraceDescriptor<'a> {
+    fn from(path: &'a std::path::Path) -> Self {
+        Self {
+            path,
+            format: TraceFormat::Ron,
+        }
+    }
+}
+
+/// Describes how the API calls of a new [`Device`] can be traced.
+#[derive(Clone, Copy, Debug)]
+pub enum DeviceTrace<'a> {
+    /// Record a trace from the creation of the device on.
+    Record(TraceDescriptor<'a>),
+    /// Don't record anything yet, but allow [`Device::start_trace`] to be called later on.
+    ///
+    /// This keeps the contents of the resources readable, which can cost some performance.
+    OnDemand,
+}
+
+impl<'a> From<TraceDescriptor<'a>> for DeviceTrace<'a> {
+    fn from(desc: TraceDescriptor<'a>) -> Self {
+        Self::Record(desc)
+    }
+}
+
+/// Records a RON trace into the given directory.
+impl<'a> From<&'a std::path::Path> for DeviceTrace<'a> {
+    fn from(path: &'a std::path::Path) -> Self {
+        Self::Record(path.into())
+    }
+}
+
 /// Passed to [`Device::poll`] to control if it should block or not. This has no effect on
 /// the web.
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum Maintain {
     /// Block
     Wait,
+    /// Block until the given submission is done, or until the timeout, if any, runs out.
+    WaitForSubmissionIndex(SubmissionIndex, Option<std::time::Duration>),
     /// Don't block
     Poll,
 }
@@ -625,6 +716,7 @@ pub struct Texture {
     context: Arc<C>,
     id: <C as Context>::TextureId,
     owned: bool,
+    format: TextureFormat,
 }
 
 /// Handle to a texture view.
@@ -664,6 +756,9 @@ impl Drop for Sampler {
 pub struct Surface {
     context: Arc<C>,
     id: <C as Context>::SurfaceId,
+    /// Configuration passed to [`Surface::configure`], which the textures
+    /// of the surface are created with.
+    config: Mutex<Option<SurfaceConfiguration>>,
 }
 
 impl Drop for Surface {
@@ -1388,6 +1483,24 @@ impl Instance {
         }
     }
 
+    /// Create an new instance of wgpu, enabling the extra layers and extensions of
+    /// `vulkan_extras` on the Vulkan instance.
+    ///
+    /// # Arguments
+    ///
+    /// - `backends` - Controls from which [backends][Backends] wgpu will choose
+    ///   during instantiation.
+    /// - `vulkan_extras` - Layers and extensions to enable on top of the ones wgpu needs.
+    #[cfg(any(windows, all(unix, not(target_os = "ios"), not(target_os = "macos"))))]
+    pub fn new_with_vulkan_extras(
+        backends: Backends,
+        vulkan_extras: &hal::vulkan::InstanceExtras,
+    ) -> Self {
+        Self {
+            context: Arc::new(C::new_with_vulkan_extras(backends, vulkan_extras)),
+        }
+    }
+
     /// Create an new instance of wgpu from a wgpu-hal instance.
     ///
     /// # Arguments
@@ -1463,6 +1576,7 @@ impl Instance {
         Surface {
             context: Arc::clone(&self.context),
             id: Context::instance_create_surface(&*self.context, window),
+            config: Mutex::new(None),
         }
     }
 
@@ -1502,8 +1616,9 @@ impl Adapter {
     /// # Arguments
     ///
     /// - `desc` - Description of the features and limits requested from the given device.
-    /// - `trace_path` - Can be used for API call tracing, if that feature is
-    ///   enabled in `wgpu-core`.
+    /// - `trace` - Can be used for API call tracing, if that feature is
+    ///   enabled in `wgpu-core`. A directory path converts into a RON trace,
+    ///   and [`DeviceTrace::OnDemand`] allows [`Device::start_trace`] to be used later on.
     ///
     /// # Panics
     ///
@@ -1514,10 +1629,10 @@ impl Adapter {
     pub fn request_device(
         &self,
         desc: &DeviceDescriptor,
-        trace_path: Option<&std::path::Path>,
+        trace: Option<DeviceTrace<'_>>,
     ) -> impl Future<Output = Result<(Device, Queue), RequestDeviceError>> + Send {
         let context = Arc::clone(&self.context);
-        let device = Context::adapter_request_device(&*self.context, &self.id, desc, trace_path);
+        let device = Context::adapter_request_device(&*self.context, &self.id, desc, trace);
         async move {
             device.await.map(|(device_id, queue_id)| {
                 (
@@ -1534,6 +1649,45 @@ impl Adapter {
         }
     }
 
+    /// Requests a connection to a physical device like [`Adapter::request_device`],
+    /// enabling the extra extensions and features of `vulkan_extras` if this is
+    /// a Vulkan adapter.
+    ///
+    /// # Safety
+    ///
+    /// - The extra extensions and features must not change the behavior wgpu relies on
+    /// - The structures chained by the features callback must be valid, must extend
+    ///   `VkDeviceCreateInfo`, and must not be of a type wgpu chains already
+    #[cfg(any(windows, all(unix, not(target_os = "ios"), not(target_os = "macos"))))]
+    pub unsafe fn request_device_with_vulkan_extras(
+        &self,
+        desc: &DeviceDescriptor,
+        vulkan_extras: hal::vulkan::DeviceExtras,
+        trace: Option<DeviceTrace<'_>>,
+    ) -> impl Future<Output = Result<(Device, Queue), RequestDeviceError>> + Send {
+        let device = self.context.adapter_request_device_with_vulkan_extras(
+            &self.id,
+            desc,
+            vulkan_extras,
+            trace,
+        );
+        let context = Arc::clone(&self.context);
+        async move {
+            device.map(|(device_id, queue_id)| {
+                (
+                    Device {
+                        context: Arc::clone(&context),
+                        id: device_id,
+                    },
+                    Queue {
+                        context,
+                        id: queue_id,
+                    },
+                )
+            })
+        }
+    }
+
     /// Create a wgpu [`Device`] and [`Queue`] from a wgpu-hal `OpenDevice`
     ///
     /// # Safety
@@ -1545,11 +1699,11 @@ impl Adapter {
         &self,
         hal_device: hal::OpenDevice<A>,
         desc: &DeviceDescriptor,
-        trace_path: Option<&std::path::Path>,
+        trace: Option<DeviceTrace<'_>>,
     ) -> Result<(Device, Queue), RequestDeviceError> {
         let context = Arc::clone(&self.context);
         self.context
-            .create_device_from_hal(&self.id, hal_device, desc, trace_path)
+            .create_device_from_hal(&self.id, hal_device, desc, trace)
             .map(|(device_id, queue_id)| {
                 (
                     Device {
@@ -1602,14 +1756,49 @@ impl Adapter {
     pub fn get_texture_format_features(&self, format: TextureFormat) -> TextureFormatFeatures {
         Context::adapter_get_texture_format_features(&*self.context, &self.id, format)
     }
+
+    /// Returns the inner hal adapter using a callback. The hal adapter will be `None` if the
+    /// backend type argument does not match with this wgpu adapter.
+    ///
+    /// # Safety
+    ///
+    /// - The raw handle obtained from the hal adapter must not be manually destroyed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Adapter>) -> R, R>(
+        &self,
+        hal_adapter_callback: F,
+    ) -> R {
+        self.context
+            .adapter_as_hal::<A, F, R>(self.id, hal_adapter_callback)
+    }
 }
 
 impl Device {
     /// Check for resource cleanups and mapping callbacks.
     ///
     /// no-op on the web, device is automatically polled.
-    pub fn poll(&self, maintain: Maintain) {
-        Context::device_poll(&*self.context, &self.id, maintain);
+    ///
+    /// Returns `false` if the timeout of a [`Maintain::WaitForSubmissionIndex`]
+    /// ran out before the submission was done, and `true` otherwise.
+    pub fn poll(&self, maintain: Maintain) -> bool {
+        Context::device_poll(&*self.context, &self.id, maintain)
+    }
+
+    /// Start a background thread that polls the device whenever work is submitted,
+    /// a buffer mapping is requested, or a submitted work callback is registered.
+    ///
+    /// With this, the futures of [`BufferSlice::map_async`] and [`Queue::on_submitted_work_done`]
+    /// complete on their own, without calling [`Device::poll`]. Their callbacks run on that thread.
+    /// The thread is stopped with [`Device::stop_background_polling`], or when the device is dropped.
+    ///
+    /// no-op on the web, device is automatically polled.
+    pub fn start_background_polling(&self) {
+        Context::device_start_background_polling(Arc::clone(&self.context), &self.id);
+    }
+
+    /// Stop the thread started by [`Device::start_background_polling`], if any.
+    pub fn stop_background_polling(&self) {
+        Context::device_stop_background_polling(&*self.context, &self.id);
     }
 
     /// List all features that may be used with this device.
@@ -1732,6 +1921,34 @@ impl Device {
         }
     }
 
+    /// Creates a [`Buffer`] from a wgpu-hal Buffer.
+    ///
+    /// The whole buffer is considered initialized, so its existing contents are kept.
+    /// If `owned` is false, wgpu never destroys `hal_buffer`, not even on
+    /// [`Buffer::destroy`], and the underlying handle stays the caller's to destroy.
+    ///
+    /// # Safety
+    ///
+    /// - `hal_buffer` must be created from this device internal handle
+    /// - `hal_buffer` must be created respecting `desc`
+    /// - if `owned` is false, the underlying handle must outlive any use of the buffer by wgpu
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn create_buffer_from_hal<A: wgc::hub::HalApi>(
+        &self,
+        hal_buffer: A::Buffer,
+        desc: &BufferDescriptor,
+        owned: bool,
+    ) -> Buffer {
+        Buffer {
+            context: Arc::clone(&self.context),
+            id: self
+                .context
+                .create_buffer_from_hal::<A>(hal_buffer, &self.id, desc, owned),
+            map_context: Mutex::new(MapContext::new(desc.size)),
+            usage: desc.usage,
+        }
+    }
+
     /// Creates a new [`Texture`].
     ///
     /// `desc` specifies the general format of the texture.
@@ -1740,6 +1957,7 @@ impl Device {
             context: Arc::clone(&self.context),
             id: Context::device_create_texture(&*self.context, &self.id, desc),
             owned: true,
+            format: desc.format,
         }
     }
 
@@ -1761,6 +1979,7 @@ impl Device {
                 .context
                 .create_texture_from_hal::<A>(hal_texture, &self.id, desc),
             owned: true,
+            format: desc.format,
         }
     }
 
@@ -1787,6 +2006,21 @@ impl Device {
         self.context.device_on_uncaptured_error(&self.id, handler);
     }
 
+    /// Set a callback to be called once the device is lost, with the reason and a message.
+    ///
+    /// After that, pending buffer mappings fail and all further work is ignored.
+    /// The resources have to be recreated on a new device.
+    ///
+    /// A callback that was set before is called right away with
+    /// [`DeviceLostReason::ReplacedCallback`].
+    pub fn set_device_lost_callback(
+        &self,
+        callback: impl FnOnce(DeviceLostReason, String) + Send + 'static,
+    ) {
+        self.context
+            .device_set_device_lost_callback(&self.id, callback);
+    }
+
     /// Starts frame capture.
     pub fn start_capture(&self) {
         Context::device_start_capture(&*self.context, &self.id)
@@ -1796,6 +2030,37 @@ impl Device {
     pub fn stop_capture(&self) {
         Context::device_stop_capture(&*self.context, &self.id)
     }
+
+    /// Starts API call tracing into the `path` directory in the given `format`,
+    /// if the `trace` feature is enabled.
+    ///
+    /// The device has to be requested with [`DeviceTrace::OnDemand`] for this to work.
+    /// The trace begins with the creation and contents of the resources that are
+    /// currently alive, so it can be replayed on its own. This waits for all the
+    /// submitted work to complete.
+    pub fn start_trace(&self, path: &std::path::Path, format: TraceFormat) {
+        Context::device_start_trace(&*self.context, &self.id, TraceDescriptor { path, format })
+    }
+
+    /// Stops API call tracing.
+    pub fn stop_trace(&self) {
+        Context::device_stop_trace(&*self.context, &self.id)
+    }
+
+    /// Returns the inner hal device using a callback. The hal device will be `None` if the
+    /// backend type argument does not match with this wgpu device.
+    ///
+    /// # Safety
+    ///
+    /// - The raw handle obtained from the hal device must not be manually destroyed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Device>) -> R, R>(
+        &self,
+        hal_device_callback: F,
+    ) -> R {
+        self.context
+            .device_as_hal::<A, F, R>(&self.id, hal_device_callback)
+    }
 }
 
 impl Drop for Device {
@@ -1963,6 +2228,51 @@ impl Drop for BufferViewMut<'_> {
     }
 }
 
+/// Write only view into staging memory, returned by [`Queue::write_buffer_with`].
+///
+/// The contents are written into the destination buffer when the view is dropped.
+#[derive(Debug)]
+pub struct QueueWriteBufferView<'a> {
+    queue: &'a Queue,
+    buffer: &'a Buffer,
+    offset: BufferAddress,
+    inner: Option<QueueWriteBuffer>,
+}
+
+impl std::ops::Deref for QueueWriteBufferView<'_> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.inner.as_ref().unwrap().slice()
+    }
+}
+
+impl std::ops::DerefMut for QueueWriteBufferView<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.inner.as_mut().unwrap().slice_mut()
+    }
+}
+
+impl AsMut<[u8]> for QueueWriteBufferView<'_> {
+    fn as_mut(&mut self) -> &mut [u8] {
+        self.inner.as_mut().unwrap().slice_mut()
+    }
+}
+
+impl Drop for QueueWriteBufferView<'_> {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.take() {
+            Context::queue_write_staging_buffer(
+                &*self.queue.context,
+                &self.queue.id,
+                &self.buffer.id,
+                self.offset,
+                inner,
+            );
+        }
+    }
+}
+
 impl Buffer {
     /// Return the binding view of the entire buffer.
     pub fn as_entire_binding(&self) -> BindingResource {
@@ -1999,6 +2309,21 @@ impl Buffer {
     pub fn destroy(&self) {
         Context::buffer_destroy(&*self.context, &self.id);
     }
+
+    /// Returns the inner hal buffer using a callback. The hal buffer will be `None` if the
+    /// backend type argument does not match with this wgpu buffer, or if the buffer is destroyed.
+    ///
+    /// # Safety
+    ///
+    /// - The raw handle obtained from the hal buffer must not be manually destroyed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Buffer>) -> R, R>(
+        &self,
+        hal_buffer_callback: F,
+    ) -> R {
+        self.context
+            .buffer_as_hal::<A, F, R>(&self.id, hal_buffer_callback)
+    }
 }
 
 impl<'a> BufferSlice<'a> {
@@ -2088,6 +2413,11 @@ impl Texture {
         Context::texture_destroy(&*self.context, &self.id);
     }
 
+    /// Returns the format of this texture.
+    pub fn format(&self) -> TextureFormat {
+        self.format
+    }
+
     /// Make an `ImageCopyTexture` representing the whole texture.
     pub fn as_image_copy(&self) -> ImageCopyTexture {
         ImageCopyTexture {
@@ -2097,6 +2427,38 @@ impl Texture {
             aspect: TextureAspect::All,
         }
     }
+
+    /// Returns the inner hal texture using a callback. The hal texture will be `None` if the
+    /// backend type argument does not match with this wgpu texture, or if the texture is destroyed.
+    ///
+    /// # Safety
+    ///
+    /// - The raw handle obtained from the hal texture must not be manually destroyed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Texture>) -> R, R>(
+        &self,
+        hal_texture_callback: F,
+    ) -> R {
+        self.context
+            .texture_as_hal::<A, F, R>(&self.id, hal_texture_callback)
+    }
+}
+
+impl TextureView {
+    /// Returns the inner hal texture view using a callback. The hal texture view will be `None` if the
+    /// backend type argument does not match with this wgpu texture view.
+    ///
+    /// # Safety
+    ///
+    /// - The raw handle obtained from the hal texture view must not be manually destroyed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::TextureView>) -> R, R>(
+        &self,
+        hal_texture_view_callback: F,
+    ) -> R {
+        self.context
+            .texture_view_as_hal::<A, F, R>(self.id, hal_texture_view_callback)
+    }
 }
 
 impl Drop for Texture {
@@ -2127,6 +2489,40 @@ impl CommandEncoder {
         }
     }
 
+    /// Records raw commands through the inner hal command encoder using a callback.
+    /// The hal command encoder will be `None` if the backend type argument does not match
+    /// with this wgpu command encoder, or if the encoder or any of the declared resources is invalid.
+    ///
+    /// `buffers` and `textures` declare the resources the raw commands use, along with
+    /// their usages, so that wgpu can synchronize them with the rest of the commands.
+    /// Passes can't be open at the same time, so the raw commands are always recorded
+    /// between them.
+    ///
+    /// # Safety
+    ///
+    /// - The raw commands must only use the declared resources, each declared once,
+    ///   in the declared usages, which must be allowed by the resource usages
+    /// - The hal command encoder must be left recording, outside of any pass, and
+    ///   must not be ended, reset, or destroyed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn as_hal_mut<
+        A: wgc::hub::HalApi,
+        F: FnOnce(Option<&mut A::CommandEncoder>) -> R,
+        R,
+    >(
+        &mut self,
+        buffers: &[(&Buffer, hal::BufferUses)],
+        textures: &[(&Texture, hal::TextureUses)],
+        hal_command_encoder_callback: F,
+    ) -> R {
+        self.context.command_encoder_as_hal_mut::<A, F, R>(
+            self.id.as_ref().unwrap(),
+            buffers,
+            textures,
+            hal_command_encoder_callback,
+        )
+    }
+
     /// Begins recording of a render pass.
     ///
     /// This function returns a [`RenderPass`] object which records a single render pass.
@@ -3023,6 +3419,37 @@ impl Queue {
         Context::queue_write_buffer(&*self.context, &self.id, &buffer.id, offset, data)
     }
 
+    /// Schedule a data write into `buffer` starting at `offset`, and return
+    /// a view into staging memory of `size` bytes to write the data into.
+    ///
+    /// Unlike [`Queue::write_buffer`], the data doesn't need to be in a slice
+    /// beforehand, so it can be generated in place without another copy.
+    /// The write is enqueued when the view is dropped, and happens at the start
+    /// of the next `submit()` call, just like with `write_buffer`.
+    ///
+    /// The view starts out zeroed. `offset` and `size` have to be multiples
+    /// of [`COPY_BUFFER_ALIGNMENT`].
+    pub fn write_buffer_with<'a>(
+        &'a self,
+        buffer: &'a Buffer,
+        offset: BufferAddress,
+        size: BufferSize,
+    ) -> QueueWriteBufferView<'a> {
+        let inner = Context::queue_create_staging_buffer(
+            &*self.context,
+            &self.id,
+            &buffer.id,
+            offset,
+            size,
+        );
+        QueueWriteBufferView {
+            queue: self,
+            buffer,
+            offset,
+            inner: Some(inner),
+        }
+    }
+
     /// Schedule a data write into `texture`.
     ///
     /// This method is intended to have low performance costs.
@@ -3039,14 +3466,19 @@ impl Queue {
     }
 
     /// Submits a series of finished command buffers for execution.
-    pub fn submit<I: IntoIterator<Item = CommandBuffer>>(&self, command_buffers: I) {
-        Context::queue_submit(
+    ///
+    /// Returns the index of the submission, which can be waited on with [`Device::poll`].
+    pub fn submit<I: IntoIterator<Item = CommandBuffer>>(
+        &self,
+        command_buffers: I,
+    ) -> SubmissionIndex {
+        SubmissionIndex(Context::queue_submit(
             &*self.context,
             &self.id,
             command_buffers
                 .into_iter()
                 .map(|mut comb| comb.id.take().unwrap()),
-        );
+        ))
     }
 
     /// Gets the amount of nanoseconds each tick of a timestamp query represents.
@@ -3061,6 +3493,21 @@ impl Queue {
     pub fn on_submitted_work_done(&self) -> impl Future<Output = ()> + Send {
         Context::queue_on_submitted_work_done(&*self.context, &self.id)
     }
+
+    /// Returns the inner hal queue using a callback. The hal queue will be `None` if the
+    /// backend type argument does not match with this wgpu queue.
+    ///
+    /// # Safety
+    ///
+    /// - The raw handle obtained from the hal queue must not be manually destroyed
+    #[cfg(not(target_arch = "wasm32"))]
+    pub unsafe fn as_hal<A: wgc::hub::HalApi, F: FnOnce(Option<&A::Queue>) -> R, R>(
+        &self,
+        hal_queue_callback: F,
+    ) -> R {
+        self.context
+            .queue_as_hal::<A, F, R>(&self.id, hal_queue_callback)
+    }
 }
 
 impl Drop for SurfaceTexture {
@@ -3079,14 +3526,24 @@ impl Surface {
         Context::surface_get_preferred_format(&*self.context, &self.id, &adapter.id)
     }
 
+    /// Returns the formats, extents, usages, present modes, and alpha modes
+    /// supported by the [`Surface`] with this adapter.
+    ///
+    /// Returns None if the surface is incompatible with the adapter.
+    pub fn get_capabilities(&self, adapter: &Adapter) -> Option<SurfaceCapabilities> {
+        Context::surface_get_capabilities(&*self.context, &self.id, &adapter.id)
+    }
+
     /// Initializes [`Surface`] for presentation.
     ///
     /// # Panics
     ///
     /// - A old [`SurfaceFrame`] is still alive referencing an old surface.
     /// - Texture format requested is unsupported on the surface.
+    /// - Composite alpha mode requested is unsupported on the surface.
     pub fn configure(&self, device: &Device, config: &SurfaceConfiguration) {
-        Context::surface_configure(&*self.context, &self.id, &device.id, config)
+        Context::surface_configure(&*self.context, &self.id, &device.id, config);
+        *self.config.lock() = Some(config.clone());
     }
 
     /// Returns the next texture to be presented by the swapchain for drawing.
@@ -3104,6 +3561,8 @@ impl Surface {
                 context: Arc::clone(&self.context),
                 id,
                 owned: false,
+                // Textures are only handed out by configured surfaces.
+                format: self.config.lock().as_ref().unwrap().format,
             },
             detail,
         });
@@ -3132,9 +3591,11 @@ impl<T> UncapturedErrorHandler for T where T: Fn(Error) + Send + 'static {}
 #[derive(Debug)]
 pub enum Error {
     /// Out of memory error
-    OutOfMemoryError {
+    OutOfMemory {
         ///
         source: Box<dyn error::Error + Send + 'static>,
+        /// Description naming the operation and the label of the resource involved.
+        description: String,
     },
     /// Validation error, signifying a bug in code or data
     ValidationError {
@@ -3148,7 +3609,7 @@ pub enum Error {
 impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
-            Error::OutOfMemoryError { source } => Some(source.as_ref()),
+            Error::OutOfMemory { source, .. } => Some(source.as_ref()),
             Error::ValidationError { source, .. } => Some(source.as_ref()),
         }
     }
@@ -3157,7 +3618,7 @@ impl error::Error for Error {
 impl Display for Error {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Error::OutOfMemoryError { .. } => f.write_str("Out of Memory"),
+            Error::OutOfMemory { description, .. } => f.write_str(description),
             Error::ValidationError { description, .. } => f.write_str(description),
         }
     }
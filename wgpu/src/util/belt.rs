@@ -1,10 +1,17 @@
 use crate::{
-    Buffer, BufferAddress, BufferDescriptor, BufferSize, BufferUsages, BufferViewMut,
-    CommandEncoder, Device, MapMode,
+    Buffer, BufferAddress, BufferAsyncError, BufferDescriptor, BufferMappedRange,
+    BufferMappedRangeSlice, BufferSize, BufferUsages, BufferViewMut, CommandEncoder, Device,
+    Extent3d, ImageCopyBuffer, ImageCopyTexture, ImageDataLayout, MapMode, TextureFormat,
 };
+use parking_lot::Mutex;
 use std::pin::Pin;
-use std::task::{self, Poll};
-use std::{future::Future, sync::mpsc};
+use std::task::{self, Poll, Wake, Waker};
+use std::{
+    future::Future,
+    num::NonZeroU32,
+    ops::Range,
+    sync::{mpsc, Arc},
+};
 
 // Given a vector of futures, poll each in parallel until all are ready.
 struct Join<F> {
@@ -182,3 +189,362 @@ impl StagingBelt {
         Join { futures }
     }
 }
+
+type MapFuture = Pin<Box<dyn Future<Output = Result<(), BufferAsyncError>> + Send>>;
+
+/// Readback buffer that downloads are sub-allocated from.
+struct DownloadChunk {
+    buffer: Buffer,
+    size: BufferAddress,
+}
+
+enum ChunkMapState {
+    /// The copies into the chunk aren't submitted yet.
+    Unmapped,
+    Mapping(MapFuture),
+    Mapped,
+    Failed(BufferAsyncError),
+}
+
+/// Wakers of the downloads waiting on a chunk to be mapped.
+#[derive(Default)]
+struct ChunkWakers(Mutex<Vec<Waker>>);
+
+impl ChunkWakers {
+    fn register(&self, waker: &Waker) {
+        let mut wakers = self.0.lock();
+        if !wakers.iter().any(|w| w.will_wake(waker)) {
+            wakers.push(waker.clone());
+        }
+    }
+}
+
+impl Wake for ChunkWakers {
+    fn wake(self: Arc<Self>) {
+        self.wake_by_ref();
+    }
+
+    fn wake_by_ref(self: &Arc<Self>) {
+        let wakers = std::mem::take(&mut *self.0.lock());
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// Chunk shared by the downloads recorded into it.
+///
+/// It's unmapped and sent back to the belt once they are all dropped.
+struct SharedChunk {
+    chunk: Option<DownloadChunk>,
+    state: Mutex<ChunkMapState>,
+    wakers: Arc<ChunkWakers>,
+    sender: Mutex<mpsc::Sender<DownloadChunk>>,
+}
+
+impl SharedChunk {
+    fn buffer(&self) -> &Buffer {
+        &self.chunk.as_ref().unwrap().buffer
+    }
+
+    fn start_mapping(&self) {
+        let future = self.buffer().slice(..).map_async(MapMode::Read);
+        *self.state.lock() = ChunkMapState::Mapping(Box::pin(future));
+        // Let the downloads that were polled early poll the mapping.
+        self.wakers.wake_by_ref();
+    }
+
+    fn poll_mapped(&self, cx: &mut task::Context) -> Poll<Result<(), BufferAsyncError>> {
+        let mut state = self.state.lock();
+        let result = match *state {
+            ChunkMapState::Unmapped => {
+                self.wakers.register(cx.waker());
+                return Poll::Pending;
+            }
+            ChunkMapState::Mapping(ref mut future) => {
+                // The mapping is shared, so it has to wake all the downloads.
+                self.wakers.register(cx.waker());
+                let waker = Waker::from(Arc::clone(&self.wakers));
+                match future.as_mut().poll(&mut task::Context::from_waker(&waker)) {
+                    Poll::Ready(result) => result,
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+            ChunkMapState::Mapped => return Poll::Ready(Ok(())),
+            ChunkMapState::Failed(ref error) => return Poll::Ready(Err(error.clone())),
+        };
+        *state = match result {
+            Ok(()) => ChunkMapState::Mapped,
+            Err(ref error) => ChunkMapState::Failed(error.clone()),
+        };
+        drop(state);
+        self.wakers.wake_by_ref();
+        Poll::Ready(result)
+    }
+}
+
+impl Drop for SharedChunk {
+    fn drop(&mut self) {
+        let chunk = self.chunk.take().unwrap();
+        match *self.state.get_mut() {
+            ChunkMapState::Unmapped => (),
+            ChunkMapState::Mapping(_) | ChunkMapState::Mapped => chunk.buffer.unmap(),
+            // The buffer is not usable any more.
+            ChunkMapState::Failed(_) => return,
+        }
+        // The only possible error is the belt being dropped, which is fine
+        let _ = self.sender.get_mut().send(chunk);
+    }
+}
+
+struct ActiveDownloadChunk {
+    shared: Arc<SharedChunk>,
+    size: BufferAddress,
+    offset: BufferAddress,
+}
+
+/// Download belt is a machine that reads data back from the GPU.
+///
+/// It's the counterpart of [`StagingBelt`]: internally it uses a ring-buffer
+/// of readback buffers that are sub-allocated, instead of creating
+/// and mapping a buffer for every download.
+///
+/// Using a download belt goes as follows:
+/// - Record the copies of the data to read with `read_buffer` and `read_texture`,
+///   and keep the returned futures.
+/// - Submit all command encoders used with `read_buffer` and `read_texture`.
+/// - Call `finish`.
+/// - Await the futures, while polling the device.
+///
+/// A chunk is recycled once all the downloads from it are dropped.
+pub struct DownloadBelt {
+    chunk_size: BufferAddress,
+    /// Chunks that we are actively recording copies into at this moment.
+    active_chunks: Vec<ActiveDownloadChunk>,
+    /// Chunks that are unmapped and ready to be used.
+    free_chunks: Vec<DownloadChunk>,
+    sender: mpsc::Sender<DownloadChunk>,
+    receiver: mpsc::Receiver<DownloadChunk>,
+}
+
+impl DownloadBelt {
+    /// Create a new download belt.
+    ///
+    /// The `chunk_size` is the unit of internal buffer allocation.
+    /// It's better when it's big, but ideally still 1-4 times less than
+    /// the total amount of data downloaded per submission.
+    pub fn new(chunk_size: BufferAddress) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        DownloadBelt {
+            chunk_size,
+            active_chunks: Vec::new(),
+            free_chunks: Vec::new(),
+            sender,
+            receiver,
+        }
+    }
+
+    /// Reserve `size` bytes at a multiple of `alignment` in a chunk,
+    /// and return the chunk with the offset.
+    fn allocate(
+        &mut self,
+        size: BufferAddress,
+        alignment: BufferAddress,
+        device: &Device,
+    ) -> (Arc<SharedChunk>, BufferAddress) {
+        while let Ok(chunk) = self.receiver.try_recv() {
+            self.free_chunks.push(chunk);
+        }
+
+        let align = |offset: BufferAddress| match offset % alignment {
+            0 => offset,
+            rem => offset + alignment - rem,
+        };
+        let index = match self
+            .active_chunks
+            .iter()
+            .position(|chunk| align(chunk.offset) + size <= chunk.size)
+        {
+            Some(index) => index,
+            None => {
+                let chunk = match self.free_chunks.iter().position(|chunk| size <= chunk.size) {
+                    Some(index) => self.free_chunks.swap_remove(index),
+                    None => {
+                        let size = self.chunk_size.max(size);
+                        DownloadChunk {
+                            buffer: device.create_buffer(&BufferDescriptor {
+                                label: Some("download"),
+                                size,
+                                usage: BufferUsages::MAP_READ | BufferUsages::COPY_DST,
+                                mapped_at_creation: false,
+                            }),
+                            size,
+                        }
+                    }
+                };
+                self.active_chunks.push(ActiveDownloadChunk {
+                    size: chunk.size,
+                    offset: 0,
+                    shared: Arc::new(SharedChunk {
+                        chunk: Some(chunk),
+                        state: Mutex::new(ChunkMapState::Unmapped),
+                        wakers: Arc::default(),
+                        sender: Mutex::new(self.sender.clone()),
+                    }),
+                });
+                self.active_chunks.len() - 1
+            }
+        };
+
+        let chunk = &mut self.active_chunks[index];
+        let offset = align(chunk.offset);
+        chunk.offset = offset + size;
+        (Arc::clone(&chunk.shared), offset)
+    }
+
+    /// Record the copy of `size` bytes of the `source` buffer at `offset`
+    /// into the provided command encoder, and return a future of the data.
+    ///
+    /// The future resolves after the encoder is submitted and `finish` is called.
+    pub fn read_buffer(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        source: &Buffer,
+        offset: BufferAddress,
+        size: BufferSize,
+        device: &Device,
+    ) -> impl Future<Output = Result<DownloadView, BufferAsyncError>> + Send {
+        let (chunk, chunk_offset) = self.allocate(size.get(), crate::MAP_ALIGNMENT, device);
+        encoder.copy_buffer_to_buffer(source, offset, chunk.buffer(), chunk_offset, size.get());
+        DownloadFuture {
+            chunk: Some(chunk),
+            range: chunk_offset..chunk_offset + size.get(),
+            bytes_per_row: None,
+        }
+    }
+
+    /// Record the copy of a `size` region of the `source` texture into
+    /// the provided command encoder, and return a future of the data.
+    ///
+    /// `format` is the format of the copied aspect of the texture.
+    /// The rows of the data are padded to [`COPY_BYTES_PER_ROW_ALIGNMENT`],
+    /// see [`DownloadView::bytes_per_row`].
+    ///
+    /// The future resolves after the encoder is submitted and `finish` is called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is empty.
+    ///
+    /// [`COPY_BYTES_PER_ROW_ALIGNMENT`]: crate::COPY_BYTES_PER_ROW_ALIGNMENT
+    pub fn read_texture(
+        &mut self,
+        encoder: &mut CommandEncoder,
+        source: ImageCopyTexture,
+        format: TextureFormat,
+        size: Extent3d,
+        device: &Device,
+    ) -> impl Future<Output = Result<DownloadView, BufferAsyncError>> + Send {
+        let info = format.describe();
+        let (block_width, block_height) = info.block_dimensions;
+        let width_blocks = super::block_count(size.width, block_width);
+        let height_blocks = super::block_count(size.height, block_height);
+        let bytes_per_row = super::padded_bytes_per_row(width_blocks * info.block_size as u32);
+        let total_size = bytes_per_row as BufferAddress
+            * height_blocks as BufferAddress
+            * size.depth_or_array_layers as BufferAddress;
+        assert_ne!(total_size, 0, "Reading an empty texture region");
+
+        let (chunk, chunk_offset) = self.allocate(
+            total_size,
+            crate::COPY_BYTES_PER_ROW_ALIGNMENT as BufferAddress,
+            device,
+        );
+        let bytes_per_row = NonZeroU32::new(bytes_per_row);
+        encoder.copy_texture_to_buffer(
+            source,
+            ImageCopyBuffer {
+                buffer: chunk.buffer(),
+                layout: ImageDataLayout {
+                    offset: chunk_offset,
+                    bytes_per_row,
+                    rows_per_image: NonZeroU32::new(height_blocks),
+                },
+            },
+            size,
+        );
+        DownloadFuture {
+            chunk: Some(chunk),
+            range: chunk_offset..chunk_offset + total_size,
+            bytes_per_row,
+        }
+    }
+
+    /// Start mapping the chunks used by the downloads recorded so far.
+    ///
+    /// This has to be called after the command encoders given to `read_buffer`
+    /// and `read_texture` are submitted!
+    pub fn finish(&mut self) {
+        for chunk in self.active_chunks.drain(..) {
+            chunk.shared.start_mapping();
+        }
+    }
+}
+
+struct DownloadFuture {
+    chunk: Option<Arc<SharedChunk>>,
+    range: Range<BufferAddress>,
+    bytes_per_row: Option<NonZeroU32>,
+}
+
+impl Future for DownloadFuture {
+    type Output = Result<DownloadView, BufferAsyncError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut task::Context) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        match this.chunk.as_ref().unwrap().poll_mapped(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(error)) => Poll::Ready(Err(error)),
+            Poll::Ready(Ok(())) => {
+                let chunk = this.chunk.take().unwrap();
+                let buffer = chunk.buffer();
+                let data = crate::Context::buffer_get_mapped_range(
+                    &*buffer.context,
+                    &buffer.id,
+                    this.range.clone(),
+                );
+                Poll::Ready(Ok(DownloadView {
+                    data,
+                    _chunk: chunk,
+                    bytes_per_row: this.bytes_per_row,
+                }))
+            }
+        }
+    }
+}
+
+/// Data read back by a [`DownloadBelt`].
+///
+/// The chunk of the belt it's read from is recycled once all
+/// the downloads from it are dropped.
+pub struct DownloadView {
+    // Declared first, so that it's dropped before the chunk is unmapped.
+    data: BufferMappedRange,
+    _chunk: Arc<SharedChunk>,
+    bytes_per_row: Option<NonZeroU32>,
+}
+
+impl DownloadView {
+    /// Stride between the rows of a texture download, or `None` for a buffer download.
+    pub fn bytes_per_row(&self) -> Option<NonZeroU32> {
+        self.bytes_per_row
+    }
+}
+
+impl std::ops::Deref for DownloadView {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.data.slice()
+    }
+}
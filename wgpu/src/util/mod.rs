@@ -12,7 +12,7 @@ use std::{
     ptr::copy_nonoverlapping,
 };
 
-pub use belt::StagingBelt;
+pub use belt::{DownloadBelt, DownloadView, StagingBelt};
 pub use device::{BufferInitDescriptor, DeviceExt};
 pub use encoder::RenderEncoder;
 pub use init::{
@@ -70,6 +70,24 @@ pub fn make_spirv_raw(data: &[u8]) -> Cow<[u32]> {
     words
 }
 
+/// Number of blocks of `block_size` texels needed to cover `texels`.
+fn block_count(texels: u32, block_size: u8) -> u32 {
+    let block_size = block_size as u32;
+    match texels % block_size {
+        0 => texels / block_size,
+        _ => texels / block_size + 1,
+    }
+}
+
+/// Round `bytes_per_row` up to [`COPY_BYTES_PER_ROW_ALIGNMENT`](super::COPY_BYTES_PER_ROW_ALIGNMENT).
+fn padded_bytes_per_row(bytes_per_row: u32) -> u32 {
+    let align = super::COPY_BYTES_PER_ROW_ALIGNMENT;
+    match bytes_per_row % align {
+        0 => bytes_per_row,
+        rem => bytes_per_row + align - rem,
+    }
+}
+
 /// CPU accessible buffer used to download data back from the GPU.
 pub struct DownloadBuffer(super::Buffer, super::BufferMappedRange);
 
@@ -114,3 +132,138 @@ impl std::ops::Deref for DownloadBuffer {
         super::BufferMappedRangeSlice::slice(&self.1)
     }
 }
+
+/// Texel data read back from a texture, with tightly packed rows.
+///
+/// The data is made of the texel blocks of the copied aspect, row after row,
+/// and then image after image for 3D textures and array layers.
+#[derive(Debug)]
+pub struct DownloadTexture {
+    data: Vec<u8>,
+    format: super::TextureFormat,
+    aspect: super::TextureAspect,
+    size: super::Extent3d,
+}
+
+impl DownloadTexture {
+    /// Asynchronously read the `size` region of a texture.
+    ///
+    /// Any mip level, array layer, or aspect of `texture` can be read, as long
+    /// as the aspect can be copied out of the texture. Depth and stencil aspects
+    /// have to be selected with [`TextureAspect::DepthOnly`] or
+    /// [`TextureAspect::StencilOnly`] on combined formats. Note that the
+    /// `Depth24Plus` formats can't be copied from yet, so reading them
+    /// results in a validation error.
+    ///
+    /// [`TextureAspect::DepthOnly`]: super::TextureAspect::DepthOnly
+    /// [`TextureAspect::StencilOnly`]: super::TextureAspect::StencilOnly
+    pub fn read_texture(
+        device: &super::Device,
+        queue: &super::Queue,
+        texture: super::ImageCopyTexture,
+        size: super::Extent3d,
+    ) -> impl Future<Output = Result<Self, super::BufferAsyncError>> + Send {
+        let mut download = Self {
+            data: Vec::new(),
+            format: texture.texture.format(),
+            aspect: texture.aspect,
+            size,
+        };
+        let height_blocks = download.rows_per_image();
+        let bytes_per_row = download.bytes_per_row() as usize;
+        let padded_bytes_per_row = padded_bytes_per_row(bytes_per_row as u32);
+        let rows = height_blocks as usize * size.depth_or_array_layers as usize;
+
+        let buffer = if bytes_per_row == 0 || rows == 0 {
+            None
+        } else {
+            let buffer = device.create_buffer(&super::BufferDescriptor {
+                size: padded_bytes_per_row as u64 * rows as u64,
+                usage: super::BufferUsages::COPY_DST | super::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+                label: None,
+            });
+
+            let mut encoder =
+                device.create_command_encoder(&super::CommandEncoderDescriptor { label: None });
+            encoder.copy_texture_to_buffer(
+                texture,
+                super::ImageCopyBuffer {
+                    buffer: &buffer,
+                    layout: super::ImageDataLayout {
+                        offset: 0,
+                        bytes_per_row: std::num::NonZeroU32::new(padded_bytes_per_row),
+                        rows_per_image: std::num::NonZeroU32::new(height_blocks),
+                    },
+                },
+                size,
+            );
+            queue.submit(Some(encoder.finish()));
+
+            let fut = buffer.slice(..).map_async(super::MapMode::Read);
+            Some((buffer, fut))
+        };
+
+        async move {
+            if let Some((buffer, fut)) = buffer {
+                fut.await?;
+                let padded = buffer.slice(..).get_mapped_range();
+                download.data.reserve_exact(bytes_per_row * rows);
+                for row in padded.chunks(padded_bytes_per_row as usize) {
+                    download.data.extend_from_slice(&row[..bytes_per_row]);
+                }
+            }
+            Ok(download)
+        }
+    }
+
+    /// Format of the texture that the data was read from.
+    pub fn format(&self) -> super::TextureFormat {
+        self.format
+    }
+
+    /// Aspect of the texture that the data was read from.
+    pub fn aspect(&self) -> super::TextureAspect {
+        self.aspect
+    }
+
+    /// Size of the region that was read, in texels.
+    pub fn size(&self) -> super::Extent3d {
+        self.size
+    }
+
+    /// Dimensions of a block of texels, which is `(1, 1)` for uncompressed formats.
+    pub fn block_dimensions(&self) -> (u8, u8) {
+        self.format.describe().block_dimensions
+    }
+
+    /// Size in bytes of a block of texels of the read aspect.
+    ///
+    /// This differs from the size of a block of the format for
+    /// the stencil aspect of a combined depth-stencil format.
+    pub fn block_size(&self) -> u8 {
+        match (self.format, self.aspect) {
+            (super::TextureFormat::Depth24PlusStencil8, super::TextureAspect::StencilOnly) => 1,
+            (format, _) => format.describe().block_size,
+        }
+    }
+
+    /// Size in bytes of a row of texel blocks in the data.
+    pub fn bytes_per_row(&self) -> u32 {
+        let (block_width, _) = self.block_dimensions();
+        block_count(self.size.width, block_width) * self.block_size() as u32
+    }
+
+    /// Number of rows of texel blocks in each image of the data.
+    pub fn rows_per_image(&self) -> u32 {
+        let (_, block_height) = self.block_dimensions();
+        block_count(self.size.height, block_height)
+    }
+}
+
+impl std::ops::Deref for DownloadTexture {
+    type Target = [u8];
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
@@ -0,0 +1,34 @@
+use crate::common::{initialize_test, TestParameters};
+
+#[test]
+fn adapter_as_hal() {
+    initialize_test(
+        TestParameters::default().specific_failure(
+            Some(wgpu::Backends::all() - wgpu::Backends::GL),
+            None,
+            None,
+            true,
+        ),
+        |ctx| {
+            let caps = unsafe {
+                ctx.adapter.as_hal::<hal::api::Gles, _, _>(|hal_adapter| {
+                    use hal::Adapter as _;
+                    hal_adapter
+                        .unwrap()
+                        .texture_format_capabilities(wgpu::TextureFormat::Rgba8Unorm)
+                })
+            };
+            assert!(caps.contains(
+                hal::TextureFormatCapabilities::SAMPLED
+                    | hal::TextureFormatCapabilities::COLOR_ATTACHMENT
+            ));
+
+            // The adapter isn't handed out for the other backends.
+            let vulkan_adapter = unsafe {
+                ctx.adapter
+                    .as_hal::<hal::api::Vulkan, _, _>(|hal_adapter| hal_adapter.is_some())
+            };
+            assert!(!vulkan_adapter);
+        },
+    )
+}
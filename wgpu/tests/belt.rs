@@ -0,0 +1,104 @@
+use std::num::{NonZeroU32, NonZeroU64};
+
+use wgpu::util::{DeviceExt, DownloadBelt};
+
+use crate::common::{initialize_test, TestParameters};
+
+#[test]
+fn download_belt_buffers() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let data: Vec<u8> = (0..=255).collect();
+        let source = ctx
+            .device
+            .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: None,
+                contents: &data,
+                usage: wgpu::BufferUsages::COPY_SRC,
+            });
+
+        let mut belt = DownloadBelt::new(1024);
+        // Run twice, so that the second round reuses the recycled chunk.
+        for _ in 0..2 {
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+            let first = belt.read_buffer(
+                &mut encoder,
+                &source,
+                0,
+                NonZeroU64::new(16).unwrap(),
+                &ctx.device,
+            );
+            let second = belt.read_buffer(
+                &mut encoder,
+                &source,
+                100,
+                NonZeroU64::new(132).unwrap(),
+                &ctx.device,
+            );
+            ctx.queue.submit(Some(encoder.finish()));
+            belt.finish();
+            ctx.device.poll(wgpu::Maintain::Wait);
+
+            let first = pollster::block_on(first).unwrap();
+            let second = pollster::block_on(second).unwrap();
+            assert_eq!(&*first, &data[..16]);
+            assert_eq!(&*second, &data[100..232]);
+            assert_eq!(first.bytes_per_row(), None);
+        }
+    })
+}
+
+#[test]
+fn download_belt_texture() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let size = wgpu::Extent3d {
+            width: 4,
+            height: 4,
+            depth_or_array_layers: 1,
+        };
+        let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+        });
+        let data: Vec<u8> = (0..64).collect();
+        ctx.queue.write_texture(
+            texture.as_image_copy(),
+            &data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(16),
+                rows_per_image: None,
+            },
+            size,
+        );
+
+        let mut belt = DownloadBelt::new(4096);
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        let download = belt.read_texture(
+            &mut encoder,
+            texture.as_image_copy(),
+            wgpu::TextureFormat::Rgba8Unorm,
+            size,
+            &ctx.device,
+        );
+        ctx.queue.submit(Some(encoder.finish()));
+        belt.finish();
+        ctx.device.poll(wgpu::Maintain::Wait);
+
+        let download = pollster::block_on(download).unwrap();
+        let bytes_per_row = download.bytes_per_row().unwrap().get() as usize;
+        assert_eq!(bytes_per_row, wgpu::COPY_BYTES_PER_ROW_ALIGNMENT as usize);
+        for (row, expected) in data.chunks(16).enumerate() {
+            let start = row * bytes_per_row;
+            assert_eq!(&download[start..start + 16], expected);
+        }
+    })
+}
@@ -0,0 +1,72 @@
+use crate::common::{initialize_test, TestParameters};
+
+#[test]
+fn write_mapped_at_offset() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let size = 512;
+        let source = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_WRITE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let destination = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Only map the second half, so that the written range doesn't start at 0.
+        let slice = source.slice(256..);
+        let mapping = slice.map_async(wgpu::MapMode::Write);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapping).unwrap();
+        slice.get_mapped_range_mut().fill(7);
+        source.unmap();
+
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
+        encoder.copy_buffer_to_buffer(&source, 0, &destination, 0, size);
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let slice = destination.slice(256..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapping).unwrap();
+        assert!(slice.get_mapped_range().iter().all(|&byte| byte == 7));
+    })
+}
+
+#[test]
+fn write_buffer_with_views() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let size = 512;
+        let destination = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size,
+            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        // Keep both views alive at once, so that they share a staging chunk.
+        let half = wgpu::BufferSize::new(size / 2).unwrap();
+        let mut first = ctx.queue.write_buffer_with(&destination, 0, half);
+        let mut second = ctx.queue.write_buffer_with(&destination, size / 2, half);
+        assert!(first.iter().all(|&byte| byte == 0));
+        first.fill(1);
+        second.fill(2);
+        drop(first);
+        drop(second);
+        ctx.queue.submit(None);
+
+        let slice = destination.slice(..);
+        let mapping = slice.map_async(wgpu::MapMode::Read);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        pollster::block_on(mapping).unwrap();
+        let data = slice.get_mapped_range();
+        assert!(data[..256].iter().all(|&byte| byte == 1));
+        assert!(data[256..].iter().all(|&byte| byte == 2));
+    })
+}
@@ -1,3 +1,12 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    ptr,
+    sync::{Arc, Mutex},
+    task::{RawWaker, RawWakerVTable, Waker},
+    time::Duration,
+};
+
 use crate::common::{initialize_test, TestParameters};
 
 #[test]
@@ -6,3 +15,108 @@ fn device_initialization() {
         // intentionally empty
     })
 }
+
+#[test]
+fn out_of_memory_is_reported() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&errors);
+        ctx.device.on_uncaptured_error(move |error| {
+            sink.lock().unwrap().push(error);
+        });
+
+        // Too large for any adapter to back, but otherwise valid.
+        let _buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("huge"),
+            size: 1 << 40,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let errors = errors.lock().unwrap();
+        assert_eq!(errors.len(), 1);
+        match errors[0] {
+            wgpu::Error::OutOfMemory {
+                ref description, ..
+            } => assert!(description.contains("huge"), "{}", description),
+            ref other => panic!("Expected an out of memory error, got {}", other),
+        }
+    })
+}
+
+#[test]
+fn background_polling_completes_mappings() {
+    initialize_test(TestParameters::default(), |ctx| {
+        ctx.device.start_background_polling();
+
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 16,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        // Keep submitting while the mapping is pending, which must not stall on the poller.
+        for value in 0..8u32 {
+            ctx.queue
+                .write_buffer(&buffer, 0, bytemuck::cast_slice(&[value; 4]));
+            ctx.queue.submit(None);
+        }
+
+        let slice = buffer.slice(..);
+        pollster::block_on(slice.map_async(wgpu::MapMode::Read)).unwrap();
+        let data = slice.get_mapped_range();
+        let values: &[u32] = bytemuck::cast_slice(&data);
+        assert_eq!(values, &[7; 4]);
+    })
+}
+
+/// Whether `future` has completed, without blocking on it.
+fn is_ready(future: Pin<&mut impl Future>) -> bool {
+    fn raw_waker() -> RawWaker {
+        fn clone(_: *const ()) -> RawWaker {
+            raw_waker()
+        }
+        fn noop(_: *const ()) {}
+        static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, noop, noop, noop);
+        RawWaker::new(ptr::null(), &VTABLE)
+    }
+    let waker = unsafe { Waker::from_raw(raw_waker()) };
+    future
+        .poll(&mut std::task::Context::from_waker(&waker))
+        .is_ready()
+}
+
+#[test]
+fn wait_for_submission_index() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+            label: None,
+            size: 1 << 20,
+            usage: wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        ctx.queue.write_buffer(&buffer, 0, &vec![1; 1 << 20]);
+        let index = ctx.queue.submit(None);
+        let mut early = Box::pin(ctx.queue.on_submitted_work_done());
+        let mut late = Box::pin(ctx.queue.on_submitted_work_done());
+
+        // Without a timeout to wait for, the submission is only done if it's reported so.
+        if ctx.device.poll(wgpu::Maintain::WaitForSubmissionIndex(
+            index,
+            Some(Duration::ZERO),
+        )) {
+            assert!(is_ready(early.as_mut()));
+        }
+        assert!(ctx
+            .device
+            .poll(wgpu::Maintain::WaitForSubmissionIndex(index, None)));
+        assert!(is_ready(late.as_mut()));
+
+        // A finished submission doesn't time out.
+        assert!(ctx.device.poll(wgpu::Maintain::WaitForSubmissionIndex(
+            index,
+            Some(Duration::ZERO),
+        )));
+        assert!(ctx.device.poll(wgpu::Maintain::Poll));
+    })
+}
@@ -0,0 +1,162 @@
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+
+use wgpu::util::DownloadTexture;
+
+use crate::common::{initialize_test, TestParameters};
+
+#[test]
+fn download_texture_mip_level() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+            label: None,
+            size: wgpu::Extent3d {
+                width: 16,
+                height: 16,
+                depth_or_array_layers: 2,
+            },
+            mip_level_count: 2,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+        });
+        let copy = wgpu::ImageCopyTexture {
+            texture: &texture,
+            mip_level: 1,
+            origin: wgpu::Origin3d { x: 0, y: 0, z: 1 },
+            aspect: wgpu::TextureAspect::All,
+        };
+        let size = wgpu::Extent3d {
+            width: 8,
+            height: 8,
+            depth_or_array_layers: 1,
+        };
+        let data: Vec<u8> = (0..=255).collect();
+        ctx.queue.write_texture(
+            copy.clone(),
+            &data,
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(32),
+                rows_per_image: None,
+            },
+            size,
+        );
+
+        let download = DownloadTexture::read_texture(&ctx.device, &ctx.queue, copy, size);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let download = pollster::block_on(download).unwrap();
+
+        assert_eq!(download.format(), wgpu::TextureFormat::Rgba8Unorm);
+        assert_eq!(download.block_size(), 4);
+        assert_eq!(download.bytes_per_row(), 32);
+        assert_eq!(download.rows_per_image(), 8);
+        assert_eq!(&*download, &data[..]);
+    })
+}
+
+fn create_depth_texture(ctx: &crate::common::TestingContext) -> wgpu::Texture {
+    ctx.device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: 4,
+            height: 4,
+            depth_or_array_layers: 1,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Depth32Float,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+    })
+}
+
+#[test]
+fn download_texture_depth() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let texture = create_depth_texture(&ctx);
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = ctx
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0.5),
+                    store: true,
+                }),
+                stencil_ops: None,
+            }),
+        });
+        ctx.queue.submit(Some(encoder.finish()));
+
+        let size = wgpu::Extent3d {
+            width: 4,
+            height: 4,
+            depth_or_array_layers: 1,
+        };
+        let download = DownloadTexture::read_texture(
+            &ctx.device,
+            &ctx.queue,
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::DepthOnly,
+            },
+            size,
+        );
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let download = pollster::block_on(download).unwrap();
+
+        assert_eq!(download.aspect(), wgpu::TextureAspect::DepthOnly);
+        assert_eq!(download.block_size(), 4);
+        assert_eq!(download.bytes_per_row(), 16);
+        let depths = download
+            .chunks(4)
+            .map(|texel| f32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]]))
+            .collect::<Vec<_>>();
+        assert_eq!(depths, [0.5; 16]);
+    })
+}
+
+#[test]
+fn download_texture_missing_stencil() {
+    initialize_test(TestParameters::default(), |ctx| {
+        let errors = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&errors);
+        ctx.device.on_uncaptured_error(move |error| {
+            sink.lock().unwrap().push(error.to_string());
+        });
+
+        // Stencil formats can't be copied from yet, so this is the only way to ask for a
+        // stencil aspect, and it has to be reported instead of reading depth.
+        let texture = create_depth_texture(&ctx);
+        let _download = DownloadTexture::read_texture(
+            &ctx.device,
+            &ctx.queue,
+            wgpu::ImageCopyTexture {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::StencilOnly,
+            },
+            wgpu::Extent3d {
+                width: 4,
+                height: 4,
+                depth_or_array_layers: 1,
+            },
+        );
+
+        let errors = errors.lock().unwrap();
+        assert!(
+            errors.iter().any(|error| error.contains("aspect")),
+            "{:?}",
+            errors
+        );
+    })
+}
@@ -0,0 +1,65 @@
+use glow::HasContext;
+
+use crate::common::{initialize_test, TestParameters};
+
+type Gles = hal::api::Gles;
+
+#[test]
+fn external_buffer_survives_destroy() {
+    initialize_test(
+        TestParameters::default().specific_failure(
+            Some(wgpu::Backends::all() - wgpu::Backends::GL),
+            None,
+            None,
+            true,
+        ),
+        |ctx| {
+            let (device, queue) = (&ctx.device, &ctx.queue);
+
+            let size = 256;
+            let hal_buffer = unsafe {
+                device.as_hal::<Gles, _, _>(|hal_device| {
+                    use hal::Device as _;
+                    hal_device
+                        .unwrap()
+                        .create_buffer(&hal::BufferDescriptor {
+                            label: Some("external"),
+                            size,
+                            usage: hal::BufferUses::COPY_DST,
+                            memory_flags: hal::MemoryFlags::empty(),
+                        })
+                        .unwrap()
+                })
+            };
+            let raw = hal_buffer.raw_handle();
+
+            let buffer = unsafe {
+                device.create_buffer_from_hal::<Gles>(
+                    hal_buffer,
+                    &wgpu::BufferDescriptor {
+                        label: Some("external"),
+                        size,
+                        usage: wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    },
+                    false,
+                )
+            };
+            // Leave a pending write behind, so that the destruction has to wait for it.
+            queue.write_buffer(&buffer, 0, &[1; 16]);
+            buffer.destroy();
+            queue.submit(None);
+            device.poll(wgpu::Maintain::Wait);
+            drop(buffer);
+            device.poll(wgpu::Maintain::Wait);
+
+            unsafe {
+                device.as_hal::<Gles, _, _>(|hal_device| {
+                    let gl = hal_device.unwrap().context().lock();
+                    assert!(gl.is_buffer(raw));
+                    gl.delete_buffer(raw);
+                })
+            };
+        },
+    )
+}
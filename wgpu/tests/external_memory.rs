@@ -0,0 +1,157 @@
+use std::{ffi::CStr, num::NonZeroU32};
+
+use wgpu::util::DownloadTexture;
+
+use crate::common::{initialize_test, TestParameters};
+
+type Vulkan = hal::api::Vulkan;
+
+const SIZE: wgpu::Extent3d = wgpu::Extent3d {
+    width: 8,
+    height: 8,
+    depth_or_array_layers: 1,
+};
+
+fn texture_descriptor(label: &str) -> hal::TextureDescriptor<'_> {
+    hal::TextureDescriptor {
+        label: Some(label),
+        size: SIZE,
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: hal::TextureUses::COPY_SRC | hal::TextureUses::COPY_DST,
+        memory_flags: hal::MemoryFlags::empty(),
+    }
+}
+
+#[test]
+fn texture_shared_through_opaque_fd() {
+    initialize_test(
+        TestParameters::default().specific_failure(
+            Some(wgpu::Backends::all() - wgpu::Backends::VULKAN),
+            None,
+            None,
+            true,
+        ),
+        |ctx| {
+            let extensions = [
+                &b"VK_KHR_external_memory_fd\0"[..],
+                &b"VK_KHR_external_semaphore_fd\0"[..],
+            ]
+            .iter()
+            .map(|name| CStr::from_bytes_with_nul(name).unwrap())
+            .collect::<Vec<_>>();
+
+            let (device, queue) = pollster::block_on(unsafe {
+                ctx.adapter.request_device_with_vulkan_extras(
+                    &wgpu::DeviceDescriptor::default(),
+                    hal::vulkan::DeviceExtras {
+                        extensions: extensions.clone(),
+                        features_callback: None,
+                    },
+                    None,
+                )
+            })
+            .unwrap();
+
+            let supported = unsafe {
+                device.as_hal::<Vulkan, _, _>(|hal_device| {
+                    let enabled = hal_device.unwrap().enabled_extensions();
+                    extensions
+                        .iter()
+                        .all(|extension| enabled.contains(extension))
+                })
+            };
+            assert!(
+                supported,
+                "External memory and semaphores are not supported"
+            );
+
+            // Both textures share the same memory.
+            let (exported, imported) = unsafe {
+                device.as_hal::<Vulkan, _, _>(|hal_device| {
+                    let hal_device = hal_device.unwrap();
+                    let exported = hal_device
+                        .create_exportable_texture(
+                            &texture_descriptor("exported"),
+                            hal::vulkan::ExternalMemoryType::OpaqueFd,
+                        )
+                        .unwrap();
+                    let fd = exported.export_fd(hal_device).unwrap();
+                    let imported = hal_device
+                        .create_texture_from_external_memory(
+                            &texture_descriptor("imported"),
+                            hal::vulkan::ExternalMemoryType::OpaqueFd,
+                            fd,
+                        )
+                        .unwrap();
+                    (exported, imported)
+                })
+            };
+            let desc = wgpu::TextureDescriptor {
+                label: None,
+                size: SIZE,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8Unorm,
+                usage: wgpu::TextureUsages::COPY_SRC | wgpu::TextureUsages::COPY_DST,
+            };
+            let exported = unsafe { device.create_texture_from_hal::<Vulkan>(exported, &desc) };
+            let imported = unsafe { device.create_texture_from_hal::<Vulkan>(imported, &desc) };
+
+            let data: Vec<u8> = (0..=255).collect();
+            queue.write_texture(
+                exported.as_image_copy(),
+                &data,
+                wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(32),
+                    rows_per_image: None,
+                },
+                SIZE,
+            );
+
+            // Order the download after the upload through a semaphore passed around as an fd.
+            let (signalled, waited) = unsafe {
+                device.as_hal::<Vulkan, _, _>(|hal_device| {
+                    let hal_device = hal_device.unwrap();
+                    let signalled = hal_device
+                        .create_exportable_semaphore(hal::vulkan::ExternalSemaphoreType::OpaqueFd)
+                        .unwrap();
+                    let fd = signalled.export_fd(hal_device).unwrap();
+                    let waited = hal_device
+                        .import_semaphore_fd(hal::vulkan::ExternalSemaphoreType::OpaqueFd, fd)
+                        .unwrap();
+                    (signalled, waited)
+                })
+            };
+            unsafe {
+                queue.as_hal::<Vulkan, _, _>(|hal_queue| {
+                    hal_queue.unwrap().signal_external_semaphore(&signalled)
+                });
+            }
+            queue.submit(None);
+            unsafe {
+                queue.as_hal::<Vulkan, _, _>(|hal_queue| {
+                    hal_queue.unwrap().wait_external_semaphore(&waited)
+                });
+            }
+
+            let download =
+                DownloadTexture::read_texture(&device, &queue, imported.as_image_copy(), SIZE);
+            device.poll(wgpu::Maintain::Wait);
+            let download = pollster::block_on(download).unwrap();
+            assert_eq!(&*download, &data[..]);
+
+            unsafe {
+                device.as_hal::<Vulkan, _, _>(|hal_device| {
+                    let hal_device = hal_device.unwrap();
+                    hal_device.destroy_external_semaphore(signalled);
+                    hal_device.destroy_external_semaphore(waited);
+                });
+            }
+        },
+    )
+}
@@ -5,6 +5,19 @@ fn initialize() {
     );
 }
 
+#[cfg(any(windows, all(unix, not(target_os = "ios"), not(target_os = "macos"))))]
+#[test]
+fn initialize_with_vulkan_extras() {
+    let extension = std::ffi::CStr::from_bytes_with_nul(b"VK_KHR_external_memory_capabilities\0");
+    let _ = wgpu::Instance::new_with_vulkan_extras(
+        wgpu::util::backend_bits_from_env().unwrap_or_else(wgpu::Backends::all),
+        &hal::vulkan::InstanceExtras {
+            layers: Vec::new(),
+            extensions: vec![extension.unwrap()],
+        },
+    );
+}
+
 fn request_adapter_inner(power: wgt::PowerPreference) {
     let instance = wgpu::Instance::new(
         wgpu::util::backend_bits_from_env().unwrap_or_else(wgpu::Backends::all),
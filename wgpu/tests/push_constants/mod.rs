@@ -0,0 +1,139 @@
+use std::num::NonZeroU64;
+
+use wgpu::util::DownloadBuffer;
+
+use crate::common::{initialize_test, TestParameters};
+
+fn run_push_constant_test(
+    shader: wgpu::ShaderModuleDescriptor,
+    entry_point: &str,
+    record: impl FnOnce(&mut wgpu::ComputePass),
+    expected: [u32; 4],
+) {
+    initialize_test(
+        TestParameters::default()
+            .features(wgpu::Features::PUSH_CONSTANTS)
+            .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+            .limits(wgpu::Limits {
+                max_push_constant_size: 16,
+                ..wgpu::Limits::downlevel_defaults()
+            }),
+        |ctx| {
+            let shader = ctx.device.create_shader_module(&shader);
+
+            let bgl = ctx
+                .device
+                .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                    label: None,
+                    entries: &[wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Storage { read_only: false },
+                            has_dynamic_offset: false,
+                            min_binding_size: NonZeroU64::new(16),
+                        },
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        count: None,
+                    }],
+                });
+
+            let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: 16,
+                usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            });
+
+            let bg = ctx.device.create_bind_group(&wgpu::BindGroupDescriptor {
+                label: None,
+                layout: &bgl,
+                entries: &[wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: buffer.as_entire_binding(),
+                }],
+            });
+
+            let ppl = ctx
+                .device
+                .create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: None,
+                    bind_group_layouts: &[&bgl],
+                    push_constant_ranges: &[wgpu::PushConstantRange {
+                        stages: wgpu::ShaderStages::COMPUTE,
+                        range: 0..16,
+                    }],
+                });
+
+            let pipeline = ctx
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: None,
+                    layout: Some(&ppl),
+                    module: &shader,
+                    entry_point,
+                });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            {
+                let mut cpass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+                cpass.set_pipeline(&pipeline);
+                cpass.set_bind_group(0, &bg, &[]);
+                record(&mut cpass);
+            }
+            ctx.queue.submit(Some(encoder.finish()));
+
+            let download = DownloadBuffer::read_buffer(&ctx.device, &ctx.queue, &buffer.slice(..));
+            ctx.device.poll(wgpu::Maintain::Wait);
+            let download = pollster::block_on(download).unwrap();
+            let values: &[u32] = bytemuck::cast_slice(&download);
+            assert_eq!(values, &expected);
+        },
+    )
+}
+
+#[test]
+fn set_push_constants() {
+    run_push_constant_test(
+        wgpu::include_wgsl!("shader.wgsl"),
+        "main",
+        |cpass| {
+            cpass.set_push_constants(0, bytemuck::cast_slice(&[1u32, 2, 3, 4]));
+            // Only the last two values are overwritten.
+            cpass.set_push_constants(8, bytemuck::cast_slice(&[30u32, 40]));
+            cpass.dispatch(1, 1, 1);
+        },
+        [1, 2, 30, 40],
+    )
+}
+
+#[test]
+fn push_constants_per_dispatch() {
+    run_push_constant_test(
+        wgpu::include_wgsl!("shader.wgsl"),
+        "write_one",
+        |cpass| {
+            // Every dispatch has to see the values that were set right before it.
+            for (index, value) in [10u32, 20, 30, 40].iter().enumerate() {
+                cpass.set_push_constants(0, bytemuck::cast_slice(&[index as u32, *value]));
+                cpass.dispatch(1, 1, 1);
+            }
+        },
+        [10, 20, 30, 40],
+    )
+}
+
+#[test]
+fn vector_push_constants() {
+    run_push_constant_test(
+        wgpu::include_wgsl!("vector.wgsl"),
+        "main",
+        |cpass| {
+            cpass.set_push_constants(0, bytemuck::cast_slice(&[5u32, 6, 7, 8]));
+            cpass.dispatch(1, 1, 1);
+        },
+        [5, 6, 7, 8],
+    )
+}
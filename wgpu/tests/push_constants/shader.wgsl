@@ -0,0 +1,27 @@
+[[block]]
+struct PushConstants {
+    values: vec4<u32>;
+};
+
+[[block]]
+struct Output {
+    values: array<u32, 4>;
+};
+
+var<push_constant> push_constants: PushConstants;
+
+[[group(0), binding(0)]]
+var<storage, read_write> output: Output;
+
+[[stage(compute), workgroup_size(1)]]
+fn main() {
+    output.values[0] = push_constants.values.x;
+    output.values[1] = push_constants.values.y;
+    output.values[2] = push_constants.values.z;
+    output.values[3] = push_constants.values.w;
+}
+
+[[stage(compute), workgroup_size(1)]]
+fn write_one() {
+    output.values[push_constants.values.x] = push_constants.values.y;
+}
@@ -0,0 +1,21 @@
+[[block]]
+struct Output {
+    values: array<u32, 4>;
+};
+
+var<push_constant> push_constants: vec4<u32>;
+
+[[group(0), binding(0)]]
+var<storage, read_write> output: Output;
+
+fn first() -> u32 {
+    return push_constants.x;
+}
+
+[[stage(compute), workgroup_size(1)]]
+fn main() {
+    output.values[0] = first();
+    output.values[1] = push_constants.y;
+    output.values[2] = push_constants.z;
+    output.values[3] = push_constants.w;
+}
@@ -0,0 +1,98 @@
+use wgpu::util::DownloadBuffer;
+
+use crate::common::{initialize_test, TestParameters};
+
+#[test]
+fn timestamps_increase() {
+    initialize_test(
+        TestParameters::default().features(wgpu::Features::TIMESTAMP_QUERY),
+        |ctx| {
+            let query_set = ctx.device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: None,
+                ty: wgpu::QueryType::Timestamp,
+                count: 2,
+            });
+            let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: 2 * wgpu::QUERY_SIZE as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            encoder.write_timestamp(&query_set, 0);
+            encoder.write_timestamp(&query_set, 1);
+            encoder.resolve_query_set(&query_set, 0..2, &buffer, 0);
+            ctx.queue.submit(Some(encoder.finish()));
+
+            let download = DownloadBuffer::read_buffer(&ctx.device, &ctx.queue, &buffer.slice(..));
+            ctx.device.poll(wgpu::Maintain::Wait);
+            let download = pollster::block_on(download).unwrap();
+            let timestamps: &[u64] = bytemuck::cast_slice(&download);
+            assert_ne!(timestamps[0], 0);
+            assert!(timestamps[0] <= timestamps[1]);
+        },
+    )
+}
+
+#[test]
+fn pipeline_statistics_count_invocations() {
+    initialize_test(
+        TestParameters::default()
+            .features(wgpu::Features::PIPELINE_STATISTICS_QUERY)
+            .downlevel_flags(wgpu::DownlevelFlags::COMPUTE_SHADERS),
+        |ctx| {
+            let shader = ctx
+                .device
+                .create_shader_module(&wgpu::ShaderModuleDescriptor {
+                    label: None,
+                    source: wgpu::ShaderSource::Wgsl(
+                        "[[stage(compute), workgroup_size(64)]] fn main() {}".into(),
+                    ),
+                });
+            let pipeline = ctx
+                .device
+                .create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                    label: None,
+                    layout: None,
+                    module: &shader,
+                    entry_point: "main",
+                });
+            let query_set = ctx.device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: None,
+                ty: wgpu::QueryType::PipelineStatistics(
+                    wgpu::PipelineStatisticsTypes::COMPUTE_SHADER_INVOCATIONS,
+                ),
+                count: 1,
+            });
+            let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
+                label: None,
+                size: wgpu::QUERY_SIZE as wgpu::BufferAddress,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::COPY_SRC,
+                mapped_at_creation: false,
+            });
+
+            let mut encoder = ctx
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
+            {
+                let mut cpass =
+                    encoder.begin_compute_pass(&wgpu::ComputePassDescriptor { label: None });
+                cpass.set_pipeline(&pipeline);
+                cpass.begin_pipeline_statistics_query(&query_set, 0);
+                cpass.dispatch(2, 1, 1);
+                cpass.end_pipeline_statistics_query();
+            }
+            encoder.resolve_query_set(&query_set, 0..1, &buffer, 0);
+            ctx.queue.submit(Some(encoder.finish()));
+
+            let download = DownloadBuffer::read_buffer(&ctx.device, &ctx.queue, &buffer.slice(..));
+            ctx.device.poll(wgpu::Maintain::Wait);
+            let download = pollster::block_on(download).unwrap();
+            let invocations: &[u64] = bytemuck::cast_slice(&download);
+            assert_eq!(invocations, &[128]);
+        },
+    )
+}
@@ -1,7 +1,19 @@
 // All files containing tests
 mod common;
 
+#[cfg(all(unix, not(target_os = "ios"), not(target_os = "macos")))]
+mod as_hal;
+mod belt;
+mod buffer_map;
 mod device;
+mod download;
 mod example_wgsl;
+#[cfg(all(unix, not(target_os = "ios"), not(target_os = "macos")))]
+mod external_buffer;
+#[cfg(all(unix, not(target_os = "ios"), not(target_os = "macos")))]
+mod external_memory;
 mod instance;
+mod push_constants;
+mod query;
+mod texture_layers;
 mod vertex_indices;
@@ -0,0 +1,73 @@
+use std::num::NonZeroU32;
+
+use wgpu::util::DownloadTexture;
+
+use crate::common::{initialize_test, TestParameters};
+
+/// Writes a different pattern into each layer, then reads each of them back.
+fn write_and_read_layers(ctx: &crate::common::TestingContext, dimension: wgpu::TextureDimension) {
+    let layers = 3;
+    let texture = ctx.device.create_texture(&wgpu::TextureDescriptor {
+        label: None,
+        size: wgpu::Extent3d {
+            width: 4,
+            height: 4,
+            depth_or_array_layers: layers,
+        },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension,
+        format: wgpu::TextureFormat::Rgba8Unorm,
+        usage: wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::COPY_SRC,
+    });
+    let size = wgpu::Extent3d {
+        width: 4,
+        height: 4,
+        depth_or_array_layers: 1,
+    };
+    let layer_copy = |layer| wgpu::ImageCopyTexture {
+        texture: &texture,
+        mip_level: 0,
+        origin: wgpu::Origin3d {
+            x: 0,
+            y: 0,
+            z: layer,
+        },
+        aspect: wgpu::TextureAspect::All,
+    };
+
+    for layer in 0..layers {
+        ctx.queue.write_texture(
+            layer_copy(layer),
+            &[layer as u8 + 1; 64],
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(16),
+                rows_per_image: None,
+            },
+            size,
+        );
+    }
+
+    for layer in 0..layers {
+        let download =
+            DownloadTexture::read_texture(&ctx.device, &ctx.queue, layer_copy(layer), size);
+        ctx.device.poll(wgpu::Maintain::Wait);
+        let download = pollster::block_on(download).unwrap();
+        assert_eq!(&*download, &[layer as u8 + 1; 64][..], "layer {}", layer);
+    }
+}
+
+#[test]
+fn array_texture_layers() {
+    initialize_test(TestParameters::default(), |ctx| {
+        write_and_read_layers(&ctx, wgpu::TextureDimension::D2);
+    })
+}
+
+#[test]
+fn texture_3d_slices() {
+    initialize_test(TestParameters::default(), |ctx| {
+        write_and_read_layers(&ctx, wgpu::TextureDimension::D3);
+    })
+}
@@ -1,13 +1,33 @@
 use std::num::NonZeroU64;
 
-use wgpu::util::DeviceExt;
+use wgpu::util::{DeviceExt, DownloadBuffer};
 
 use crate::common::{initialize_test, TestParameters, TestingContext};
 
+/// Buffers that the draws of [`pulling`] take their indices and arguments from.
+struct DrawBuffers {
+    index: Option<wgpu::Buffer>,
+    indirect: Option<wgpu::Buffer>,
+}
+
 fn pulling_common(
     ctx: TestingContext,
     expected: &[u32],
     function: impl FnOnce(&mut wgpu::RenderPass<'_>),
+) {
+    pulling(ctx, expected, &[], &[], |cmb, _| function(cmb))
+}
+
+/// Draws with a pipeline writing out the vertex indices, and checks them against `expected`.
+///
+/// The index buffer holding `indices` is bound for the draws, and the indirect
+/// buffer holding `indirect_args` is given to them, if there are any.
+fn pulling(
+    ctx: TestingContext,
+    expected: &[u32],
+    indices: &[u32],
+    indirect_args: &[u32],
+    function: impl for<'a> FnOnce(&mut wgpu::RenderPass<'a>, &'a DrawBuffers),
 ) {
     let shader = ctx
         .device
@@ -32,9 +52,7 @@ fn pulling_common(
     let buffer = ctx.device.create_buffer(&wgpu::BufferDescriptor {
         label: None,
         size: 4 * expected.len() as u64,
-        usage: wgpu::BufferUsages::COPY_SRC
-            | wgpu::BufferUsages::STORAGE
-            | wgpu::BufferUsages::MAP_READ,
+        usage: wgpu::BufferUsages::COPY_SRC | wgpu::BufferUsages::STORAGE,
         mapped_at_creation: false,
     });
 
@@ -100,6 +118,24 @@ fn pulling_common(
         )
         .create_view(&wgpu::TextureViewDescriptor::default());
 
+    let buffer_init = |label, contents: &[u32], usage| {
+        if contents.is_empty() {
+            return None;
+        }
+        Some(
+            ctx.device
+                .create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                    label: Some(label),
+                    contents: bytemuck::cast_slice(contents),
+                    usage,
+                }),
+        )
+    };
+    let draw_buffers = DrawBuffers {
+        index: buffer_init("index", indices, wgpu::BufferUsages::INDEX),
+        indirect: buffer_init("indirect", indirect_args, wgpu::BufferUsages::INDIRECT),
+    };
+
     let mut encoder = ctx
         .device
         .create_command_encoder(&wgpu::CommandEncoderDescriptor::default());
@@ -116,15 +152,18 @@ fn pulling_common(
 
     rpass.set_pipeline(&pipeline);
     rpass.set_bind_group(0, &bg, &[]);
-    function(&mut rpass);
+    if let Some(ref index) = draw_buffers.index {
+        rpass.set_index_buffer(index.slice(..), wgpu::IndexFormat::Uint32);
+    }
+    function(&mut rpass, &draw_buffers);
 
     drop(rpass);
 
     ctx.queue.submit(Some(encoder.finish()));
-    let slice = buffer.slice(..);
-    let _ = slice.map_async(wgpu::MapMode::Read);
+    let download = DownloadBuffer::read_buffer(&ctx.device, &ctx.queue, &buffer.slice(..));
     ctx.device.poll(wgpu::Maintain::Wait);
-    let data: Vec<u32> = bytemuck::cast_slice(&*slice.get_mapped_range()).to_vec();
+    let download = pollster::block_on(download).unwrap();
+    let data: Vec<u32> = bytemuck::cast_slice(&download).to_vec();
 
     assert_eq!(data, expected);
 }
@@ -176,3 +215,83 @@ fn draw_instanced_offset() {
         },
     )
 }
+
+fn multi_draw_indirect_parameters() -> TestParameters {
+    TestParameters::default()
+        .features(wgpu::Features::VERTEX_WRITABLE_STORAGE | wgpu::Features::MULTI_DRAW_INDIRECT)
+        .downlevel_flags(wgpu::DownlevelFlags::INDIRECT_EXECUTION)
+        .backend_failure(wgpu::Backends::DX11)
+}
+
+/// Two draws of a triangle each, with the second one starting at the fourth vertex.
+fn multi_draw_indirect_common(ctx: TestingContext) {
+    // vertex count, instance count, first vertex, first instance
+    let args = [3, 1, 0, 0, 3, 1, 3, 0];
+    pulling(ctx, &[0, 1, 2, 3, 4, 5], &[], &args, |cmb, buffers| {
+        cmb.multi_draw_indirect(buffers.indirect.as_ref().unwrap(), 0, 2);
+    })
+}
+
+#[test]
+fn multi_draw_indirect() {
+    initialize_test(multi_draw_indirect_parameters(), multi_draw_indirect_common)
+}
+
+#[test]
+fn multi_draw_indexed_indirect() {
+    initialize_test(multi_draw_indirect_parameters(), |ctx| {
+        // index count, instance count, first index, base vertex, first instance
+        let args = [3, 1, 0, 0, 0, 3, 1, 3, 0, 0];
+        pulling(
+            ctx,
+            &[0, 1, 2, 3, 4, 5],
+            &[0, 1, 2, 3, 4, 5],
+            &args,
+            |cmb, buffers| {
+                cmb.multi_draw_indexed_indirect(buffers.indirect.as_ref().unwrap(), 0, 2);
+            },
+        )
+    })
+}
+
+/// Splits the indirect draws up on Vulkan, by creating the device without `multiDrawIndirect`.
+#[cfg(any(windows, all(unix, not(target_os = "ios"), not(target_os = "macos"))))]
+#[test]
+fn multi_draw_indirect_split_on_vulkan() {
+    initialize_test(
+        TestParameters::default()
+            .features(wgpu::Features::VERTEX_WRITABLE_STORAGE | wgpu::Features::MULTI_DRAW_INDIRECT)
+            .downlevel_flags(wgpu::DownlevelFlags::INDIRECT_EXECUTION)
+            // The Vulkan extras can't be passed to the other backends.
+            .specific_failure(
+                Some(wgpu::Backends::all() - wgpu::Backends::VULKAN),
+                None,
+                None,
+                true,
+            ),
+        |ctx| {
+            let (device, queue) = pollster::block_on(unsafe {
+                ctx.adapter.request_device_with_vulkan_extras(
+                    &wgpu::DeviceDescriptor {
+                        label: None,
+                        features: ctx.device.features(),
+                        limits: ctx.device.limits(),
+                    },
+                    hal::vulkan::DeviceExtras {
+                        extensions: Vec::new(),
+                        features_callback: Some(Box::new(|features| {
+                            features.features.multi_draw_indirect = 0;
+                        })),
+                    },
+                    None,
+                )
+            })
+            .unwrap();
+            multi_draw_indirect_common(TestingContext {
+                device,
+                queue,
+                ..ctx
+            });
+        },
+    )
+}